@@ -0,0 +1,88 @@
+//! Parameter sweep for the Avellaneda strategy over a market data recording.
+//!
+//! Reads the base profile from `[avellaneda]` and the sweep ranges from
+//! `[avellaneda_sweep]` in `config.toml`, backtests every combination over a
+//! `MarketRecorder` directory and writes `results.csv` plus `best.toml` to the
+//! output directory.
+//!
+//! ```text
+//! cargo run --release --example mm_avellaneda_sweep -- <recording_dir> [objective] [out_dir] [sweep.toml]
+//! ```
+//!
+//! `objective` is one of `sharpe` (default), `pnl_per_notional`, `pnl` or
+//! `max_drawdown`.
+
+use anyhow::{Context, Result};
+use lighter_client::{
+    avellaneda::{
+        backtest::events_from_recording, AvellanedaConfig, Objective, Sweep, SweepConfig, SweepSpec,
+    },
+    types::MarketId,
+};
+use std::{fs::create_dir_all, path::PathBuf};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    init_tracing();
+
+    let mut args = std::env::args().skip(1);
+    let recording = args
+        .next()
+        .context("usage: mm_avellaneda_sweep <recording_dir> [objective] [out_dir] [sweep.toml]")?;
+    let objective: Objective = args.next().as_deref().unwrap_or("sharpe").parse()?;
+    let out_dir = PathBuf::from(args.next().unwrap_or_else(|| "logs/sweeps".to_string()));
+    let spec_path = args.next().unwrap_or_else(|| "config.toml".to_string());
+
+    let base = AvellanedaConfig::from_file_without_env("config.toml")?;
+    let spec = SweepSpec::from_file(&spec_path)?;
+    let events = events_from_recording(&recording, MarketId::new(base.market_id)).await?;
+    println!(
+        "Sweeping {} configurations over {} events ({})",
+        spec.combinations(),
+        events.len(),
+        objective
+    );
+
+    let config = SweepConfig {
+        objective,
+        ..SweepConfig::default()
+    };
+    let outcome =
+        tokio::task::spawn_blocking(move || Sweep::new(base, spec, config).run(&events)).await??;
+
+    for rejected in &outcome.rejected {
+        println!("skipped {:?}: {}", rejected.params, rejected.reason);
+    }
+    for (rank, result) in outcome.results.iter().take(10).enumerate() {
+        println!(
+            "#{:<3} score {:>10.4}  pnl {:>10.4}  fills {:>5}  dd {:>8.4}  {:?}",
+            rank + 1,
+            result.score,
+            result.metrics.pnl,
+            result.metrics.fills,
+            result.metrics.max_drawdown,
+            result.params
+        );
+    }
+
+    create_dir_all(&out_dir)?;
+    outcome.write_table(out_dir.join("results.csv"))?;
+    outcome.write_best_config(out_dir.join("best.toml"))?;
+    println!("Wrote {}", out_dir.display());
+    Ok(())
+}
+
+fn init_tracing() {
+    if tracing::subscriber::set_global_default(
+        tracing_subscriber::fmt()
+            .with_env_filter(
+                tracing_subscriber::EnvFilter::try_from_default_env()
+                    .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
+            )
+            .finish(),
+    )
+    .is_err()
+    {
+        // Tracing already initialised elsewhere.
+    }
+}
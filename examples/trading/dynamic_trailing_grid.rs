@@ -1396,7 +1396,11 @@ fn event_label(event: &WsEvent) -> &'static str {
         WsEvent::Trade(_) => "Trade",
         WsEvent::BBO(_) => "BBO",
         WsEvent::Closed(_) => "Closed",
+        WsEvent::Reconnecting { .. } => "Reconnecting",
+        WsEvent::Resynced { .. } => "Resynced",
+        WsEvent::Stale { .. } => "Stale",
         WsEvent::Unknown(_) => "Unknown",
+        _ => "Other",
     }
 }
 
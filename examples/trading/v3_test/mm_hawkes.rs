@@ -12,10 +12,12 @@ use chrono::Local;
 use futures_util::StreamExt;
 use lighter_client::{
     lighter_client::LighterClient,
+    models,
     signer_client::SignerClient,
     tx_executor::{send_batch_tx_ws, TX_TYPE_CANCEL_ALL_ORDERS, TX_TYPE_CREATE_ORDER},
     types::{AccountId, ApiKeyIndex, BaseQty, MarketId, Nonce, Price},
     ws_client::{AccountEventEnvelope, OrderBookLevel, WsConnection, WsEvent, WsStream},
+    PositionBook, TypedAccountEvent,
 };
 use std::{
     collections::{HashMap, VecDeque},
@@ -1445,7 +1447,7 @@ async fn main() -> Result<()> {
     let mut tob_guard = TobGuard::new();
     let mut last_rest_refresh: Option<Instant> = None;
     let mut tracked_orders: HashMap<i64, OrderTrackerEntry> = HashMap::new();
-    let mut inventory: f64 = 0.0;
+    let positions = PositionBook::new(AccountId::new(account_index));
     let mut client_order_seq: i64 = 1;
     let mut active_orders: ActiveOrders = ActiveOrders::default();
     let mut pending_orders: PendingOrders = PendingOrders::default();
@@ -1700,6 +1702,8 @@ async fn main() -> Result<()> {
                                 CrashSignal::None => {}
                             }
 
+                            let inventory = positions.size(MarketId::new(MARKET_ID));
+
                             if crash_mode && crash_trim_pending && !dry_run {
                                 match submit_crash_trim_orders(
                                     &client,
@@ -1908,16 +1912,22 @@ async fn main() -> Result<()> {
                                     if simulation_counter % 10 == 0 {
                                         // Build short position to test ask widening
                                         let fill_size = 0.001;  // 0.001 BTC per fill
-                                        inventory -= fill_size;  // Always sell to build short position
-
-                                        // Cap for safety
-                                        inventory = inventory.clamp(-HARD_POS_CAP, HARD_POS_CAP);
+                                        // Always sell to build short position, capped for safety
+                                        let simulated =
+                                            (inventory - fill_size).clamp(-HARD_POS_CAP, HARD_POS_CAP);
+                                        positions.on_position(&models::AccountPosition {
+                                            market_id: MARKET_ID,
+                                            sign: if simulated < 0.0 { -1 } else { 1 },
+                                            position: simulated.abs().to_string(),
+                                            avg_entry_price: mark.to_string(),
+                                            ..Default::default()
+                                        });
 
                                         log_action(&format!(
                                             "🎮  SIMULATED FILL: SELL {:.4} BTC | New inventory: {:.6} BTC ({:.1}% of soft cap)",
                                             fill_size,
-                                            inventory,
-                                            (inventory.abs() / SOFT_POS_CAP) * 100.0
+                                            simulated,
+                                            (simulated.abs() / SOFT_POS_CAP) * 100.0
                                         ));
 
                                         // Also trigger Hawkes intensity
@@ -2181,7 +2191,7 @@ async fn main() -> Result<()> {
                                 &mut hawkes_bid,
                                 &mut hawkes_ask,
                                 &mut tracked_orders,
-                                &mut inventory,
+                                &positions,
                                 &mut active_orders,
                                 &mut pending_orders,
                                 &mut markout_tracker,
@@ -2377,7 +2387,7 @@ fn process_account_event(
     hawkes_bid: &mut HawkesSide,
     hawkes_ask: &mut HawkesSide,
     tracker: &mut HashMap<i64, OrderTrackerEntry>,
-    inventory: &mut f64,
+    positions: &PositionBook,
     active_orders: &mut ActiveOrders,
     pending: &mut PendingOrders,
     markouts: &mut MarkOutTracker,
@@ -2388,8 +2398,8 @@ fn process_account_event(
 ) {
     let value = envelope.event.as_value();
 
-    if let Some(positions_obj) = value.get("positions").and_then(|v| v.as_object()) {
-        sync_inventory_from_positions(positions_obj, inventory);
+    if value.get("positions").is_some() {
+        sync_inventory_from_positions(value, positions);
     }
 
     if value.get("orders").is_some() {
@@ -2399,7 +2409,6 @@ fn process_account_event(
             hawkes_bid,
             hawkes_ask,
             tracker,
-            inventory,
             active_orders,
             pending,
             markouts,
@@ -2417,7 +2426,6 @@ fn process_orders_payload(
     hawkes_bid: &mut HawkesSide,
     hawkes_ask: &mut HawkesSide,
     tracker: &mut HashMap<i64, OrderTrackerEntry>,
-    inventory: &mut f64,
     active_orders: &mut ActiveOrders,
     pending: &mut PendingOrders,
     markouts: &mut MarkOutTracker,
@@ -2506,13 +2514,10 @@ fn process_orders_payload(
                     let fill = entry.remaining - remaining;
                     match entry.side {
                         QuoteSide::Bid => {
-                            *inventory += fill;
                             hawkes_bid.on_event(now_secs);
                             log_action(&format!(
-                                "🟣 acct-fill BID Δ={fill:.6} λb={:.2} | inv: {:.6} -> {:.6}",
-                                hawkes_bid.value(now_secs),
-                                *inventory - fill, // Show before
-                                *inventory         // Show after
+                                "🟣 acct-fill BID Δ={fill:.6} λb={:.2}",
+                                hawkes_bid.value(now_secs)
                             ));
                             markouts.on_fill(
                                 now_instant,
@@ -2523,13 +2528,10 @@ fn process_orders_payload(
                             telemetry.on_fill(now_instant, fill, QuoteSide::Bid);
                         }
                         QuoteSide::Ask => {
-                            *inventory -= fill;
                             hawkes_ask.on_event(now_secs);
                             log_action(&format!(
-                                "🟢 acct-fill ASK Δ={fill:.6} λa={:.2} | inv: {:.6} -> {:.6}",
-                                hawkes_ask.value(now_secs),
-                                *inventory + fill, // Show before
-                                *inventory         // Show after
+                                "🟢 acct-fill ASK Δ={fill:.6} λa={:.2}",
+                                hawkes_ask.value(now_secs)
                             ));
                             markouts.on_fill(
                                 now_instant,
@@ -2571,14 +2573,12 @@ fn process_orders_payload(
     for (order_index, fill, side, price_ticks) in vanished {
         match side {
             QuoteSide::Bid => {
-                *inventory += fill;
                 hawkes_bid.on_event(now_secs);
                 markouts.on_fill(now_instant, price_ticks as f64 * tick_size, QuoteSide::Bid);
                 breaker.on_fill(QuoteSide::Bid, now_instant);
                 telemetry.on_fill(now_instant, fill, QuoteSide::Bid);
             }
             QuoteSide::Ask => {
-                *inventory -= fill;
                 hawkes_ask.on_event(now_secs);
                 markouts.on_fill(now_instant, price_ticks as f64 * tick_size, QuoteSide::Ask);
                 breaker.on_fill(QuoteSide::Ask, now_instant);
@@ -2622,66 +2622,23 @@ fn process_orders_payload(
     }
 }
 
-fn sync_inventory_from_positions(
-    positions_obj: &serde_json::Map<String, serde_json::Value>,
-    inventory: &mut f64,
-) {
-    let matched = positions_obj.get(&MARKET_ID.to_string()).or_else(|| {
-        positions_obj.values().find(|val| {
-            val.get("market_id")
-                .and_then(|v| v.as_i64())
-                .map(|id| id == MARKET_ID as i64)
-                .unwrap_or(false)
-        })
-    });
-
-    let Some(pos_value) = matched else {
-        return;
-    };
-
-    let position_raw = pos_value
-        .get("position")
-        .and_then(|v| {
-            v.as_str()
-                .and_then(|s| s.parse::<f64>().ok())
-                .or_else(|| v.as_f64())
-        })
-        .unwrap_or(0.0);
-
-    let sign_hint = pos_value
-        .get("sign")
-        .and_then(|v| v.as_i64())
-        .map(|s| s.clamp(-1, 1));
-
-    let side_hint = pos_value
-        .get("side")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_ascii_lowercase());
-
-    let derived_sign = sign_hint.filter(|s| *s != 0).or_else(|| {
-        side_hint.as_deref().map(|side| match side {
-            "sell" | "short" => -1,
-            "buy" | "long" => 1,
-            _ => 0,
-        })
-    });
-
-    let new_inventory = if let Some(sign) = derived_sign {
-        position_raw.abs() * sign as f64
-    } else {
-        position_raw
+fn sync_inventory_from_positions(value: &serde_json::Value, positions: &PositionBook) {
+    let event = match TypedAccountEvent::decode(value) {
+        Ok(event) => event,
+        Err(err) => {
+            log_action(&format!("⚠️  Failed to decode positions update: {err}"));
+            return;
+        }
     };
+    let market = MarketId::new(MARKET_ID);
+    let before = positions.size(market);
+    positions.apply(&event);
+    let after = positions.size(market);
 
-    if (new_inventory - *inventory).abs() > 1e-9 {
-        log_action(&format!(
-            "ℹ️  Synced inventory from positions feed: {:.6} (was {:.6})",
-            new_inventory, *inventory
-        ));
+    if (after - before).abs() > 1e-9 {
         log_action(&format!(
-            "ℹ️  Position payload snapshot: position_raw={position_raw:.6}, sign_hint={:?}, side_hint={:?}",
-            sign_hint, side_hint
+            "ℹ️  Synced inventory from positions feed: {after:.6} (was {before:.6})"
         ));
-        *inventory = new_inventory;
     }
 }
 
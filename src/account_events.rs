@@ -0,0 +1,531 @@
+//! Typed views over the private account websocket channels.
+//!
+//! [`AccountEvent`](crate::ws_client::AccountEvent) keeps the raw JSON for
+//! every account channel message. [`AccountEvent::decode`](crate::ws_client::AccountEvent::decode)
+//! turns it into a [`TypedAccountEvent`] whose payloads reuse the REST models
+//! (`models::Order`, `models::AccountPosition`, `models::Trade`, ...), so a
+//! field the server stops sending surfaces as a decode error instead of a
+//! silent `None` deep inside a strategy.
+
+use std::collections::BTreeMap;
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    errors::{DecodeResult, WsClientError},
+    models,
+    types::{AccountId, MarketId},
+    ws_client::TradeSide,
+};
+
+/// Fee rates on trades are reported in millionths of notional.
+pub(crate) const FEE_RATE_SCALE: f64 = 1_000_000.0;
+/// `account_tx` status of a transaction that failed to execute.
+pub(crate) const TX_STATUS_FAILED: i64 = 0;
+/// `account_tx` status of a successfully executed transaction.
+pub(crate) const TX_STATUS_SUCCESS: i64 = 1;
+
+/// Account scoped websocket channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AccountChannel {
+    /// `account_all/{account}`
+    AccountAll,
+    /// `account_market/{market}/{account}`
+    AccountMarket,
+    /// `account_all_orders/{account}`
+    AllOrders,
+    /// `account_orders/{market}/{account}`
+    MarketOrders,
+    /// `account_all_trades/{account}`
+    AllTrades,
+    /// `account_trades/{market}/{account}`
+    MarketTrades,
+    /// `account_all_positions/{account}`
+    AllPositions,
+    /// `account_positions/{market}/{account}`
+    MarketPositions,
+    /// `user_stats/{account}`
+    UserStats,
+    /// `account_tx/{account}`
+    AccountTx,
+    /// `pool_data/{account}`
+    PoolData,
+    /// `pool_info/{account}`
+    PoolInfo,
+    /// `notification/{account}`
+    Notification,
+}
+
+impl AccountChannel {
+    /// Classify a channel string such as `account_all_orders:42` or
+    /// `account_orders/1/42`.
+    pub fn from_channel(channel: &str) -> Option<Self> {
+        let name = channel.split(['/', ':']).next()?;
+        Some(match name {
+            "account_all" => Self::AccountAll,
+            "account_market" => Self::AccountMarket,
+            "account_all_orders" => Self::AllOrders,
+            "account_orders" => Self::MarketOrders,
+            "account_all_trades" => Self::AllTrades,
+            "account_trades" => Self::MarketTrades,
+            "account_all_positions" => Self::AllPositions,
+            "account_positions" => Self::MarketPositions,
+            "user_stats" => Self::UserStats,
+            "account_tx" => Self::AccountTx,
+            "pool_data" => Self::PoolData,
+            "pool_info" => Self::PoolInfo,
+            "notification" => Self::Notification,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AccountAll => "account_all",
+            Self::AccountMarket => "account_market",
+            Self::AllOrders => "account_all_orders",
+            Self::MarketOrders => "account_orders",
+            Self::AllTrades => "account_all_trades",
+            Self::MarketTrades => "account_trades",
+            Self::AllPositions => "account_all_positions",
+            Self::MarketPositions => "account_positions",
+            Self::UserStats => "user_stats",
+            Self::AccountTx => "account_tx",
+            Self::PoolData => "pool_data",
+            Self::PoolInfo => "pool_info",
+            Self::Notification => "notification",
+        }
+    }
+}
+
+/// Decoded account channel message.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TypedAccountEvent {
+    AccountAll(AccountAllUpdate),
+    AccountMarket(AccountMarketUpdate),
+    AllOrders(OrdersUpdate),
+    MarketOrders(OrdersUpdate),
+    AllTrades(TradesUpdate),
+    MarketTrades(TradesUpdate),
+    AllPositions(PositionsUpdate),
+    MarketPositions(PositionsUpdate),
+    UserStats(UserStatsUpdate),
+    AccountTx(AccountTxUpdate),
+    PoolData(PoolDataUpdate),
+    PoolInfo(PoolInfoUpdate),
+    Notification(NotificationUpdate),
+}
+
+impl TypedAccountEvent {
+    /// Decode a raw account channel message (as returned by
+    /// [`AccountEvent::as_value`](crate::ws_client::AccountEvent::as_value)).
+    pub fn decode(value: &Value) -> DecodeResult<Self> {
+        let channel = value
+            .get("channel")
+            .and_then(Value::as_str)
+            .ok_or_else(|| {
+                Box::new(WsClientError::InvalidChannel("missing channel".to_string()))
+            })?;
+        let kind = AccountChannel::from_channel(channel)
+            .ok_or_else(|| Box::new(WsClientError::InvalidChannel(channel.to_string())))?;
+
+        Ok(match kind {
+            AccountChannel::AccountAll => Self::AccountAll(decode_as(kind, value)?),
+            AccountChannel::AccountMarket => Self::AccountMarket(decode_as(kind, value)?),
+            AccountChannel::AllOrders => Self::AllOrders(decode_as(kind, value)?),
+            AccountChannel::MarketOrders => Self::MarketOrders(decode_as(kind, value)?),
+            AccountChannel::AllTrades => Self::AllTrades(decode_as(kind, value)?),
+            AccountChannel::MarketTrades => Self::MarketTrades(decode_as(kind, value)?),
+            AccountChannel::AllPositions => Self::AllPositions(decode_as(kind, value)?),
+            AccountChannel::MarketPositions => Self::MarketPositions(decode_as(kind, value)?),
+            AccountChannel::UserStats => Self::UserStats(decode_as(kind, value)?),
+            AccountChannel::AccountTx => Self::AccountTx(decode_as(kind, value)?),
+            AccountChannel::PoolData => Self::PoolData(decode_as(kind, value)?),
+            AccountChannel::PoolInfo => Self::PoolInfo(decode_as(kind, value)?),
+            AccountChannel::Notification => Self::Notification(decode_as(kind, value)?),
+        })
+    }
+
+    pub fn channel(&self) -> AccountChannel {
+        match self {
+            Self::AccountAll(_) => AccountChannel::AccountAll,
+            Self::AccountMarket(_) => AccountChannel::AccountMarket,
+            Self::AllOrders(_) => AccountChannel::AllOrders,
+            Self::MarketOrders(_) => AccountChannel::MarketOrders,
+            Self::AllTrades(_) => AccountChannel::AllTrades,
+            Self::MarketTrades(_) => AccountChannel::MarketTrades,
+            Self::AllPositions(_) => AccountChannel::AllPositions,
+            Self::MarketPositions(_) => AccountChannel::MarketPositions,
+            Self::UserStats(_) => AccountChannel::UserStats,
+            Self::AccountTx(_) => AccountChannel::AccountTx,
+            Self::PoolData(_) => AccountChannel::PoolData,
+            Self::PoolInfo(_) => AccountChannel::PoolInfo,
+            Self::Notification(_) => AccountChannel::Notification,
+        }
+    }
+}
+
+fn decode_as<T: DeserializeOwned>(channel: AccountChannel, value: &Value) -> DecodeResult<T> {
+    T::deserialize(value).map_err(|source| {
+        Box::new(WsClientError::AccountDecode {
+            channel: channel.as_str(),
+            source,
+        })
+    })
+}
+
+/// `account_all` payload: positions, trades, shares and funding across markets.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountAllUpdate {
+    pub account: i64,
+    #[serde(default)]
+    pub daily_trades_count: Option<i64>,
+    #[serde(default)]
+    pub weekly_trades_count: Option<i64>,
+    #[serde(default)]
+    pub monthly_trades_count: Option<i64>,
+    #[serde(default)]
+    pub total_trades_count: Option<i64>,
+    #[serde(default)]
+    pub daily_volume: Option<f64>,
+    #[serde(default)]
+    pub weekly_volume: Option<f64>,
+    #[serde(default)]
+    pub monthly_volume: Option<f64>,
+    #[serde(default)]
+    pub total_volume: Option<f64>,
+    pub positions: BTreeMap<i32, models::AccountPosition>,
+    pub trades: BTreeMap<i32, Vec<models::Trade>>,
+    #[serde(default)]
+    pub shares: Vec<models::PublicPoolShare>,
+    #[serde(default)]
+    pub funding_histories: BTreeMap<i32, Vec<models::PositionFunding>>,
+}
+
+/// `account_market` payload: orders, position, trades and funding for one market.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountMarketUpdate {
+    pub account: i64,
+    pub orders: Vec<models::Order>,
+    #[serde(default)]
+    pub position: Option<models::AccountPosition>,
+    #[serde(default)]
+    pub trades: Vec<models::Trade>,
+    #[serde(default)]
+    pub funding_history: Option<models::PositionFunding>,
+}
+
+/// `account_all_orders` / `account_orders` payload, keyed by market index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OrdersUpdate {
+    #[serde(default)]
+    pub account: Option<i64>,
+    #[serde(default)]
+    pub nonce: Option<i64>,
+    pub orders: BTreeMap<i32, Vec<models::Order>>,
+}
+
+impl OrdersUpdate {
+    /// Orders reported for a market (empty if the market is absent).
+    pub fn orders_for(&self, market: MarketId) -> &[models::Order] {
+        self.orders
+            .get(&market.into_inner())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &models::Order> {
+        self.orders.values().flatten()
+    }
+}
+
+/// `account_all_trades` / `account_trades` payload, keyed by market index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TradesUpdate {
+    pub trades: BTreeMap<i32, Vec<models::Trade>>,
+    #[serde(default)]
+    pub daily_volume: Option<f64>,
+    #[serde(default)]
+    pub weekly_volume: Option<f64>,
+    #[serde(default)]
+    pub monthly_volume: Option<f64>,
+    #[serde(default)]
+    pub total_volume: Option<f64>,
+}
+
+impl TradesUpdate {
+    /// Trades reported for a market (empty if the market is absent).
+    pub fn trades_for(&self, market: MarketId) -> &[models::Trade] {
+        self.trades
+            .get(&market.into_inner())
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &models::Trade> {
+        self.trades.values().flatten()
+    }
+}
+
+/// Side `account` took in `trade`, or `None` if it was not a counterparty.
+pub fn trade_side(trade: &models::Trade, account: AccountId) -> Option<TradeSide> {
+    let account = account.into_inner();
+    if trade.ask_account_id == account {
+        Some(TradeSide::Sell)
+    } else if trade.bid_account_id == account {
+        Some(TradeSide::Buy)
+    } else {
+        None
+    }
+}
+
+/// `account_all_positions` / `account_positions` payload, keyed by market index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PositionsUpdate {
+    pub positions: BTreeMap<i32, models::AccountPosition>,
+    #[serde(default)]
+    pub shares: Vec<models::PublicPoolShare>,
+}
+
+impl PositionsUpdate {
+    pub fn position(&self, market: MarketId) -> Option<&models::AccountPosition> {
+        self.positions.get(&market.into_inner())
+    }
+}
+
+/// `user_stats` payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UserStatsUpdate {
+    pub stats: models::AccountStats,
+}
+
+/// `account_tx` payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AccountTxUpdate {
+    pub txs: Vec<models::Tx>,
+}
+
+/// `pool_data` payload: complete trading state of a public pool account.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoolDataUpdate {
+    pub account: i64,
+    pub trades: BTreeMap<i32, Vec<models::Trade>>,
+    pub orders: BTreeMap<i32, Vec<models::Order>>,
+    pub positions: BTreeMap<i32, models::AccountPosition>,
+    #[serde(default)]
+    pub shares: Vec<models::PublicPoolShare>,
+    #[serde(default)]
+    pub funding_histories: BTreeMap<i32, Vec<models::PositionFunding>>,
+}
+
+/// `pool_info` payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PoolInfoUpdate {
+    pub pool_info: models::PublicPoolInfo,
+}
+
+/// `notification` payload.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NotificationUpdate {
+    pub notifs: Vec<Notification>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    Liquidation,
+    Deleverage,
+    Announcement,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: i64,
+    pub kind: NotificationKind,
+    #[serde(default)]
+    pub created_at: Option<i64>,
+    #[serde(default)]
+    pub updated_at: Option<i64>,
+    #[serde(default)]
+    pub account_index: Option<i64>,
+    #[serde(default)]
+    pub content: Value,
+    #[serde(default)]
+    pub ack: bool,
+    #[serde(default)]
+    pub acked_at: Option<i64>,
+}
+
+/// Decoded notification content.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NotificationContent {
+    Liquidation(LiquidationNotice),
+    Deleverage(DeleverageNotice),
+    Announcement(Announcement),
+    Other(Value),
+}
+
+impl Notification {
+    /// Decode `content` according to `kind`.
+    pub fn decode_content(&self) -> DecodeResult<NotificationContent> {
+        let content = &self.content;
+        Ok(match self.kind {
+            NotificationKind::Liquidation => {
+                NotificationContent::Liquidation(decode_as(AccountChannel::Notification, content)?)
+            }
+            NotificationKind::Deleverage => {
+                NotificationContent::Deleverage(decode_as(AccountChannel::Notification, content)?)
+            }
+            NotificationKind::Announcement => {
+                NotificationContent::Announcement(decode_as(AccountChannel::Notification, content)?)
+            }
+            NotificationKind::Other => NotificationContent::Other(content.clone()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LiquidationNotice {
+    pub id: i64,
+    pub is_ask: bool,
+    pub usdc_amount: String,
+    pub size: String,
+    pub market_index: i32,
+    pub price: String,
+    pub timestamp: i64,
+    #[serde(default)]
+    pub avg_price: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeleverageNotice {
+    pub id: i64,
+    pub usdc_amount: String,
+    pub size: String,
+    pub market_index: i32,
+    pub settlement_price: String,
+    pub timestamp: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Announcement {
+    pub title: String,
+    pub content: String,
+    #[serde(default)]
+    pub created_at: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn order(market: i32, index: i64) -> Value {
+        let mut value = serde_json::to_value(models::Order::default()).unwrap();
+        value["market_index"] = json!(market);
+        value["order_index"] = json!(index);
+        value
+    }
+
+    #[test]
+    fn test_channel_classification() {
+        assert_eq!(
+            AccountChannel::from_channel("account_all_orders:42"),
+            Some(AccountChannel::AllOrders)
+        );
+        assert_eq!(
+            AccountChannel::from_channel("account_trades/1/42"),
+            Some(AccountChannel::MarketTrades)
+        );
+        assert_eq!(AccountChannel::from_channel("order_book/1"), None);
+    }
+
+    #[test]
+    fn test_decode_orders_by_market() {
+        let value = json!({
+            "channel": "account_all_orders:42",
+            "orders": { "1": [order(1, 7), order(1, 8)], "2": [order(2, 9)] },
+        });
+        let TypedAccountEvent::AllOrders(update) = TypedAccountEvent::decode(&value).unwrap()
+        else {
+            panic!("expected AllOrders");
+        };
+        assert_eq!(update.orders_for(MarketId(1)).len(), 2);
+        assert_eq!(update.orders_for(MarketId(3)).len(), 0);
+        assert_eq!(update.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_decode_trades_and_side() {
+        let mut trade = serde_json::to_value(models::Trade::default()).unwrap();
+        trade["ask_account_id"] = json!(42);
+        trade["bid_account_id"] = json!(7);
+        let value = json!({
+            "channel": "account_all_trades:42",
+            "trades": { "0": [trade] },
+            "daily_volume": 12.5,
+        });
+        let TypedAccountEvent::AllTrades(update) = TypedAccountEvent::decode(&value).unwrap()
+        else {
+            panic!("expected AllTrades");
+        };
+        assert_eq!(update.daily_volume, Some(12.5));
+        let trade = &update.trades_for(MarketId(0))[0];
+        assert_eq!(trade_side(trade, AccountId(42)), Some(TradeSide::Sell));
+        assert_eq!(trade_side(trade, AccountId(7)), Some(TradeSide::Buy));
+        assert_eq!(trade_side(trade, AccountId(1)), None);
+    }
+
+    #[test]
+    fn test_schema_drift_is_an_error() {
+        let mut position = serde_json::to_value(models::AccountPosition::default()).unwrap();
+        position.as_object_mut().unwrap().remove("sign");
+        let value = json!({
+            "channel": "account_all_positions:42",
+            "positions": { "0": position },
+        });
+        match TypedAccountEvent::decode(&value).map_err(|err| *err) {
+            Err(WsClientError::AccountDecode { channel, .. }) => {
+                assert_eq!(channel, "account_all_positions")
+            }
+            other => panic!("expected decode error, got {other:?}"),
+        }
+
+        // The collection that defines a channel is required, not defaulted.
+        let value = json!({ "channel": "account_all_trades:42", "daily_volume": 1.0 });
+        assert!(matches!(
+            TypedAccountEvent::decode(&value).map_err(|err| *err),
+            Err(WsClientError::AccountDecode {
+                channel: "account_all_trades",
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_notification_content() {
+        let value = json!({
+            "channel": "notification:42",
+            "notifs": [
+                {
+                    "id": 1,
+                    "kind": "announcement",
+                    "content": { "title": "Upgrade", "content": "Maintenance" }
+                },
+                { "id": 2, "kind": "something_new", "content": { "x": 1 } }
+            ]
+        });
+        let TypedAccountEvent::Notification(update) = TypedAccountEvent::decode(&value).unwrap()
+        else {
+            panic!("expected Notification");
+        };
+        assert!(matches!(
+            update.notifs[0].decode_content().unwrap(),
+            NotificationContent::Announcement(Announcement { ref title, .. }) if title == "Upgrade"
+        ));
+        assert_eq!(update.notifs[1].kind, NotificationKind::Other);
+    }
+}
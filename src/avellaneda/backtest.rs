@@ -0,0 +1,772 @@
+//! Event-driven backtests for [`AvellanedaStrategy`].
+//!
+//! A [`Backtest`] feeds historical book and trade events through the
+//! strategy on a simulated clock, quoting on book updates at the same
+//! `refresh_interval_ms` cadence as the live loop. Resting quotes join the
+//! back of the queue at their price and fill against recorded trades once
+//! the volume ahead of them has traded, or immediately when the book moves
+//! through them. Fills pay `maker_fee_bps` and are marked out against the
+//! mid a fixed horizon later.
+//!
+//! Events come from a [`MarketRecorder`](crate::ws_recorder::MarketRecorder)
+//! recording via [`events_from_recording`], or are synthesised from price
+//! candles with [`events_from_candles`].
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    path::Path,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::Serialize;
+
+use super::{
+    config::AvellanedaConfig,
+    strategy::AvellanedaStrategy,
+    types::{FillEvent, FillSide, QuoteOrder, StrategyDecision},
+};
+use crate::{
+    clock::ReplayClock,
+    errors::RecorderError,
+    models::Candlestick,
+    types::MarketId,
+    ws_client::{OrderBookLevel, OrderBookState, WsEvent},
+    ws_recorder::{ReplaySpeed, ReplayStream},
+};
+
+/// Sizes and prices closer than this are treated as equal.
+const EPSILON: f64 = 1e-12;
+/// Candle spacing assumed when a series has a single candle.
+const DEFAULT_CANDLE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Simulation settings that are not part of the strategy config.
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    pub initial_base: f64,
+    pub initial_quote: f64,
+    /// Delay between a decision and its quotes resting on the book.
+    pub latency: Duration,
+    /// Price grid quotes are rounded to (bids down, asks up); `0.0` disables
+    /// rounding.
+    pub tick_size: f64,
+    /// How long after a fill its markout is measured.
+    pub markout_horizon: Duration,
+    /// Spacing of inventory path samples between fills.
+    pub sample_interval: Duration,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        Self {
+            initial_base: 0.0,
+            initial_quote: 10_000.0,
+            latency: Duration::from_millis(50),
+            tick_size: 0.0,
+            markout_horizon: Duration::from_secs(1),
+            sample_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// One historical market data update.
+#[derive(Debug, Clone)]
+pub struct BacktestEvent {
+    pub at: SystemTime,
+    pub kind: BacktestEventKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum BacktestEventKind {
+    /// Full book after the update.
+    Book(OrderBookState),
+    Trade(HistoricalTrade),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct HistoricalTrade {
+    pub price: f64,
+    pub size: f64,
+    /// Resting side the trade executed against: `Bid` for a taker sell.
+    pub maker_side: FillSide,
+}
+
+/// Book and trade events for `market` from a recording, timestamped with
+/// their receive time. Trade sides are read as the taker's side.
+pub async fn events_from_recording(
+    dir: impl AsRef<Path>,
+    market: MarketId,
+) -> Result<Vec<BacktestEvent>, RecorderError> {
+    let mut replay = ReplayStream::open(dir, ReplaySpeed::Unthrottled)?;
+    let mut events = Vec::new();
+    while let Some(event) = replay.next_event().await? {
+        let Some(at) = replay.position() else {
+            continue;
+        };
+        match event {
+            WsEvent::OrderBook(book) if book.market == market => events.push(BacktestEvent {
+                at,
+                kind: BacktestEventKind::Book(book.state),
+            }),
+            WsEvent::Trade(trade) => {
+                for data in trade
+                    .trades
+                    .iter()
+                    .filter(|data| data.market_id as i32 == market.into_inner())
+                {
+                    let (Ok(price), Ok(size)) = (data.price.parse(), data.base_size.parse()) else {
+                        continue;
+                    };
+                    let maker_side = if data.side.eq_ignore_ascii_case("sell") {
+                        FillSide::Bid
+                    } else {
+                        FillSide::Ask
+                    };
+                    events.push(BacktestEvent {
+                        at,
+                        kind: BacktestEventKind::Trade(HistoricalTrade {
+                            price,
+                            size,
+                            maker_side,
+                        }),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+/// Synthetic events from price candles, e.g. the result of
+/// [`CandlesHandle::price`](crate::lighter_client::CandlesHandle::price).
+///
+/// Each candle is walked open, low, high, close (open, high, low, close when
+/// it closed down) at evenly spaced times. Every point becomes a one-level
+/// book `spread_bps` wide with `depth` on each side, and every move a trade
+/// of a quarter of the candle's base volume against the side the price moved
+/// into. This is coarse: use recordings where queue dynamics matter.
+pub fn events_from_candles(
+    candles: &[Candlestick],
+    spread_bps: f64,
+    depth: f64,
+) -> Vec<BacktestEvent> {
+    let half_spread = spread_bps / 20_000.0;
+    let mut events = Vec::with_capacity(candles.len() * 7);
+    let mut interval = DEFAULT_CANDLE_INTERVAL;
+    for (idx, candle) in candles.iter().enumerate() {
+        if let Some(next) = candles.get(idx + 1) {
+            if next.timestamp > candle.timestamp {
+                interval = Duration::from_millis((next.timestamp - candle.timestamp) as u64);
+            }
+        }
+        let start = UNIX_EPOCH + Duration::from_millis(candle.timestamp.max(0) as u64);
+        let path = if candle.close >= candle.open {
+            [candle.open, candle.low, candle.high, candle.close]
+        } else {
+            [candle.open, candle.high, candle.low, candle.close]
+        };
+        let step = interval / path.len() as u32;
+        let trade_size = candle.volume0 / 4.0;
+        for (n, price) in path.iter().copied().enumerate() {
+            let at = start + step * n as u32;
+            if n > 0 && trade_size > 0.0 && price != path[n - 1] {
+                let maker_side = if price > path[n - 1] {
+                    FillSide::Ask
+                } else {
+                    FillSide::Bid
+                };
+                events.push(BacktestEvent {
+                    at,
+                    kind: BacktestEventKind::Trade(HistoricalTrade {
+                        price,
+                        size: trade_size,
+                        maker_side,
+                    }),
+                });
+            }
+            events.push(BacktestEvent {
+                at,
+                kind: BacktestEventKind::Book(OrderBookState {
+                    asks: vec![synthetic_level(price * (1.0 + half_spread), depth)],
+                    bids: vec![synthetic_level(price * (1.0 - half_spread), depth)],
+                }),
+            });
+        }
+    }
+    events
+}
+
+fn synthetic_level(price: f64, size: f64) -> OrderBookLevel {
+    OrderBookLevel {
+        price: price.to_string(),
+        size: size.to_string(),
+        remaining_base_amount: None,
+        extra: Default::default(),
+    }
+}
+
+/// Per-side fill statistics.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SideReport {
+    pub fills: u64,
+    /// Base quantity quoted, summed over every quote placed.
+    pub quoted_volume: f64,
+    pub filled_volume: f64,
+    pub filled_notional: f64,
+    /// `filled_volume / quoted_volume`.
+    pub fill_ratio: f64,
+    pub avg_fill_price: Option<f64>,
+    /// Mean markout in bps after `markout_horizon`, positive when the fill
+    /// was profitable. Fills too close to the end of the data are excluded.
+    pub mean_markout_bps: Option<f64>,
+    pub markout_samples: u64,
+}
+
+/// Inventory and PnL at one point of the simulation.
+#[derive(Debug, Clone, Serialize)]
+pub struct InventoryPoint {
+    pub at: SystemTime,
+    pub base: f64,
+    pub quote: f64,
+    pub mid: f64,
+    pub pnl: f64,
+}
+
+/// Outcome of a [`Backtest`].
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestReport {
+    pub start: Option<SystemTime>,
+    pub end: Option<SystemTime>,
+    pub events: u64,
+    pub decisions: u64,
+    pub quotes_placed: u64,
+    /// Quotes dropped because they would have crossed the book.
+    pub post_only_rejects: u64,
+    pub bid: SideReport,
+    pub ask: SideReport,
+    /// Filled over quoted volume across both sides.
+    pub fill_ratio: f64,
+    pub fees_paid: f64,
+    /// Mark-to-market PnL at the last mid, net of fees.
+    pub pnl: f64,
+    /// Largest drop of the mark-to-market PnL from its running peak.
+    pub max_drawdown: f64,
+    pub final_base: f64,
+    pub final_quote: f64,
+    /// Markout kill switch engagements, both sides.
+    pub kill_switch_activations: u64,
+    pub global_pause_activations: u64,
+    /// `Skip` reasons returned by the strategy, with counts.
+    pub skips: BTreeMap<&'static str, u64>,
+    /// `Cancel` reasons returned by the strategy, with counts.
+    pub cancels: BTreeMap<&'static str, u64>,
+    pub inventory_path: Vec<InventoryPoint>,
+}
+
+#[derive(Debug, Clone)]
+struct RestingQuote {
+    price: f64,
+    remaining: f64,
+    /// Displayed size ahead of this quote at its price.
+    queue_ahead: f64,
+    active_from: Instant,
+}
+
+#[derive(Debug, Default)]
+struct SideStats {
+    report: SideReport,
+    markout_sum: f64,
+}
+
+/// Runs an [`AvellanedaStrategy`] over historical events.
+///
+/// The strategy reads the time from a [`ReplayClock`] that follows event
+/// timestamps, so its horizon, pauses and kill switches run on event time.
+/// The strategy's per-fill CSV log is disabled; re-enable it through
+/// [`strategy_mut`](Self::strategy_mut) if needed.
+pub struct Backtest {
+    config: BacktestConfig,
+    strategy: AvellanedaStrategy,
+    min_refresh: Duration,
+    maker_fee: f64,
+    clock: ReplayClock,
+    now: Option<Instant>,
+    at: Option<SystemTime>,
+    last_decision: Option<Instant>,
+    last_sample: Option<Instant>,
+    book: Option<OrderBookState>,
+    mid: Option<f64>,
+    bid: Option<RestingQuote>,
+    ask: Option<RestingQuote>,
+    base: f64,
+    quote: f64,
+    initial_value: Option<f64>,
+    peak_pnl: f64,
+    pending_markouts: VecDeque<(Instant, FillSide, f64)>,
+    sides: [SideStats; 2],
+    report: BacktestReport,
+}
+
+impl Backtest {
+    pub fn new(strategy_config: AvellanedaConfig, config: BacktestConfig) -> Self {
+        let min_refresh = Duration::from_millis(strategy_config.refresh_interval_ms.max(20));
+        let maker_fee = strategy_config.maker_fee_bps / 10_000.0;
+        let clock = ReplayClock::new();
+        let mut strategy = AvellanedaStrategy::with_clock(strategy_config, clock.shared());
+        strategy.set_fill_log_path(None);
+        strategy.update_balances(config.initial_base, config.initial_quote);
+        Self {
+            base: config.initial_base,
+            quote: config.initial_quote,
+            config,
+            strategy,
+            min_refresh,
+            maker_fee,
+            clock,
+            now: None,
+            at: None,
+            last_decision: None,
+            last_sample: None,
+            book: None,
+            mid: None,
+            bid: None,
+            ask: None,
+            initial_value: None,
+            peak_pnl: 0.0,
+            pending_markouts: VecDeque::new(),
+            sides: Default::default(),
+            report: BacktestReport::default(),
+        }
+    }
+
+    pub fn strategy(&self) -> &AvellanedaStrategy {
+        &self.strategy
+    }
+
+    pub fn strategy_mut(&mut self) -> &mut AvellanedaStrategy {
+        &mut self.strategy
+    }
+
+    /// Feed every event in order and report.
+    pub fn run(mut self, events: impl IntoIterator<Item = BacktestEvent>) -> BacktestReport {
+        for event in events {
+            self.on_event(&event);
+        }
+        self.finish()
+    }
+
+    /// Advance the simulated clock to `event.at` and apply the event.
+    /// Events older than the last one are applied at the current time.
+    pub fn on_event(&mut self, event: &BacktestEvent) {
+        let now = self.advance(event.at);
+        self.report.events += 1;
+        match &event.kind {
+            BacktestEventKind::Book(book) => self.on_book(book, now),
+            BacktestEventKind::Trade(trade) => self.on_trade(trade, now),
+        }
+    }
+
+    pub fn finish(mut self) -> BacktestReport {
+        let participation = self.strategy.participation_metrics();
+        let mut report = std::mem::take(&mut self.report);
+        report.end = self.at;
+        report.final_base = self.base;
+        report.final_quote = self.quote;
+        report.pnl = self.pnl().unwrap_or(0.0);
+        report.kill_switch_activations =
+            participation.kill_activations_bid + participation.kill_activations_ask;
+        report.global_pause_activations = participation.global_pause_activations;
+
+        let [bid, ask] = std::mem::take(&mut self.sides);
+        report.bid = bid.finish();
+        report.ask = ask.finish();
+        let quoted = report.bid.quoted_volume + report.ask.quoted_volume;
+        if quoted > 0.0 {
+            report.fill_ratio = (report.bid.filled_volume + report.ask.filled_volume) / quoted;
+        }
+        if let (Some(at), Some(now), Some(mid)) = (self.at, self.now, self.mid) {
+            if self.last_sample != Some(now) {
+                report.inventory_path.push(self.inventory_point(at, mid));
+            }
+        }
+        report
+    }
+
+    fn advance(&mut self, at: SystemTime) -> Instant {
+        let now = self.clock.observe(at);
+        self.report.start.get_or_insert(at);
+        self.now = Some(now);
+        self.at = Some(self.at.map_or(at, |last| last.max(at)));
+        now
+    }
+
+    fn on_book(&mut self, book: &OrderBookState, now: Instant) {
+        let (Some(best_bid), Some(best_ask)) = (top_price(&book.bids), top_price(&book.asks))
+        else {
+            self.book = Some(book.clone());
+            return;
+        };
+        let mid = 0.5 * (best_bid + best_ask);
+        self.mid = Some(mid);
+        self.initial_value
+            .get_or_insert(self.config.initial_base * mid + self.config.initial_quote);
+
+        // The book trading through a resting quote fills all of it.
+        if let Some(bid) = &self.bid {
+            if now >= bid.active_from && best_ask <= bid.price {
+                let (price, size) = (bid.price, bid.remaining);
+                self.fill(FillSide::Bid, price, size, now);
+            }
+        }
+        if let Some(ask) = &self.ask {
+            if now >= ask.active_from && best_bid >= ask.price {
+                let (price, size) = (ask.price, ask.remaining);
+                self.fill(FillSide::Ask, price, size, now);
+            }
+        }
+        // Displayed size shrinking below our queue position means orders
+        // ahead of us were cancelled or filled.
+        if let Some(bid) = self.bid.as_mut() {
+            bid.queue_ahead = bid.queue_ahead.min(level_size(&book.bids, bid.price));
+        }
+        if let Some(ask) = self.ask.as_mut() {
+            ask.queue_ahead = ask.queue_ahead.min(level_size(&book.asks, ask.price));
+        }
+
+        while let Some(&(due, side, price)) = self.pending_markouts.front() {
+            if due > now {
+                break;
+            }
+            self.pending_markouts.pop_front();
+            let sign = match side {
+                FillSide::Bid => 1.0,
+                FillSide::Ask => -1.0,
+            };
+            let stats = &mut self.sides[side_index(side)];
+            stats.markout_sum += sign * (mid - price) / price * 10_000.0;
+            stats.report.markout_samples += 1;
+        }
+
+        self.track_pnl(now);
+        self.book = Some(book.clone());
+
+        if self
+            .last_decision
+            .is_some_and(|last| now.duration_since(last) < self.min_refresh)
+        {
+            return;
+        }
+        self.last_decision = Some(now);
+        self.report.decisions += 1;
+        let decision = self.strategy.on_order_book(book, now);
+        self.apply_decision(decision, now);
+    }
+
+    fn on_trade(&mut self, trade: &HistoricalTrade, now: Instant) {
+        let tolerance = trade.price.abs() * 1e-9;
+        let quote = match trade.maker_side {
+            FillSide::Bid => self.bid.as_mut(),
+            FillSide::Ask => self.ask.as_mut(),
+        };
+        let Some(quote) = quote.filter(|quote| now >= quote.active_from) else {
+            return;
+        };
+        let through = match trade.maker_side {
+            FillSide::Bid => trade.price < quote.price - tolerance,
+            FillSide::Ask => trade.price > quote.price + tolerance,
+        };
+        let size = if through {
+            trade.size.min(quote.remaining)
+        } else if (trade.price - quote.price).abs() <= tolerance {
+            let reached = (trade.size - quote.queue_ahead).max(0.0);
+            quote.queue_ahead = (quote.queue_ahead - trade.size).max(0.0);
+            reached.min(quote.remaining)
+        } else {
+            0.0
+        };
+        if size > EPSILON {
+            let price = quote.price;
+            self.fill(trade.maker_side, price, size, now);
+        }
+    }
+
+    fn apply_decision(&mut self, decision: StrategyDecision, now: Instant) {
+        match decision {
+            StrategyDecision::Skip(reason) => {
+                *self.report.skips.entry(reason).or_default() += 1;
+                return;
+            }
+            StrategyDecision::Cancel(reason) => {
+                *self.report.cancels.entry(reason).or_default() += 1;
+                self.bid = None;
+                self.ask = None;
+            }
+            StrategyDecision::Quote(pair) => {
+                self.place(FillSide::Bid, &pair.bid, now);
+                self.place(FillSide::Ask, &pair.ask, now);
+            }
+            StrategyDecision::QuoteBidOnly(order) => self.place(FillSide::Bid, &order, now),
+            StrategyDecision::QuoteAskOnly(order) => self.place(FillSide::Ask, &order, now),
+        }
+        self.strategy.record_quote();
+    }
+
+    /// Replace the quote on `side`. An unchanged quote keeps its place in
+    /// the queue.
+    fn place(&mut self, side: FillSide, order: &QuoteOrder, now: Instant) {
+        let price = round_to_tick(order.price, self.config.tick_size, side);
+        let slot = match side {
+            FillSide::Bid => &mut self.bid,
+            FillSide::Ask => &mut self.ask,
+        };
+        if order.size <= EPSILON || price <= 0.0 {
+            *slot = None;
+            return;
+        }
+        if slot.as_ref().is_some_and(|resting| {
+            (resting.price - price).abs() <= price * 1e-9
+                && (resting.remaining - order.size).abs() <= EPSILON
+        }) {
+            return;
+        }
+
+        let book = self.book.as_ref();
+        let crosses = match side {
+            FillSide::Bid => book
+                .and_then(|book| top_price(&book.asks))
+                .is_some_and(|ask| price >= ask),
+            FillSide::Ask => book
+                .and_then(|book| top_price(&book.bids))
+                .is_some_and(|bid| price <= bid),
+        };
+        if crosses {
+            *slot = None;
+            self.report.post_only_rejects += 1;
+            return;
+        }
+        let queue_ahead = book.map_or(0.0, |book| match side {
+            FillSide::Bid => level_size(&book.bids, price),
+            FillSide::Ask => level_size(&book.asks, price),
+        });
+        *slot = Some(RestingQuote {
+            price,
+            remaining: order.size,
+            queue_ahead,
+            active_from: now + self.config.latency,
+        });
+        self.report.quotes_placed += 1;
+        self.sides[side_index(side)].report.quoted_volume += order.size;
+    }
+
+    fn fill(&mut self, side: FillSide, price: f64, size: f64, now: Instant) {
+        let slot = match side {
+            FillSide::Bid => &mut self.bid,
+            FillSide::Ask => &mut self.ask,
+        };
+        if let Some(quote) = slot.as_mut() {
+            quote.remaining -= size;
+            if quote.remaining <= EPSILON {
+                *slot = None;
+            }
+        }
+
+        let notional = price * size;
+        let fee = notional * self.maker_fee;
+        match side {
+            FillSide::Bid => {
+                self.base += size;
+                self.quote -= notional;
+            }
+            FillSide::Ask => {
+                self.base -= size;
+                self.quote += notional;
+            }
+        }
+        self.quote -= fee;
+        self.report.fees_paid += fee;
+
+        let stats = &mut self.sides[side_index(side)].report;
+        stats.fills += 1;
+        stats.filled_volume += size;
+        stats.filled_notional += notional;
+
+        self.strategy.on_fill(&FillEvent {
+            side,
+            price,
+            size,
+            timestamp: now,
+        });
+        // Keep the strategy's balances net of fees.
+        self.strategy.update_balances(self.base, self.quote);
+        self.pending_markouts
+            .push_back((now + self.config.markout_horizon, side, price));
+        self.last_sample = None;
+        self.track_pnl(now);
+    }
+
+    fn pnl(&self) -> Option<f64> {
+        let mid = self.mid?;
+        Some(self.base * mid + self.quote - self.initial_value?)
+    }
+
+    fn track_pnl(&mut self, now: Instant) {
+        let (Some(pnl), Some(mid), Some(at)) = (self.pnl(), self.mid, self.at) else {
+            return;
+        };
+        self.peak_pnl = self.peak_pnl.max(pnl);
+        self.report.max_drawdown = self.report.max_drawdown.max(self.peak_pnl - pnl);
+        if self
+            .last_sample
+            .is_some_and(|last| now.duration_since(last) < self.config.sample_interval)
+        {
+            return;
+        }
+        self.last_sample = Some(now);
+        let point = self.inventory_point(at, mid);
+        self.report.inventory_path.push(point);
+    }
+
+    fn inventory_point(&self, at: SystemTime, mid: f64) -> InventoryPoint {
+        InventoryPoint {
+            at,
+            base: self.base,
+            quote: self.quote,
+            mid,
+            pnl: self.pnl().unwrap_or(0.0),
+        }
+    }
+}
+
+impl SideStats {
+    fn finish(self) -> SideReport {
+        let mut report = self.report;
+        if report.quoted_volume > 0.0 {
+            report.fill_ratio = report.filled_volume / report.quoted_volume;
+        }
+        if report.filled_volume > 0.0 {
+            report.avg_fill_price = Some(report.filled_notional / report.filled_volume);
+        }
+        if report.markout_samples > 0 {
+            report.mean_markout_bps = Some(self.markout_sum / report.markout_samples as f64);
+        }
+        report
+    }
+}
+
+fn side_index(side: FillSide) -> usize {
+    match side {
+        FillSide::Bid => 0,
+        FillSide::Ask => 1,
+    }
+}
+
+fn top_price(levels: &[OrderBookLevel]) -> Option<f64> {
+    levels.first()?.price.parse().ok()
+}
+
+fn level_size(levels: &[OrderBookLevel], price: f64) -> f64 {
+    levels
+        .iter()
+        .find(|level| {
+            level
+                .price
+                .parse::<f64>()
+                .is_ok_and(|level_price| (level_price - price).abs() <= price.abs() * 1e-9)
+        })
+        .and_then(|level| level.size.parse().ok())
+        .unwrap_or(0.0)
+}
+
+fn round_to_tick(price: f64, tick: f64, side: FillSide) -> f64 {
+    if tick <= 0.0 {
+        return price;
+    }
+    let ticks = price / tick;
+    match side {
+        FillSide::Bid => (ticks + 1e-9).floor() * tick,
+        FillSide::Ask => (ticks - 1e-9).ceil() * tick,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::avellaneda::config::test_config;
+
+    fn book(bid: f64, ask: f64, size: f64) -> OrderBookState {
+        OrderBookState {
+            bids: vec![synthetic_level(bid, size)],
+            asks: vec![synthetic_level(ask, size)],
+        }
+    }
+
+    fn at(ms: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(1_700_000_000_000 + ms)
+    }
+
+    #[test]
+    fn resting_bid_fills_after_the_queue_ahead_trades() {
+        let config = BacktestConfig {
+            latency: Duration::ZERO,
+            ..BacktestConfig::default()
+        };
+        let mut backtest = Backtest::new(test_config(), config);
+        let now = backtest.advance(at(0));
+        backtest.book = Some(book(99.0, 101.0, 2.0));
+        backtest.place(FillSide::Bid, &QuoteOrder::new(99.0, 1.0, "test"), now);
+        assert_eq!(backtest.bid.as_ref().unwrap().queue_ahead, 2.0);
+
+        let sell = |price, size| BacktestEvent {
+            at: at(10),
+            kind: BacktestEventKind::Trade(HistoricalTrade {
+                price,
+                size,
+                maker_side: FillSide::Bid,
+            }),
+        };
+        backtest.on_event(&sell(99.0, 1.5));
+        assert_eq!(backtest.base, 0.0);
+        backtest.on_event(&sell(99.0, 1.0));
+        assert!((backtest.base - 0.5).abs() < 1e-12);
+        backtest.on_event(&sell(98.0, 5.0));
+        assert!((backtest.base - 1.0).abs() < 1e-12);
+        assert!(backtest.bid.is_none());
+
+        let report = backtest.finish();
+        assert_eq!(report.bid.fills, 2);
+        assert!((report.fees_paid - 99.0 * 0.2 / 10_000.0).abs() < 1e-9);
+        assert!((report.final_quote - (10_000.0 - 99.0 - report.fees_paid)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn candle_backtest_quotes_fills_and_reports() {
+        let candles = (0..240)
+            .map(|n| {
+                let open = 100.0 + (n as f64 * 0.3).sin();
+                let close = 100.0 + ((n + 1) as f64 * 0.3).sin();
+                Candlestick::new(
+                    1_700_000_000_000 + n * 1_000,
+                    open,
+                    open.max(close) + 0.05,
+                    open.min(close) - 0.05,
+                    close,
+                    4.0,
+                    400.0,
+                    n,
+                )
+            })
+            .collect::<Vec<_>>();
+        let events = events_from_candles(&candles, 2.0, 1.0);
+        assert_eq!(events.len(), 240 * 7);
+
+        let report = Backtest::new(test_config(), BacktestConfig::default()).run(events.clone());
+        assert_eq!(report.events, events.len() as u64);
+        assert!(report.quotes_placed > 0);
+        assert!(report.bid.fills + report.ask.fills > 0);
+        assert!(report.fill_ratio > 0.0 && report.fill_ratio <= 1.0);
+        assert!(report.fees_paid > 0.0);
+        assert!(report.pnl.is_finite());
+        assert!(!report.inventory_path.is_empty());
+        assert!(report.bid.markout_samples + report.ask.markout_samples > 0);
+    }
+}
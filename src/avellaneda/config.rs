@@ -1,9 +1,10 @@
 use super::types::StrategyParams;
+use crate::clock::{system_clock, SharedClock};
 use anyhow::{Context, Result};
-use serde::Deserialize;
-use std::{env, fs, path::Path, time::Instant};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::Path};
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct AvellanedaConfig {
     pub market_id: i32,
     #[serde(default = "default_true")]
@@ -156,6 +157,21 @@ fn default_refresh_tolerance() -> i64 {
 
 impl AvellanedaConfig {
     pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let mut cfg = Self::parse_file(path)?;
+        cfg.apply_env_overrides();
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    /// Like [`from_file`](Self::from_file) but ignoring `AVELLANEDA_*`
+    /// overrides, for tools that write the config back out.
+    pub fn from_file_without_env(path: impl AsRef<Path>) -> Result<Self> {
+        let cfg = Self::parse_file(path)?;
+        cfg.validate()?;
+        Ok(cfg)
+    }
+
+    fn parse_file(path: impl AsRef<Path>) -> Result<Self> {
         let data =
             fs::read_to_string(path.as_ref()).with_context(|| "Failed to read config.toml")?;
         let mut raw: toml::Value =
@@ -171,10 +187,16 @@ impl AvellanedaConfig {
             raw.try_into()
                 .map_err(|_| anyhow::anyhow!("Invalid avellaneda config structure"))?
         };
-        let mut cfg: AvellanedaConfig = toml::from_str(&toml::to_string(&table)?)?;
-        cfg.apply_env_overrides();
-        cfg.validate()?;
-        Ok(cfg)
+        Ok(toml::from_str(&toml::to_string(&table)?)?)
+    }
+
+    /// Write the config as an `[avellaneda]` table that
+    /// [`from_file`](Self::from_file) reads back.
+    pub fn write_file(&self, path: impl AsRef<Path>) -> Result<()> {
+        let mut root = toml::Table::new();
+        root.insert("avellaneda".to_string(), toml::Value::try_from(self)?);
+        fs::write(path.as_ref(), toml::to_string(&root)?)
+            .with_context(|| format!("Failed to write {}", path.as_ref().display()))
     }
 
     fn apply_env_overrides(&mut self) {
@@ -252,7 +274,7 @@ impl AvellanedaConfig {
         }
     }
 
-    fn validate(&self) -> Result<()> {
+    pub(crate) fn validate(&self) -> Result<()> {
         anyhow::ensure!(
             (0.01..=1.0).contains(&self.gamma),
             "gamma must be between 0.01 and 1.0"
@@ -307,13 +329,17 @@ impl AvellanedaConfig {
     }
 
     pub fn core_params(&self) -> StrategyParams {
-        StrategyParams {
-            gamma: self.gamma,
-            kappa: self.kappa,
-            order_size: self.order_size,
-            time_horizon_hours: self.time_horizon_hours,
-            start_time: Instant::now(),
-        }
+        self.core_params_with_clock(system_clock())
+    }
+
+    pub fn core_params_with_clock(&self, clock: SharedClock) -> StrategyParams {
+        StrategyParams::with_clock(
+            self.gamma,
+            self.kappa,
+            self.order_size,
+            self.time_horizon_hours,
+            clock,
+        )
     }
 }
 
@@ -348,3 +374,27 @@ fn override_bool(key: &str, field: &mut bool) {
         }
     }
 }
+
+/// Minimal valid config shared by the backtest and sweep tests.
+#[cfg(test)]
+pub(crate) fn test_config() -> AvellanedaConfig {
+    toml::from_str(
+        r#"
+        market_id = 1
+        order_size = 0.01
+        gamma = 0.1
+        kappa = 1.5
+        time_horizon_hours = 1.0
+        target_base_pct = 0.5
+        vol_lookback = 20
+        vol_ewma_alpha = 0.1
+        refresh_interval_ms = 100
+        min_spread_bps = 1.0
+        max_spread_bps = 50.0
+        max_position = 1.0
+        min_notional = 0.0
+        volatility_breaker = 1000.0
+        "#,
+    )
+    .unwrap()
+}
@@ -1,5 +1,7 @@
 use super::types::{QuoteOrder, QuotePair, StrategyDecision};
 use crate::{
+    batch_retry::{RetryBudget, RetryPolicy},
+    clock::{system_clock, SharedClock},
     lighter_client::LighterClient,
     signer_client::BatchEntry,
     tx_executor::{
@@ -14,7 +16,7 @@ use serde_json::Value;
 use std::{
     collections::{hash_map::Entry, HashMap},
     sync::Arc,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 use tokio::{
     sync::{mpsc, oneshot, Mutex},
@@ -29,9 +31,6 @@ const ACCOUNT_CHANNEL_DEPTH: usize = 128;
 const TX_ACK_CHANNEL_DEPTH: usize = 64;
 const CONTROL_CHANNEL_DEPTH: usize = 8;
 
-const INITIAL_BACKOFF_MS: u64 = 50;
-const MAX_BACKOFF_MS: u64 = 1_000;
-const MAX_RETRY_ATTEMPTS: u8 = 5;
 const FAST_MODE_TIMEOUT_MS: u64 = 100;
 const MAX_SAFE_LIVE_ORDERS: usize = 10;
 const EMERGENCY_COOLDOWN_MIN: Duration = Duration::from_secs(5);
@@ -88,27 +87,29 @@ struct PendingOrder {
     _created_at: Instant,
     submitted_at: Option<Instant>,
     last_attempt: Option<Instant>,
-    attempts: u8,
-    retry_budget: u8,
-    backoff_ms: u64,
+    retry: RetryBudget,
     nonce: Option<i64>,
     api_key_index: Option<i32>,
     superseded: bool,
 }
 
 impl PendingOrder {
-    fn new(client_order_id: i64, side: OrderSide, version: u64, action: PendingAction) -> Self {
+    fn new(
+        client_order_id: i64,
+        side: OrderSide,
+        version: u64,
+        action: PendingAction,
+        now: Instant,
+    ) -> Self {
         Self {
             client_order_id,
             side,
             _version: version,
             action,
-            _created_at: Instant::now(),
+            _created_at: now,
             submitted_at: None,
             last_attempt: None,
-            attempts: 0,
-            retry_budget: MAX_RETRY_ATTEMPTS,
-            backoff_ms: INITIAL_BACKOFF_MS,
+            retry: RetryBudget::new(RetryPolicy::default()),
             nonce: None,
             api_key_index: None,
             superseded: false,
@@ -119,17 +120,13 @@ impl PendingOrder {
         self.superseded = true;
     }
 
-    fn record_attempt(&mut self) {
-        self.attempts = self.attempts.saturating_add(1);
-        self.last_attempt = Some(Instant::now());
-        self.backoff_ms = (self.backoff_ms * 2).min(MAX_BACKOFF_MS);
-        if self.retry_budget > 0 {
-            self.retry_budget -= 1;
-        }
+    fn record_attempt(&mut self, now: Instant) {
+        self.last_attempt = Some(now);
+        self.retry.record_attempt();
     }
 
-    fn mark_submitted(&mut self) {
-        self.submitted_at = Some(Instant::now());
+    fn mark_submitted(&mut self, now: Instant) {
+        self.submitted_at = Some(now);
     }
 
     fn set_nonce(&mut self, nonce: Option<i64>) {
@@ -141,7 +138,7 @@ impl PendingOrder {
     }
 
     fn exhausted(&self) -> bool {
-        self.retry_budget == 0
+        self.retry.exhausted()
     }
 }
 
@@ -216,6 +213,7 @@ struct ExecutionState {
     client_id_high_water: i64,
     emergency_last_triggered: Option<Instant>,
     emergency_cooldown: Duration,
+    clock: SharedClock,
 }
 
 impl ExecutionState {
@@ -228,6 +226,7 @@ impl ExecutionState {
         dry_run: bool,
         fast_execution: bool,
         optimistic_acks: bool,
+        clock: SharedClock,
     ) -> Self {
         let mut per_side = HashMap::new();
         per_side.insert(OrderSide::Bid, SideSlot::default());
@@ -251,6 +250,7 @@ impl ExecutionState {
             client_id_high_water: 0,
             emergency_last_triggered: None,
             emergency_cooldown: EMERGENCY_COOLDOWN_MIN,
+            clock,
         }
     }
 
@@ -299,10 +299,12 @@ impl ExecutionState {
     }
 
     fn next_client_order_id(&mut self) -> i64 {
-        let now_ms = SystemTime::now()
+        let now_ms = self
+            .clock
+            .system_now()
             .duration_since(UNIX_EPOCH)
             .map(|d| d.as_millis() as i64)
-            .unwrap_or_else(|_| Instant::now().elapsed().as_millis() as i64);
+            .unwrap_or_default();
         let candidate = now_ms << 4;
         if candidate <= self.client_id_high_water {
             self.client_id_high_water += 1;
@@ -324,7 +326,7 @@ impl ExecutionState {
         client_id_override: Option<i64>,
     ) -> i64 {
         let client_id = client_id_override.unwrap_or_else(|| self.next_client_order_id());
-        let order = PendingOrder::new(client_id, side, version, action.clone());
+        let order = PendingOrder::new(client_id, side, version, action.clone(), self.clock.now());
 
         match &action {
             PendingAction::Create { .. } => {
@@ -351,7 +353,7 @@ impl ExecutionState {
         api_key_index: Option<i32>,
     ) {
         if let Some(order) = self.pending_by_client.get_mut(&client_id) {
-            order.mark_submitted();
+            order.mark_submitted(self.clock.now());
             order.set_nonce(nonce);
             order.set_api_key(api_key_index);
             if let Some(value) = nonce {
@@ -371,7 +373,7 @@ impl ExecutionState {
             if let Some(nonce) = order.nonce {
                 self.pending_by_nonce.remove(&nonce);
             }
-            order.record_attempt();
+            order.record_attempt(self.clock.now());
             if order.exhausted() {
                 match order.action {
                     PendingAction::Create { .. } => {
@@ -592,6 +594,39 @@ impl ExecutionEngine {
         tx_connection: WsConnection,
         refresh_tolerance_ticks: i64,
         auth_token: Option<String>,
+    ) -> Self {
+        Self::with_clock(
+            client,
+            market,
+            base_qty,
+            tick_size,
+            dry_run,
+            refresh_interval_ms,
+            fast_execution,
+            optimistic_acks,
+            tx_connection,
+            refresh_tolerance_ticks,
+            auth_token,
+            system_clock(),
+        )
+    }
+
+    /// Like [`new`](Self::new), timing order submissions, retries and the
+    /// emergency breaker cooldown with `clock`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn with_clock(
+        client: Arc<LighterClient>,
+        market: MarketId,
+        base_qty: BaseQty,
+        tick_size: f64,
+        dry_run: bool,
+        refresh_interval_ms: u64,
+        fast_execution: bool,
+        optimistic_acks: bool,
+        tx_connection: WsConnection,
+        refresh_tolerance_ticks: i64,
+        auth_token: Option<String>,
+        clock: SharedClock,
     ) -> Self {
         let state = Arc::new(Mutex::new(ExecutionState::new(
             market,
@@ -602,6 +637,7 @@ impl ExecutionEngine {
             dry_run,
             fast_execution,
             optimistic_acks,
+            clock,
         )));
         let connection = Arc::new(Mutex::new(tx_connection));
         let auth_state = Arc::new(Mutex::new(auth_token));
@@ -738,21 +774,26 @@ async fn handle_decision(
     {
         let mut guard = state.lock().await;
         if guard.emergency_cancel_required() {
+            let now = guard.clock.now();
             let should_trigger = guard
                 .emergency_last_triggered
-                .map(|last| last.elapsed() >= guard.emergency_cooldown)
+                .map(|last| now.saturating_duration_since(last) >= guard.emergency_cooldown)
                 .unwrap_or(true);
             if !should_trigger {
                 let remaining = guard
                     .emergency_last_triggered
-                    .map(|last| guard.emergency_cooldown.saturating_sub(last.elapsed()))
+                    .map(|last| {
+                        guard
+                            .emergency_cooldown
+                            .saturating_sub(now.saturating_duration_since(last))
+                    })
                     .unwrap_or(Duration::ZERO);
                 debug!("Emergency breaker cooling down ({:?} remaining)", remaining);
                 drop(guard);
                 return Ok(());
             }
 
-            guard.emergency_last_triggered = Some(Instant::now());
+            guard.emergency_last_triggered = Some(now);
             guard.emergency_cooldown = (guard.emergency_cooldown * 2).min(EMERGENCY_COOLDOWN_MAX);
             let live_count = guard.live_by_order.len();
             let current_cooldown = guard.emergency_cooldown;
@@ -1295,7 +1336,8 @@ async fn handle_batch_result(
 
         if ok {
             let mut guard = state.lock().await;
-            guard.record_submission(side, Instant::now());
+            let now = guard.clock.now();
+            guard.record_submission(side, now);
         } else {
             failures.push(client_id);
         }
@@ -1343,7 +1385,7 @@ async fn handle_batch_result(
         return Ok(());
     }
 
-    let max_backoff_ms = {
+    let backoff = {
         let guard = state.lock().await;
         retry_ops
             .iter()
@@ -1351,16 +1393,15 @@ async fn handle_batch_result(
                 OrderOperation::Place(place) => guard
                     .pending_by_client
                     .get(&place.client_order_id)
-                    .map(|p| p.backoff_ms),
+                    .map(|p| p.retry.backoff()),
                 OrderOperation::Cancel(cancel) => guard
                     .pending_by_client
                     .get(&cancel.client_order_id)
-                    .map(|p| p.backoff_ms),
+                    .map(|p| p.retry.backoff()),
             })
             .max()
-            .unwrap_or(INITIAL_BACKOFF_MS)
+            .unwrap_or(RetryPolicy::default().initial_backoff)
     };
-    let backoff = Duration::from_millis(max_backoff_ms);
 
     let id = { state.lock().await.next_directive_id() };
     let directive = OrderDirective::new(id, retry_ops).with_attempt(batch.attempt + 1);
@@ -1421,7 +1462,7 @@ async fn handle_batch_failure(
         return Ok(());
     }
 
-    let max_backoff_ms = {
+    let backoff = {
         let guard = state.lock().await;
         retry_ops
             .iter()
@@ -1429,16 +1470,15 @@ async fn handle_batch_failure(
                 OrderOperation::Place(place) => guard
                     .pending_by_client
                     .get(&place.client_order_id)
-                    .map(|p| p.backoff_ms),
+                    .map(|p| p.retry.backoff()),
                 OrderOperation::Cancel(cancel) => guard
                     .pending_by_client
                     .get(&cancel.client_order_id)
-                    .map(|p| p.backoff_ms),
+                    .map(|p| p.retry.backoff()),
             })
             .max()
-            .unwrap_or(INITIAL_BACKOFF_MS)
+            .unwrap_or(RetryPolicy::default().initial_backoff)
     };
-    let backoff = Duration::from_millis(max_backoff_ms);
 
     let id = { state.lock().await.next_directive_id() };
     let directive = OrderDirective::new(id, retry_ops).with_attempt(batch.attempt + 1);
@@ -1536,8 +1576,7 @@ async fn reconcile_account_orders(
                     {
                         pending.set_nonce(None);
                         pending.superseded = false;
-                        pending.backoff_ms = INITIAL_BACKOFF_MS;
-                        pending.retry_budget = MAX_RETRY_ATTEMPTS;
+                        pending.retry.reset();
                         clear_pending_create = true;
                         set_live_index = true;
                     }
@@ -1650,7 +1689,7 @@ async fn process_transaction_ack(
                             });
                             let id = guard.next_directive_id();
                             let directive = OrderDirective::new(id, vec![op])
-                                .with_attempt(pending.attempts + 1);
+                                .with_attempt(pending.retry.attempts() + 1);
                             drop(guard);
                             directive_tx
                                 .send(directive)
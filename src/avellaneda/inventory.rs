@@ -1,4 +1,5 @@
 use super::types::{FillEvent, FillSide, InventorySnapshot};
+use crate::clock::{system_clock, SharedClock};
 use std::time::Instant;
 
 #[derive(Clone, Debug)]
@@ -9,17 +10,23 @@ pub struct InventoryState {
     pub target_pct: f64,
     pub max_position: f64,
     pub last_update: Instant,
+    clock: SharedClock,
 }
 
 impl InventoryState {
     pub fn new(target_pct: f64, max_position: f64) -> Self {
+        Self::with_clock(target_pct, max_position, system_clock())
+    }
+
+    pub fn with_clock(target_pct: f64, max_position: f64, clock: SharedClock) -> Self {
         Self {
             base_balance: 0.0,
             quote_balance: 0.0,
             mid_price: 0.0,
             target_pct,
             max_position,
-            last_update: Instant::now(),
+            last_update: clock.now(),
+            clock,
         }
     }
 
@@ -27,7 +34,7 @@ impl InventoryState {
         self.base_balance = base_balance;
         self.quote_balance = quote_balance;
         self.mid_price = mid_price;
-        self.last_update = Instant::now();
+        self.last_update = self.clock.now();
     }
 
     pub fn apply_fill(&mut self, fill: &FillEvent) {
@@ -2,8 +2,10 @@
 //!
 //! This module exposes the high-level `AvellanedaStrategy` orchestrator together with
 //! supporting building blocks (configuration, inventory tracking, volatility estimation,
-//! spread optimisation, execution helpers, and market-data adapters).
+//! spread optimisation, execution helpers, and market-data adapters), plus an
+//! event-driven backtest harness and a parameter sweep runner built on it.
 
+pub mod backtest;
 pub mod config;
 pub mod execution;
 pub mod inventory;
@@ -11,9 +13,12 @@ pub mod market_data;
 pub mod participation;
 pub mod spreads;
 pub mod strategy;
+pub mod sweep;
 pub mod types;
 pub mod volatility;
 
+pub use backtest::{Backtest, BacktestConfig, BacktestEvent, BacktestReport};
 pub use config::AvellanedaConfig;
 pub use strategy::AvellanedaStrategy;
+pub use sweep::{Objective, Sweep, SweepConfig, SweepOutcome, SweepSpec};
 pub use types::{QuoteContext, QuoteOrder, QuotePair, SafetyBounds, StrategyEvent, StrategyParams};
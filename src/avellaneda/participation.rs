@@ -2,12 +2,13 @@ use super::types::{
     FillEvent, FillSide, InventorySnapshot, QuoteOrder, QuotePair, StrategyDecision,
     StrategyMetrics,
 };
+use crate::clock::{system_clock, Clock, SharedClock};
 use std::{
     collections::VecDeque,
     fs::{create_dir_all, OpenOptions},
     io::{BufWriter, Write},
     path::PathBuf,
-    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 use tracing::{debug, info, warn};
 
@@ -428,6 +429,11 @@ pub struct ParticipationMetricsSnapshot {
     pub global_pause_active: bool,
     pub kill_active_bid: bool,
     pub kill_active_ask: bool,
+    /// Times the markout kill switch engaged on each side.
+    pub kill_activations_bid: u64,
+    pub kill_activations_ask: u64,
+    /// Times the global PnL pause engaged.
+    pub global_pause_activations: u64,
 }
 
 pub struct ParticipationController {
@@ -446,10 +452,23 @@ pub struct ParticipationController {
     last_partial_exit: Option<Instant>,
     flip_widen_until: Option<Instant>,
     active_ladder_index: Option<usize>,
+    kill_activations: [u64; 2],
+    global_pause_activations: u64,
+    clock: SharedClock,
 }
 
 impl ParticipationController {
     pub fn new(config: ParticipationConfig, snapshot: InventorySnapshot) -> Self {
+        Self::with_clock(config, snapshot, system_clock())
+    }
+
+    /// Like [`new`](Self::new), reading the time for metrics, inventory age
+    /// and the fill log from `clock`.
+    pub fn with_clock(
+        config: ParticipationConfig,
+        snapshot: InventorySnapshot,
+        clock: SharedClock,
+    ) -> Self {
         let log_path = config.fill_log_path.clone();
         Self {
             fill_logger: log_path.map(FillLogger::new),
@@ -459,7 +478,7 @@ impl ParticipationController {
             flips: VecDeque::new(),
             last_mid: None,
             last_mid_direction: None,
-            inventory_age_start: Instant::now(),
+            inventory_age_start: clock.now(),
             last_inventory_snapshot: snapshot.clone(),
             global_kill_until: None,
             pnl_tracker: GlobalPnlTracker::new(Duration::from_secs(3600)),
@@ -467,7 +486,19 @@ impl ParticipationController {
             last_partial_exit: None,
             flip_widen_until: None,
             active_ladder_index: None,
+            kill_activations: [0; 2],
+            global_pause_activations: 0,
+            clock,
+        }
+    }
+
+    /// Redirect (or with `None`, stop) the per-fill CSV log.
+    pub fn set_fill_log_path(&mut self, path: Option<PathBuf>) {
+        if let Some(logger) = self.fill_logger.as_mut() {
+            logger.flush();
         }
+        self.config.fill_log_path = path.clone();
+        self.fill_logger = path.map(FillLogger::new);
     }
 
     pub fn on_market_tick(&mut self, mid: f64, now: Instant) {
@@ -505,7 +536,7 @@ impl ParticipationController {
             };
             if let Some(logger) = self.fill_logger.as_mut() {
                 let record = FillCsvRecord {
-                    timestamp_ms: unix_millis_now(),
+                    timestamp_ms: unix_millis(self.clock.as_ref()),
                     side: match side {
                         FillSide::Bid => "bid",
                         FillSide::Ask => "ask",
@@ -525,6 +556,9 @@ impl ParticipationController {
             }
 
             if markout <= self.config.kill_switch_markout_bps {
+                if !self.side_state[side_idx].is_kill_active(now) {
+                    self.kill_activations[side_idx] += 1;
+                }
                 let until = now + Duration::from_secs(self.config.kill_pause_secs);
                 self.side_state[side_idx].enforce_kill(until);
             } else if markout < 0.0 {
@@ -583,6 +617,13 @@ impl ParticipationController {
 
         if let Some(pnl_per_mm) = self.pnl_tracker.pnl_per_million() {
             if pnl_per_mm < self.config.global_pnl_threshold_bps_per_mm {
+                let paused = self
+                    .global_kill_until
+                    .map(|until| now < until)
+                    .unwrap_or(false);
+                if !paused {
+                    self.global_pause_activations += 1;
+                }
                 self.global_kill_until =
                     Some(now + Duration::from_secs(self.config.global_pause_secs));
             }
@@ -785,7 +826,7 @@ impl ParticipationController {
     }
 
     pub fn metrics_snapshot(&self) -> ParticipationMetricsSnapshot {
-        let now = Instant::now();
+        let now = self.clock.now();
         let flips_rate = self.flips.len() as f64 / FLIP_WINDOW.as_secs_f64();
         let p95_bid = self.side_state[0].fills_per_second_percentile(0.95);
         let p95_ask = self.side_state[1].fills_per_second_percentile(0.95);
@@ -806,13 +847,61 @@ impl ParticipationController {
                 .unwrap_or(false),
             kill_active_bid: self.side_state[0].is_kill_active(now),
             kill_active_ask: self.side_state[1].is_kill_active(now),
+            kill_activations_bid: self.kill_activations[0],
+            kill_activations_ask: self.kill_activations[1],
+            global_pause_activations: self.global_pause_activations,
         }
     }
 }
 
-fn unix_millis_now() -> i128 {
-    SystemTime::now()
+fn unix_millis(clock: &dyn Clock) -> i128 {
+    clock
+        .system_now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_millis() as i128)
         .unwrap_or_default()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn markout_kill_switch_holds_for_exactly_the_configured_pause() {
+        let clock = ManualClock::new();
+        let config = ParticipationConfig {
+            fill_log_path: None,
+            kill_pause_secs: 5,
+            ..ParticipationConfig::default()
+        };
+        let snapshot = InventorySnapshot {
+            base_balance: 0.0,
+            quote_balance: 1_000.0,
+            mid_price: 100.0,
+            normalized_inventory: 0.0,
+            max_position: 1.0,
+        };
+        let mut controller = ParticipationController::with_clock(config, snapshot, clock.shared());
+
+        let fill = FillEvent {
+            side: FillSide::Bid,
+            price: 100.0,
+            size: 1.0,
+            timestamp: clock.now(),
+        };
+        controller.on_fill(&fill, 100.0, 0.0, 1.0, &StrategyMetrics::default());
+
+        clock.advance(MARKOUT_HORIZON);
+        controller.on_market_tick(99.0, clock.now());
+        let metrics = controller.metrics_snapshot();
+        assert!(metrics.kill_active_bid);
+        assert!(!metrics.kill_active_ask);
+        assert_eq!(metrics.kill_activations_bid, 1);
+
+        clock.advance(Duration::from_secs(5) - Duration::from_millis(1));
+        assert!(controller.metrics_snapshot().kill_active_bid);
+        clock.advance(Duration::from_millis(1));
+        assert!(!controller.metrics_snapshot().kill_active_bid);
+    }
+}
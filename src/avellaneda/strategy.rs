@@ -12,10 +12,11 @@ use super::{
     },
     volatility::VolEstimator,
 };
+use crate::clock::{system_clock, SharedClock};
 use crate::ws_client::OrderBookState;
 use std::{
     path::PathBuf,
-    time::{Instant, SystemTime, UNIX_EPOCH},
+    time::{Instant, UNIX_EPOCH},
 };
 
 const INVENTORY_LIMIT_TOLERANCE: f64 = 0.95;
@@ -36,7 +37,13 @@ pub struct AvellanedaStrategy {
 
 impl AvellanedaStrategy {
     pub fn new(config: AvellanedaConfig) -> Self {
-        let params = config.core_params();
+        Self::with_clock(config, system_clock())
+    }
+
+    /// Like [`new`](Self::new), with every time-based decision (horizon,
+    /// pauses, kill switches, inventory age) read from `clock`.
+    pub fn with_clock(config: AvellanedaConfig, clock: SharedClock) -> Self {
+        let params = config.core_params_with_clock(clock.clone());
         let bounds = SafetyBounds {
             min_spread_bps: config.min_spread_bps,
             max_spread_bps: config.max_spread_bps,
@@ -52,10 +59,11 @@ impl AvellanedaStrategy {
         let vol_alpha = config.vol_ewma_alpha;
         let refresh_ms = config.refresh_interval_ms;
 
-        let inventory = InventoryState::new(target_pct, max_pos);
+        let inventory = InventoryState::with_clock(target_pct, max_pos, clock.clone());
         let initial_snapshot = inventory.snapshot();
-        let participation_config = build_participation_config(&config);
-        let participation = ParticipationController::new(participation_config, initial_snapshot);
+        let participation_config = build_participation_config(&config, &clock);
+        let participation =
+            ParticipationController::with_clock(participation_config, initial_snapshot, clock);
 
         Self {
             config,
@@ -200,6 +208,12 @@ impl AvellanedaStrategy {
         self.metrics.total_quotes += 1;
     }
 
+    /// Redirect (or with `None`, stop) the per-fill CSV log, which defaults
+    /// to `logs/fills/session-<unix secs>.csv`.
+    pub fn set_fill_log_path(&mut self, path: Option<PathBuf>) {
+        self.participation.set_fill_log_path(path);
+    }
+
     pub fn participation_metrics(&self) -> ParticipationMetricsSnapshot {
         self.participation.metrics_snapshot()
     }
@@ -221,8 +235,9 @@ impl AvellanedaStrategy {
     }
 }
 
-fn build_participation_config(cfg: &AvellanedaConfig) -> ParticipationConfig {
-    let timestamp = SystemTime::now()
+fn build_participation_config(cfg: &AvellanedaConfig, clock: &SharedClock) -> ParticipationConfig {
+    let timestamp = clock
+        .system_now()
         .duration_since(UNIX_EPOCH)
         .map(|d| d.as_secs())
         .unwrap_or(0);
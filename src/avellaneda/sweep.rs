@@ -0,0 +1,614 @@
+//! Parameter sweeps for [`AvellanedaConfig`].
+//!
+//! A [`SweepSpec`] lists values for config fields. [`Sweep::run`] backtests
+//! every combination over the same events on a pool of threads and ranks the
+//! results by an [`Objective`]. The ranked [`SweepOutcome`] writes a CSV
+//! results table and the winning config as TOML.
+//!
+//! Specs are usually read from TOML, one key per config field:
+//!
+//! ```toml
+//! [avellaneda_sweep]
+//! gamma = { start = 0.05, end = 0.20, step = 0.05 }
+//! kappa = [1.0, 1.5, 2.0]
+//! ladder_offsets_bps = [[1.0, 3.0, 5.0, 8.0], [2.0, 4.0, 6.0, 10.0]]
+//! inventory_partial_bps = { start = 4.0, end = 8.0, step = 2.0 }
+//! inventory_partial_cooldown_ms = { start = 1000, end = 3000, step = 1000 }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    io::{BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tracing::debug;
+
+use super::{
+    backtest::{Backtest, BacktestConfig, BacktestEvent, BacktestReport},
+    config::AvellanedaConfig,
+};
+
+/// Values within this distance of a range's end are still included.
+const RANGE_EPSILON: f64 = 1e-9;
+/// Range values are rounded to this many steps per unit.
+const RANGE_SCALE: f64 = 1e9;
+
+/// What a sweep ranks configurations by. Higher scores rank first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Objective {
+    /// Mean over standard deviation of the PnL change per inventory sample
+    /// (`BacktestConfig::sample_interval`), not annualised.
+    #[default]
+    Sharpe,
+    /// PnL in bps of filled notional.
+    PnlPerNotional,
+    /// Mark-to-market PnL.
+    Pnl,
+    /// Smallest maximum drawdown first. A config that never fills has no
+    /// drawdown, so check its PnL too.
+    MaxDrawdown,
+}
+
+impl Objective {
+    pub fn score(self, metrics: &SweepMetrics) -> f64 {
+        match self {
+            Self::Sharpe => metrics.sharpe,
+            Self::PnlPerNotional => metrics.pnl_per_notional_bps,
+            Self::Pnl => metrics.pnl,
+            Self::MaxDrawdown => -metrics.max_drawdown,
+        }
+    }
+}
+
+impl fmt::Display for Objective {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sharpe => "sharpe",
+            Self::PnlPerNotional => "pnl_per_notional",
+            Self::Pnl => "pnl",
+            Self::MaxDrawdown => "max_drawdown",
+        })
+    }
+}
+
+impl FromStr for Objective {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "sharpe" => Ok(Self::Sharpe),
+            "pnl_per_notional" => Ok(Self::PnlPerNotional),
+            "pnl" => Ok(Self::Pnl),
+            "max_drawdown" | "drawdown" => Ok(Self::MaxDrawdown),
+            other => anyhow::bail!(
+                "unknown objective {other:?}; expected sharpe, pnl_per_notional, pnl or max_drawdown"
+            ),
+        }
+    }
+}
+
+/// The values one config field takes in a sweep.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SweepAxis {
+    pub field: String,
+    pub values: Vec<toml::Value>,
+}
+
+impl SweepAxis {
+    pub fn values<V: Into<toml::Value>>(
+        field: impl Into<String>,
+        values: impl IntoIterator<Item = V>,
+    ) -> Self {
+        Self {
+            field: field.into(),
+            values: values.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// `start, start + step, ...` up to and including `end`.
+    pub fn range(field: impl Into<String>, start: f64, end: f64, step: f64) -> Result<Self> {
+        let field = field.into();
+        anyhow::ensure!(
+            step > 0.0 && end >= start,
+            "{field}: range needs step > 0 and end >= start"
+        );
+        let count = ((end - start) / step + RANGE_EPSILON).floor() as usize + 1;
+        let values = (0..count)
+            .map(|n| {
+                let value = start + n as f64 * step;
+                // Keep 0.1 + 0.2 from being written out as 0.30000000000000004.
+                toml::Value::Float((value * RANGE_SCALE).round() / RANGE_SCALE)
+            })
+            .collect();
+        Ok(Self { field, values })
+    }
+
+    /// Integer range for fields such as `inventory_partial_cooldown_ms`.
+    pub fn int_range(field: impl Into<String>, start: i64, end: i64, step: i64) -> Result<Self> {
+        let field = field.into();
+        anyhow::ensure!(
+            step > 0 && end >= start,
+            "{field}: range needs step > 0 and end >= start"
+        );
+        let values = (start..=end)
+            .step_by(step as usize)
+            .map(toml::Value::Integer)
+            .collect();
+        Ok(Self { field, values })
+    }
+
+    fn from_toml(field: &str, value: &toml::Value) -> Result<Self> {
+        match value {
+            toml::Value::Table(range) => {
+                let bound = |key: &str| {
+                    range
+                        .get(key)
+                        .with_context(|| format!("{field}: range is missing {key}"))
+                };
+                match (bound("start")?, bound("end")?, bound("step")?) {
+                    (
+                        toml::Value::Integer(start),
+                        toml::Value::Integer(end),
+                        toml::Value::Integer(step),
+                    ) => Self::int_range(field, *start, *end, *step),
+                    (start, end, step) => {
+                        let number = |value: &toml::Value| match value {
+                            toml::Value::Integer(n) => Ok(*n as f64),
+                            toml::Value::Float(n) => Ok(*n),
+                            _ => anyhow::bail!("{field}: range bounds must be numbers"),
+                        };
+                        Self::range(field, number(start)?, number(end)?, number(step)?)
+                    }
+                }
+            }
+            toml::Value::Array(values) => Ok(Self::values(field, values.iter().cloned())),
+            value => Ok(Self::values(field, [value.clone()])),
+        }
+    }
+}
+
+/// Fields to sweep and their values.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SweepSpec {
+    pub axes: Vec<SweepAxis>,
+}
+
+impl SweepSpec {
+    /// Read a spec from the `[avellaneda_sweep]` table of a TOML file, or
+    /// from its top level when there is no such table.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self> {
+        let data = fs::read_to_string(path.as_ref())
+            .with_context(|| format!("Failed to read {}", path.as_ref().display()))?;
+        Self::from_toml_str(&data)
+    }
+
+    pub fn from_toml_str(data: &str) -> Result<Self> {
+        let mut root: toml::Table =
+            toml::from_str(data).with_context(|| "Failed to parse sweep TOML")?;
+        let table = match root.remove("avellaneda_sweep") {
+            Some(toml::Value::Table(table)) => table,
+            Some(_) => anyhow::bail!("avellaneda_sweep must be a table"),
+            None if root.contains_key("avellaneda") => {
+                anyhow::bail!("no [avellaneda_sweep] table next to [avellaneda]")
+            }
+            None => root,
+        };
+        let axes = table
+            .iter()
+            .map(|(field, value)| SweepAxis::from_toml(field, value))
+            .collect::<Result<_>>()?;
+        Ok(Self { axes })
+    }
+
+    pub fn axis(mut self, axis: SweepAxis) -> Self {
+        self.axes.push(axis);
+        self
+    }
+
+    /// Number of configurations the spec expands to.
+    pub fn combinations(&self) -> usize {
+        self.axes.iter().map(|axis| axis.values.len()).product()
+    }
+}
+
+/// Sweep settings that are not part of the spec.
+#[derive(Debug, Clone, Default)]
+pub struct SweepConfig {
+    pub backtest: BacktestConfig,
+    pub objective: Objective,
+    /// Worker threads; `0` uses the available parallelism.
+    pub threads: usize,
+}
+
+/// Headline numbers of one backtest.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SweepMetrics {
+    pub pnl: f64,
+    pub pnl_per_notional_bps: f64,
+    pub sharpe: f64,
+    pub max_drawdown: f64,
+    pub fills: u64,
+    pub fill_ratio: f64,
+    pub fees_paid: f64,
+    pub kill_switch_activations: u64,
+}
+
+impl SweepMetrics {
+    pub fn from_report(report: &BacktestReport) -> Self {
+        let notional = report.bid.filled_notional + report.ask.filled_notional;
+        let pnl_per_notional_bps = if notional > 0.0 {
+            report.pnl / notional * 10_000.0
+        } else {
+            0.0
+        };
+        Self {
+            pnl: report.pnl,
+            pnl_per_notional_bps,
+            sharpe: sharpe(report),
+            max_drawdown: report.max_drawdown,
+            fills: report.bid.fills + report.ask.fills,
+            fill_ratio: report.fill_ratio,
+            fees_paid: report.fees_paid,
+            kill_switch_activations: report.kill_switch_activations,
+        }
+    }
+}
+
+fn sharpe(report: &BacktestReport) -> f64 {
+    let changes = report
+        .inventory_path
+        .windows(2)
+        .map(|pair| pair[1].pnl - pair[0].pnl)
+        .collect::<Vec<_>>();
+    if changes.len() < 2 {
+        return 0.0;
+    }
+    let n = changes.len() as f64;
+    let mean = changes.iter().sum::<f64>() / n;
+    let variance = changes.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    if variance <= f64::EPSILON {
+        return 0.0;
+    }
+    mean / variance.sqrt()
+}
+
+/// Swept field names and the values one configuration took.
+pub type SweepParams = BTreeMap<String, toml::Value>;
+
+/// One backtested configuration.
+#[derive(Debug, Clone)]
+pub struct SweepResult {
+    pub params: SweepParams,
+    pub config: AvellanedaConfig,
+    pub metrics: SweepMetrics,
+    pub score: f64,
+}
+
+/// A combination that failed config validation and was not run.
+#[derive(Debug, Clone)]
+pub struct RejectedConfig {
+    pub params: SweepParams,
+    pub reason: String,
+}
+
+/// Results of a [`Sweep`], best first.
+#[derive(Debug, Clone)]
+pub struct SweepOutcome {
+    pub objective: Objective,
+    /// Swept fields, in spec order.
+    pub fields: Vec<String>,
+    pub results: Vec<SweepResult>,
+    pub rejected: Vec<RejectedConfig>,
+}
+
+impl SweepOutcome {
+    pub fn best(&self) -> Option<&SweepResult> {
+        self.results.first()
+    }
+
+    /// Write the ranked results as CSV, one column per swept field.
+    pub fn write_table(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let file = fs::File::create(path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut out = BufWriter::new(file);
+        write!(
+            out,
+            "rank,score,pnl,pnl_per_notional_bps,sharpe,max_drawdown,fills,fill_ratio,fees_paid,kill_switch_activations"
+        )?;
+        for field in &self.fields {
+            write!(out, ",{field}")?;
+        }
+        writeln!(out)?;
+        for (rank, result) in self.results.iter().enumerate() {
+            let m = &result.metrics;
+            write!(
+                out,
+                "{},{},{},{},{},{},{},{},{},{}",
+                rank + 1,
+                result.score,
+                m.pnl,
+                m.pnl_per_notional_bps,
+                m.sharpe,
+                m.max_drawdown,
+                m.fills,
+                m.fill_ratio,
+                m.fees_paid,
+                m.kill_switch_activations
+            )?;
+            for field in &self.fields {
+                let value = result.params.get(field).map(csv_value).unwrap_or_default();
+                write!(out, ",{value}")?;
+            }
+            writeln!(out)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Write the best configuration in the format
+    /// [`AvellanedaConfig::from_file`] reads.
+    pub fn write_best_config(&self, path: impl AsRef<Path>) -> Result<()> {
+        let best = self
+            .best()
+            .context("sweep produced no valid configuration")?;
+        best.config.write_file(path)
+    }
+}
+
+fn csv_value(value: &toml::Value) -> String {
+    let text = match value {
+        toml::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+    if text.contains([',', '"']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text
+    }
+}
+
+#[derive(Default)]
+struct Expanded {
+    valid: Vec<(SweepParams, AvellanedaConfig)>,
+    rejected: Vec<RejectedConfig>,
+}
+
+/// Backtests every combination of a [`SweepSpec`] applied to a base config.
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    base: AvellanedaConfig,
+    spec: SweepSpec,
+    config: SweepConfig,
+}
+
+impl Sweep {
+    pub fn new(base: AvellanedaConfig, spec: SweepSpec, config: SweepConfig) -> Self {
+        Self { base, spec, config }
+    }
+
+    /// Expand the spec into configs, splitting off the ones that fail
+    /// validation. Errors when a swept field is not an `AvellanedaConfig`
+    /// field.
+    fn expand(&self) -> Result<Expanded> {
+        let base = match toml::Value::try_from(&self.base)? {
+            toml::Value::Table(table) => table,
+            _ => anyhow::bail!("AvellanedaConfig did not serialize to a table"),
+        };
+        for axis in &self.spec.axes {
+            anyhow::ensure!(
+                base.contains_key(&axis.field),
+                "{} is not an AvellanedaConfig field",
+                axis.field
+            );
+        }
+
+        let mut expanded = Expanded::default();
+        if self.spec.axes.iter().any(|axis| axis.values.is_empty()) {
+            return Ok(expanded);
+        }
+        let mut digits = vec![0usize; self.spec.axes.len()];
+        loop {
+            let params = self
+                .spec
+                .axes
+                .iter()
+                .zip(&digits)
+                .map(|(axis, &n)| (axis.field.clone(), axis.values[n].clone()))
+                .collect::<SweepParams>();
+            let mut table = base.clone();
+            table.extend(params.clone());
+            let config = toml::Value::Table(table)
+                .try_into::<AvellanedaConfig>()
+                .map_err(anyhow::Error::from)
+                .and_then(|config| config.validate().map(|()| config));
+            match config {
+                Ok(config) => expanded.valid.push((params, config)),
+                Err(err) => expanded.rejected.push(RejectedConfig {
+                    params,
+                    reason: err.to_string(),
+                }),
+            }
+
+            // Advance the mixed-radix counter, last axis fastest.
+            let mut idx = digits.len();
+            loop {
+                if idx == 0 {
+                    return Ok(expanded);
+                }
+                idx -= 1;
+                digits[idx] += 1;
+                if digits[idx] < self.spec.axes[idx].values.len() {
+                    break;
+                }
+                digits[idx] = 0;
+            }
+        }
+    }
+
+    /// Backtest every valid combination over `events` and rank the results.
+    pub fn run(&self, events: &[BacktestEvent]) -> Result<SweepOutcome> {
+        let Expanded {
+            valid: candidates,
+            rejected,
+        } = self.expand()?;
+        let threads = match self.config.threads {
+            0 => thread::available_parallelism().map_or(1, |n| n.get()),
+            n => n,
+        }
+        .min(candidates.len())
+        .max(1);
+        let next = AtomicUsize::new(0);
+
+        let mut results = thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut done = Vec::new();
+                        while let Some((params, config)) =
+                            candidates.get(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            let report =
+                                Backtest::new(config.clone(), self.config.backtest.clone())
+                                    .run(events.iter().cloned());
+                            let metrics = SweepMetrics::from_report(&report);
+                            let score = self.config.objective.score(&metrics);
+                            debug!(?params, score, "sweep config finished");
+                            done.push(SweepResult {
+                                params: params.clone(),
+                                config: config.clone(),
+                                metrics,
+                                score,
+                            });
+                        }
+                        done
+                    })
+                })
+                .collect::<Vec<_>>();
+            workers
+                .into_iter()
+                .flat_map(|worker| worker.join().expect("sweep worker panicked"))
+                .collect::<Vec<_>>()
+        });
+        results.sort_by(|a, b| b.score.total_cmp(&a.score));
+
+        Ok(SweepOutcome {
+            objective: self.config.objective,
+            fields: self
+                .spec
+                .axes
+                .iter()
+                .map(|axis| axis.field.clone())
+                .collect(),
+            results,
+            rejected,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        avellaneda::{backtest::events_from_candles, config::test_config},
+        models::Candlestick,
+    };
+
+    #[test]
+    fn spec_parses_ranges_and_lists() {
+        let spec = SweepSpec::from_toml_str(
+            r#"
+            [avellaneda_sweep]
+            gamma = { start = 0.1, end = 0.3, step = 0.1 }
+            inventory_partial_cooldown_ms = { start = 1000, end = 3000, step = 1000 }
+            ladder_offsets_bps = [[1.0, 3.0, 5.0, 8.0], [2.0, 4.0, 6.0, 10.0]]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(spec.combinations(), 18);
+        let gamma = spec.axes.iter().find(|a| a.field == "gamma").unwrap();
+        assert_eq!(
+            gamma.values,
+            vec![
+                toml::Value::Float(0.1),
+                toml::Value::Float(0.2),
+                toml::Value::Float(0.3)
+            ]
+        );
+        let cooldown = spec
+            .axes
+            .iter()
+            .find(|a| a.field == "inventory_partial_cooldown_ms")
+            .unwrap();
+        assert_eq!(cooldown.values[2], toml::Value::Integer(3000));
+    }
+
+    #[test]
+    fn sweep_ranks_configs_and_writes_the_winner() {
+        let candles = (0..120)
+            .map(|n| {
+                let open = 100.0 + (n as f64 * 0.3).sin();
+                let close = 100.0 + ((n + 1) as f64 * 0.3).sin();
+                Candlestick::new(
+                    1_700_000_000_000 + n * 1_000,
+                    open,
+                    open.max(close) + 0.05,
+                    open.min(close) - 0.05,
+                    close,
+                    4.0,
+                    400.0,
+                    n,
+                )
+            })
+            .collect::<Vec<_>>();
+        let events = events_from_candles(&candles, 2.0, 1.0);
+        let spec = SweepSpec::default()
+            .axis(SweepAxis::values("gamma", [0.05, 0.2]))
+            .axis(SweepAxis::values("kappa", [1.0, 9.0]))
+            .axis(SweepAxis::values(
+                "ladder_offsets_bps",
+                [vec![1.0, 3.0, 5.0, 8.0], vec![2.0, 4.0, 6.0, 10.0]],
+            ));
+        let config = SweepConfig {
+            objective: Objective::Pnl,
+            threads: 2,
+            ..SweepConfig::default()
+        };
+        let outcome = Sweep::new(test_config(), spec, config)
+            .run(&events)
+            .unwrap();
+
+        // kappa = 9.0 is outside the validated range.
+        assert_eq!(outcome.rejected.len(), 4);
+        assert_eq!(outcome.results.len(), 4);
+        assert!(outcome
+            .results
+            .windows(2)
+            .all(|pair| pair[0].score >= pair[1].score));
+
+        let dir = std::env::temp_dir().join(format!("lighter-sweep-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        outcome.write_table(dir.join("results.csv")).unwrap();
+        let table = fs::read_to_string(dir.join("results.csv")).unwrap();
+        assert_eq!(table.lines().count(), 5);
+        assert!(table
+            .lines()
+            .next()
+            .unwrap()
+            .ends_with(",gamma,kappa,ladder_offsets_bps"));
+
+        outcome.write_best_config(dir.join("best.toml")).unwrap();
+        let best = AvellanedaConfig::from_file(dir.join("best.toml")).unwrap();
+        let winner = &outcome.best().unwrap().config;
+        assert_eq!(best.gamma, winner.gamma);
+        assert_eq!(best.ladder_offsets_bps, winner.ladder_offsets_bps);
+        fs::remove_dir_all(dir).ok();
+    }
+}
@@ -1,4 +1,5 @@
 use crate::avellaneda::market_data::MarketTick;
+use crate::clock::{system_clock, SharedClock};
 use serde::{Deserialize, Serialize};
 use std::time::Instant;
 
@@ -10,27 +11,43 @@ pub struct StrategyParams {
     pub order_size: f64,
     pub time_horizon_hours: f64,
     pub start_time: Instant,
+    pub clock: SharedClock,
 }
 
 impl StrategyParams {
     pub fn new(gamma: f64, kappa: f64, order_size: f64, time_horizon_hours: f64) -> Self {
+        Self::with_clock(gamma, kappa, order_size, time_horizon_hours, system_clock())
+    }
+
+    pub fn with_clock(
+        gamma: f64,
+        kappa: f64,
+        order_size: f64,
+        time_horizon_hours: f64,
+        clock: SharedClock,
+    ) -> Self {
         Self {
             gamma,
             kappa,
             order_size,
             time_horizon_hours,
-            start_time: Instant::now(),
+            start_time: clock.now(),
+            clock,
         }
     }
 
     pub fn time_left_seconds(&self) -> f64 {
         let total = self.time_horizon_hours * 3600.0;
-        let elapsed = self.start_time.elapsed().as_secs_f64();
+        let elapsed = self
+            .clock
+            .now()
+            .saturating_duration_since(self.start_time)
+            .as_secs_f64();
         (total - elapsed).max(0.01)
     }
 
     pub fn reset_start_time(&mut self) {
-        self.start_time = Instant::now();
+        self.start_time = self.clock.now();
     }
 }
 
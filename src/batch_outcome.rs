@@ -0,0 +1,384 @@
+//! Per-transaction outcomes for batch submissions.
+//!
+//! `sendTxBatch` answers a whole batch with one code and a list of tx hashes,
+//! and a reply that never arrives says nothing at all. [`BatchOutcome`] keeps
+//! one [`EntryOutcome`] per submitted transaction, seeds it from the batch
+//! reply and settles it from `account_tx` events. Entries are matched by tx
+//! hash, or by account, API key and nonce when no hash is known yet.
+
+use std::ops::Range;
+
+use serde::Deserialize;
+
+use crate::{
+    account_events::{TypedAccountEvent, TX_STATUS_SUCCESS},
+    models,
+    signer_client::{BatchEntry, CODE_OK},
+};
+
+/// What is known about one transaction of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxOutcome {
+    /// No reply and no matching `account_tx` yet.
+    Unknown,
+    /// Queued by the exchange; execution not seen in `account_tx` yet.
+    Accepted,
+    /// Executed successfully according to `account_tx`.
+    Confirmed,
+    /// Refused at submission (`code` set), failed on execution or never sent.
+    Rejected { code: Option<i32>, message: String },
+}
+
+impl TxOutcome {
+    /// `Confirmed` or `Rejected`; later events no longer change the entry.
+    pub fn is_settled(&self) -> bool {
+        matches!(self, Self::Confirmed | Self::Rejected { .. })
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        matches!(self, Self::Rejected { .. })
+    }
+}
+
+/// One transaction of a batch, in submission order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryOutcome {
+    /// Position in the submitted batch.
+    pub index: usize,
+    pub tx_type: i32,
+    pub account_index: Option<i64>,
+    pub api_key_index: Option<i32>,
+    pub nonce: Option<i64>,
+    pub tx_hash: Option<String>,
+    pub outcome: TxOutcome,
+}
+
+/// Signer fields common to every transaction's `tx_info`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct TxHeader {
+    #[serde(default)]
+    pub(crate) account_index: Option<i64>,
+    #[serde(default)]
+    pub(crate) api_key_index: Option<i32>,
+    #[serde(default)]
+    pub(crate) nonce: Option<i64>,
+}
+
+impl TxHeader {
+    pub(crate) fn parse(tx_info: &str) -> Self {
+        serde_json::from_str(tx_info).unwrap_or_default()
+    }
+}
+
+impl EntryOutcome {
+    fn new(index: usize, tx_type: i32, tx_info: &str) -> Self {
+        let header = TxHeader::parse(tx_info);
+        Self {
+            index,
+            tx_type,
+            account_index: header.account_index,
+            api_key_index: header.api_key_index,
+            nonce: header.nonce,
+            tx_hash: None,
+            outcome: TxOutcome::Unknown,
+        }
+    }
+
+    fn matches(&self, tx: &models::Tx) -> bool {
+        if let Some(hash) = &self.tx_hash {
+            return *hash == tx.hash;
+        }
+        if self.nonce != Some(tx.nonce) {
+            return false;
+        }
+        if self
+            .account_index
+            .is_some_and(|account| account != tx.account_index)
+        {
+            return false;
+        }
+        match (self.api_key_index, TxHeader::parse(&tx.info).api_key_index) {
+            (Some(ours), Some(theirs)) => ours == theirs,
+            _ => true,
+        }
+    }
+}
+
+/// Outcomes of every transaction in a batch, settled as replies and
+/// `account_tx` events arrive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchOutcome {
+    entries: Vec<EntryOutcome>,
+}
+
+impl BatchOutcome {
+    /// Start tracking `(tx_type, tx_info)` pairs, all [`TxOutcome::Unknown`].
+    pub fn new<'a>(entries: impl IntoIterator<Item = (i32, &'a str)>) -> Self {
+        Self {
+            entries: entries
+                .into_iter()
+                .enumerate()
+                .map(|(index, (tx_type, tx_info))| EntryOutcome::new(index, tx_type, tx_info))
+                .collect(),
+        }
+    }
+
+    pub fn from_entries(entries: &[BatchEntry]) -> Self {
+        Self::new(
+            entries
+                .iter()
+                .map(|entry| (entry.tx_type, entry.tx_info.as_str())),
+        )
+    }
+
+    /// Apply a REST `sendTxBatch` response covering the whole batch.
+    pub fn apply_response(&mut self, response: &models::RespSendTxBatch) {
+        self.apply_reply(
+            0..self.entries.len(),
+            response.code,
+            response.message.as_deref(),
+            &response.tx_hash,
+        );
+    }
+
+    /// Apply the reply to the entries in `range` (one WebSocket chunk).
+    ///
+    /// A non-200 code rejects every entry in the range. Otherwise entries
+    /// become [`TxOutcome::Accepted`]; hashes are assigned by position only
+    /// when the reply carries one per entry.
+    pub fn apply_reply(
+        &mut self,
+        range: Range<usize>,
+        code: i32,
+        message: Option<&str>,
+        tx_hashes: &[String],
+    ) {
+        let hashes_line_up = tx_hashes.len() == range.len();
+        for (position, entry) in self.entries[range].iter_mut().enumerate() {
+            if entry.outcome.is_settled() {
+                continue;
+            }
+            if code != CODE_OK {
+                entry.outcome = TxOutcome::Rejected {
+                    code: Some(code),
+                    message: message.unwrap_or_default().to_string(),
+                };
+                continue;
+            }
+            if hashes_line_up {
+                entry.tx_hash = Some(tx_hashes[position].clone());
+            }
+            entry.outcome = TxOutcome::Accepted;
+        }
+    }
+
+    /// Reject entries in `range` that never left the client.
+    pub fn mark_unsent(&mut self, range: Range<usize>, reason: &str) {
+        for entry in &mut self.entries[range] {
+            entry.outcome = TxOutcome::Rejected {
+                code: None,
+                message: format!("not sent: {reason}"),
+            };
+        }
+    }
+
+    /// Replace the entries at `indices` with the outcome of resubmitting
+    /// them; entry `i` of `retried` lands at `indices[i]`.
+    pub fn merge(&mut self, indices: &[usize], retried: BatchOutcome) {
+        for (&index, mut entry) in indices.iter().zip(retried.entries) {
+            entry.index = index;
+            self.entries[index] = entry;
+        }
+    }
+
+    /// Settle entries from an `account_tx` event. Returns whether anything changed.
+    pub fn apply(&mut self, event: &TypedAccountEvent) -> bool {
+        match event {
+            TypedAccountEvent::AccountTx(update) => update
+                .txs
+                .iter()
+                .fold(false, |changed, tx| self.apply_tx(tx) | changed),
+            _ => false,
+        }
+    }
+
+    /// Settle the entry matching an executed transaction, if any.
+    pub fn apply_tx(&mut self, tx: &models::Tx) -> bool {
+        let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| !entry.outcome.is_settled() && entry.matches(tx))
+        else {
+            return false;
+        };
+        entry.tx_hash.get_or_insert_with(|| tx.hash.clone());
+        entry.outcome = if tx.status == TX_STATUS_SUCCESS {
+            TxOutcome::Confirmed
+        } else {
+            TxOutcome::Rejected {
+                code: None,
+                message: format!("transaction status {}", tx.status),
+            }
+        };
+        true
+    }
+
+    pub fn entries(&self) -> &[EntryOutcome] {
+        &self.entries
+    }
+
+    pub fn get(&self, index: usize) -> Option<&EntryOutcome> {
+        self.entries.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Every entry is `Confirmed` or `Rejected`.
+    pub fn is_settled(&self) -> bool {
+        self.entries.iter().all(|entry| entry.outcome.is_settled())
+    }
+
+    /// Entries still `Unknown` or `Accepted`.
+    pub fn unsettled(&self) -> impl Iterator<Item = &EntryOutcome> {
+        self.entries
+            .iter()
+            .filter(|entry| !entry.outcome.is_settled())
+    }
+
+    pub fn confirmed(&self) -> impl Iterator<Item = &EntryOutcome> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.outcome == TxOutcome::Confirmed)
+    }
+
+    pub fn rejected(&self) -> impl Iterator<Item = &EntryOutcome> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.outcome.is_rejected())
+    }
+
+    /// One flag per entry: `Accepted` and `Confirmed` are `true`, `Rejected`
+    /// is `false` and `Unknown` becomes `unknown_as`.
+    pub fn acks(&self, unknown_as: bool) -> Vec<bool> {
+        self.entries
+            .iter()
+            .map(|entry| match entry.outcome {
+                TxOutcome::Accepted | TxOutcome::Confirmed => true,
+                TxOutcome::Rejected { .. } => false,
+                TxOutcome::Unknown => unknown_as,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        order_book::BookScale,
+        sim_exchange::SimExchange,
+        tx_executor::{TX_TYPE_CANCEL_ORDER, TX_TYPE_CREATE_ORDER},
+        types::{AccountId, MarketId},
+        ws_client::WsEvent,
+    };
+
+    fn create(nonce: i64, base_amount: i64) -> BatchEntry {
+        BatchEntry::new(
+            i32::from(TX_TYPE_CREATE_ORDER),
+            format!(
+                r#"{{"AccountIndex":7,"ApiKeyIndex":2,"MarketIndex":0,"ClientOrderIndex":{nonce},"BaseAmount":{base_amount},"Price":100,"IsAsk":0,"Type":0,"TimeInForce":1,"Nonce":{nonce}}}"#
+            ),
+        )
+    }
+
+    fn cancel(nonce: i64, order_index: i64) -> BatchEntry {
+        BatchEntry::new(
+            i32::from(TX_TYPE_CANCEL_ORDER),
+            format!(
+                r#"{{"AccountIndex":7,"ApiKeyIndex":2,"MarketIndex":0,"Index":{order_index},"Nonce":{nonce}}}"#
+            ),
+        )
+    }
+
+    fn exchange() -> SimExchange {
+        let mut sim = SimExchange::new(AccountId::new(7));
+        sim.add_market(MarketId::new(0), BookScale::new(2, 2));
+        sim
+    }
+
+    fn account_events(sim: &mut SimExchange) -> Vec<TypedAccountEvent> {
+        sim.drain_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                WsEvent::Account(envelope) => envelope.decode().ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rejected_batch_rejects_every_entry() {
+        let mut outcome = BatchOutcome::from_entries(&[create(1, 10), create(2, 10)]);
+        outcome.apply_response(&models::RespSendTxBatch {
+            code: 21104,
+            message: Some("invalid nonce".into()),
+            ..Default::default()
+        });
+
+        assert!(outcome.is_settled());
+        assert_eq!(outcome.rejected().count(), 2);
+        assert_eq!(outcome.acks(true), vec![false, false]);
+    }
+
+    #[test]
+    fn account_tx_settles_each_leg_separately() {
+        let mut sim = exchange();
+        let entries = [create(1, 10), cancel(2, 999_999), create(3, 5)];
+
+        let response = sim.submit_batch(&entries);
+        let mut outcome = BatchOutcome::from_entries(&entries);
+        outcome.apply_response(&response);
+        assert!(outcome
+            .entries()
+            .iter()
+            .all(|entry| entry.outcome == TxOutcome::Accepted && entry.tx_hash.is_some()));
+
+        for event in account_events(&mut sim) {
+            outcome.apply(&event);
+        }
+        assert!(outcome.is_settled());
+        assert_eq!(
+            outcome
+                .confirmed()
+                .map(|entry| entry.index)
+                .collect::<Vec<_>>(),
+            vec![0, 2]
+        );
+        assert!(outcome.get(1).unwrap().outcome.is_rejected());
+    }
+
+    #[test]
+    fn unknown_entries_match_account_tx_by_nonce() {
+        let mut sim = exchange();
+        let entries = [create(5, 10)];
+        sim.submit_batch(&entries);
+
+        // The reply was lost; only the account stream saw the transaction.
+        let mut outcome = BatchOutcome::from_entries(&entries);
+        assert_eq!(outcome.acks(false), vec![false]);
+        for event in account_events(&mut sim) {
+            outcome.apply(&event);
+        }
+
+        let entry = outcome.get(0).unwrap();
+        assert_eq!(entry.outcome, TxOutcome::Confirmed);
+        assert!(entry.tx_hash.is_some());
+    }
+}
@@ -0,0 +1,593 @@
+//! Re-signing pipeline for batches that lose nonces.
+//!
+//! An entry refused at submission never consumes its nonce, so every later
+//! entry signed with the same API key is rejected with `invalid nonce`.
+//! Decrementing one counter (`acknowledge_nonce_failure`) cannot repair that.
+//! [`BatchRetry`] signs a batch from [`SignRequest`]s, finds the entries
+//! rejected for their nonce, hands the unused nonces back to the
+//! [`NonceManager`](crate::nonce_manager::NonceManager) (or refreshes the key
+//! from the server when the local counter drifted), re-signs those entries
+//! with fresh nonces and resubmits them within a [`RetryPolicy`].
+
+use std::{collections::BTreeMap, time::Duration};
+
+use async_trait::async_trait;
+use tracing::{debug, warn};
+
+use crate::{
+    batch_outcome::{BatchOutcome, TxOutcome},
+    errors::{BatchRetryError, SignerClientError},
+    signer_client::{is_invalid_nonce, BatchEntry, SignerClient},
+    tx_executor::{reconcile_batch_ws, send_batch_tx_ws_outcome, BatchAckMode},
+    tx_signer::SignRequest,
+    ws_client::WsConnection,
+};
+
+/// How often and how fast a failed submission is retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    pub max_attempts: u8,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Attempts left and the current backoff for one unit of work.
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    policy: RetryPolicy,
+    attempts: u8,
+    remaining: u8,
+    backoff: Duration,
+}
+
+impl RetryBudget {
+    pub fn new(policy: RetryPolicy) -> Self {
+        Self {
+            policy,
+            attempts: 0,
+            remaining: policy.max_attempts,
+            backoff: policy.initial_backoff,
+        }
+    }
+
+    /// Spend one attempt and double the backoff, capped at the policy maximum.
+    pub fn record_attempt(&mut self) {
+        self.attempts = self.attempts.saturating_add(1);
+        self.remaining = self.remaining.saturating_sub(1);
+        self.backoff = (self.backoff * 2).min(self.policy.max_backoff);
+    }
+
+    /// Restore the full budget after progress. The attempt count is kept.
+    pub fn reset(&mut self) {
+        self.remaining = self.policy.max_attempts;
+        self.backoff = self.policy.initial_backoff;
+    }
+
+    /// Attempts recorded so far, including those before a [`reset`](Self::reset).
+    pub fn attempts(&self) -> u8 {
+        self.attempts
+    }
+
+    pub fn remaining(&self) -> u8 {
+        self.remaining
+    }
+
+    pub fn backoff(&self) -> Duration {
+        self.backoff
+    }
+
+    pub fn exhausted(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+/// Rejected because the exchange did not accept the entry's nonce.
+pub fn is_nonce_rejection(outcome: &TxOutcome) -> bool {
+    match outcome {
+        TxOutcome::Rejected {
+            code: Some(code),
+            message,
+        } => is_invalid_nonce(*code, Some(message)),
+        _ => false,
+    }
+}
+
+/// Sends signed entries and reports one outcome per entry.
+#[async_trait]
+pub trait BatchSubmitter: Send {
+    async fn submit(&mut self, entries: &[BatchEntry]) -> Result<BatchOutcome, BatchRetryError>;
+}
+
+#[async_trait]
+impl BatchSubmitter for &SignerClient {
+    async fn submit(&mut self, entries: &[BatchEntry]) -> Result<BatchOutcome, BatchRetryError> {
+        Ok(self.send_tx_batch_outcome(entries).await?)
+    }
+}
+
+/// Submits over a [`WsConnection`], optionally settling each batch from
+/// `account_tx` before returning.
+pub struct WsSubmitter<'a> {
+    ws: &'a mut WsConnection,
+    mode: BatchAckMode,
+    reconcile: Option<Duration>,
+}
+
+impl<'a> WsSubmitter<'a> {
+    pub fn new(ws: &'a mut WsConnection, mode: BatchAckMode) -> Self {
+        Self {
+            ws,
+            mode,
+            reconcile: None,
+        }
+    }
+
+    /// Wait up to `timeout` for `account_tx` after each batch; see
+    /// [`reconcile_batch_ws`].
+    pub fn reconcile_for(mut self, timeout: Duration) -> Self {
+        self.reconcile = Some(timeout);
+        self
+    }
+}
+
+#[async_trait]
+impl BatchSubmitter for WsSubmitter<'_> {
+    async fn submit(&mut self, entries: &[BatchEntry]) -> Result<BatchOutcome, BatchRetryError> {
+        let txs = entries
+            .iter()
+            .map(|entry| {
+                let tx_type = u8::try_from(entry.tx_type).map_err(|_| {
+                    SignerClientError::InvalidInput(format!("tx type {}", entry.tx_type))
+                })?;
+                Ok((tx_type, entry.tx_info.clone()))
+            })
+            .collect::<Result<Vec<_>, SignerClientError>>()?;
+        let mut outcome = send_batch_tx_ws_outcome(self.ws, &txs, self.mode).await?;
+        if let Some(timeout) = self.reconcile {
+            reconcile_batch_ws(self.ws, &mut outcome, timeout).await?;
+        }
+        Ok(outcome)
+    }
+}
+
+/// One entry signed again under a new nonce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resubmission {
+    /// Position in the original batch.
+    pub index: usize,
+    /// Retry round, starting at 1.
+    pub attempt: u8,
+    pub api_key_index: i32,
+    pub previous_nonce: i64,
+    pub nonce: i64,
+}
+
+/// Result of [`BatchRetry::submit`].
+#[derive(Debug, Clone)]
+pub struct RetryReport {
+    /// Latest outcome of every entry, indexed like the submitted requests.
+    pub outcome: BatchOutcome,
+    pub resubmitted: Vec<Resubmission>,
+    /// Retry rounds used.
+    pub attempts: u8,
+    /// Entries were still rejected for their nonce when the budget ran out.
+    pub exhausted: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rollback {
+    Release {
+        api_key_index: i32,
+        first: i64,
+        last: i64,
+    },
+    Refresh {
+        api_key_index: i32,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct Signed {
+    request: SignRequest,
+    api_key_index: i32,
+    nonce: i64,
+    entry: BatchEntry,
+}
+
+/// Signs, submits and re-signs batches through a [`SignerClient`].
+pub struct BatchRetry<'a> {
+    client: &'a SignerClient,
+    policy: RetryPolicy,
+}
+
+impl<'a> BatchRetry<'a> {
+    pub fn new(client: &'a SignerClient) -> Self {
+        Self {
+            client,
+            policy: RetryPolicy::default(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sign `requests` with nonces from the client's nonce manager (their own
+    /// nonce fields are ignored), submit them and resubmit nonce rejections
+    /// until none are left or the budget is spent.
+    pub async fn submit<S>(
+        &self,
+        requests: Vec<SignRequest>,
+        submitter: &mut S,
+    ) -> Result<RetryReport, BatchRetryError>
+    where
+        S: BatchSubmitter + ?Sized,
+    {
+        let mut signed = Vec::with_capacity(requests.len());
+        for request in requests {
+            signed.push(self.sign_fresh(request).await?);
+        }
+        let entries: Vec<BatchEntry> = signed.iter().map(|tx| tx.entry.clone()).collect();
+        let mut outcome = submitter.submit(&entries).await?;
+        let mut submitted: Vec<usize> = (0..signed.len()).collect();
+        let mut budget = RetryBudget::new(self.policy);
+        let mut resubmitted = Vec::new();
+
+        loop {
+            let gap: Vec<usize> = submitted
+                .iter()
+                .copied()
+                .filter(|&index| is_nonce_rejection(&outcome.entries()[index].outcome))
+                .collect();
+            if gap.is_empty() {
+                break;
+            }
+            if budget.exhausted() {
+                warn!(
+                    "retry budget spent with {} entries still rejected for their nonce",
+                    gap.len()
+                );
+                break;
+            }
+
+            for step in rollback_plan(&signed, &submitted, &outcome) {
+                self.roll_back(step).await?;
+            }
+            tokio::time::sleep(budget.backoff()).await;
+            budget.record_attempt();
+
+            let mut retry_entries = Vec::with_capacity(gap.len());
+            for &index in &gap {
+                let fresh = self.sign_fresh(signed[index].request.clone()).await?;
+                resubmitted.push(Resubmission {
+                    index,
+                    attempt: budget.attempts(),
+                    api_key_index: fresh.api_key_index,
+                    previous_nonce: signed[index].nonce,
+                    nonce: fresh.nonce,
+                });
+                retry_entries.push(fresh.entry.clone());
+                signed[index] = fresh;
+            }
+            debug!(
+                "resubmitting {} entries (attempt {})",
+                gap.len(),
+                budget.attempts()
+            );
+            let retried = submitter.submit(&retry_entries).await?;
+            outcome.merge(&gap, retried);
+            submitted = gap;
+        }
+
+        let exhausted = submitted
+            .iter()
+            .any(|&index| is_nonce_rejection(&outcome.entries()[index].outcome));
+        Ok(RetryReport {
+            outcome,
+            resubmitted,
+            attempts: budget.attempts(),
+            exhausted,
+        })
+    }
+
+    async fn sign_fresh(&self, request: SignRequest) -> Result<Signed, BatchRetryError> {
+        let (api_key_index, nonce) = self.client.next_nonce().await?;
+        let request = request.with_nonce(nonce);
+        match self.client.sign_request(&request, api_key_index).await {
+            Ok(entry) => Ok(Signed {
+                request,
+                api_key_index,
+                nonce,
+                entry,
+            }),
+            Err(err) => {
+                self.client.acknowledge_nonce_failure(api_key_index).await?;
+                Err(err.into())
+            }
+        }
+    }
+
+    async fn roll_back(&self, step: Rollback) -> Result<(), BatchRetryError> {
+        match step {
+            Rollback::Release {
+                api_key_index,
+                first,
+                last,
+            } => {
+                if self.client.release_nonces(api_key_index, first, last).await {
+                    debug!(api_key_index, first, last, "released unused nonces");
+                    return Ok(());
+                }
+                // Later nonces were issued meanwhile; only the server knows
+                // where the key stands now.
+                self.client.refresh_nonce(api_key_index).await?;
+            }
+            Rollback::Refresh { api_key_index } => {
+                self.client.refresh_nonce(api_key_index).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decide how to repair each API key that had entries refused at submission.
+///
+/// Refused entries never consumed their nonces. When one of them was refused
+/// for another reason, the nonce rejections after it are a gap we opened, and
+/// the unused range can be released. When every refusal was a nonce
+/// rejection, the local counter itself is off and the key is refreshed.
+fn rollback_plan(signed: &[Signed], submitted: &[usize], outcome: &BatchOutcome) -> Vec<Rollback> {
+    struct KeyState {
+        first: i64,
+        last: i64,
+        gap_opened: bool,
+        nonce_rejected: bool,
+    }
+
+    let mut keys: BTreeMap<i32, KeyState> = BTreeMap::new();
+    for &index in submitted {
+        let outcome = &outcome.entries()[index].outcome;
+        if !matches!(outcome, TxOutcome::Rejected { code: Some(_), .. }) {
+            continue;
+        }
+        let tx = &signed[index];
+        let state = keys.entry(tx.api_key_index).or_insert(KeyState {
+            first: tx.nonce,
+            last: tx.nonce,
+            gap_opened: false,
+            nonce_rejected: false,
+        });
+        state.first = state.first.min(tx.nonce);
+        state.last = state.last.max(tx.nonce);
+        if is_nonce_rejection(outcome) {
+            state.nonce_rejected = true;
+        } else {
+            state.gap_opened = true;
+        }
+    }
+
+    keys.into_iter()
+        .filter(|(_, state)| state.nonce_rejected)
+        .map(|(api_key_index, state)| {
+            if state.gap_opened {
+                Rollback::Release {
+                    api_key_index,
+                    first: state.first,
+                    last: state.last,
+                }
+            } else {
+                Rollback::Refresh { api_key_index }
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        clock::system_clock,
+        nonce_manager::NonceManagerType,
+        order_book::BookScale,
+        signer_client::{CODE_INVALID_NONCE, CODE_OK},
+        sim_exchange::SimExchange,
+        sim_gateway::SimGateway,
+        tx_executor::{ORDER_TIME_IN_FORCE_POST_ONLY, ORDER_TYPE_LIMIT, TX_TYPE_CANCEL_ORDER},
+        tx_signer::MockSigner,
+        types::{AccountId, MarketId},
+    };
+
+    const CANCEL: i32 = TX_TYPE_CANCEL_ORDER as i32;
+
+    fn signed(api_key_index: i32, nonce: i64) -> Signed {
+        let request = SignRequest::SignCancelOrder {
+            market_index: 0,
+            order_index: 1,
+            nonce,
+        };
+        Signed {
+            request,
+            api_key_index,
+            nonce,
+            entry: BatchEntry::new(
+                CANCEL,
+                format!(r#"{{"ApiKeyIndex":{api_key_index},"Nonce":{nonce}}}"#),
+            ),
+        }
+    }
+
+    fn outcome_for(signed: &[Signed]) -> BatchOutcome {
+        BatchOutcome::from_entries(&signed.iter().map(|tx| tx.entry.clone()).collect::<Vec<_>>())
+    }
+
+    fn bid(base_amount: i64, price: i32) -> SignRequest {
+        SignRequest::SignCreateOrder {
+            market_index: 0,
+            client_order_index: 0,
+            base_amount,
+            price,
+            is_ask: false,
+            order_type: ORDER_TYPE_LIMIT,
+            time_in_force: ORDER_TIME_IN_FORCE_POST_ONLY,
+            reduce_only: false,
+            trigger_price: 0,
+            order_expiry: -1,
+            nonce: 0,
+        }
+    }
+
+    /// Sends each entry to the simulator on its own. The simulator only
+    /// checks that nonces increase, so the exchange's gapless rule is applied
+    /// here: once an entry is refused, the ones after it fail on their nonce.
+    struct OneByOne<'a>(&'a SimGateway);
+
+    #[async_trait]
+    impl BatchSubmitter for OneByOne<'_> {
+        async fn submit(
+            &mut self,
+            entries: &[BatchEntry],
+        ) -> Result<BatchOutcome, BatchRetryError> {
+            let mut outcome = BatchOutcome::from_entries(entries);
+            let mut gap = false;
+            for (index, entry) in entries.iter().enumerate() {
+                if gap {
+                    outcome.apply_reply(
+                        index..index + 1,
+                        CODE_INVALID_NONCE,
+                        Some("invalid nonce"),
+                        &[],
+                    );
+                    continue;
+                }
+                let reply = self
+                    .0
+                    .with_sim(|sim| sim.submit_tx(entry.tx_type(), entry.tx_info()));
+                gap = reply.code != CODE_OK;
+                outcome.apply_reply(
+                    index..index + 1,
+                    reply.code,
+                    reply.message.as_deref(),
+                    &[reply.tx_hash],
+                );
+            }
+            Ok(outcome)
+        }
+    }
+
+    fn reject(outcome: &mut BatchOutcome, index: usize, code: i32, message: &str) {
+        let mut single = BatchOutcome::from_entries(&[BatchEntry::new(CANCEL, "{}")]);
+        single.apply_reply(0..1, code, Some(message), &[]);
+        outcome.merge(&[index], single);
+    }
+
+    #[test]
+    fn budget_doubles_backoff_and_resets() {
+        let policy = RetryPolicy {
+            max_attempts: 2,
+            initial_backoff: Duration::from_millis(400),
+            max_backoff: Duration::from_secs(1),
+        };
+        let mut budget = RetryBudget::new(policy);
+        budget.record_attempt();
+        assert_eq!(budget.backoff(), Duration::from_millis(800));
+        budget.record_attempt();
+        assert_eq!(budget.backoff(), Duration::from_secs(1));
+        assert!(budget.exhausted());
+
+        budget.reset();
+        assert!(!budget.exhausted());
+        assert_eq!(budget.backoff(), policy.initial_backoff);
+        assert_eq!(budget.attempts(), 2);
+    }
+
+    #[test]
+    fn gap_after_a_refused_entry_is_released() {
+        let signed = [signed(2, 10), signed(2, 11), signed(2, 12), signed(3, 40)];
+        let mut outcome = outcome_for(&signed);
+        outcome.apply_reply(0..4, 200, None, &[]);
+        reject(&mut outcome, 1, 21120, "invalid order");
+        reject(&mut outcome, 2, 21104, "invalid nonce");
+
+        let plan = rollback_plan(&signed, &[0, 1, 2, 3], &outcome);
+        assert_eq!(
+            plan,
+            vec![Rollback::Release {
+                api_key_index: 2,
+                first: 11,
+                last: 12,
+            }]
+        );
+    }
+
+    #[test]
+    fn nonce_rejections_alone_refresh_the_key() {
+        let signed = [signed(2, 10), signed(2, 11)];
+        let mut outcome = outcome_for(&signed);
+        outcome.apply_reply(0..2, 21104, Some("invalid nonce"), &[]);
+
+        assert!(is_nonce_rejection(&outcome.entries()[0].outcome));
+        assert_eq!(
+            rollback_plan(&signed, &[0, 1], &outcome),
+            vec![Rollback::Refresh { api_key_index: 2 }]
+        );
+    }
+
+    #[tokio::test]
+    async fn refused_entry_releases_the_nonces_behind_it() {
+        let mut sim = SimExchange::new(AccountId::new(42));
+        sim.add_market(MarketId::new(0), BookScale::new(2, 4));
+        let gateway = SimGateway::start(sim, None, system_clock()).await.unwrap();
+        let client = SignerClient::with_signer(
+            gateway.url(),
+            Arc::new(MockSigner::new(42)),
+            2,
+            42,
+            None,
+            NonceManagerType::Optimistic,
+        )
+        .await
+        .unwrap();
+
+        let requests = vec![bid(500, 300_000), bid(0, 299_000), bid(500, 298_000)];
+        let report = BatchRetry::new(&client)
+            .submit(requests, &mut OneByOne(&gateway))
+            .await
+            .unwrap();
+
+        let outcomes: Vec<_> = report
+            .outcome
+            .entries()
+            .iter()
+            .map(|e| &e.outcome)
+            .collect();
+        assert_eq!(outcomes[0], &TxOutcome::Accepted);
+        assert!(outcomes[1].is_rejected() && !is_nonce_rejection(outcomes[1]));
+        assert_eq!(outcomes[2], &TxOutcome::Accepted);
+        assert!(!report.exhausted);
+        assert_eq!(report.attempts, 1);
+
+        // The refused entry's nonce was released and reused by the last one.
+        let [resubmission] = report.resubmitted[..] else {
+            panic!("expected one resubmission, got {:?}", report.resubmitted);
+        };
+        assert_eq!(resubmission.index, 2);
+        assert_eq!(resubmission.nonce, resubmission.previous_nonce - 1);
+        assert_eq!(
+            gateway.with_sim(|sim| sim.open_orders(MarketId::new(0)).len()),
+            2
+        );
+    }
+}
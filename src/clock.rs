@@ -0,0 +1,179 @@
+//! Injectable time sources.
+//!
+//! Components that make time-based decisions (strategy pauses, kill switch
+//! windows, auth token expiry, retry backoff) read the time through a
+//! [`Clock`] instead of calling [`Instant::now`] or [`SystemTime::now`]
+//! directly. Production code uses [`SystemClock`]; tests step a
+//! [`ManualClock`] and backtests follow event timestamps with a
+//! [`ReplayClock`].
+//!
+//! Only reading the time is abstracted. Code that waits still uses tokio
+//! timers, which a paused tokio runtime can step.
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// A source of monotonic and wall-clock time.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Monotonic time, in place of [`Instant::now`].
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, in place of [`SystemTime::now`].
+    fn system_now(&self) -> SystemTime;
+}
+
+/// A clock shared between the components it drives.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// The [`SystemClock`] as a [`SharedClock`].
+pub fn system_clock() -> SharedClock {
+    Arc::new(SystemClock)
+}
+
+/// The operating system's clocks.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn system_now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when [`advance`](Self::advance)d. Clones share
+/// the same time, so a test can keep one and hand [`shared`](Self::shared)
+/// to the component under test.
+#[derive(Debug, Clone)]
+pub struct ManualClock {
+    state: Arc<Mutex<(Instant, SystemTime)>>,
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::starting_at(SystemTime::now())
+    }
+}
+
+impl ManualClock {
+    /// Starts at the current time.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts with [`system_now`](Clock::system_now) at `system`.
+    pub fn starting_at(system: SystemTime) -> Self {
+        Self {
+            state: Arc::new(Mutex::new((Instant::now(), system))),
+        }
+    }
+
+    /// Move both clocks forward by `by`.
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().expect("clock lock poisoned");
+        state.0 += by;
+        state.1 += by;
+    }
+
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.state.lock().expect("clock lock poisoned").0
+    }
+
+    fn system_now(&self) -> SystemTime {
+        self.state.lock().expect("clock lock poisoned").1
+    }
+}
+
+/// A clock that follows the timestamps of replayed events.
+///
+/// [`observe`](Self::observe) moves it to an event's time; an event older
+/// than the current time leaves it unchanged, so time never runs backwards.
+/// Before the first event, [`system_now`](Clock::system_now) is the unix
+/// epoch. Clones share the same time.
+#[derive(Debug, Clone)]
+pub struct ReplayClock {
+    anchor: Instant,
+    /// Time of the first observed event and the current time.
+    state: Arc<Mutex<Option<(SystemTime, SystemTime)>>>,
+}
+
+impl Default for ReplayClock {
+    fn default() -> Self {
+        Self {
+            anchor: Instant::now(),
+            state: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl ReplayClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Move to `at` if it is later than the current time and return the
+    /// resulting [`now`](Clock::now).
+    pub fn observe(&self, at: SystemTime) -> Instant {
+        let mut state = self.state.lock().expect("clock lock poisoned");
+        let (origin, current) = state.get_or_insert((at, at));
+        if at > *current {
+            *current = at;
+        }
+        self.anchor + current.duration_since(*origin).unwrap_or_default()
+    }
+
+    pub fn shared(&self) -> SharedClock {
+        Arc::new(self.clone())
+    }
+}
+
+impl Clock for ReplayClock {
+    fn now(&self) -> Instant {
+        let state = self.state.lock().expect("clock lock poisoned");
+        match *state {
+            Some((origin, current)) => {
+                self.anchor + current.duration_since(origin).unwrap_or_default()
+            }
+            None => self.anchor,
+        }
+    }
+
+    fn system_now(&self) -> SystemTime {
+        let state = self.state.lock().expect("clock lock poisoned");
+        state.map_or(UNIX_EPOCH, |(_, current)| current)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_clock_follows_events_and_never_runs_backwards() {
+        let clock = ReplayClock::new();
+        let start = clock.now();
+        assert_eq!(clock.system_now(), UNIX_EPOCH);
+
+        let t0 = UNIX_EPOCH + Duration::from_secs(1_000);
+        assert_eq!(clock.observe(t0), start);
+        assert_eq!(clock.system_now(), t0);
+
+        let later = clock.observe(t0 + Duration::from_millis(250));
+        assert_eq!(later, start + Duration::from_millis(250));
+        assert_eq!(clock.observe(t0 + Duration::from_millis(100)), later);
+        assert_eq!(clock.system_now(), t0 + Duration::from_millis(250));
+        assert_eq!(clock.shared().now(), later);
+    }
+}
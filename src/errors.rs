@@ -2,6 +2,8 @@ use std::fmt;
 
 use thiserror::Error;
 
+use crate::{kill_switch::KillReason, types::MarketId};
+
 pub type Result<T> = std::result::Result<T, SignerClientError>;
 
 #[derive(Debug, Error)]
@@ -40,6 +42,8 @@ pub enum SignerClientError {
     Library(#[from] libloading::Error),
     #[error(transparent)]
     CString(#[from] std::ffi::NulError),
+    #[error(transparent)]
+    PreTrade(#[from] PreTradeError),
 }
 
 impl SignerClientError {
@@ -61,8 +65,35 @@ impl SignerClientError {
     }
 }
 
+/// Errors raised when converting human prices/sizes with a [`MarketSpec`](crate::market::MarketSpec).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum MarketSpecError {
+    #[error("invalid decimal {0:?}")]
+    InvalidDecimal(String),
+    #[error("{value} is not a multiple of 10^-{decimals}")]
+    NotRepresentable { value: String, decimals: u32 },
+    #[error("{0} overflows the market scale")]
+    Overflow(String),
+    #[error("value must be positive")]
+    NonPositive,
+    #[error("size of {units} units is below the market minimum of {min_units}")]
+    BelowMinBase { units: i64, min_units: i64 },
+    #[error("order notional is below the market minimum quote amount of {min_quote}")]
+    BelowMinQuote { min_quote: String },
+    #[error("touch-relative rounding requires an order side")]
+    SideRequired,
+    #[error("a market spec is required to convert decimal values")]
+    SpecRequired,
+    #[error("invalid market metadata field {0}")]
+    InvalidMetadata(&'static str),
+}
+
 pub type WsResult<T> = std::result::Result<T, WsClientError>;
 
+/// Result of decoding an account channel payload. The error is boxed so the
+/// decoded value, not the rarely taken error path, sets the size.
+pub type DecodeResult<T> = std::result::Result<T, Box<WsClientError>>;
+
 #[derive(Debug, Error)]
 pub enum WsClientError {
     #[error("no subscriptions provided")]
@@ -83,4 +114,122 @@ pub enum WsClientError {
     WebSocket(#[from] tokio_tungstenite::tungstenite::Error),
     #[error(transparent)]
     Json(#[from] serde_json::Error),
+    #[error("failed to decode {channel} payload: {source}")]
+    AccountDecode {
+        channel: &'static str,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("no response to transaction {0} before the timeout")]
+    TxTimeout(String),
+    #[error("connection dropped before transaction {0} was answered")]
+    TxAbandoned(String),
+    #[error("no live websocket connection can send transactions")]
+    NoTxConnection,
+    #[error("no acknowledgement for channel {0} before the timeout")]
+    SubscriptionTimeout(String),
+    #[error("subscription to {channel} rejected: {message}")]
+    SubscriptionRejected { channel: String, message: String },
+    #[error("{channel} is computed from {carrier}; subscribe to {carrier} first")]
+    MissingSource { channel: String, carrier: String },
+    #[error(transparent)]
+    PreTrade(#[from] PreTradeError),
+}
+
+/// Errors from [`BatchRetry`](crate::batch_retry::BatchRetry): signing and
+/// nonce bookkeeping go through the signer client, submission may go over
+/// either transport.
+#[derive(Debug, Error)]
+pub enum BatchRetryError {
+    #[error(transparent)]
+    Signer(#[from] SignerClientError),
+    #[error(transparent)]
+    Ws(#[from] WsClientError),
+}
+
+/// Errors from [`MarketRecorder`](crate::ws_recorder::MarketRecorder) and
+/// [`ReplayStream`](crate::ws_recorder::ReplayStream).
+#[derive(Debug, Error)]
+pub enum RecorderError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Ws(Box<WsClientError>),
+    #[error("corrupt recording segment {file}: {reason}")]
+    Corrupt { file: String, reason: String },
+    #[error("no recording segments in {0}")]
+    Empty(String),
+}
+
+impl From<WsClientError> for RecorderError {
+    fn from(err: WsClientError) -> Self {
+        RecorderError::Ws(Box::new(err))
+    }
+}
+
+/// Errors from [`RiskEngine`](crate::risk::RiskEngine).
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum RiskError {
+    #[error("no margin fractions loaded for market {0}")]
+    UnknownMarket(MarketId),
+    #[error("no mark price for market {0}")]
+    NoMarkPrice(MarketId),
+    #[error("invalid order: {0}")]
+    InvalidOrder(String),
+}
+
+/// An order stopped by a [`PreTradeCheck`](crate::pre_trade::PreTradeCheck)
+/// before signing.
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PreTradeError {
+    #[error("pre-trade: no market spec for market {0}")]
+    UnknownMarket(MarketId),
+    #[error("pre-trade: order for market {0} has no price to check")]
+    NoPrice(MarketId),
+    #[error("pre-trade: no mark price for market {0}")]
+    NoMarkPrice(MarketId),
+    #[error("pre-trade: order notional {notional} on market {market} exceeds {limit}")]
+    MaxOrderNotional {
+        market: MarketId,
+        notional: f64,
+        limit: f64,
+    },
+    #[error("pre-trade: position {projected} on market {market} would exceed {limit}")]
+    MaxPosition {
+        market: MarketId,
+        projected: f64,
+        limit: f64,
+    },
+    #[error("pre-trade: {open} open orders, limit {limit}")]
+    MaxOpenOrders { open: usize, limit: usize },
+    #[error(
+        "pre-trade: price {price} on market {market} is more than {limit} away from mark {mark}"
+    )]
+    PriceBand {
+        market: MarketId,
+        price: f64,
+        mark: f64,
+        limit: f64,
+    },
+    #[error(
+        "pre-trade: market {market} order below min size {min_size} / notional {min_notional}"
+    )]
+    BelowMinimum {
+        market: MarketId,
+        size: f64,
+        notional: f64,
+        min_size: f64,
+        min_notional: f64,
+    },
+    #[error("pre-trade: invalid {field} for market {market}")]
+    InvalidMinimum {
+        market: MarketId,
+        field: &'static str,
+    },
+    #[error("pre-trade: signed transaction of type {tx_type} is not a readable order")]
+    UnreadableOrder { tx_type: i32 },
+    #[error("pre-trade: daily PnL {pnl} has reached the loss limit {limit}")]
+    DailyLoss { pnl: f64, limit: f64 },
+    #[error("pre-trade: kill switch tripped ({0})")]
+    KillSwitch(KillReason),
 }
@@ -0,0 +1,636 @@
+//! Account-wide kill switch.
+//!
+//! A [`KillSwitch`] trips on an API call ([`trigger`](KillSwitch::trigger)),
+//! a Unix signal, a flag file appearing or a missed
+//! [`heartbeat`](KillSwitch::heartbeat). Once tripped it blocks every order
+//! that is not reduce-only until [`rearm`](KillSwitch::rearm)ed; add it to
+//! the client with
+//! [`LighterClientBuilder::kill_switch`](crate::lighter_client::LighterClientBuilder::kill_switch),
+//! which installs it as a [`PreTradeCheck`].
+//!
+//! [`KillSwitch::spawn`] runs the watchdog: it polls the triggers, and on a
+//! trip cancels all orders and, with
+//! [`flatten_slippage`](KillSwitchConfig::flatten_slippage) set, closes every
+//! position with reduce-only IOC market orders. In dead-man mode it also
+//! keeps a scheduled cancel-all rolling forward, so the exchange cancels
+//! everything if the process dies or hangs and stops renewing it.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use time::OffsetDateTime;
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    errors::{MarketSpecError, PreTradeError},
+    lighter_client::{Error, LighterClient, OrderTimeInForce, Result},
+    market::{DecimalSize, Rounding},
+    pre_trade::{PreTradeCheck, PreTradeOrder},
+    types::{Expiry, MarketId},
+};
+
+/// Why a [`KillSwitch`] tripped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KillReason {
+    Manual(String),
+    Signal(&'static str),
+    FlagFile(PathBuf),
+    MissedHeartbeat { silent: Duration },
+}
+
+impl fmt::Display for KillReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KillReason::Manual(reason) => write!(f, "manual: {reason}"),
+            KillReason::Signal(name) => write!(f, "received {name}"),
+            KillReason::FlagFile(path) => write!(f, "flag file {} present", path.display()),
+            KillReason::MissedHeartbeat { silent } => {
+                write!(f, "no heartbeat for {:.1}s", silent.as_secs_f64())
+            }
+        }
+    }
+}
+
+/// Keeps a scheduled cancel-all `window` ahead of now, renewed every
+/// `renew_every`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadManConfig {
+    pub window: Duration,
+    pub renew_every: Duration,
+}
+
+impl Default for DeadManConfig {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(300),
+            renew_every: Duration::from_secs(60),
+        }
+    }
+}
+
+/// What the watchdog started by [`KillSwitch::spawn`] watches and does.
+#[derive(Debug, Clone)]
+pub struct KillSwitchConfig {
+    /// Close positions after cancelling, with market orders allowed this
+    /// much slippage. `None` only cancels.
+    pub flatten_slippage: Option<f64>,
+    /// Trip when [`KillSwitch::heartbeat`] has not been called for this long.
+    pub heartbeat_timeout: Option<Duration>,
+    /// Trip when this file exists.
+    pub flag_file: Option<PathBuf>,
+    /// Trip on SIGINT or SIGTERM (Ctrl-C elsewhere). Watching a signal
+    /// replaces its default action, so once the watchdog has acted on one it
+    /// exits the process with the conventional `128 + signal` status.
+    pub signals: bool,
+    pub dead_man: Option<DeadManConfig>,
+    /// How often the heartbeat and flag file are checked.
+    pub poll_interval: Duration,
+}
+
+impl Default for KillSwitchConfig {
+    fn default() -> Self {
+        Self {
+            flatten_slippage: None,
+            heartbeat_timeout: None,
+            flag_file: None,
+            signals: false,
+            dead_man: None,
+            poll_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Shared trip state. Cheap to clone; clones trip and re-arm together.
+#[derive(Debug, Clone)]
+pub struct KillSwitch {
+    clock: SharedClock,
+    last_heartbeat: Arc<Mutex<Instant>>,
+    state: Arc<watch::Sender<Option<KillReason>>>,
+}
+
+impl Default for KillSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KillSwitch {
+    pub fn new() -> Self {
+        Self::with_clock(system_clock())
+    }
+
+    pub fn with_clock(clock: SharedClock) -> Self {
+        let (state, _) = watch::channel(None);
+        Self {
+            last_heartbeat: Arc::new(Mutex::new(clock.now())),
+            clock,
+            state: Arc::new(state),
+        }
+    }
+
+    /// Trip with a manual reason. Returns `false` if already tripped.
+    pub fn trigger(&self, reason: impl Into<String>) -> bool {
+        self.trip(KillReason::Manual(reason.into()))
+    }
+
+    /// Trip with `reason`. Returns `false` if already tripped, in which case
+    /// the first reason is kept.
+    pub fn trip(&self, reason: KillReason) -> bool {
+        let tripped = self.state.send_if_modified(|state| {
+            if state.is_some() {
+                return false;
+            }
+            *state = Some(reason.clone());
+            true
+        });
+        if tripped {
+            tracing::error!(reason = %reason, "kill switch tripped");
+        }
+        tripped
+    }
+
+    /// Allow orders again and restart the heartbeat timeout. Remove the flag
+    /// file first, or the watchdog trips again on its next poll.
+    pub fn rearm(&self) {
+        self.heartbeat();
+        if self.state.send_replace(None).is_some() {
+            tracing::info!("kill switch re-armed");
+        }
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.state.borrow().is_some()
+    }
+
+    pub fn reason(&self) -> Option<KillReason> {
+        self.state.borrow().clone()
+    }
+
+    /// The trip reason, updated on every trip and re-arm.
+    pub fn subscribe(&self) -> watch::Receiver<Option<KillReason>> {
+        self.state.subscribe()
+    }
+
+    /// Tell the watchdog the caller is alive.
+    pub fn heartbeat(&self) {
+        *self.last_heartbeat.lock().expect("heartbeat lock poisoned") = self.clock.now();
+    }
+
+    pub fn since_heartbeat(&self) -> Duration {
+        let last = *self.last_heartbeat.lock().expect("heartbeat lock poisoned");
+        self.clock.now().saturating_duration_since(last)
+    }
+
+    /// Trip if no heartbeat arrived within `timeout`.
+    pub fn check_heartbeat(&self, timeout: Duration) -> bool {
+        let silent = self.since_heartbeat();
+        silent > timeout && self.trip(KillReason::MissedHeartbeat { silent })
+    }
+
+    /// Trip if `path` exists.
+    pub fn check_flag_file(&self, path: &Path) -> bool {
+        path.exists() && self.trip(KillReason::FlagFile(path.to_path_buf()))
+    }
+
+    /// Cancel all orders and, if configured, flatten every position. Orders
+    /// are cancelled even when the switch is not tripped.
+    pub async fn execute(&self, client: &LighterClient, config: &KillSwitchConfig) -> Result<()> {
+        let cancelled = client
+            .cancel_all()
+            .tif(OrderTimeInForce::ImmediateOrCancel)
+            .submit()
+            .await;
+        if let Err(err) = &cancelled {
+            tracing::error!(error = %err, "kill switch cancel-all failed");
+        }
+        if let Some(max_slippage) = config.flatten_slippage {
+            let closed = flatten(client, max_slippage).await?;
+            tracing::warn!(positions = closed, "kill switch flattened positions");
+        }
+        cancelled.map(|_| ())
+    }
+
+    /// Run the watchdog until the task is dropped, acting on every trip.
+    pub async fn run(&self, client: &LighterClient, config: KillSwitchConfig) {
+        let mut poll = tokio::time::interval(config.poll_interval);
+        let mut renew = config
+            .dead_man
+            .map(|dead_man| tokio::time::interval(dead_man.renew_every));
+        let mut signals = Signals::new(config.signals);
+        let mut state = self.subscribe();
+        let mut handled = false;
+
+        loop {
+            tokio::select! {
+                _ = poll.tick() => {
+                    if let Some(timeout) = config.heartbeat_timeout {
+                        self.check_heartbeat(timeout);
+                    }
+                    if let Some(path) = &config.flag_file {
+                        self.check_flag_file(path);
+                    }
+                }
+                _ = tick(&mut renew) => {
+                    if let Some(dead_man) = config.dead_man {
+                        self.renew_dead_man(client, dead_man.window).await;
+                    }
+                }
+                name = signals.recv() => {
+                    self.trip(KillReason::Signal(name));
+                }
+                changed = state.changed() => {
+                    if changed.is_err() {
+                        return;
+                    }
+                }
+            }
+
+            match (self.reason(), handled) {
+                (Some(reason), false) => {
+                    handled = true;
+                    if let Err(err) = self.execute(client, &config).await {
+                        tracing::error!(error = %err, "kill switch could not stop trading");
+                    }
+                    if let KillReason::Signal(name) = reason {
+                        tracing::warn!(signal = name, "exiting after kill switch");
+                        std::process::exit(signal_exit_code(name));
+                    }
+                }
+                (None, true) => handled = false,
+                _ => {}
+            }
+        }
+    }
+
+    /// Run the watchdog on a tokio task, stopped when the returned handle is
+    /// dropped.
+    pub fn spawn(&self, client: Arc<LighterClient>, config: KillSwitchConfig) -> KillSwitchTask {
+        let switch = self.clone();
+        KillSwitchTask {
+            task: tokio::spawn(async move { switch.run(&client, config).await }),
+        }
+    }
+
+    /// Push the scheduled cancel-all `window` past now, unless tripped.
+    async fn renew_dead_man(&self, client: &LighterClient, window: Duration) {
+        if self.is_tripped() {
+            return;
+        }
+        let at = OffsetDateTime::from(self.clock.system_now() + window);
+        // Cancel-all reuses the order time-in-force codes: GoodTillTime is
+        // the scheduled variant.
+        let scheduled = client
+            .cancel_all()
+            .tif(OrderTimeInForce::GoodTillTime)
+            .expires_at(Expiry::from(at))
+            .submit()
+            .await;
+        if let Err(err) = scheduled {
+            tracing::warn!(error = %err, "dead-man cancel-all renewal failed");
+        }
+    }
+}
+
+impl PreTradeCheck for KillSwitch {
+    fn name(&self) -> &'static str {
+        "kill_switch"
+    }
+
+    /// Reduce-only orders still pass so positions can be closed.
+    fn check(&self, order: &PreTradeOrder) -> std::result::Result<(), PreTradeError> {
+        match self.reason() {
+            Some(reason) if !order.reduce_only => Err(PreTradeError::KillSwitch(reason)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Watchdog started by [`KillSwitch::spawn`]. Dropping it stops the task;
+/// a scheduled dead-man cancel then fires once its window passes.
+#[derive(Debug)]
+pub struct KillSwitchTask {
+    task: JoinHandle<()>,
+}
+
+impl KillSwitchTask {
+    pub fn is_finished(&self) -> bool {
+        self.task.is_finished()
+    }
+}
+
+impl Drop for KillSwitchTask {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Close every open position of the client's account with a reduce-only
+/// market order, the way [`close_position`](crate::tx_executor::close_position)
+/// does over WebSocket. Returns the number of orders accepted; positions
+/// below the market's minimum size are skipped. Every other position is
+/// still attempted when one fails, and the last failure is returned,
+/// including a reported size that does not parse.
+pub async fn flatten(client: &LighterClient, max_slippage: f64) -> Result<usize> {
+    let details = client.account().details().await?;
+    let Some(account) = details.accounts.first() else {
+        return Ok(0);
+    };
+    let specs = client.orders().market_specs().await?;
+
+    let mut closed = 0;
+    let mut failure = None;
+    for position in &account.positions {
+        let market = MarketId::new(position.market_id);
+        let size = match position.position.trim().parse::<f64>() {
+            Ok(size) => size.abs(),
+            Err(_) => {
+                tracing::error!(market = %market, position = %position.position, "unreadable position size");
+                failure = Some(Error::MarketSpec {
+                    field: "position",
+                    source: MarketSpecError::InvalidDecimal(position.position.clone()),
+                });
+                continue;
+            }
+        };
+        if position.sign == 0 || size == 0.0 {
+            continue;
+        }
+        let Some(spec) = specs.get(&market) else {
+            tracing::warn!(market = %market, "no market spec, position left open");
+            continue;
+        };
+        let qty = match DecimalSize::from_f64(size).and_then(|s| spec.size(s, Rounding::Nearest)) {
+            Ok(qty) => qty,
+            Err(err) => {
+                tracing::warn!(market = %market, size, error = %err, "position not closable");
+                continue;
+            }
+        };
+        // `sign` is the direction; `position` is always positive.
+        let order = if position.sign > 0 {
+            client.order(market).sell()
+        } else {
+            client.order(market).buy()
+        };
+        let submitted = order
+            .qty(qty)
+            .reduce_only()
+            .market()
+            .with_slippage(max_slippage)
+            .submit()
+            .await;
+        match submitted {
+            Ok(_) => closed += 1,
+            Err(err) => {
+                tracing::error!(market = %market, error = %err, "failed to close position");
+                failure = Some(err);
+            }
+        }
+    }
+    failure.map_or(Ok(closed), Err)
+}
+
+/// Exit status of a process ended by `name`.
+fn signal_exit_code(name: &str) -> i32 {
+    match name {
+        "SIGTERM" => 128 + 15,
+        _ => 128 + 2,
+    }
+}
+
+async fn tick(interval: &mut Option<tokio::time::Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Termination signals the watchdog trips on.
+struct Signals {
+    #[cfg(unix)]
+    streams: Vec<(&'static str, tokio::signal::unix::Signal)>,
+    #[cfg(not(unix))]
+    enabled: bool,
+}
+
+#[cfg(unix)]
+impl Signals {
+    fn new(enabled: bool) -> Self {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let kinds = [
+            ("SIGINT", SignalKind::interrupt()),
+            ("SIGTERM", SignalKind::terminate()),
+        ];
+        let streams = kinds
+            .into_iter()
+            .filter(|_| enabled)
+            .filter_map(|(name, kind)| match signal(kind) {
+                Ok(stream) => Some((name, stream)),
+                Err(err) => {
+                    tracing::warn!(signal = name, error = %err, "cannot watch signal");
+                    None
+                }
+            })
+            .collect();
+        Self { streams }
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        if self.streams.is_empty() {
+            return std::future::pending().await;
+        }
+        let pending = self
+            .streams
+            .iter_mut()
+            .map(|(name, stream)| Box::pin(async move { stream.recv().await.map(|_| *name) }));
+        match futures_util::future::select_all(pending).await.0 {
+            Some(name) => name,
+            None => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(not(unix))]
+impl Signals {
+    fn new(enabled: bool) -> Self {
+        Self { enabled }
+    }
+
+    async fn recv(&mut self) -> &'static str {
+        if !self.enabled {
+            return std::future::pending().await;
+        }
+        match tokio::signal::ctrl_c().await {
+            Ok(()) => "Ctrl-C",
+            Err(_) => std::future::pending().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::{
+        clock::ManualClock,
+        order_book::BookScale,
+        pre_trade::PreTradeAction,
+        sim_exchange::SimExchange,
+        tx_executor::{ORDER_TIME_IN_FORCE_GTT, ORDER_TIME_IN_FORCE_IOC},
+        tx_signer::{MockSigner, SignRequest},
+        types::AccountId,
+        ws_client::TradeSide,
+    };
+
+    fn order(reduce_only: bool) -> PreTradeOrder {
+        PreTradeOrder {
+            market: MarketId::new(1),
+            action: PreTradeAction::Create,
+            side: Some(TradeSide::Buy),
+            size: 1.0,
+            price: Some(100.0),
+            reduce_only,
+        }
+    }
+
+    #[test]
+    fn tripped_switch_blocks_new_risk_until_rearmed() {
+        let switch = KillSwitch::new();
+        assert!(switch.check(&order(false)).is_ok());
+
+        assert!(switch.trigger("operator"));
+        assert!(!switch.trigger("again"));
+        assert_eq!(
+            switch.check(&order(false)),
+            Err(PreTradeError::KillSwitch(KillReason::Manual(
+                "operator".to_string()
+            )))
+        );
+        assert!(switch.check(&order(true)).is_ok());
+
+        switch.rearm();
+        assert!(!switch.is_tripped());
+        assert!(switch.check(&order(false)).is_ok());
+    }
+
+    #[test]
+    fn missed_heartbeat_trips() {
+        let clock = ManualClock::new();
+        let switch = KillSwitch::with_clock(clock.shared());
+        let timeout = Duration::from_secs(5);
+
+        clock.advance(Duration::from_secs(4));
+        assert!(!switch.check_heartbeat(timeout));
+        switch.heartbeat();
+        clock.advance(Duration::from_secs(4));
+        assert!(!switch.check_heartbeat(timeout));
+
+        clock.advance(Duration::from_secs(2));
+        assert!(switch.check_heartbeat(timeout));
+        assert_eq!(
+            switch.reason(),
+            Some(KillReason::MissedHeartbeat {
+                silent: Duration::from_secs(6)
+            })
+        );
+    }
+
+    async fn simulated_client(signer: Arc<MockSigner>) -> Arc<LighterClient> {
+        let mut sim = SimExchange::new(AccountId::new(42));
+        sim.add_market(MarketId::new(0), BookScale::new(2, 4));
+        let client = LighterClient::builder()
+            .simulated(sim)
+            .tx_signer(signer)
+            .api_key_index(2)
+            .account_index(42)
+            .build()
+            .await
+            .unwrap();
+        Arc::new(client)
+    }
+
+    /// `(time_in_force, time)` of every cancel-all signed so far.
+    fn cancel_alls(signer: &MockSigner) -> Vec<(i32, i64)> {
+        signer
+            .calls()
+            .into_iter()
+            .filter_map(|call| match call {
+                SignRequest::SignCancelAllOrders {
+                    time_in_force,
+                    time,
+                    ..
+                } => Some((time_in_force, time)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flag_file_trips() {
+        let path = std::env::temp_dir().join(format!("kill-switch-{}.flag", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let switch = KillSwitch::with_clock(ManualClock::new().shared());
+
+        assert!(!switch.check_flag_file(&path));
+        std::fs::write(&path, b"").unwrap();
+        assert!(switch.check_flag_file(&path));
+        assert_eq!(switch.reason(), Some(KillReason::FlagFile(path.clone())));
+        assert!(!switch.check_flag_file(&path));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn watchdog_acts_once_per_trip() {
+        let signer = Arc::new(MockSigner::new(42));
+        let client = simulated_client(Arc::clone(&signer)).await;
+        let switch = KillSwitch::with_clock(ManualClock::new().shared());
+        let config = KillSwitchConfig {
+            poll_interval: Duration::from_millis(5),
+            ..KillSwitchConfig::default()
+        };
+        let _task = switch.spawn(Arc::clone(&client), config);
+        let immediate = (ORDER_TIME_IN_FORCE_IOC, 0);
+
+        switch.trigger("first");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cancel_alls(&signer), vec![immediate]);
+
+        switch.rearm();
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        switch.trigger("second");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(cancel_alls(&signer), vec![immediate, immediate]);
+    }
+
+    #[tokio::test]
+    async fn dead_man_renews_until_tripped() {
+        let signer = Arc::new(MockSigner::new(42));
+        let client = simulated_client(Arc::clone(&signer)).await;
+        let clock = ManualClock::starting_at(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+        let switch = KillSwitch::with_clock(clock.shared());
+        let window = Duration::from_secs(300);
+
+        switch.renew_dead_man(&client, window).await;
+        clock.advance(Duration::from_secs(10));
+        switch.renew_dead_man(&client, window).await;
+        let scheduled = |secs: i64| (ORDER_TIME_IN_FORCE_GTT, (secs + 300) * 1_000);
+        assert_eq!(
+            cancel_alls(&signer),
+            vec![scheduled(1_700_000_000), scheduled(1_700_000_010)]
+        );
+
+        switch.trigger("stop");
+        clock.advance(Duration::from_secs(10));
+        switch.renew_dead_man(&client, window).await;
+        assert_eq!(cancel_alls(&signer).len(), 2);
+    }
+}
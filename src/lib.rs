@@ -7,36 +7,73 @@ extern crate serde_json;
 extern crate serde_repr;
 extern crate url;
 
+pub mod account_events;
 pub mod apis;
 pub mod avellaneda;
+pub mod batch_outcome;
+pub mod batch_retry;
+pub mod clock;
 pub mod errors;
+pub mod kill_switch;
 pub mod lighter_client;
+pub mod market;
 pub mod models;
 pub mod nonce_manager;
+pub mod order_book;
+pub mod order_manager;
+pub mod position_book;
+pub mod pre_trade;
+pub mod rate_limit;
+#[cfg(unix)]
+pub mod remote_signer;
+pub mod risk;
 pub mod signer;
 pub mod signer_client;
+pub mod sim_exchange;
+pub mod sim_gateway;
 pub(crate) mod timings;
 pub mod trading_helpers;
 pub mod transactions;
 pub mod tx_executor;
+pub mod tx_signer;
 pub mod types;
 pub mod ws_client;
+pub mod ws_health;
+pub mod ws_pool;
+pub mod ws_recorder;
 
+pub use account_events::{AccountChannel, TypedAccountEvent};
+pub use batch_outcome::{BatchOutcome, EntryOutcome, TxOutcome};
+pub use batch_retry::{BatchRetry, RetryPolicy, RetryReport};
+pub use clock::{Clock, ManualClock, ReplayClock, SharedClock, SystemClock};
+pub use kill_switch::{KillReason, KillSwitch, KillSwitchConfig};
 pub use lighter_client::{
     Error as LighterError, LighterClient, LighterClientBuilder, LighterClientOptions, OrderBuilder,
     OrderSide, OrderStateInit, OrderTimeInForce, Result as LighterResult, Submission,
 };
+pub use position_book::{PositionBook, PositionDirection, PositionDrift, TrackedPosition};
+pub use pre_trade::{PreTradeChain, PreTradeCheck, PreTradeOrder};
+pub use rate_limit::{RateLimitConfig, RateLimiter, RequestClass, RestRetryPolicy};
+pub use risk::{MarginScope, OrderIntent, RiskConfig, RiskEngine, RiskWarning, WhatIf};
 pub use signer_client::{BatchEntry, SignedPayload};
 pub use tx_executor::{
-    close_position, send_batch_tx_ws, send_tx_ws, Position, ORDER_TIME_IN_FORCE_GTT,
-    ORDER_TIME_IN_FORCE_IOC, ORDER_TIME_IN_FORCE_POST_ONLY, ORDER_TYPE_LIMIT, ORDER_TYPE_MARKET,
-    ORDER_TYPE_STOP_LOSS, ORDER_TYPE_STOP_LOSS_LIMIT, ORDER_TYPE_TAKE_PROFIT,
-    ORDER_TYPE_TAKE_PROFIT_LIMIT, TX_TYPE_CANCEL_ALL_ORDERS, TX_TYPE_CANCEL_ORDER,
-    TX_TYPE_CHANGE_PUB_KEY, TX_TYPE_CREATE_ORDER, TX_TYPE_CREATE_PUBLIC_POOL,
-    TX_TYPE_CREATE_SUB_ACCOUNT, TX_TYPE_MODIFY_ORDER, TX_TYPE_TRANSFER, TX_TYPE_WITHDRAW,
+    close_position, reconcile_batch_ws, send_batch_tx_ws, send_batch_tx_ws_outcome, send_tx_ws,
+    send_tx_ws_with_response, Position, ORDER_TIME_IN_FORCE_GTT, ORDER_TIME_IN_FORCE_IOC,
+    ORDER_TIME_IN_FORCE_POST_ONLY, ORDER_TYPE_LIMIT, ORDER_TYPE_MARKET, ORDER_TYPE_STOP_LOSS,
+    ORDER_TYPE_STOP_LOSS_LIMIT, ORDER_TYPE_TAKE_PROFIT, ORDER_TYPE_TAKE_PROFIT_LIMIT,
+    TX_TYPE_CANCEL_ALL_ORDERS, TX_TYPE_CANCEL_ORDER, TX_TYPE_CHANGE_PUB_KEY, TX_TYPE_CREATE_ORDER,
+    TX_TYPE_CREATE_PUBLIC_POOL, TX_TYPE_CREATE_SUB_ACCOUNT, TX_TYPE_MODIFY_ORDER, TX_TYPE_TRANSFER,
+    TX_TYPE_WITHDRAW,
 };
+pub use tx_signer::{MockSigner, TxSigner};
 pub use ws_client::{
-    CloseFrameInfo, ExponentialBackoff, OrderBookDelta, OrderBookEvent, OrderBookLevel,
-    OrderBookState, SubscriptionSet, WsBuilder, WsClient, WsConfig, WsConnection, WsEvent,
-    WsStream,
+    Channel, CloseFrameInfo, ExponentialBackoff, OrderBookDelta, OrderBookEvent, OrderBookLevel,
+    OrderBookState, PendingTx, SubscriptionSet, SubscriptionStatus, SupervisorConfig, TxResponse,
+    WsBuilder, WsClient, WsConfig, WsConnection, WsEvent, WsStream,
+};
+pub use ws_health::{ChannelHealth, HealthConfig, HealthSnapshot, LatencyHistogram};
+pub use ws_pool::{LinkHealth, LinkRole, LinkState, TxSender, WsPool, WsPoolConfig};
+pub use ws_recorder::{
+    MarketRecorder, RecorderConfig, RecorderTap, RecordingIndex, RecordingSummary, ReplaySpeed,
+    ReplayStream, SegmentInfo,
 };
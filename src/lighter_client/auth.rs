@@ -1,25 +1,27 @@
 use std::{
     sync::Arc,
-    time::{Duration, Instant, SystemTime},
+    time::{Duration, Instant},
 };
 
 use tokio::sync::Mutex;
 
 use super::errors::{Error, Result};
-use crate::signer_client::SignerClient;
+use crate::{
+    clock::{system_clock, SharedClock},
+    signer_client::SignerClient,
+};
 
 const AUTH_TTL_SECS: u64 = 9 * 60;
 
 #[derive(Clone)]
 pub(crate) struct AuthCache {
     inner: Arc<Mutex<AuthCacheState>>,
+    clock: SharedClock,
 }
 
 impl Default for AuthCache {
     fn default() -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(AuthCacheState::default())),
-        }
+        Self::with_clock(system_clock())
     }
 }
 
@@ -30,10 +32,10 @@ struct AuthCacheState {
 }
 
 impl AuthCacheState {
-    fn is_valid(&self) -> bool {
+    fn is_valid(&self, now: Instant) -> bool {
         matches!(
             (&self.token, self.expires_at),
-            (Some(_), Some(exp)) if Instant::now() < exp
+            (Some(_), Some(exp)) if now < exp
         )
     }
 
@@ -53,20 +55,29 @@ impl AuthCacheState {
 }
 
 impl AuthCache {
+    /// A cache that checks token expiry against `clock`.
+    pub(crate) fn with_clock(clock: SharedClock) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(AuthCacheState::default())),
+            clock,
+        }
+    }
+
     pub(crate) async fn header(&self, signer: &SignerClient) -> Result<String> {
         if let Some(token) = self.current_token().await {
             return Ok(token);
         }
 
         let issued = signer.create_auth_token_with_expiry(None)?;
+        let now = self.clock.now();
         let expiry = issued
             .expires_at
-            .and_then(|deadline| deadline.duration_since(SystemTime::now()).ok())
-            .map(|duration| Instant::now() + duration)
-            .unwrap_or_else(|| Instant::now() + Duration::from_secs(AUTH_TTL_SECS));
+            .and_then(|deadline| deadline.duration_since(self.clock.system_now()).ok())
+            .map(|duration| now + duration)
+            .unwrap_or_else(|| now + Duration::from_secs(AUTH_TTL_SECS));
 
         let mut guard = self.inner.lock().await;
-        if guard.is_valid() {
+        if guard.is_valid(self.clock.now()) {
             return guard.token().ok_or_else(Self::cache_empty_error);
         }
 
@@ -86,7 +97,7 @@ impl AuthCache {
 
     async fn current_token(&self) -> Option<String> {
         let guard = self.inner.lock().await;
-        if guard.is_valid() {
+        if guard.is_valid(self.clock.now()) {
             guard.token()
         } else {
             None
@@ -100,3 +111,27 @@ impl AuthCache {
         }
     }
 }
+
+/// Owned pairing of the [`AuthCache`] with the signer that issues its tokens.
+#[derive(Clone)]
+pub(crate) struct AuthTokenSource {
+    cache: AuthCache,
+    signer: Arc<SignerClient>,
+}
+
+impl AuthTokenSource {
+    pub(crate) fn new(cache: AuthCache, signer: Arc<SignerClient>) -> Self {
+        Self { cache, signer }
+    }
+
+    /// Return the cached token, issuing a fresh one if it expired.
+    pub(crate) async fn token(&self) -> Result<String> {
+        self.cache.header(&self.signer).await
+    }
+}
+
+impl std::fmt::Debug for AuthTokenSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthTokenSource").finish_non_exhaustive()
+    }
+}
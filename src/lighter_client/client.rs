@@ -3,13 +3,14 @@ use std::{
     fmt,
     marker::PhantomData,
     path::PathBuf,
+    sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
 
 use futures_util::Stream;
 
 use super::{
-    auth::AuthCache,
+    auth::{AuthCache, AuthTokenSource},
     errors::{Error, Result},
     pagination::paginate_items,
     params::{
@@ -21,10 +22,19 @@ use super::{
 };
 use crate::{
     apis::configuration,
+    clock::{system_clock, SharedClock},
+    errors::MarketSpecError,
+    kill_switch::KillSwitch,
+    market::{DecimalPrice, DecimalSize, MarketSpec, Rounding},
     models,
     nonce_manager::NonceManagerType,
+    pre_trade::{PreTradeChain, PreTradeCheck},
+    rate_limit::{RateLimitConfig, RateLimiter, RequestPolicy, RestRetryPolicy},
     signer_client::{SignedPayload, SignerClient},
+    sim_exchange::SimExchange,
+    sim_gateway::SimGateway,
     transactions,
+    tx_signer::TxSigner,
     types::{AccountId, ApiKeyIndex, BaseQty, Expiry, MarketId, Nonce, Price, UsdcAmount},
     ws_client::{WsBuilder, WsConfig},
 };
@@ -37,6 +47,10 @@ pub struct LighterClientOptions {
     nonce_management: NonceManagerType,
     signer_library_path: Option<PathBuf>,
     websocket: Option<WsConfig>,
+    clock: SharedClock,
+    rate_limit: Option<RateLimitConfig>,
+    retry: Option<RestRetryPolicy>,
+    pre_trade: PreTradeChain,
 }
 
 impl Default for LighterClientOptions {
@@ -47,6 +61,10 @@ impl Default for LighterClientOptions {
             nonce_management: NonceManagerType::Optimistic,
             signer_library_path: None,
             websocket: None,
+            clock: system_clock(),
+            rate_limit: None,
+            retry: None,
+            pre_trade: PreTradeChain::default(),
         }
     }
 }
@@ -76,17 +94,44 @@ impl LighterClientOptions {
         self.websocket = Some(config);
         self
     }
+
+    /// Clock used for auth token expiry.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Hold REST calls, transaction submissions included, to `config`'s
+    /// budgets.
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limit = Some(config);
+        self
+    }
+
+    /// Retry failed REST calls per `policy`. Submissions are only resent
+    /// once the exchange is known not to have accepted them.
+    pub fn with_retry_policy(mut self, policy: RestRetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Checks every order must pass before it is signed.
+    pub fn with_pre_trade(mut self, chain: PreTradeChain) -> Self {
+        self.pre_trade = chain;
+        self
+    }
 }
 
 /// Top level wrapper that exposes a high level API for both REST and
 /// transaction signing capabilities.
 pub struct LighterClient {
     rest: RestClient,
-    signer: Option<SignerClient>,
+    signer: Option<Arc<SignerClient>>,
     ws_cfg: WsConfig,
     auth: AuthCache,
     account_id: Option<AccountId>,
     opts: LighterClientOptions,
+    simulator: Option<Arc<SimGateway>>,
 }
 
 impl LighterClient {
@@ -95,9 +140,11 @@ impl LighterClient {
         LighterClientBuilder {
             api_url: None,
             private_key: None,
+            tx_signer: None,
             api_key_index: None,
             account_index: None,
             options: LighterClientOptions::default(),
+            simulator: None,
         }
     }
 
@@ -111,8 +158,15 @@ impl LighterClient {
         api_url: impl Into<String>,
         options: LighterClientOptions,
     ) -> Result<Self> {
-        let auth = AuthCache::default();
-        let rest = RestClient::new(api_url, auth.clone());
+        let auth = AuthCache::with_clock(options.clock.clone());
+        let mut rest = RestClient::new(api_url, auth.clone());
+        rest.set_policy(RequestPolicy {
+            limiter: options
+                .rate_limit
+                .clone()
+                .map(|config| Arc::new(RateLimiter::with_clock(config, options.clock.clone()))),
+            retry: options.retry,
+        });
         let ws_cfg = options.websocket.clone().unwrap_or_else(WsConfig::default);
 
         Ok(Self {
@@ -122,6 +176,7 @@ impl LighterClient {
             auth,
             account_id: None,
             opts: options,
+            simulator: None,
         })
     }
 
@@ -136,9 +191,31 @@ impl LighterClient {
         self.account_id
     }
 
+    /// Clock this client was configured with.
+    pub fn clock(&self) -> &SharedClock {
+        &self.opts.clock
+    }
+
+    /// Rate limiter shared by REST calls and transaction submissions, if one
+    /// was configured.
+    pub fn rate_limiter(&self) -> Option<&Arc<RateLimiter>> {
+        self.rest.policy().limiter.as_ref()
+    }
+
+    /// Checks run on every order before it is signed.
+    pub fn pre_trade(&self) -> &PreTradeChain {
+        &self.opts.pre_trade
+    }
+
+    /// Simulator answering this client's transactions and account channels,
+    /// when it was built with [`LighterClientBuilder::simulated`].
+    pub fn simulator(&self) -> Option<&SimGateway> {
+        self.simulator.as_deref()
+    }
+
     /// Access the inner [`SignerClient`] for advanced use cases.
     pub fn signer(&self) -> Option<&SignerClient> {
-        self.signer.as_ref()
+        self.signer.as_deref()
     }
 
     /// Access account scoped REST helpers.
@@ -210,6 +287,44 @@ impl LighterClient {
         WithdrawBuilder::new(self, amount)
     }
 
+    /// Create a builder that modifies the size or price of a resting order.
+    pub fn modify(&self, market: MarketId, order_index: i64) -> ModifyOrderBuilder<'_> {
+        ModifyOrderBuilder::new(self, market, order_index)
+    }
+
+    /// Create a builder that transfers USDC from the active account to another account.
+    ///
+    /// Transfers require an L1 signature, so the Ethereum private key owning the
+    /// account must be supplied alongside the API key.
+    pub fn transfer(
+        &self,
+        to: AccountId,
+        amount: UsdcAmount,
+        eth_private_key: impl Into<String>,
+    ) -> TransferBuilder<'_> {
+        TransferBuilder::new(self, to, amount, eth_private_key.into())
+    }
+
+    /// Create a builder that updates leverage and margin mode for a market.
+    pub fn leverage(&self, market: MarketId) -> LeverageBuilder<'_> {
+        LeverageBuilder::new(self, market)
+    }
+
+    /// Create a builder that adds or removes isolated margin for a market.
+    pub fn margin(&self, market: MarketId) -> MarginBuilder<'_> {
+        MarginBuilder::new(self, market)
+    }
+
+    /// Create a builder that creates a sub-account under the active account.
+    pub fn sub_account(&self) -> SubAccountBuilder<'_> {
+        SubAccountBuilder::new(self)
+    }
+
+    /// Create a builder that mints or burns shares of a public pool.
+    pub fn pool_shares(&self, public_pool_index: i64) -> PoolSharesBuilder<'_> {
+        PoolSharesBuilder::new(self, public_pool_index)
+    }
+
     /// Create a websocket builder scoped to this client.
     pub fn ws(&self) -> WsBuilder<'_> {
         WsBuilder::new(self)
@@ -246,16 +361,53 @@ impl LighterClient {
             account_index.into(),
             self.opts.max_api_key_index.map(Into::into),
             extra_keys,
-            self.opts.nonce_management,
+            self.opts.nonce_management.clone(),
             self.opts.signer_library_path.as_deref(),
         )
         .await?;
 
+        self.install_signer(signer, account_index).await
+    }
+
+    /// Configure the account against an external signing backend such as a
+    /// [`RemoteSigner`](crate::remote_signer::RemoteSigner) or
+    /// [`MockSigner`](crate::tx_signer::MockSigner), keeping private keys out
+    /// of this process.
+    pub async fn configure_account_with_signer(
+        &mut self,
+        signer: Arc<dyn TxSigner>,
+        api_key_index: ApiKeyIndex,
+        account_index: AccountId,
+    ) -> Result<()> {
+        let signer = SignerClient::with_signer(
+            self.rest.base_path().to_string(),
+            signer,
+            api_key_index.into(),
+            account_index.into(),
+            self.opts.max_api_key_index.map(Into::into),
+            self.opts.nonce_management.clone(),
+        )
+        .await?;
+
+        self.install_signer(signer, account_index).await
+    }
+
+    async fn install_signer(
+        &mut self,
+        mut signer: SignerClient,
+        account_index: AccountId,
+    ) -> Result<()> {
+        let pre_trade = &self.opts.pre_trade;
+        if !pre_trade.is_empty() && !pre_trade.has_specs() {
+            // Checks need market precision; without it every order fails.
+            pre_trade.set_specs(self.orders().market_specs().await?.into_values());
+        }
+        signer.set_request_policy(self.rest.policy().clone());
+        let signer = signer.with_pre_trade(self.opts.pre_trade.clone());
         self.rest.set_configuration(signer.configuration());
         self.auth.invalidate().await;
-        self.signer = Some(signer);
+        self.signer = Some(Arc::new(signer));
         self.account_id = Some(account_index);
-
         Ok(())
     }
 
@@ -272,7 +424,7 @@ impl LighterClient {
     }
 
     fn signer_ref(&self) -> Result<&SignerClient> {
-        self.signer.as_ref().ok_or(Error::NotAuthenticated)
+        self.signer.as_deref().ok_or(Error::NotAuthenticated)
     }
 
     fn require_account(&self) -> Result<AccountId> {
@@ -292,6 +444,14 @@ impl LighterClient {
         self.auth.header(signer).await
     }
 
+    /// Owned handle onto the auth token cache, used by long lived websocket
+    /// sessions that need to refresh their token after reconnecting.
+    pub(crate) fn auth_token_source(&self) -> Option<AuthTokenSource> {
+        self.signer
+            .as_ref()
+            .map(|signer| AuthTokenSource::new(self.auth.clone(), Arc::clone(signer)))
+    }
+
     pub(crate) fn websocket_config(&self) -> &WsConfig {
         &self.ws_cfg
     }
@@ -845,6 +1005,31 @@ impl<'a> OrdersHandle<'a> {
         self.c.rest.order_books_metadata(market).await
     }
 
+    /// Load the precision and minimum order amounts for a market.
+    pub async fn market_spec(&self, market: MarketId) -> Result<MarketSpec> {
+        let metadata = self.books_metadata(Some(market)).await?;
+        let book = metadata
+            .order_books
+            .iter()
+            .find(|book| book.market_id == market.into_inner())
+            .ok_or(Error::InvalidConfig {
+                field: "market",
+                why: "market not found in order book metadata",
+            })?;
+        Ok(MarketSpec::from_metadata(book)?)
+    }
+
+    /// Load specs for every market listed in the order book metadata.
+    pub async fn market_specs(&self) -> Result<HashMap<MarketId, MarketSpec>> {
+        let metadata = self.books_metadata(None).await?;
+        let specs = metadata
+            .order_books
+            .iter()
+            .map(|book| MarketSpec::from_metadata(book).map(|spec| (spec.market, spec)))
+            .collect::<std::result::Result<_, MarketSpecError>>()?;
+        Ok(specs)
+    }
+
     pub async fn recent(&self, market: MarketId, limit: i64) -> Result<models::Trades> {
         self.c.rest.recent_trades(market, limit).await
     }
@@ -927,9 +1112,11 @@ impl<'a> TransactionsHandle<'a> {
 pub struct LighterClientBuilder {
     api_url: Option<String>,
     private_key: Option<String>,
+    tx_signer: Option<Arc<dyn TxSigner>>,
     api_key_index: Option<ApiKeyIndex>,
     account_index: Option<AccountId>,
     options: LighterClientOptions,
+    simulator: Option<SimExchange>,
 }
 
 impl LighterClientBuilder {
@@ -943,6 +1130,13 @@ impl LighterClientBuilder {
         self
     }
 
+    /// Sign with an external backend instead of a private key. Takes
+    /// precedence over [`private_key`](Self::private_key).
+    pub fn tx_signer(mut self, signer: Arc<dyn TxSigner>) -> Self {
+        self.tx_signer = Some(signer);
+        self
+    }
+
     pub fn api_key_index(mut self, index: impl Into<ApiKeyIndex>) -> Self {
         self.api_key_index = Some(index.into());
         self
@@ -991,15 +1185,83 @@ impl LighterClientBuilder {
         self
     }
 
-    pub async fn build(self) -> Result<LighterClient> {
+    /// Clock used for auth token expiry. Defaults to the system clock.
+    pub fn clock(mut self, clock: SharedClock) -> Self {
+        self.options.clock = clock;
+        self
+    }
+
+    /// Rate limit REST calls and submissions. Off by default.
+    pub fn rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.options.rate_limit = Some(config);
+        self
+    }
+
+    /// Retry policy for failed REST calls. Off by default.
+    pub fn retry_policy(mut self, policy: RestRetryPolicy) -> Self {
+        self.options.retry = Some(policy);
+        self
+    }
+
+    /// Replace the pre-trade checks run on every order before signing.
+    pub fn pre_trade(mut self, chain: PreTradeChain) -> Self {
+        self.options.pre_trade = chain;
+        self
+    }
+
+    /// Add one pre-trade check. Market specs are loaded over REST when the
+    /// account is configured unless the chain already has some.
+    pub fn pre_trade_check(mut self, check: impl PreTradeCheck + 'static) -> Self {
+        self.options.pre_trade = self.options.pre_trade.check(check);
+        self
+    }
+
+    /// Block orders that are not reduce-only while `switch` is tripped. Call
+    /// after [`pre_trade`](Self::pre_trade), which replaces the chain.
+    pub fn kill_switch(self, switch: KillSwitch) -> Self {
+        self.pre_trade_check(switch)
+    }
+
+    /// Paper trade: serve REST and websocket traffic from `sim` through a
+    /// loopback [`SimGateway`], so orders are signed and sent as usual but
+    /// fill in the simulator. The [`api_url`](Self::api_url), if set, becomes
+    /// the upstream for market data and every unsimulated request; without
+    /// it the simulator runs offline. Overrides [`websocket`](Self::websocket).
+    pub fn simulated(mut self, sim: SimExchange) -> Self {
+        self.simulator = Some(sim);
+        self
+    }
+
+    pub async fn build(mut self) -> Result<LighterClient> {
+        let simulator = match self.simulator.take() {
+            Some(sim) => {
+                let gateway =
+                    SimGateway::start(sim, self.api_url.as_deref(), self.options.clock.clone())
+                        .await?;
+                self.api_url = Some(gateway.url().to_string());
+                let mut websocket = self.options.websocket.take().unwrap_or_default();
+                websocket.host = gateway.url().to_string();
+                websocket.path = "/stream".to_string();
+                self.options.websocket = Some(websocket);
+                Some(Arc::new(gateway))
+            }
+            None => None,
+        };
         let api_url = self.api_url.ok_or(Error::InvalidConfig {
             field: "api_url",
             why: "must be provided",
         })?;
 
         let mut client = LighterClient::new_with_options(api_url, self.options).await?;
+        client.simulator = simulator;
 
-        if let (Some(private_key), Some(api_key_index), Some(account_index)) =
+        if let (Some(signer), Some(api_key_index), Some(account_index)) =
+            (self.tx_signer, self.api_key_index, self.account_index)
+        {
+            client
+                .configure_account_with_signer(signer, api_key_index, account_index)
+                .await?;
+        } else if let (Some(private_key), Some(api_key_index), Some(account_index)) =
             (self.private_key, self.api_key_index, self.account_index)
         {
             client
@@ -1119,6 +1381,10 @@ struct OrderBuilderState<'a> {
     avg_execution_override: Option<Price>,
     slippage: Option<SlippageStrategy>,
     price_offset_ticks: i64,
+    spec: Option<MarketSpec>,
+    /// Decimal inputs converted through `spec` on submit.
+    pending_size: Option<(DecimalSize, Rounding)>,
+    pending_price: Option<(DecimalPrice, Rounding)>,
 }
 
 impl<'a> OrderBuilderState<'a> {
@@ -1138,6 +1404,9 @@ impl<'a> OrderBuilderState<'a> {
             avg_execution_override: None,
             slippage: None,
             price_offset_ticks: 0,
+            spec: None,
+            pending_size: None,
+            pending_price: None,
         }
     }
 
@@ -1153,6 +1422,49 @@ impl<'a> OrderBuilderState<'a> {
         self.kind = Some(kind);
     }
 
+    /// Convert the decimal inputs given to `size()` / `limit_at()` through the
+    /// market spec. Runs on submit so the spec may be attached anywhere in
+    /// the chain.
+    fn resolve_decimals(&mut self) -> std::result::Result<(), MarketSpecError> {
+        if self.pending_size.is_none() && self.pending_price.is_none() {
+            return Ok(());
+        }
+        let spec = self.spec.as_ref().ok_or(MarketSpecError::SpecRequired)?;
+        if let Some((size, rounding)) = self.pending_size.take() {
+            self.qty = Some(spec.size(size, rounding)?);
+        }
+        if let Some((price, rounding)) = self.pending_price.take() {
+            let side = self.side.unwrap_or(OrderSide::Bid);
+            let price = spec.price(price, side, rounding)?;
+            self.kind = Some(OrderKind::Limit { price });
+        }
+        Ok(())
+    }
+
+    fn validate_spec(
+        &self,
+        spec: &MarketSpec,
+        kind: &OrderKind,
+    ) -> std::result::Result<(), MarketSpecError> {
+        if let Some(qty) = self.qty {
+            spec.check_size(qty)?;
+            let price = match kind {
+                OrderKind::Limit { price }
+                | OrderKind::StopLossLimit {
+                    limit_price: price, ..
+                }
+                | OrderKind::TakeProfitLimit {
+                    limit_price: price, ..
+                } => Some(*price),
+                _ => None,
+            };
+            if let Some(price) = price {
+                spec.check_notional(qty, price)?;
+            }
+        }
+        Ok(())
+    }
+
     fn validate(&self) -> Result<()> {
         if self.side.is_none() {
             return Err(Error::InvalidConfig {
@@ -1173,6 +1485,16 @@ impl<'a> OrderBuilderState<'a> {
             why: "must be selected via limit(), market(), or conditional helpers",
         })?;
 
+        if let Some(spec) = &self.spec {
+            if spec.market != self.market {
+                return Err(Error::InvalidConfig {
+                    field: "market_spec",
+                    why: "spec belongs to a different market",
+                });
+            }
+            self.validate_spec(spec, kind)?;
+        }
+
         if let Some(OrderTimeInForce::PostOnly) = self.tif {
             if matches!(
                 kind,
@@ -1332,7 +1654,8 @@ impl<'a> OrderBuilderState<'a> {
         resolve_time_in_force(self.time_in_force(), signer)
     }
 
-    async fn submit(self) -> Result<Submission<transactions::CreateOrder>> {
+    async fn submit(mut self) -> Result<Submission<transactions::CreateOrder>> {
+        self.resolve_decimals()?;
         self.validate()?;
         let signer = self.client.signer_ref()?;
         let client_order_id = self.resolved_client_order_id();
@@ -1584,7 +1907,8 @@ impl<'a> OrderBuilderState<'a> {
         }
     }
 
-    async fn sign(self) -> Result<SignedPayload<transactions::CreateOrder>> {
+    async fn sign(mut self) -> Result<SignedPayload<transactions::CreateOrder>> {
+        self.resolve_decimals()?;
         self.validate()?;
         let signer = self.client.signer_ref()?;
         let client_order_id = self.resolved_client_order_id();
@@ -1776,9 +2100,33 @@ impl<'a> OrderBuilder<'a, OrderStateSide> {
             _marker: PhantomData,
         }
     }
+
+    /// Set the quantity as a human readable size. Requires
+    /// [`market_spec`](OrderBuilder::market_spec) before submitting; sizes
+    /// that need rounding under `rounding` or fall below the market minimum
+    /// fail on submit.
+    pub fn size(
+        mut self,
+        size: DecimalSize,
+        rounding: Rounding,
+    ) -> OrderBuilder<'a, OrderStateQty> {
+        self.state.pending_size = Some((size, rounding));
+        OrderBuilder {
+            state: self.state,
+            _marker: PhantomData,
+        }
+    }
 }
 
 impl<'a, S> OrderBuilder<'a, S> {
+    /// Attach the market's precision and minimums. Enables [`size`](OrderBuilder::size)
+    /// and [`limit_at`](OrderBuilder::limit_at) and checks the order against
+    /// `min_base_amount` / `min_quote_amount` before signing.
+    pub fn market_spec(mut self, spec: &MarketSpec) -> Self {
+        self.state.spec = Some(spec.clone());
+        self
+    }
+
     pub fn ioc(mut self) -> Self {
         self.state.tif = Some(OrderTimeInForce::ImmediateOrCancel);
         self
@@ -1890,6 +2238,7 @@ impl<'a, S> OrderBuilder<'a, S> {
 
 impl<'a> OrderBuilder<'a, OrderStateQty> {
     pub fn limit(mut self, price: Price) -> OrderBuilder<'a, OrderStateReady> {
+        self.state.pending_price = None;
         self.state.slippage = None;
         self.state.avg_execution_override = None;
         self.state.with_kind(OrderKind::Limit { price });
@@ -1899,6 +2248,18 @@ impl<'a> OrderBuilder<'a, OrderStateQty> {
         }
     }
 
+    /// Limit order at a human readable price, rounded to the market tick
+    /// with `rounding` (see [`Rounding::TowardTouch`] / [`Rounding::AwayFromTouch`]).
+    pub fn limit_at(
+        self,
+        price: DecimalPrice,
+        rounding: Rounding,
+    ) -> OrderBuilder<'a, OrderStateReady> {
+        let mut builder = self.limit(Price::ticks(0));
+        builder.state.pending_price = Some((price, rounding));
+        builder
+    }
+
     pub fn market(mut self) -> OrderBuilder<'a, OrderStateReady> {
         let avg = self.state.avg_execution_override.take();
         let slippage = self.state.slippage.take();
@@ -2029,10 +2390,12 @@ impl<'a> BracketBuilder<'a> {
 
     pub async fn submit(self) -> Result<BracketSubmission> {
         let BracketBuilder {
-            entry,
+            mut entry,
             take_profit,
             stop_loss,
         } = self;
+        // The legs reuse the entry's quantity.
+        entry.resolve_decimals()?;
 
         let client = entry.client;
         let market = entry.market;
@@ -2088,10 +2451,12 @@ impl<'a> BracketBuilder<'a> {
 
     pub async fn sign(self) -> Result<BracketSigned> {
         let BracketBuilder {
-            entry,
+            mut entry,
             take_profit,
             stop_loss,
         } = self;
+        // The legs reuse the entry's quantity.
+        entry.resolve_decimals()?;
 
         let client = entry.client;
         let market = entry.market;
@@ -2323,12 +2688,16 @@ impl<'a> CancelAllBuilder<'a> {
     pub async fn submit(self) -> Result<Submission<String>> {
         let signer = self.client.signer_ref()?;
         let tif = resolve_time_in_force(self.tif.unwrap_or(OrderTimeInForce::GoodTillTime), signer);
-        let expiry = self
-            .expiry
-            .map(|value| value.into_unix())
-            .unwrap_or_else(|| {
+        // Scheduled cancel-all times are in milliseconds, like order expiries.
+        let expiry = match self.expiry {
+            Some(expiry) => expiry.into_unix_millis().ok_or(Error::InvalidConfig {
+                field: "expiry",
+                why: "expiry timestamp overflow",
+            })?,
+            None => {
                 default_order_expiry(self.tif.unwrap_or(OrderTimeInForce::GoodTillTime), signer)
-            });
+            }
+        };
 
         let (payload, response) = signer
             .cancel_all_orders(
@@ -2383,9 +2752,448 @@ impl<'a> WithdrawBuilder<'a> {
     }
 }
 
+/// Modify order builder.
+pub struct ModifyOrderBuilder<'a> {
+    client: &'a LighterClient,
+    market: MarketId,
+    order_index: i64,
+    qty: Option<BaseQty>,
+    price: Option<Price>,
+    trigger: Option<Price>,
+    nonce: Option<Nonce>,
+    api_key_override: Option<ApiKeyIndex>,
+}
+
+impl<'a> ModifyOrderBuilder<'a> {
+    fn new(client: &'a LighterClient, market: MarketId, order_index: i64) -> Self {
+        Self {
+            client,
+            market,
+            order_index,
+            qty: None,
+            price: None,
+            trigger: None,
+            nonce: None,
+            api_key_override: None,
+        }
+    }
+
+    pub fn qty(mut self, qty: BaseQty) -> Self {
+        self.qty = Some(qty);
+        self
+    }
+
+    pub fn price(mut self, price: Price) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    pub fn trigger(mut self, trigger: Price) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn api_key(mut self, index: ApiKeyIndex) -> Self {
+        self.api_key_override = Some(index);
+        self
+    }
+
+    pub async fn submit(self) -> Result<Submission<transactions::ModifyOrder>> {
+        let qty = self.qty.ok_or(Error::InvalidConfig {
+            field: "qty",
+            why: "must be provided via qty()",
+        })?;
+        let price = self.price.ok_or(Error::InvalidConfig {
+            field: "price",
+            why: "must be provided via price()",
+        })?;
+        let trigger = self
+            .trigger
+            .map(Price::into_ticks)
+            .unwrap_or(DEFAULT_TRIGGER_PRICE as i64);
+
+        let signer = self.client.signer_ref()?;
+        let (payload, response) = signer
+            .modify_order_typed(
+                self.market.into(),
+                self.order_index,
+                qty.into_inner(),
+                price.into_ticks(),
+                trigger,
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+}
+
+/// Transfer builder.
+pub struct TransferBuilder<'a> {
+    client: &'a LighterClient,
+    to: AccountId,
+    amount: UsdcAmount,
+    eth_private_key: String,
+    fee: Option<i64>,
+    memo: String,
+    nonce: Option<Nonce>,
+    api_key_override: Option<ApiKeyIndex>,
+}
+
+impl<'a> TransferBuilder<'a> {
+    fn new(
+        client: &'a LighterClient,
+        to: AccountId,
+        amount: UsdcAmount,
+        eth_private_key: String,
+    ) -> Self {
+        Self {
+            client,
+            to,
+            amount,
+            eth_private_key,
+            fee: None,
+            memo: DEFAULT_TRANSFER_MEMO.to_string(),
+            nonce: None,
+            api_key_override: None,
+        }
+    }
+
+    /// Override the transfer fee (in USDC base units). When unset the fee is
+    /// looked up from the exchange before signing.
+    pub fn fee(mut self, fee: i64) -> Self {
+        self.fee = Some(fee);
+        self
+    }
+
+    /// Attach a memo to the transfer. The exchange requires exactly 32 bytes.
+    pub fn memo(mut self, memo: impl Into<String>) -> Self {
+        self.memo = memo.into();
+        self
+    }
+
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn api_key(mut self, index: ApiKeyIndex) -> Self {
+        self.api_key_override = Some(index);
+        self
+    }
+
+    pub async fn submit(self) -> Result<Submission<transactions::Transfer>> {
+        if self.memo.len() != TRANSFER_MEMO_LEN {
+            return Err(Error::InvalidConfig {
+                field: "memo",
+                why: "must be exactly 32 bytes long",
+            });
+        }
+
+        let signer = self.client.signer_ref()?;
+        let fee = match self.fee {
+            Some(fee) => fee,
+            None => {
+                self.client
+                    .account()
+                    .transfer_fee_info(Some(self.to))
+                    .await?
+                    .transfer_fee_usdc
+            }
+        };
+
+        let (payload, response) = signer
+            .transfer_typed(
+                &self.eth_private_key,
+                self.to.into(),
+                self.amount.into(),
+                fee,
+                &self.memo,
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+}
+
+/// Margin mode applied to a market position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarginMode {
+    Cross,
+    Isolated,
+}
+
+/// Update leverage builder.
+pub struct LeverageBuilder<'a> {
+    client: &'a LighterClient,
+    market: MarketId,
+    leverage: Option<u16>,
+    margin_mode: MarginMode,
+    nonce: Option<Nonce>,
+    api_key_override: Option<ApiKeyIndex>,
+}
+
+impl<'a> LeverageBuilder<'a> {
+    fn new(client: &'a LighterClient, market: MarketId) -> Self {
+        Self {
+            client,
+            market,
+            leverage: None,
+            margin_mode: MarginMode::Cross,
+            nonce: None,
+            api_key_override: None,
+        }
+    }
+
+    /// Target leverage expressed as a multiple (e.g. `10` for 10x).
+    pub fn leverage(mut self, leverage: u16) -> Self {
+        self.leverage = Some(leverage);
+        self
+    }
+
+    pub fn cross(mut self) -> Self {
+        self.margin_mode = MarginMode::Cross;
+        self
+    }
+
+    pub fn isolated(mut self) -> Self {
+        self.margin_mode = MarginMode::Isolated;
+        self
+    }
+
+    pub fn margin_mode(mut self, mode: MarginMode) -> Self {
+        self.margin_mode = mode;
+        self
+    }
+
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn api_key(mut self, index: ApiKeyIndex) -> Self {
+        self.api_key_override = Some(index);
+        self
+    }
+
+    pub async fn submit(self) -> Result<Submission<transactions::UpdateLeverage>> {
+        let leverage = match self.leverage {
+            Some(value) if value > 0 => value,
+            Some(_) => {
+                return Err(Error::InvalidConfig {
+                    field: "leverage",
+                    why: "must be greater than zero",
+                })
+            }
+            None => {
+                return Err(Error::InvalidConfig {
+                    field: "leverage",
+                    why: "must be provided via leverage()",
+                })
+            }
+        };
+
+        let signer = self.client.signer_ref()?;
+        let margin_mode = match self.margin_mode {
+            MarginMode::Cross => signer.margin_mode_cross(),
+            MarginMode::Isolated => signer.margin_mode_isolated(),
+        };
+        let (payload, response) = signer
+            .update_leverage_typed(
+                self.market.into(),
+                margin_mode,
+                leverage.into(),
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+}
+
+/// Update isolated margin builder.
+pub struct MarginBuilder<'a> {
+    client: &'a LighterClient,
+    market: MarketId,
+    change: Option<(UsdcAmount, bool)>,
+    nonce: Option<Nonce>,
+    api_key_override: Option<ApiKeyIndex>,
+}
+
+impl<'a> MarginBuilder<'a> {
+    fn new(client: &'a LighterClient, market: MarketId) -> Self {
+        Self {
+            client,
+            market,
+            change: None,
+            nonce: None,
+            api_key_override: None,
+        }
+    }
+
+    /// Move `amount` of collateral into the isolated position.
+    pub fn add_collateral(mut self, amount: UsdcAmount) -> Self {
+        self.change = Some((amount, true));
+        self
+    }
+
+    /// Release `amount` of collateral from the isolated position.
+    pub fn remove_collateral(mut self, amount: UsdcAmount) -> Self {
+        self.change = Some((amount, false));
+        self
+    }
+
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn api_key(mut self, index: ApiKeyIndex) -> Self {
+        self.api_key_override = Some(index);
+        self
+    }
+
+    pub async fn submit(self) -> Result<Submission<transactions::UpdateMargin>> {
+        let (amount, add) = self.change.ok_or(Error::InvalidConfig {
+            field: "amount",
+            why: "must be provided via add_collateral() or remove_collateral()",
+        })?;
+
+        let signer = self.client.signer_ref()?;
+        let direction = if add {
+            signer.margin_direction_add()
+        } else {
+            signer.margin_direction_remove()
+        };
+        let (payload, response) = signer
+            .update_margin_typed(
+                self.market.into(),
+                amount.into(),
+                direction,
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+}
+
+/// Create sub-account builder.
+pub struct SubAccountBuilder<'a> {
+    client: &'a LighterClient,
+    nonce: Option<Nonce>,
+    api_key_override: Option<ApiKeyIndex>,
+}
+
+impl<'a> SubAccountBuilder<'a> {
+    fn new(client: &'a LighterClient) -> Self {
+        Self {
+            client,
+            nonce: None,
+            api_key_override: None,
+        }
+    }
+
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn api_key(mut self, index: ApiKeyIndex) -> Self {
+        self.api_key_override = Some(index);
+        self
+    }
+
+    pub async fn submit(self) -> Result<Submission<transactions::CreateSubAccount>> {
+        let signer = self.client.signer_ref()?;
+        let (payload, response) = signer
+            .create_sub_account_typed(
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+}
+
+/// Public pool share builder.
+pub struct PoolSharesBuilder<'a> {
+    client: &'a LighterClient,
+    public_pool_index: i64,
+    nonce: Option<Nonce>,
+    api_key_override: Option<ApiKeyIndex>,
+}
+
+impl<'a> PoolSharesBuilder<'a> {
+    fn new(client: &'a LighterClient, public_pool_index: i64) -> Self {
+        Self {
+            client,
+            public_pool_index,
+            nonce: None,
+            api_key_override: None,
+        }
+    }
+
+    pub fn nonce(mut self, nonce: Nonce) -> Self {
+        self.nonce = Some(nonce);
+        self
+    }
+
+    pub fn api_key(mut self, index: ApiKeyIndex) -> Self {
+        self.api_key_override = Some(index);
+        self
+    }
+
+    pub async fn mint(self, share_amount: i64) -> Result<Submission<transactions::MintShares>> {
+        if share_amount <= 0 {
+            return Err(Error::InvalidConfig {
+                field: "share_amount",
+                why: "must be greater than zero",
+            });
+        }
+        let signer = self.client.signer_ref()?;
+        let (payload, response) = signer
+            .mint_shares_typed(
+                self.public_pool_index,
+                share_amount,
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+
+    pub async fn burn(self, share_amount: i64) -> Result<Submission<transactions::BurnShares>> {
+        if share_amount <= 0 {
+            return Err(Error::InvalidConfig {
+                field: "share_amount",
+                why: "must be greater than zero",
+            });
+        }
+        let signer = self.client.signer_ref()?;
+        let (payload, response) = signer
+            .burn_shares_typed(
+                self.public_pool_index,
+                share_amount,
+                self.nonce.map(Into::into),
+                self.api_key_override.map(Into::into),
+            )
+            .await?;
+        Ok(Submission::new(payload, response))
+    }
+}
+
 const DEFAULT_TRIGGER_PRICE: i32 = 0;
 const DEFAULT_MARKET_PRICE: i32 = -1;
 const DEFAULT_ORDER_EXPIRY: i64 = -1;
+const TRANSFER_MEMO_LEN: usize = 32;
+const DEFAULT_TRANSFER_MEMO: &str = "00000000000000000000000000000000";
 
 fn resolve_time_in_force(time_in_force: OrderTimeInForce, signer: &SignerClient) -> i32 {
     match time_in_force {
@@ -2401,3 +3209,202 @@ fn default_order_expiry(time_in_force: OrderTimeInForce, signer: &SignerClient)
         OrderTimeInForce::GoodTillTime | OrderTimeInForce::PostOnly => DEFAULT_ORDER_EXPIRY,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx_signer::{MockSigner, SignRequest};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    /// Answers every request with a body that decodes both as `NextNonce` and
+    /// as `RespSendTx`, recording the request paths.
+    async fn stub_exchange() -> (String, Arc<std::sync::Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let paths = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = Arc::clone(&paths);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let read = stream.read(&mut buf).await.unwrap_or(0);
+                    request.extend_from_slice(&buf[..read]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|line| {
+                                let (name, value) = line.split_once(':')?;
+                                name.eq_ignore_ascii_case("content-length")
+                                    .then(|| value.trim().parse::<usize>().ok())
+                                    .flatten()
+                            })
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                    if read == 0 {
+                        break;
+                    }
+                }
+                let text = String::from_utf8_lossy(&request);
+                if let Some(path) = text.split_whitespace().nth(1) {
+                    seen.lock().unwrap().push(path.to_string());
+                }
+                let body =
+                    r#"{"code":200,"nonce":7,"tx_hash":"0xfeed","predicted_execution_time_ms":1}"#;
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(reply.as_bytes()).await;
+            }
+        });
+        (url, paths)
+    }
+
+    async fn client_with_mock(url: &str, signer: Arc<MockSigner>) -> LighterClient {
+        let mut client = LighterClient::new(url).await.unwrap();
+        client
+            .configure_account_with_signer(signer, ApiKeyIndex::new(2), AccountId::new(42))
+            .await
+            .unwrap();
+        client
+    }
+
+    #[tokio::test]
+    async fn builders_submit_typed_payloads() {
+        let (url, paths) = stub_exchange().await;
+        let signer = Arc::new(MockSigner::new(42));
+        let client = client_with_mock(&url, Arc::clone(&signer)).await;
+
+        let modify = client
+            .modify(MarketId::new(1), 77)
+            .qty(BaseQty::from_i64(250).unwrap())
+            .price(Price::ticks(1_234))
+            .submit()
+            .await
+            .unwrap();
+        assert_eq!(modify.payload().market_index, Some(1));
+        assert_eq!(modify.payload().base_amount, Some(250));
+        assert_eq!(modify.payload().price, Some(1_234));
+        assert!(modify.payload().sig.is_some());
+        assert_eq!(modify.response().tx_hash, "0xfeed");
+
+        let leverage = client
+            .leverage(MarketId::new(1))
+            .leverage(4)
+            .isolated()
+            .submit()
+            .await
+            .unwrap();
+        assert_eq!(leverage.payload().market_index, Some(1));
+        assert!(matches!(
+            signer.calls().last(),
+            Some(SignRequest::SignUpdateLeverage {
+                fraction: 2_500,
+                ..
+            })
+        ));
+
+        let sends = paths
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|path| path.ends_with("/sendTx"))
+            .count();
+        assert_eq!(sends, 2);
+    }
+
+    #[tokio::test]
+    async fn decimal_inputs_resolve_against_a_spec_attached_later() {
+        let (url, _) = stub_exchange().await;
+        let client = client_with_mock(&url, Arc::new(MockSigner::new(42))).await;
+        let spec = MarketSpec::from_metadata(&models::OrderBook {
+            market_id: 0,
+            min_base_amount: "0.0050".to_string(),
+            min_quote_amount: "10.000000".to_string(),
+            supported_size_decimals: 4,
+            supported_price_decimals: 2,
+            ..Default::default()
+        })
+        .unwrap();
+
+        let signed = client
+            .order(MarketId::new(0))
+            .buy()
+            .size("0.01234".parse().unwrap(), Rounding::Down)
+            .limit_at("3024.667".parse().unwrap(), Rounding::TowardTouch)
+            .market_spec(&spec)
+            .sign()
+            .await
+            .unwrap();
+        assert_eq!(signed.parsed().base_amount, Some(123));
+        assert_eq!(signed.parsed().price, Some(302_467));
+
+        let missing_spec = client
+            .order(MarketId::new(0))
+            .buy()
+            .size("0.01".parse().unwrap(), Rounding::Exact)
+            .limit(Price::ticks(302_466))
+            .sign()
+            .await;
+        assert!(matches!(
+            missing_spec,
+            Err(Error::MarketSpec {
+                field: "market_spec",
+                source: MarketSpecError::SpecRequired,
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn builders_reject_incomplete_requests_before_signing() {
+        let (url, paths) = stub_exchange().await;
+        let signer = Arc::new(MockSigner::new(42));
+        let client = client_with_mock(&url, Arc::clone(&signer)).await;
+        let signed_before = signer.calls().len();
+
+        let missing_price = client
+            .modify(MarketId::new(1), 77)
+            .qty(BaseQty::from_i64(250).unwrap())
+            .submit()
+            .await;
+        assert!(matches!(
+            missing_price,
+            Err(Error::InvalidConfig { field: "price", .. })
+        ));
+
+        let short_memo = client
+            .transfer(AccountId::new(7), UsdcAmount::new(1.0), "0xkey")
+            .memo("too short")
+            .fee(0)
+            .submit()
+            .await;
+        assert!(matches!(
+            short_memo,
+            Err(Error::InvalidConfig { field: "memo", .. })
+        ));
+
+        let zero_shares = client.pool_shares(3).mint(0).await;
+        assert!(matches!(
+            zero_shares,
+            Err(Error::InvalidConfig {
+                field: "share_amount",
+                ..
+            })
+        ));
+
+        assert_eq!(signer.calls().len(), signed_before);
+        assert!(!paths
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|path| path.ends_with("/sendTx")));
+    }
+}
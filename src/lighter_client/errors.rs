@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-use crate::errors::{SignerClientError, WsClientError};
+use crate::errors::{MarketSpecError, SignerClientError, WsClientError};
 
 /// Result type used by [`LighterClient`](super::LighterClient).
 pub type Result<T> = std::result::Result<T, Error>;
@@ -20,6 +20,13 @@ pub enum Error {
         field: &'static str,
         why: &'static str,
     },
+    /// A human readable price or size did not fit the market's precision.
+    #[error("invalid {field}: {source}")]
+    MarketSpec {
+        field: &'static str,
+        #[source]
+        source: MarketSpecError,
+    },
     /// Attempted to call an authenticated method without configuring an account.
     #[error("unauthenticated")]
     NotAuthenticated,
@@ -36,6 +43,9 @@ pub enum Error {
     /// Raw HTTP error when no structured error could be parsed.
     #[error("http {status}: {body}")]
     Http { status: u16, body: String },
+    /// Local I/O failure, such as binding the simulator's port.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
 }
 
 #[derive(Debug, Deserialize)]
@@ -46,3 +56,19 @@ pub(crate) struct ServerErr {
     #[serde(default)]
     pub(crate) retry_after: Option<u64>,
 }
+
+impl From<MarketSpecError> for Error {
+    fn from(source: MarketSpecError) -> Self {
+        let field = match &source {
+            MarketSpecError::BelowMinBase { .. } | MarketSpecError::BelowMinQuote { .. } => "qty",
+            MarketSpecError::SideRequired => "rounding",
+            MarketSpecError::SpecRequired => "market_spec",
+            MarketSpecError::InvalidMetadata(field) => field,
+            MarketSpecError::InvalidDecimal(_)
+            | MarketSpecError::NotRepresentable { .. }
+            | MarketSpecError::Overflow(_)
+            | MarketSpecError::NonPositive => "value",
+        };
+        Error::MarketSpec { field, source }
+    }
+}
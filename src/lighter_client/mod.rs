@@ -1,4 +1,4 @@
-mod auth;
+pub(crate) mod auth;
 mod client;
 mod errors;
 mod pagination;
@@ -8,10 +8,12 @@ mod rest;
 
 pub use client::{
     AccountHandle, BlocksHandle, BracketBuilder, BracketSigned, BracketSubmission, BridgeHandle,
-    CancelAllBuilder, CancelOrderBuilder, CandlesHandle, FundingHandle, InfoHandle, LighterClient,
-    LighterClientBuilder, LighterClientOptions, NotificationsHandle, OrderBatchBuilder,
-    OrderBuilder, OrderSide, OrderStateInit, OrderStateQty, OrderStateReady, OrderStateSide,
-    OrderTimeInForce, OrdersHandle, Submission, TransactionsHandle, WithdrawBuilder,
+    CancelAllBuilder, CancelOrderBuilder, CandlesHandle, FundingHandle, InfoHandle,
+    LeverageBuilder, LighterClient, LighterClientBuilder, LighterClientOptions, MarginBuilder,
+    MarginMode, ModifyOrderBuilder, NotificationsHandle, OrderBatchBuilder, OrderBuilder,
+    OrderSide, OrderStateInit, OrderStateQty, OrderStateReady, OrderStateSide, OrderTimeInForce,
+    OrdersHandle, PoolSharesBuilder, SubAccountBuilder, Submission, TransactionsHandle,
+    TransferBuilder, WithdrawBuilder,
 };
 pub use errors::{Error, Result};
 pub use params::{
@@ -1,4 +1,4 @@
-use std::fmt;
+use std::{fmt, future::Future, time::Duration};
 
 use super::{
     auth::AuthCache,
@@ -11,6 +11,7 @@ use crate::{
         info_api, notification_api, order_api, transaction_api,
     },
     models,
+    rate_limit::{self, Failure, RequestClass, RequestPolicy},
     types::{AccountId, ApiKeyIndex, MarketId},
 };
 
@@ -18,6 +19,7 @@ use crate::{
 pub struct RestClient {
     configuration: configuration::Configuration,
     auth: AuthCache,
+    policy: RequestPolicy,
 }
 
 impl RestClient {
@@ -27,15 +29,20 @@ impl RestClient {
         Self {
             configuration,
             auth,
+            policy: RequestPolicy::default(),
         }
     }
 
-    pub(crate) fn configuration(&self) -> configuration::Configuration {
-        self.configuration.clone()
+    pub(crate) fn set_policy(&mut self, policy: RequestPolicy) {
+        self.policy = policy;
+    }
+
+    pub(crate) fn policy(&self) -> &RequestPolicy {
+        &self.policy
     }
 
-    pub(crate) fn configuration_ref(&self) -> &configuration::Configuration {
-        &self.configuration
+    pub(crate) fn configuration(&self) -> configuration::Configuration {
+        self.configuration.clone()
     }
 
     pub(crate) fn set_configuration(&mut self, configuration: configuration::Configuration) {
@@ -51,18 +58,20 @@ impl RestClient {
         market: MarketId,
         limit: i64,
     ) -> Result<models::OrderBookOrders> {
-        order_api::order_book_orders(self.configuration_ref(), market.into(), limit)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("orderBookOrders", move |config| {
+            order_api::order_book_orders(config, market.into(), limit)
+        })
+        .await
     }
 
     pub(crate) async fn account_details(
         &self,
         account_value: &str,
     ) -> Result<models::DetailedAccounts> {
-        account_api::account(self.configuration_ref(), "index", account_value)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("account", move |config| {
+            account_api::account(config, "index", account_value)
+        })
+        .await
     }
 
     pub(crate) async fn account_limits(
@@ -70,14 +79,10 @@ impl RestClient {
         account_index: i64,
         auth: &str,
     ) -> Result<models::AccountLimits> {
-        account_api::account_limits(
-            self.configuration_ref(),
-            account_index,
-            Some(auth),
-            Some(auth),
-        )
+        self.call("accountLimits", move |config| {
+            account_api::account_limits(config, account_index, Some(auth), Some(auth))
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn account_metadata(
@@ -85,15 +90,10 @@ impl RestClient {
         account_value: &str,
         auth: &str,
     ) -> Result<models::AccountMetadatas> {
-        account_api::account_metadata(
-            self.configuration_ref(),
-            "index",
-            account_value,
-            Some(auth),
-            Some(auth),
-        )
+        self.call("accountMetadata", move |config| {
+            account_api::account_metadata(config, "index", account_value, Some(auth), Some(auth))
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn api_keys(
@@ -101,13 +101,10 @@ impl RestClient {
         account_index: i64,
         api_key_index: Option<ApiKeyIndex>,
     ) -> Result<models::AccountApiKeys> {
-        account_api::apikeys(
-            self.configuration_ref(),
-            account_index,
-            api_key_index.map(Into::into),
-        )
+        self.call("apikeys", move |config| {
+            account_api::apikeys(config, account_index, api_key_index.map(Into::into))
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn change_account_tier(
@@ -116,15 +113,10 @@ impl RestClient {
         tier: &str,
         auth: &str,
     ) -> Result<models::RespChangeAccountTier> {
-        account_api::change_account_tier(
-            self.configuration_ref(),
-            account_index,
-            tier,
-            Some(auth),
-            Some(auth),
-        )
+        self.call_once("changeAccountTier", move |config| {
+            account_api::change_account_tier(config, account_index, tier, Some(auth), Some(auth))
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn l1_metadata(
@@ -132,9 +124,10 @@ impl RestClient {
         l1_address: &str,
         auth: &str,
     ) -> Result<models::L1Metadata> {
-        account_api::l1_metadata(self.configuration_ref(), l1_address, Some(auth), Some(auth))
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("l1Metadata", move |config| {
+            account_api::l1_metadata(config, l1_address, Some(auth), Some(auth))
+        })
+        .await
     }
 
     pub(crate) async fn liquidations(
@@ -145,17 +138,18 @@ impl RestClient {
         cursor: Option<&str>,
         auth: &str,
     ) -> Result<models::LiquidationInfos> {
-        account_api::liquidations(
-            self.configuration_ref(),
-            account_index,
-            limit,
-            Some(auth),
-            Some(auth),
-            market.map(Into::into),
-            cursor,
-        )
+        self.call("liquidations", move |config| {
+            account_api::liquidations(
+                config,
+                account_index,
+                limit,
+                Some(auth),
+                Some(auth),
+                market.map(Into::into),
+                cursor,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn account_pnl(
@@ -168,20 +162,21 @@ impl RestClient {
         ignore_transfers: Option<bool>,
         auth: &str,
     ) -> Result<models::AccountPnL> {
-        account_api::pnl(
-            self.configuration_ref(),
-            "index",
-            account_value,
-            resolution,
-            start_timestamp,
-            end_timestamp,
-            count_back,
-            Some(auth),
-            Some(auth),
-            ignore_transfers,
-        )
-        .await
-        .map_err(|err| self.map_rest_error(err))
+        self.call("pnl", move |config| {
+            account_api::pnl(
+                config,
+                "index",
+                account_value,
+                resolution,
+                start_timestamp,
+                end_timestamp,
+                count_back,
+                Some(auth),
+                Some(auth),
+                ignore_transfers,
+            )
+        })
+        .await
     }
 
     pub(crate) async fn position_funding(
@@ -193,18 +188,19 @@ impl RestClient {
         side: Option<&str>,
         auth: &str,
     ) -> Result<models::PositionFundings> {
-        account_api::position_funding(
-            self.configuration_ref(),
-            account_index,
-            limit,
-            Some(auth),
-            Some(auth),
-            market.map(Into::into),
-            cursor,
-            side,
-        )
+        self.call("positionFunding", move |config| {
+            account_api::position_funding(
+                config,
+                account_index,
+                limit,
+                Some(auth),
+                Some(auth),
+                market.map(Into::into),
+                cursor,
+                side,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn public_pools(
@@ -215,17 +211,18 @@ impl RestClient {
         account_index: i64,
         auth: &str,
     ) -> Result<models::PublicPools> {
-        account_api::public_pools(
-            self.configuration_ref(),
-            index,
-            limit,
-            Some(auth),
-            Some(auth),
-            filter,
-            Some(account_index),
-        )
+        self.call("publicPools", move |config| {
+            account_api::public_pools(
+                config,
+                index,
+                limit,
+                Some(auth),
+                Some(auth),
+                filter,
+                Some(account_index),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn public_pools_metadata(
@@ -236,32 +233,35 @@ impl RestClient {
         account_index: i64,
         auth: &str,
     ) -> Result<models::RespPublicPoolsMetadata> {
-        account_api::public_pools_metadata(
-            self.configuration_ref(),
-            index,
-            limit,
-            Some(auth),
-            Some(auth),
-            filter,
-            Some(account_index),
-        )
+        self.call("publicPoolsMetadata", move |config| {
+            account_api::public_pools_metadata(
+                config,
+                index,
+                limit,
+                Some(auth),
+                Some(auth),
+                filter,
+                Some(account_index),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn accounts_by_l1_address(
         &self,
         l1_address: &str,
     ) -> Result<models::SubAccounts> {
-        account_api::accounts_by_l1_address(self.configuration_ref(), l1_address)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("accountsByL1Address", move |config| {
+            account_api::accounts_by_l1_address(config, l1_address)
+        })
+        .await
     }
 
     pub(crate) async fn block(&self, by: By, value: &str) -> Result<models::Blocks> {
-        block_api::block(self.configuration_ref(), by.as_str(), value)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("block", move |config| {
+            block_api::block(config, by.as_str(), value)
+        })
+        .await
     }
 
     pub(crate) async fn blocks(
@@ -271,21 +271,25 @@ impl RestClient {
         sort: Option<SortDir>,
     ) -> Result<models::Blocks> {
         let sort = sort.map(|s| s.to_string());
-        block_api::blocks(self.configuration_ref(), limit, index, sort.as_deref())
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        let sort = sort.as_deref();
+        self.call("blocks", move |config| {
+            block_api::blocks(config, limit, index, sort)
+        })
+        .await
     }
 
     pub(crate) async fn current_height(&self) -> Result<models::CurrentHeight> {
-        block_api::current_height(self.configuration_ref())
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("currentHeight", move |config| {
+            block_api::current_height(config)
+        })
+        .await
     }
 
     pub(crate) async fn fastbridge_info(&self) -> Result<models::RespGetFastBridgeInfo> {
-        bridge_api::fastbridge_info(self.configuration_ref())
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("fastbridge/info", move |config| {
+            bridge_api::fastbridge_info(config)
+        })
+        .await
     }
 
     pub(crate) async fn candlesticks(
@@ -297,17 +301,18 @@ impl RestClient {
         count_back: i64,
         set_timestamp_to_end: Option<bool>,
     ) -> Result<models::Candlesticks> {
-        candlestick_api::candlesticks(
-            self.configuration_ref(),
-            market.into(),
-            resolution,
-            start_timestamp,
-            end_timestamp,
-            count_back,
-            set_timestamp_to_end,
-        )
+        self.call("candlesticks", move |config| {
+            candlestick_api::candlesticks(
+                config,
+                market.into(),
+                resolution,
+                start_timestamp,
+                end_timestamp,
+                count_back,
+                set_timestamp_to_end,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn fundings(
@@ -318,22 +323,24 @@ impl RestClient {
         end_timestamp: i64,
         count_back: i64,
     ) -> Result<models::Fundings> {
-        candlestick_api::fundings(
-            self.configuration_ref(),
-            market.into(),
-            resolution,
-            start_timestamp,
-            end_timestamp,
-            count_back,
-        )
+        self.call("fundings", move |config| {
+            candlestick_api::fundings(
+                config,
+                market.into(),
+                resolution,
+                start_timestamp,
+                end_timestamp,
+                count_back,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn funding_rates(&self) -> Result<models::FundingRates> {
-        funding_api::funding_rates(self.configuration_ref())
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("funding-rates", move |config| {
+            funding_api::funding_rates(config)
+        })
+        .await
     }
 
     pub(crate) async fn transfer_fee_info(
@@ -342,21 +349,23 @@ impl RestClient {
         to_account_index: Option<AccountId>,
         auth: &str,
     ) -> Result<models::TransferFeeInfo> {
-        info_api::transfer_fee_info(
-            self.configuration_ref(),
-            account_index,
-            Some(auth),
-            Some(auth),
-            to_account_index.map(Into::into),
-        )
+        self.call("transferFeeInfo", move |config| {
+            info_api::transfer_fee_info(
+                config,
+                account_index,
+                Some(auth),
+                Some(auth),
+                to_account_index.map(Into::into),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn withdrawal_delay(&self) -> Result<models::RespWithdrawalDelay> {
-        info_api::withdrawal_delay(self.configuration_ref())
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("withdrawalDelay", move |config| {
+            info_api::withdrawal_delay(config)
+        })
+        .await
     }
 
     pub(crate) async fn acknowledge_notification(
@@ -365,15 +374,16 @@ impl RestClient {
         account_index: i64,
         auth: &str,
     ) -> Result<models::ResultCode> {
-        notification_api::notification_ack(
-            self.configuration_ref(),
-            notification_id,
-            account_index,
-            Some(auth),
-            Some(auth),
-        )
+        self.call_once("notification/ack", move |config| {
+            notification_api::notification_ack(
+                config,
+                notification_id,
+                account_index,
+                Some(auth),
+                Some(auth),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn account_active_orders(
@@ -382,15 +392,16 @@ impl RestClient {
         market: MarketId,
         auth: &str,
     ) -> Result<models::Orders> {
-        order_api::account_active_orders(
-            self.configuration_ref(),
-            account_index,
-            market.into(),
-            Some(auth),
-            Some(auth),
-        )
+        self.call("accountActiveOrders", move |config| {
+            order_api::account_active_orders(
+                config,
+                account_index,
+                market.into(),
+                Some(auth),
+                Some(auth),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn account_inactive_orders(
@@ -403,25 +414,27 @@ impl RestClient {
         cursor: Option<&str>,
         auth: &str,
     ) -> Result<models::Orders> {
-        order_api::account_inactive_orders(
-            self.configuration_ref(),
-            account_index,
-            limit,
-            Some(auth),
-            Some(auth),
-            market.map(Into::into),
-            ask_filter,
-            between_timestamps,
-            cursor,
-        )
+        self.call("accountInactiveOrders", move |config| {
+            order_api::account_inactive_orders(
+                config,
+                account_index,
+                limit,
+                Some(auth),
+                Some(auth),
+                market.map(Into::into),
+                ask_filter,
+                between_timestamps,
+                cursor,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn exchange_stats(&self) -> Result<models::ExchangeStats> {
-        order_api::exchange_stats(self.configuration_ref())
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("exchangeStats", move |config| {
+            order_api::exchange_stats(config)
+        })
+        .await
     }
 
     pub(crate) async fn export(
@@ -431,34 +444,37 @@ impl RestClient {
         auth: Option<&str>,
         market: Option<MarketId>,
     ) -> Result<models::ExportData> {
-        order_api::export(
-            self.configuration_ref(),
-            export_type,
-            auth,
-            auth,
-            account_index,
-            market.map(Into::into),
-        )
+        self.call("export", move |config| {
+            order_api::export(
+                config,
+                export_type,
+                auth,
+                auth,
+                account_index,
+                market.map(Into::into),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn order_book_details(
         &self,
         market: Option<MarketId>,
     ) -> Result<models::OrderBookDetails> {
-        order_api::order_book_details(self.configuration_ref(), market.map(Into::into))
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("orderBookDetails", move |config| {
+            order_api::order_book_details(config, market.map(Into::into))
+        })
+        .await
     }
 
     pub(crate) async fn order_books_metadata(
         &self,
         market: Option<MarketId>,
     ) -> Result<models::OrderBooks> {
-        order_api::order_books(self.configuration_ref(), market.map(Into::into))
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("orderBooks", move |config| {
+            order_api::order_books(config, market.map(Into::into))
+        })
+        .await
     }
 
     pub(crate) async fn recent_trades(
@@ -466,9 +482,10 @@ impl RestClient {
         market: MarketId,
         limit: i64,
     ) -> Result<models::Trades> {
-        order_api::recent_trades(self.configuration_ref(), market.into(), limit)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("recentTrades", move |config| {
+            order_api::recent_trades(config, market.into(), limit)
+        })
+        .await
     }
 
     pub(crate) async fn trades(
@@ -485,22 +502,24 @@ impl RestClient {
         auth: Option<&str>,
     ) -> Result<models::Trades> {
         let sort_dir = sort_dir.map(|s| s.to_string());
-        order_api::trades(
-            self.configuration_ref(),
-            sort_by.as_str(),
-            limit,
-            auth,
-            auth,
-            market.map(Into::into),
-            account_index,
-            order_index,
-            sort_dir.as_deref(),
-            cursor,
-            from,
-            ask_filter,
-        )
+        let sort_dir = sort_dir.as_deref();
+        self.call("trades", move |config| {
+            order_api::trades(
+                config,
+                sort_by.as_str(),
+                limit,
+                auth,
+                auth,
+                market.map(Into::into),
+                account_index,
+                order_index,
+                sort_dir,
+                cursor,
+                from,
+                ask_filter,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn account_transactions(
@@ -511,24 +530,26 @@ impl RestClient {
         types: Option<Vec<i32>>,
         auth: &str,
     ) -> Result<models::Txs> {
-        transaction_api::account_txs(
-            self.configuration_ref(),
-            limit,
-            "index",
-            account_value,
-            Some(auth),
-            index,
-            types,
-            Some(auth),
-        )
+        self.call("accountTxs", move |config| {
+            transaction_api::account_txs(
+                config,
+                limit,
+                "index",
+                account_value,
+                Some(auth),
+                index,
+                types.clone(),
+                Some(auth),
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn block_transactions(&self, by: By, value: &str) -> Result<models::Txs> {
-        transaction_api::block_txs(self.configuration_ref(), by.as_str(), value)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("blockTxs", move |config| {
+            transaction_api::block_txs(config, by.as_str(), value)
+        })
+        .await
     }
 
     pub(crate) async fn deposit_history(
@@ -539,17 +560,18 @@ impl RestClient {
         filter: Option<&str>,
         auth: &str,
     ) -> Result<models::DepositHistory> {
-        transaction_api::deposit_history(
-            self.configuration_ref(),
-            account_index,
-            l1_address,
-            Some(auth),
-            Some(auth),
-            cursor,
-            filter,
-        )
+        self.call("deposit/history", move |config| {
+            transaction_api::deposit_history(
+                config,
+                account_index,
+                l1_address,
+                Some(auth),
+                Some(auth),
+                cursor,
+                filter,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn transfer_history(
@@ -558,15 +580,10 @@ impl RestClient {
         cursor: Option<&str>,
         auth: &str,
     ) -> Result<models::TransferHistory> {
-        transaction_api::transfer_history(
-            self.configuration_ref(),
-            account_index,
-            Some(auth),
-            Some(auth),
-            cursor,
-        )
+        self.call("transfer/history", move |config| {
+            transaction_api::transfer_history(config, account_index, Some(auth), Some(auth), cursor)
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn withdraw_history(
@@ -576,16 +593,17 @@ impl RestClient {
         filter: Option<&str>,
         auth: &str,
     ) -> Result<models::WithdrawHistory> {
-        transaction_api::withdraw_history(
-            self.configuration_ref(),
-            account_index,
-            Some(auth),
-            Some(auth),
-            cursor,
-            filter,
-        )
+        self.call("withdraw/history", move |config| {
+            transaction_api::withdraw_history(
+                config,
+                account_index,
+                Some(auth),
+                Some(auth),
+                cursor,
+                filter,
+            )
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn next_nonce(
@@ -593,31 +611,90 @@ impl RestClient {
         account_index: i64,
         api_key_index: ApiKeyIndex,
     ) -> Result<models::NextNonce> {
-        transaction_api::next_nonce(
-            self.configuration_ref(),
-            account_index,
-            api_key_index.into(),
-        )
+        self.call("nextNonce", move |config| {
+            transaction_api::next_nonce(config, account_index, api_key_index.into())
+        })
         .await
-        .map_err(|err| self.map_rest_error(err))
     }
 
     pub(crate) async fn transaction(&self, by: By, value: &str) -> Result<models::EnrichedTx> {
-        transaction_api::tx(self.configuration_ref(), by.as_str(), value)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("tx", move |config| {
+            transaction_api::tx(config, by.as_str(), value)
+        })
+        .await
     }
 
     pub(crate) async fn transaction_from_l1_hash(&self, hash: &str) -> Result<models::EnrichedTx> {
-        transaction_api::tx_from_l1_tx_hash(self.configuration_ref(), hash)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("txFromL1TxHash", move |config| {
+            transaction_api::tx_from_l1_tx_hash(config, hash)
+        })
+        .await
     }
 
     pub(crate) async fn transactions(&self, limit: i64, index: Option<i64>) -> Result<models::Txs> {
-        transaction_api::txs(self.configuration_ref(), limit, index)
-            .await
-            .map_err(|err| self.map_rest_error(err))
+        self.call("txs", move |config| {
+            transaction_api::txs(config, limit, index)
+        })
+        .await
+    }
+
+    /// Send an idempotent request within the rate limit, retrying rate
+    /// limits, server errors and transport failures per the retry policy.
+    async fn call<'a, T, E, F, Fut>(&'a self, endpoint: &str, request: F) -> Result<T>
+    where
+        F: Fn(&'a configuration::Configuration) -> Fut,
+        Fut: Future<Output = std::result::Result<T, apis::Error<E>>>,
+        E: serde::Serialize + fmt::Debug,
+    {
+        self.send(endpoint, true, request).await
+    }
+
+    /// Like [`call`](Self::call) for requests that change state: only a
+    /// `429`, which the server did not act on, is retried.
+    async fn call_once<'a, T, E, F, Fut>(&'a self, endpoint: &str, request: F) -> Result<T>
+    where
+        F: Fn(&'a configuration::Configuration) -> Fut,
+        Fut: Future<Output = std::result::Result<T, apis::Error<E>>>,
+        E: serde::Serialize + fmt::Debug,
+    {
+        self.send(endpoint, false, request).await
+    }
+
+    async fn send<'a, T, E, F, Fut>(
+        &'a self,
+        endpoint: &str,
+        idempotent: bool,
+        request: F,
+    ) -> Result<T>
+    where
+        F: Fn(&'a configuration::Configuration) -> Fut,
+        Fut: Future<Output = std::result::Result<T, apis::Error<E>>>,
+        E: serde::Serialize + fmt::Debug,
+    {
+        let mut retry = 0;
+        loop {
+            self.policy.acquire(RequestClass::Read, endpoint).await;
+            let err = match request(&self.configuration).await {
+                Ok(value) => return Ok(value),
+                Err(err) => err,
+            };
+            let failure = rate_limit::classify(&err);
+            let error = self.map_rest_error(err);
+            let retry_after = match &error {
+                Error::RateLimited { retry_after } => retry_after.map(Duration::from_secs),
+                _ => None,
+            };
+            match failure {
+                Failure::RateLimited => self.policy.rate_limited(retry_after),
+                Failure::Transient if idempotent => {}
+                _ => return Err(error),
+            }
+            retry += 1;
+            match self.policy.retry_delay(retry, retry_after) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return Err(error),
+            }
+        }
     }
 
     pub(crate) fn map_rest_error<E>(&self, err: apis::Error<E>) -> Error
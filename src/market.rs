@@ -0,0 +1,503 @@
+//! Per-market precision and exact decimal price/size conversion.
+//!
+//! The exchange works in integer ticks ([`Price`]) and base units
+//! ([`BaseQty`]) whose scale depends on the market. [`MarketSpec`] captures
+//! that scale together with the minimum order amounts from the order book
+//! metadata, and converts [`DecimalPrice`] / [`DecimalSize`] values into
+//! ticks without going through `f64` multiplication, using an explicit
+//! [`Rounding`] mode.
+
+use std::{fmt, str::FromStr};
+
+use crate::{
+    errors::MarketSpecError,
+    lighter_client::OrderSide,
+    models,
+    order_book::BookScale,
+    types::{BaseQty, MarketId, Price},
+};
+
+type SpecResult<T> = std::result::Result<T, MarketSpecError>;
+
+/// How to round a value that does not fall exactly on the market grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rounding {
+    /// Reject values that need rounding.
+    #[default]
+    Exact,
+    /// Round toward negative infinity.
+    Down,
+    /// Round toward positive infinity.
+    Up,
+    /// Round to the nearest step, ties away from zero.
+    Nearest,
+    /// Round toward the touch (more aggressive): bids up, asks down.
+    TowardTouch,
+    /// Round away from the touch (more passive): bids down, asks up.
+    AwayFromTouch,
+}
+
+impl Rounding {
+    fn resolve(self, side: Option<OrderSide>) -> SpecResult<Rounding> {
+        Ok(match (self, side) {
+            (Rounding::TowardTouch, Some(OrderSide::Bid))
+            | (Rounding::AwayFromTouch, Some(OrderSide::Ask)) => Rounding::Up,
+            (Rounding::TowardTouch, Some(OrderSide::Ask))
+            | (Rounding::AwayFromTouch, Some(OrderSide::Bid)) => Rounding::Down,
+            (Rounding::TowardTouch | Rounding::AwayFromTouch, None) => {
+                return Err(MarketSpecError::SideRequired)
+            }
+            (other, _) => other,
+        })
+    }
+}
+
+/// Exact base-10 decimal number (`mantissa * 10^-scale`).
+///
+/// Equality and hashing compare values, so `1.50` (`150 * 10^-2`) equals
+/// `1.5` (`15 * 10^-1`).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+/// Largest scale accepted when parsing; keeps `10^scale` within `i128`.
+const MAX_SCALE: u32 = 30;
+
+impl Decimal {
+    pub const ZERO: Decimal = Decimal {
+        mantissa: 0,
+        scale: 0,
+    };
+
+    pub const fn new(mantissa: i128, scale: u32) -> Self {
+        Self { mantissa, scale }
+    }
+
+    /// Convert from `f64` via its shortest round-trip representation, so
+    /// `0.1` becomes exactly `0.1` rather than `0.1000000000000000055...`.
+    pub fn from_f64(value: f64) -> SpecResult<Self> {
+        if !value.is_finite() {
+            return Err(MarketSpecError::InvalidDecimal(value.to_string()));
+        }
+        value.to_string().parse()
+    }
+
+    pub const fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub const fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_positive(&self) -> bool {
+        self.mantissa > 0
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.mantissa as f64 / 10f64.powi(self.scale as i32)
+    }
+
+    /// Express the value as an integer number of `10^-decimals` steps.
+    pub fn to_scaled(&self, decimals: u32, rounding: Rounding) -> SpecResult<i64> {
+        let overflow = || MarketSpecError::Overflow(self.to_string());
+        let scaled = if self.scale <= decimals {
+            pow10(decimals - self.scale)
+                .and_then(|factor| self.mantissa.checked_mul(factor))
+                .ok_or_else(overflow)?
+        } else {
+            let divisor = pow10(self.scale - decimals).ok_or_else(overflow)?;
+            let quotient = self.mantissa / divisor;
+            let remainder = self.mantissa % divisor;
+            if remainder == 0 {
+                quotient
+            } else {
+                match rounding {
+                    Rounding::Exact => {
+                        return Err(MarketSpecError::NotRepresentable {
+                            value: self.to_string(),
+                            decimals,
+                        })
+                    }
+                    Rounding::Down => quotient - i128::from(remainder < 0),
+                    Rounding::Up => quotient + i128::from(remainder > 0),
+                    Rounding::Nearest => {
+                        if remainder.abs() * 2 >= divisor {
+                            quotient + remainder.signum()
+                        } else {
+                            quotient
+                        }
+                    }
+                    Rounding::TowardTouch | Rounding::AwayFromTouch => {
+                        return Err(MarketSpecError::SideRequired)
+                    }
+                }
+            }
+        };
+        i64::try_from(scaled).map_err(|_| overflow())
+    }
+
+    /// Build a decimal from an integer number of `10^-decimals` steps.
+    pub fn from_scaled(value: i64, decimals: u32) -> Self {
+        Self::new(i128::from(value), decimals)
+    }
+
+    /// The same value with trailing fractional zeros dropped.
+    pub fn normalized(&self) -> Self {
+        let mut value = *self;
+        while value.scale > 0 && value.mantissa % 10 == 0 {
+            value.mantissa /= 10;
+            value.scale -= 1;
+        }
+        value
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        let (a, b) = (self.normalized(), other.normalized());
+        a.mantissa == b.mantissa && a.scale == b.scale
+    }
+}
+
+impl Eq for Decimal {}
+
+impl std::hash::Hash for Decimal {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let value = self.normalized();
+        value.mantissa.hash(state);
+        value.scale.hash(state);
+    }
+}
+
+fn pow10(exp: u32) -> Option<i128> {
+    10i128.checked_pow(exp)
+}
+
+impl FromStr for Decimal {
+    type Err = MarketSpecError;
+
+    fn from_str(text: &str) -> SpecResult<Self> {
+        let invalid = || MarketSpecError::InvalidDecimal(text.to_string());
+        let trimmed = text.trim();
+        let (negative, digits) = match trimmed.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+        if (int_part.is_empty() && frac_part.is_empty())
+            || !int_part.bytes().all(|b| b.is_ascii_digit())
+            || !frac_part.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let frac_part = frac_part.trim_end_matches('0');
+        let scale = frac_part.len() as u32;
+        if scale > MAX_SCALE {
+            return Err(invalid());
+        }
+
+        let mut mantissa: i128 = 0;
+        for b in int_part.bytes().chain(frac_part.bytes()) {
+            mantissa = mantissa
+                .checked_mul(10)
+                .and_then(|m| m.checked_add(i128::from(b - b'0')))
+                .ok_or_else(invalid)?;
+        }
+
+        if negative {
+            mantissa = -mantissa;
+        }
+        Ok(Self::new(mantissa, scale))
+    }
+}
+
+impl fmt::Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = self.mantissa.unsigned_abs().to_string();
+        if self.scale == 0 {
+            return write!(f, "{sign}{digits}");
+        }
+        let scale = self.scale as usize;
+        let padded = format!("{digits:0>width$}", width = scale + 1);
+        let (int_part, frac_part) = padded.split_at(padded.len() - scale);
+        write!(f, "{sign}{int_part}.{frac_part}")
+    }
+}
+
+macro_rules! decimal_newtype {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[repr(transparent)]
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+        pub struct $name(Decimal);
+
+        impl $name {
+            pub const fn new(value: Decimal) -> Self {
+                Self(value)
+            }
+
+            pub fn from_f64(value: f64) -> SpecResult<Self> {
+                Decimal::from_f64(value).map(Self)
+            }
+
+            pub const fn into_inner(self) -> Decimal {
+                self.0
+            }
+
+            pub fn to_f64(&self) -> f64 {
+                self.0.to_f64()
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = MarketSpecError;
+
+            fn from_str(text: &str) -> SpecResult<Self> {
+                text.parse().map(Self)
+            }
+        }
+
+        impl From<Decimal> for $name {
+            fn from(value: Decimal) -> Self {
+                Self(value)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                self.0.fmt(f)
+            }
+        }
+    };
+}
+
+decimal_newtype!(
+    /// Human readable price, e.g. `"3024.66"`.
+    DecimalPrice
+);
+decimal_newtype!(
+    /// Human readable base size, e.g. `"0.015"`.
+    DecimalSize
+);
+
+/// Precision and minimum order amounts for a market.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarketSpec {
+    pub market: MarketId,
+    pub symbol: String,
+    pub price_decimals: u32,
+    pub size_decimals: u32,
+    pub min_base_amount: Decimal,
+    pub min_quote_amount: Decimal,
+}
+
+impl MarketSpec {
+    /// Build the spec from an entry of
+    /// [`OrdersHandle::books_metadata`](crate::lighter_client::OrdersHandle::books_metadata).
+    pub fn from_metadata(book: &models::OrderBook) -> SpecResult<Self> {
+        let price_decimals = u32::try_from(book.supported_price_decimals)
+            .map_err(|_| MarketSpecError::InvalidMetadata("supported_price_decimals"))?;
+        let size_decimals = u32::try_from(book.supported_size_decimals)
+            .map_err(|_| MarketSpecError::InvalidMetadata("supported_size_decimals"))?;
+        let min_base_amount = parse_metadata_amount(&book.min_base_amount, "min_base_amount")?;
+        let min_quote_amount = parse_metadata_amount(&book.min_quote_amount, "min_quote_amount")?;
+
+        Ok(Self {
+            market: MarketId::new(book.market_id),
+            symbol: book.symbol.clone(),
+            price_decimals,
+            size_decimals,
+            min_base_amount,
+            min_quote_amount,
+        })
+    }
+
+    /// Scale used by [`LocalOrderBook`](crate::order_book::LocalOrderBook).
+    pub fn scale(&self) -> BookScale {
+        BookScale::new(self.price_decimals, self.size_decimals)
+    }
+
+    /// Convert a price to ticks. Touch-relative rounding uses `side`.
+    pub fn price(
+        &self,
+        price: DecimalPrice,
+        side: OrderSide,
+        rounding: Rounding,
+    ) -> SpecResult<Price> {
+        let rounding = rounding.resolve(Some(side))?;
+        let ticks = price.0.to_scaled(self.price_decimals, rounding)?;
+        if ticks <= 0 {
+            return Err(MarketSpecError::NonPositive);
+        }
+        Ok(Price::ticks(ticks))
+    }
+
+    /// Convert a size to base units and check it against `min_base_amount`.
+    ///
+    /// Touch-relative rounding is not meaningful for sizes and is rejected.
+    pub fn size(&self, size: DecimalSize, rounding: Rounding) -> SpecResult<BaseQty> {
+        let rounding = rounding.resolve(None)?;
+        let units = size.0.to_scaled(self.size_decimals, rounding)?;
+        let qty = BaseQty::from_i64(units)
+            .filter(|qty| qty.into_inner() > 0)
+            .ok_or(MarketSpecError::NonPositive)?;
+        self.check_size(qty)?;
+        Ok(qty)
+    }
+
+    /// Smallest accepted size in base units.
+    pub fn min_base_units(&self) -> i64 {
+        self.min_base_amount
+            .to_scaled(self.size_decimals, Rounding::Up)
+            .unwrap_or(i64::MAX)
+    }
+
+    pub fn check_size(&self, qty: BaseQty) -> SpecResult<()> {
+        let min_units = self.min_base_units();
+        if qty.into_inner() < min_units {
+            return Err(MarketSpecError::BelowMinBase {
+                units: qty.into_inner(),
+                min_units,
+            });
+        }
+        Ok(())
+    }
+
+    /// Check `qty * price` against `min_quote_amount`.
+    pub fn check_notional(&self, qty: BaseQty, price: Price) -> SpecResult<()> {
+        let decimals = self.price_decimals + self.size_decimals;
+        let notional = i128::from(qty.into_inner()) * i128::from(price.into_ticks());
+        let min_quote = self
+            .min_quote_amount
+            .to_scaled(decimals, Rounding::Up)
+            .map(i128::from)
+            .unwrap_or(i128::MAX);
+        if notional < min_quote {
+            return Err(MarketSpecError::BelowMinQuote {
+                min_quote: self.min_quote_amount.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn price_to_decimal(&self, price: Price) -> DecimalPrice {
+        DecimalPrice(Decimal::from_scaled(
+            price.into_ticks(),
+            self.price_decimals,
+        ))
+    }
+
+    pub fn size_to_decimal(&self, qty: BaseQty) -> DecimalSize {
+        DecimalSize(Decimal::from_scaled(qty.into_inner(), self.size_decimals))
+    }
+}
+
+fn parse_metadata_amount(text: &str, field: &'static str) -> SpecResult<Decimal> {
+    if text.trim().is_empty() {
+        return Ok(Decimal::ZERO);
+    }
+    text.parse()
+        .map_err(|_| MarketSpecError::InvalidMetadata(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> MarketSpec {
+        let book = models::OrderBook {
+            symbol: "ETH".to_string(),
+            market_id: 0,
+            min_base_amount: "0.0050".to_string(),
+            min_quote_amount: "10.000000".to_string(),
+            supported_size_decimals: 4,
+            supported_price_decimals: 2,
+            ..Default::default()
+        };
+        MarketSpec::from_metadata(&book).unwrap()
+    }
+
+    fn price(text: &str) -> DecimalPrice {
+        text.parse().unwrap()
+    }
+
+    fn size(text: &str) -> DecimalSize {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_decimal_parse_and_display() {
+        let value: Decimal = "3024.660".parse().unwrap();
+        assert_eq!(value, Decimal::new(302_466, 2));
+        assert_eq!(value.to_string(), "3024.66");
+        assert_eq!(Decimal::new(-5, 3).to_string(), "-0.005");
+        assert!("1.2.3".parse::<Decimal>().is_err());
+        assert!("".parse::<Decimal>().is_err());
+
+        let scaled = Decimal::from_scaled(150, 2);
+        assert_eq!(scaled, "1.5".parse().unwrap());
+        assert_ne!(scaled, Decimal::new(15, 2));
+        let set: std::collections::HashSet<_> = [scaled, Decimal::new(15, 1)].into();
+        assert_eq!(set.len(), 1);
+    }
+
+    #[test]
+    fn test_from_f64_avoids_binary_error() {
+        // 3024.66 * 100.0 == 302465.99999999994 in f64
+        let spec = spec();
+        let px = DecimalPrice::from_f64(3024.66).unwrap();
+        assert_eq!(
+            spec.price(px, OrderSide::Bid, Rounding::Exact).unwrap(),
+            Price::ticks(302_466)
+        );
+    }
+
+    #[test]
+    fn test_price_rounding_relative_to_touch() {
+        let spec = spec();
+        let px = price("100.005");
+        assert!(spec.price(px, OrderSide::Bid, Rounding::Exact).is_err());
+        let ticks = |side, rounding| spec.price(px, side, rounding).unwrap().into_ticks();
+        assert_eq!(ticks(OrderSide::Bid, Rounding::TowardTouch), 10_001);
+        assert_eq!(ticks(OrderSide::Bid, Rounding::AwayFromTouch), 10_000);
+        assert_eq!(ticks(OrderSide::Ask, Rounding::TowardTouch), 10_000);
+        assert_eq!(ticks(OrderSide::Ask, Rounding::AwayFromTouch), 10_001);
+        assert_eq!(ticks(OrderSide::Ask, Rounding::Nearest), 10_001);
+    }
+
+    #[test]
+    fn test_size_minimums() {
+        let spec = spec();
+        assert_eq!(spec.min_base_units(), 50);
+        assert_eq!(
+            spec.size(size("0.01"), Rounding::Exact)
+                .unwrap()
+                .into_inner(),
+            100
+        );
+        assert_eq!(
+            spec.size(size("0.004"), Rounding::Exact),
+            Err(MarketSpecError::BelowMinBase {
+                units: 40,
+                min_units: 50
+            })
+        );
+        assert_eq!(
+            spec.size(size("0.01"), Rounding::TowardTouch),
+            Err(MarketSpecError::SideRequired)
+        );
+    }
+
+    #[test]
+    fn test_notional_check() {
+        let spec = spec();
+        let qty = BaseQty::from_i64(50).unwrap(); // 0.005
+        assert!(spec.check_notional(qty, Price::ticks(300_000)).is_ok()); // $15
+        assert!(spec.check_notional(qty, Price::ticks(100_000)).is_err()); // $5
+    }
+}
@@ -1,6 +1,13 @@
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 
 use crate::{
     apis,
@@ -9,17 +16,87 @@ use crate::{
     models,
 };
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub enum NonceManagerType {
     Optimistic,
     Api,
+    /// Optimistic allocation whose per-key high-water marks are written to disk
+    /// so restarts resume without refetching every key.
+    Persistent(PersistentNonceConfig),
+}
+
+impl NonceManagerType {
+    /// Persistent manager backed by the file at `path`.
+    pub fn persistent(path: impl Into<PathBuf>) -> Self {
+        Self::Persistent(PersistentNonceConfig::new(path))
+    }
+}
+
+/// Settings for [`NonceManagerType::Persistent`].
+///
+/// The file holds one reserved mark per API key and is replaced atomically
+/// whenever a key runs past its reservation, so only one `next_nonce` call per
+/// block waits on the disk. The file never drops below a reservation: a
+/// rolled-back nonce may still have landed, so a restart always resumes past
+/// the whole block. Use one file per process; two writers sharing a file will
+/// overwrite each other's marks.
+#[derive(Debug, Clone)]
+pub struct PersistentNonceConfig {
+    path: PathBuf,
+    trust_window: Duration,
+    reserve_block: i64,
+}
+
+impl PersistentNonceConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            trust_window: DEFAULT_TRUST_WINDOW,
+            reserve_block: DEFAULT_RESERVE_BLOCK,
+        }
+    }
+
+    /// Skip the startup `next_nonce` call for keys whose stored mark was
+    /// written less than `window` ago. The default, `Duration::ZERO`, always
+    /// reconciles.
+    pub fn with_trust_window(mut self, window: Duration) -> Self {
+        self.trust_window = window;
+        self
+    }
+
+    /// Number of nonces reserved per write. `1` persists every nonce.
+    pub fn with_reserve_block(mut self, block: u32) -> Self {
+        self.reserve_block = i64::from(block.max(1));
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn trust_window(&self) -> Duration {
+        self.trust_window
+    }
+
+    pub fn reserve_block(&self) -> u32 {
+        self.reserve_block as u32
+    }
 }
 
+const DEFAULT_TRUST_WINDOW: Duration = Duration::ZERO;
+const DEFAULT_RESERVE_BLOCK: i64 = 32;
+
 #[async_trait]
 pub trait NonceManager: Send + Sync {
     async fn next_nonce(&mut self) -> Result<(i32, i64)>;
     async fn hard_refresh_nonce(&mut self, api_key_index: i32) -> Result<()>;
     fn acknowledge_failure(&mut self, api_key_index: i32);
+    /// Hand `first..=last` out again if they are still the most recent nonces
+    /// issued for the key. Returns `false`, changing nothing, once a later
+    /// nonce has been issued, which is all a manager that cannot rewind does.
+    fn release(&mut self, _api_key_index: i32, _first: i64, _last: i64) -> bool {
+        false
+    }
 }
 
 pub struct OptimisticNonceManager {
@@ -30,6 +107,14 @@ pub struct ApiNonceManager {
     state: NonceState,
 }
 
+pub struct PersistentNonceManager {
+    state: NonceState,
+    store: NonceStore,
+    /// Highest nonce per key covered by the file on disk.
+    reserved: HashMap<i32, i64>,
+    reserve_block: i64,
+}
+
 struct NonceState {
     configuration: Configuration,
     account_index: i64,
@@ -46,12 +131,7 @@ impl NonceState {
         start_api_key: i32,
         end_api_key: i32,
     ) -> Result<Self> {
-        if start_api_key > end_api_key || start_api_key < 0 || end_api_key >= 255 {
-            return Err(SignerClientError::Nonce(format!(
-                "invalid api key range start={} end={}",
-                start_api_key, end_api_key
-            )));
-        }
+        validate_range(start_api_key, end_api_key)?;
 
         let mut nonce = HashMap::new();
         for api_key_index in start_api_key..=end_api_key {
@@ -59,14 +139,30 @@ impl NonceState {
             nonce.insert(api_key_index, next_nonce - 1);
         }
 
-        Ok(Self {
+        Ok(Self::with_nonces(
+            configuration,
+            account_index,
+            start_api_key,
+            end_api_key,
+            nonce,
+        ))
+    }
+
+    fn with_nonces(
+        configuration: Configuration,
+        account_index: i64,
+        start_api_key: i32,
+        end_api_key: i32,
+        nonce: HashMap<i32, i64>,
+    ) -> Self {
+        Self {
             configuration,
             account_index,
             start_api_key,
             end_api_key,
             current_api_key: end_api_key,
             nonce,
-        })
+        }
     }
 
     fn increment_current_api_key(&mut self) -> i32 {
@@ -89,18 +185,42 @@ impl NonceState {
             *entry -= 1;
         }
     }
-}
 
-#[async_trait]
-impl NonceManager for OptimisticNonceManager {
-    async fn next_nonce(&mut self) -> Result<(i32, i64)> {
-        let api_key = self.state.increment_current_api_key();
-        let entry = self.state.nonce.get_mut(&api_key).ok_or_else(|| {
+    fn release(&mut self, api_key_index: i32, first: i64, last: i64) -> bool {
+        match self.nonce.get_mut(&api_key_index) {
+            Some(entry) if *entry == last && first <= last => {
+                *entry = first - 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn next_optimistic(&mut self) -> Result<(i32, i64)> {
+        let api_key = self.increment_current_api_key();
+        let entry = self.nonce.get_mut(&api_key).ok_or_else(|| {
             SignerClientError::Nonce(format!("missing nonce for api key {}", api_key))
         })?;
         *entry += 1;
         Ok((api_key, *entry))
     }
+}
+
+fn validate_range(start_api_key: i32, end_api_key: i32) -> Result<()> {
+    if start_api_key > end_api_key || start_api_key < 0 || end_api_key >= 255 {
+        return Err(SignerClientError::Nonce(format!(
+            "invalid api key range start={} end={}",
+            start_api_key, end_api_key
+        )));
+    }
+    Ok(())
+}
+
+#[async_trait]
+impl NonceManager for OptimisticNonceManager {
+    async fn next_nonce(&mut self) -> Result<(i32, i64)> {
+        self.state.next_optimistic()
+    }
 
     async fn hard_refresh_nonce(&mut self, api_key_index: i32) -> Result<()> {
         self.state.hard_refresh_nonce(api_key_index).await
@@ -109,6 +229,10 @@ impl NonceManager for OptimisticNonceManager {
     fn acknowledge_failure(&mut self, api_key_index: i32) {
         self.state.acknowledge_failure(api_key_index);
     }
+
+    fn release(&mut self, api_key_index: i32, first: i64, last: i64) -> bool {
+        self.state.release(api_key_index, first, last)
+    }
 }
 
 #[async_trait]
@@ -128,6 +252,198 @@ impl NonceManager for ApiNonceManager {
     fn acknowledge_failure(&mut self, api_key_index: i32) {
         self.state.acknowledge_failure(api_key_index);
     }
+
+    fn release(&mut self, api_key_index: i32, first: i64, last: i64) -> bool {
+        self.state.release(api_key_index, first, last)
+    }
+}
+
+impl PersistentNonceManager {
+    /// Load the stored high-water marks and reconcile them with the server.
+    ///
+    /// Keys whose mark is older than the trust window (or missing) are fetched
+    /// and resume from whichever of the stored and server values is higher.
+    pub async fn open(
+        config: &PersistentNonceConfig,
+        configuration: Configuration,
+        account_index: i64,
+        start_api_key: i32,
+        end_api_key: i32,
+    ) -> Result<Self> {
+        validate_range(start_api_key, end_api_key)?;
+
+        let store = NonceStore::new(config.path.clone(), account_index);
+        let stored = match store.load() {
+            Ok(file) => file,
+            Err(err) => {
+                tracing::warn!(
+                    path = %config.path.display(),
+                    error = %err,
+                    "ignoring unreadable nonce file; reconciling every key"
+                );
+                None
+            }
+        };
+        let (marks, fresh) = match stored {
+            Some(file) => {
+                let fresh = file.written_within(config.trust_window);
+                (file.keys, fresh)
+            }
+            None => (BTreeMap::new(), false),
+        };
+
+        let mut nonce = HashMap::new();
+        for api_key_index in start_api_key..=end_api_key {
+            let stored = marks.get(&api_key_index).copied();
+            let value = match stored {
+                Some(mark) if fresh => mark,
+                _ => {
+                    let next_nonce =
+                        fetch_nonce(&configuration, account_index, api_key_index).await?;
+                    reconcile(stored, next_nonce)
+                }
+            };
+            nonce.insert(api_key_index, value);
+        }
+
+        let state = NonceState::with_nonces(
+            configuration,
+            account_index,
+            start_api_key,
+            end_api_key,
+            nonce,
+        );
+        store.save(&state.nonce)?;
+        Ok(Self {
+            reserved: state.nonce.clone(),
+            state,
+            store,
+            reserve_block: config.reserve_block,
+        })
+    }
+
+    /// Persist a new reservation for `api_key_index` if `nonce` is past the
+    /// one on disk.
+    fn reserve(&mut self, api_key_index: i32, nonce: i64) -> Result<()> {
+        if self
+            .reserved
+            .get(&api_key_index)
+            .is_some_and(|&reserved| nonce <= reserved)
+        {
+            return Ok(());
+        }
+        let mut reserved = self.reserved.clone();
+        reserved.insert(api_key_index, nonce + self.reserve_block - 1);
+        self.store.save(&reserved)?;
+        self.reserved = reserved;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl NonceManager for PersistentNonceManager {
+    async fn next_nonce(&mut self) -> Result<(i32, i64)> {
+        let (api_key, nonce) = self.state.next_optimistic()?;
+        if let Err(err) = self.reserve(api_key, nonce) {
+            self.state.acknowledge_failure(api_key);
+            return Err(err);
+        }
+        Ok((api_key, nonce))
+    }
+
+    async fn hard_refresh_nonce(&mut self, api_key_index: i32) -> Result<()> {
+        self.state.hard_refresh_nonce(api_key_index).await?;
+        match self.state.nonce.get(&api_key_index) {
+            Some(&nonce) => self.reserve(api_key_index, nonce),
+            None => Ok(()),
+        }
+    }
+
+    // Rollbacks stay within the reservation on disk, which remains a valid
+    // upper bound; a stale, higher mark only costs one invalid-nonce refresh.
+    fn acknowledge_failure(&mut self, api_key_index: i32) {
+        self.state.acknowledge_failure(api_key_index);
+    }
+
+    fn release(&mut self, api_key_index: i32, first: i64, last: i64) -> bool {
+        self.state.release(api_key_index, first, last)
+    }
+}
+
+/// Highest nonce known to be used for a key, given its stored mark and the
+/// server's `next_nonce`.
+fn reconcile(stored: Option<i64>, server_next: i64) -> i64 {
+    let server_used = server_next - 1;
+    stored.map_or(server_used, |mark| mark.max(server_used))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct NonceFile {
+    account_index: i64,
+    /// Unix seconds of the last write.
+    updated_at: u64,
+    keys: BTreeMap<i32, i64>,
+}
+
+impl NonceFile {
+    fn written_within(&self, window: Duration) -> bool {
+        !window.is_zero() && unix_now().saturating_sub(self.updated_at) < window.as_secs()
+    }
+}
+
+struct NonceStore {
+    path: PathBuf,
+    account_index: i64,
+}
+
+impl NonceStore {
+    fn new(path: PathBuf, account_index: i64) -> Self {
+        Self {
+            path,
+            account_index,
+        }
+    }
+
+    /// Marks for this account, or `None` when the file is missing or belongs
+    /// to a different account.
+    fn load(&self) -> Result<Option<NonceFile>> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let file: NonceFile = serde_json::from_slice(&bytes)?;
+        Ok((file.account_index == self.account_index).then_some(file))
+    }
+
+    /// Write the marks to a sibling temp file and rename it over the target so
+    /// a crash never leaves a truncated file behind.
+    fn save(&self, nonce: &HashMap<i32, i64>) -> Result<()> {
+        let file = NonceFile {
+            account_index: self.account_index,
+            updated_at: unix_now(),
+            keys: nonce.iter().map(|(key, value)| (*key, *value)).collect(),
+        };
+        let bytes = serde_json::to_vec(&file)?;
+
+        let mut tmp_name = self.path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp_path = PathBuf::from(tmp_name);
+        {
+            let mut tmp = fs::File::create(&tmp_path)?;
+            tmp.write_all(&bytes)?;
+            tmp.sync_all()?;
+        }
+        fs::rename(&tmp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default()
 }
 
 pub async fn nonce_manager_factory(
@@ -150,6 +466,17 @@ pub async fn nonce_manager_factory(
                 NonceState::new(configuration, account_index, start_api_key, end_api_key).await?;
             Ok(Box::new(ApiNonceManager { state }))
         }
+        NonceManagerType::Persistent(config) => {
+            let manager = PersistentNonceManager::open(
+                &config,
+                configuration,
+                account_index,
+                start_api_key,
+                end_api_key,
+            )
+            .await?;
+            Ok(Box::new(manager))
+        }
     }
 }
 
@@ -189,3 +516,94 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!(
+            "lighter-nonce-{}-{}.json",
+            name,
+            std::process::id()
+        ))
+    }
+
+    #[test]
+    fn reconcile_takes_higher_of_stored_and_server() {
+        assert_eq!(reconcile(None, 10), 9);
+        assert_eq!(reconcile(Some(15), 10), 15);
+        assert_eq!(reconcile(Some(3), 10), 9);
+    }
+
+    #[test]
+    fn release_only_rewinds_the_latest_nonces() {
+        let mut state =
+            NonceState::with_nonces(Configuration::default(), 7, 2, 2, HashMap::from([(2, 9)]));
+        for expected in 10..=12 {
+            assert_eq!(state.next_optimistic().unwrap(), (2, expected));
+        }
+
+        assert!(!state.release(2, 10, 11));
+        assert!(state.release(2, 11, 12));
+        assert_eq!(state.next_optimistic().unwrap(), (2, 11));
+    }
+
+    #[test]
+    fn store_round_trips_and_scopes_to_account() {
+        let path = temp_path("roundtrip");
+        let store = NonceStore::new(path.clone(), 7);
+        let nonce = HashMap::from([(2, 41), (3, 99)]);
+        store.save(&nonce).unwrap();
+
+        let file = store.load().unwrap().expect("file for account 7");
+        assert_eq!(file.keys.get(&2), Some(&41));
+        assert_eq!(file.keys.get(&3), Some(&99));
+        assert!(file.written_within(Duration::from_secs(60)));
+        assert!(!file.written_within(Duration::ZERO));
+
+        let other = NonceStore::new(path.clone(), 8);
+        assert!(other.load().unwrap().is_none());
+
+        fs::remove_file(&path).unwrap();
+        assert!(store.load().unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn persistent_manager_writes_once_per_block() {
+        let path = temp_path("block");
+        let config = PersistentNonceConfig::new(path.clone()).with_reserve_block(4);
+        let store = NonceStore::new(path.clone(), 7);
+        let mut manager = PersistentNonceManager {
+            state: NonceState::with_nonces(
+                Configuration::default(),
+                7,
+                2,
+                2,
+                HashMap::from([(2, 9)]),
+            ),
+            store: NonceStore::new(path.clone(), 7),
+            reserved: HashMap::from([(2, 9)]),
+            reserve_block: config.reserve_block,
+        };
+        let stored = |store: &NonceStore| store.load().unwrap().unwrap().keys[&2];
+
+        assert_eq!(manager.next_nonce().await.unwrap(), (2, 10));
+        assert_eq!(stored(&store), 13);
+        fs::remove_file(&path).unwrap();
+        for expected in 11..=13 {
+            assert_eq!(manager.next_nonce().await.unwrap(), (2, expected));
+        }
+        assert!(store.load().unwrap().is_none());
+
+        assert_eq!(manager.next_nonce().await.unwrap(), (2, 14));
+        assert_eq!(stored(&store), 17);
+
+        // A rolled-back nonce may still have landed: the file keeps the
+        // reservation through shutdown.
+        manager.acknowledge_failure(2);
+        drop(manager);
+        assert_eq!(stored(&store), 17);
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,755 @@
+//! Local order book engine backed by integer ticks.
+//!
+//! [`LocalOrderBook`] keeps both sides of a market in `BTreeMap`s keyed by the
+//! price in ticks, so applying a delta level costs `O(log n)` and the best bid
+//! / ask are always available without re-sorting. Every snapshot and delta is
+//! checked against the `offset` / `nonce` sequencing fields the exchange sends,
+//! which lets callers tell a healthy book from one that silently missed an
+//! update.
+//!
+//! [`OrderBookSync`] manages books for several markets, consumes
+//! [`OrderBookEvent`]s straight from the websocket stream and, when a gap or a
+//! crossed book is detected, reloads the affected market from the REST
+//! `orderBookOrders` endpoint via [`OrdersHandle::book`](crate::lighter_client::OrdersHandle::book).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::Instant,
+};
+
+use crate::{
+    lighter_client::{LighterClient, Result as LighterResult},
+    models,
+    types::MarketId,
+    ws_client::{OrderBookEvent, OrderBookLevel},
+};
+
+/// Default number of resting orders requested per side when resnapshotting.
+pub const DEFAULT_RESNAPSHOT_DEPTH: i64 = 250;
+
+/// Decimal precision used to convert the exchange's decimal strings into ticks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookScale {
+    pub price_decimals: u32,
+    pub size_decimals: u32,
+}
+
+impl BookScale {
+    pub const fn new(price_decimals: u32, size_decimals: u32) -> Self {
+        Self {
+            price_decimals,
+            size_decimals,
+        }
+    }
+
+    /// Build the scale from the market metadata returned by
+    /// [`OrdersHandle::books_metadata`](crate::lighter_client::OrdersHandle::books_metadata).
+    pub fn from_metadata(book: &models::OrderBook) -> Self {
+        Self::new(
+            book.supported_price_decimals.max(0) as u32,
+            book.supported_size_decimals.max(0) as u32,
+        )
+    }
+
+    pub fn price_to_ticks(&self, text: &str) -> Option<i64> {
+        parse_scaled(text, self.price_decimals)
+    }
+
+    pub fn size_to_units(&self, text: &str) -> Option<i64> {
+        parse_scaled(text, self.size_decimals)
+    }
+
+    pub fn ticks_to_price(&self, ticks: i64) -> f64 {
+        ticks as f64 / 10f64.powi(self.price_decimals as i32)
+    }
+
+    pub fn units_to_size(&self, units: i64) -> f64 {
+        units as f64 / 10f64.powi(self.size_decimals as i32)
+    }
+}
+
+/// Parse a decimal string into an integer scaled by `10^decimals` without
+/// going through floating point.
+///
+/// Returns `None` for malformed input, values that overflow `i64`, or values
+/// carrying more significant fractional digits than `decimals` allows.
+pub fn parse_scaled(text: &str, decimals: u32) -> Option<i64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text),
+    };
+    let (int_part, frac_part) = match digits.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, frac_part),
+        None => (digits, ""),
+    };
+    if int_part.is_empty() && frac_part.is_empty() {
+        return None;
+    }
+    if !int_part.bytes().all(|b| b.is_ascii_digit())
+        || !frac_part.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+
+    let decimals = decimals as usize;
+    let (kept, dropped) = frac_part.split_at(frac_part.len().min(decimals));
+    if dropped.bytes().any(|b| b != b'0') {
+        return None;
+    }
+
+    let mut value: i64 = 0;
+    for b in int_part.bytes().chain(kept.bytes()) {
+        value = value.checked_mul(10)?.checked_add(i64::from(b - b'0'))?;
+    }
+    for _ in kept.len()..decimals {
+        value = value.checked_mul(10)?;
+    }
+
+    Some(if negative { -value } else { value })
+}
+
+/// Outcome of checking an update's sequence fields against the book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    /// The update directly follows the last applied one (or no sequencing
+    /// information is available to say otherwise).
+    InSequence,
+    /// The update is a duplicate or older than the last applied one.
+    Stale,
+    /// One or more updates were missed between the last applied one and this.
+    Gap { expected: i64, actual: i64 },
+}
+
+/// Result of feeding an event into [`LocalOrderBook`] or [`OrderBookSync`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookUpdate {
+    /// A full snapshot replaced the book.
+    Snapshot,
+    /// A delta was applied in sequence.
+    Delta,
+    /// A duplicate or out-of-order delta was ignored.
+    Skipped,
+    /// A delta arrived for a book that is waiting for a snapshot.
+    AwaitingSnapshot,
+    /// A sequence gap was detected; the book is no longer trusted.
+    Gap { expected: i64, actual: i64 },
+    /// Applying the delta left the best bid at or above the best ask.
+    Crossed,
+    /// The book was reloaded from the REST endpoint.
+    Resnapshotted,
+}
+
+impl BookUpdate {
+    /// Whether this outcome means the book must be reloaded before use.
+    pub fn needs_resnapshot(&self) -> bool {
+        matches!(self, BookUpdate::Gap { .. } | BookUpdate::Crossed)
+    }
+}
+
+/// Sequencing fields attached to an order book message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BookSequence {
+    pub offset: Option<i64>,
+    pub nonce: Option<u64>,
+    pub begin_nonce: Option<u64>,
+}
+
+impl BookSequence {
+    pub fn from_event(event: &OrderBookEvent) -> Self {
+        Self {
+            offset: event.offset,
+            nonce: event.nonce,
+            begin_nonce: event.begin_nonce,
+        }
+    }
+}
+
+/// Order book for a single market with integer-tick price levels.
+#[derive(Debug, Clone)]
+pub struct LocalOrderBook {
+    market: MarketId,
+    scale: BookScale,
+    bids: BTreeMap<i64, i64>,
+    asks: BTreeMap<i64, i64>,
+    last_offset: Option<i64>,
+    last_nonce: Option<u64>,
+    /// Newest sequence received over the websocket, applied or not.
+    latest: BookSequence,
+    synced: bool,
+    last_update: Option<Instant>,
+}
+
+impl LocalOrderBook {
+    pub fn new(market: MarketId, scale: BookScale) -> Self {
+        Self {
+            market,
+            scale,
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_offset: None,
+            last_nonce: None,
+            latest: BookSequence::default(),
+            synced: false,
+            last_update: None,
+        }
+    }
+
+    pub fn market(&self) -> MarketId {
+        self.market
+    }
+
+    pub fn scale(&self) -> BookScale {
+        self.scale
+    }
+
+    /// Whether the book holds a snapshot and has not seen a gap since.
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Time at which the book last changed.
+    pub fn last_update(&self) -> Option<Instant> {
+        self.last_update
+    }
+
+    pub fn last_offset(&self) -> Option<i64> {
+        self.last_offset
+    }
+
+    pub fn last_nonce(&self) -> Option<u64> {
+        self.last_nonce
+    }
+
+    /// Best bid as `(price_ticks, size_units)`.
+    pub fn best_bid(&self) -> Option<(i64, i64)> {
+        self.bids.iter().next_back().map(|(p, s)| (*p, *s))
+    }
+
+    /// Best ask as `(price_ticks, size_units)`.
+    pub fn best_ask(&self) -> Option<(i64, i64)> {
+        self.asks.iter().next().map(|(p, s)| (*p, *s))
+    }
+
+    /// Spread in ticks, when both sides are populated.
+    pub fn spread_ticks(&self) -> Option<i64> {
+        Some(self.best_ask()?.0 - self.best_bid()?.0)
+    }
+
+    /// Mid price in (fractional) ticks.
+    pub fn mid_ticks(&self) -> Option<f64> {
+        let bid = self.best_bid()?.0 as f64;
+        let ask = self.best_ask()?.0 as f64;
+        Some((bid + ask) / 2.0)
+    }
+
+    /// Mid price converted back into a human readable price.
+    pub fn mid_price(&self) -> Option<f64> {
+        let mid = self.mid_ticks()?;
+        Some(mid / 10f64.powi(self.scale.price_decimals as i32))
+    }
+
+    pub fn is_crossed(&self) -> bool {
+        matches!((self.best_bid(), self.best_ask()), (Some((bid, _)), Some((ask, _))) if bid >= ask)
+    }
+
+    /// Bid levels ordered from best (highest) to worst.
+    pub fn bids(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.bids.iter().rev().map(|(p, s)| (*p, *s))
+    }
+
+    /// Ask levels ordered from best (lowest) to worst.
+    pub fn asks(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.asks.iter().map(|(p, s)| (*p, *s))
+    }
+
+    pub fn bid_depth(&self) -> usize {
+        self.bids.len()
+    }
+
+    pub fn ask_depth(&self) -> usize {
+        self.asks.len()
+    }
+
+    /// Size resting at a price, in size units.
+    pub fn size_at(&self, is_ask: bool, price_ticks: i64) -> Option<i64> {
+        if is_ask {
+            self.asks.get(&price_ticks).copied()
+        } else {
+            self.bids.get(&price_ticks).copied()
+        }
+    }
+
+    /// Mark the book as untrusted until the next snapshot.
+    pub fn invalidate(&mut self) {
+        self.synced = false;
+    }
+
+    /// Replace the book with a websocket snapshot.
+    pub fn apply_snapshot(
+        &mut self,
+        asks: &[OrderBookLevel],
+        bids: &[OrderBookLevel],
+        sequence: BookSequence,
+    ) -> BookUpdate {
+        self.asks.clear();
+        self.bids.clear();
+        apply_levels(&mut self.asks, asks, self.scale, self.market);
+        apply_levels(&mut self.bids, bids, self.scale, self.market);
+        self.observe(sequence);
+        self.last_offset = sequence.offset;
+        self.last_nonce = sequence.nonce;
+        self.synced = true;
+        self.last_update = Some(Instant::now());
+        BookUpdate::Snapshot
+    }
+
+    /// Replace the book with the resting orders returned by the REST endpoint.
+    ///
+    /// REST snapshots carry no sequencing fields. The snapshot is fetched after
+    /// the newest websocket update this book has received, so it is seeded
+    /// with that update's sequence: deltas at or before it are skipped as
+    /// stale and sequencing resumes from the one that follows.
+    pub fn apply_rest_snapshot(&mut self, orders: &models::OrderBookOrders) -> BookUpdate {
+        self.asks.clear();
+        self.bids.clear();
+        aggregate_orders(&mut self.asks, &orders.asks, self.scale, self.market);
+        aggregate_orders(&mut self.bids, &orders.bids, self.scale, self.market);
+        self.last_offset = self.latest.offset;
+        self.last_nonce = self.latest.nonce;
+        self.synced = true;
+        self.last_update = Some(Instant::now());
+        BookUpdate::Resnapshotted
+    }
+
+    /// Check an update's sequencing fields without applying it.
+    pub fn check_sequence(&self, sequence: BookSequence) -> SequenceCheck {
+        if let (Some(last), Some(nonce)) = (self.last_nonce, sequence.nonce) {
+            if nonce <= last {
+                return SequenceCheck::Stale;
+            }
+            let expected = match sequence.begin_nonce {
+                Some(begin) => (begin == last).then_some(nonce),
+                None => (nonce == last + 1).then_some(nonce),
+            };
+            return match expected {
+                Some(_) => SequenceCheck::InSequence,
+                None => SequenceCheck::Gap {
+                    expected: last as i64 + 1,
+                    actual: sequence.begin_nonce.unwrap_or(nonce) as i64,
+                },
+            };
+        }
+
+        if let (Some(last), Some(offset)) = (self.last_offset, sequence.offset) {
+            if offset <= last {
+                return SequenceCheck::Stale;
+            }
+            if offset != last + 1 {
+                return SequenceCheck::Gap {
+                    expected: last + 1,
+                    actual: offset,
+                };
+            }
+        }
+
+        SequenceCheck::InSequence
+    }
+
+    /// Apply an incremental update, validating its sequence first.
+    pub fn apply_delta(
+        &mut self,
+        asks: &[OrderBookLevel],
+        bids: &[OrderBookLevel],
+        sequence: BookSequence,
+    ) -> BookUpdate {
+        self.observe(sequence);
+        if !self.synced {
+            return BookUpdate::AwaitingSnapshot;
+        }
+
+        match self.check_sequence(sequence) {
+            SequenceCheck::Stale => return BookUpdate::Skipped,
+            SequenceCheck::Gap { expected, actual } => {
+                tracing::warn!(
+                    market = %self.market,
+                    expected,
+                    actual,
+                    "order book sequence gap detected"
+                );
+                self.synced = false;
+                return BookUpdate::Gap { expected, actual };
+            }
+            SequenceCheck::InSequence => {}
+        }
+
+        apply_levels(&mut self.asks, asks, self.scale, self.market);
+        apply_levels(&mut self.bids, bids, self.scale, self.market);
+        if sequence.offset.is_some() {
+            self.last_offset = sequence.offset;
+        }
+        if sequence.nonce.is_some() {
+            self.last_nonce = sequence.nonce;
+        }
+        self.last_update = Some(Instant::now());
+
+        if self.is_crossed() {
+            tracing::warn!(market = %self.market, "order book crossed after delta");
+            self.synced = false;
+            return BookUpdate::Crossed;
+        }
+
+        BookUpdate::Delta
+    }
+
+    fn observe(&mut self, sequence: BookSequence) {
+        if sequence.offset > self.latest.offset {
+            self.latest.offset = sequence.offset;
+        }
+        if sequence.nonce > self.latest.nonce {
+            self.latest.nonce = sequence.nonce;
+        }
+    }
+
+    /// Feed a websocket [`OrderBookEvent`] into the book.
+    pub fn apply_event(&mut self, event: &OrderBookEvent) -> BookUpdate {
+        let sequence = BookSequence::from_event(event);
+        match &event.delta {
+            Some(delta) => self.apply_delta(&delta.asks, &delta.bids, sequence),
+            None => self.apply_snapshot(&event.state.asks, &event.state.bids, sequence),
+        }
+    }
+}
+
+/// Multi-market order book manager with automatic REST resnapshots.
+#[derive(Debug, Clone)]
+pub struct OrderBookSync {
+    books: HashMap<MarketId, LocalOrderBook>,
+    resnapshot_depth: i64,
+    resnapshots: u64,
+}
+
+impl Default for OrderBookSync {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OrderBookSync {
+    pub fn new() -> Self {
+        Self {
+            books: HashMap::new(),
+            resnapshot_depth: DEFAULT_RESNAPSHOT_DEPTH,
+            resnapshots: 0,
+        }
+    }
+
+    /// Number of resting orders requested per side when resnapshotting.
+    pub fn with_resnapshot_depth(mut self, depth: i64) -> Self {
+        self.resnapshot_depth = depth;
+        self
+    }
+
+    /// Start tracking a market with the given scale.
+    pub fn track(&mut self, market: MarketId, scale: BookScale) -> &mut LocalOrderBook {
+        self.books
+            .entry(market)
+            .or_insert_with(|| LocalOrderBook::new(market, scale))
+    }
+
+    /// Start tracking a market using its REST metadata for the scale.
+    pub async fn track_with_metadata(
+        &mut self,
+        client: &LighterClient,
+        market: MarketId,
+    ) -> LighterResult<&mut LocalOrderBook> {
+        let spec = client.orders().market_spec(market).await?;
+        Ok(self.track(market, spec.scale()))
+    }
+
+    pub fn book(&self, market: MarketId) -> Option<&LocalOrderBook> {
+        self.books.get(&market)
+    }
+
+    pub fn book_mut(&mut self, market: MarketId) -> Option<&mut LocalOrderBook> {
+        self.books.get_mut(&market)
+    }
+
+    pub fn markets(&self) -> impl Iterator<Item = MarketId> + '_ {
+        self.books.keys().copied()
+    }
+
+    /// Total number of REST resnapshots performed.
+    pub fn resnapshot_count(&self) -> u64 {
+        self.resnapshots
+    }
+
+    /// Apply an event without performing any I/O. Events for untracked markets
+    /// return `None`.
+    pub fn on_event(&mut self, event: &OrderBookEvent) -> Option<BookUpdate> {
+        self.books
+            .get_mut(&event.market)
+            .map(|book| book.apply_event(event))
+    }
+
+    /// Apply an event and reload the book over REST if it fell out of sync.
+    pub async fn handle_event(
+        &mut self,
+        client: &LighterClient,
+        event: &OrderBookEvent,
+    ) -> LighterResult<Option<BookUpdate>> {
+        let update = match self.on_event(event) {
+            Some(update) => update,
+            None => return Ok(None),
+        };
+
+        if update.needs_resnapshot() {
+            self.resnapshot(client, event.market).await?;
+        }
+
+        Ok(Some(update))
+    }
+
+    /// Reload a tracked market from the REST order book endpoint.
+    pub async fn resnapshot(
+        &mut self,
+        client: &LighterClient,
+        market: MarketId,
+    ) -> LighterResult<BookUpdate> {
+        let orders = client.orders().book(market, self.resnapshot_depth).await?;
+        let book =
+            self.books
+                .get_mut(&market)
+                .ok_or(crate::lighter_client::Error::InvalidConfig {
+                    field: "market",
+                    why: "market is not tracked by this order book sync",
+                })?;
+        self.resnapshots += 1;
+        tracing::info!(market = %market, "order book resnapshotted over REST");
+        Ok(book.apply_rest_snapshot(&orders))
+    }
+}
+
+fn apply_levels(
+    side: &mut BTreeMap<i64, i64>,
+    levels: &[OrderBookLevel],
+    scale: BookScale,
+    market: MarketId,
+) {
+    for level in levels {
+        let size_text = level
+            .remaining_base_amount
+            .as_deref()
+            .unwrap_or(&level.size);
+        let (price, size) = match (
+            scale.price_to_ticks(&level.price),
+            scale.size_to_units(size_text),
+        ) {
+            (Some(price), Some(size)) => (price, size),
+            _ => {
+                tracing::warn!(
+                    market = %market,
+                    price = %level.price,
+                    size = %size_text,
+                    "skipping order book level that does not fit the market scale"
+                );
+                continue;
+            }
+        };
+
+        if size <= 0 {
+            side.remove(&price);
+        } else {
+            side.insert(price, size);
+        }
+    }
+}
+
+fn aggregate_orders(
+    side: &mut BTreeMap<i64, i64>,
+    orders: &[models::SimpleOrder],
+    scale: BookScale,
+    market: MarketId,
+) {
+    for order in orders {
+        let (price, size) = match (
+            scale.price_to_ticks(&order.price),
+            scale.size_to_units(&order.remaining_base_amount),
+        ) {
+            (Some(price), Some(size)) => (price, size),
+            _ => {
+                tracing::warn!(
+                    market = %market,
+                    price = %order.price,
+                    "skipping resting order that does not fit the market scale"
+                );
+                continue;
+            }
+        };
+        if size > 0 {
+            *side.entry(price).or_insert(0) += size;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: &str, size: &str) -> OrderBookLevel {
+        OrderBookLevel {
+            price: price.to_string(),
+            size: size.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn seq(offset: i64) -> BookSequence {
+        BookSequence {
+            offset: Some(offset),
+            ..Default::default()
+        }
+    }
+
+    fn book() -> LocalOrderBook {
+        let mut book = LocalOrderBook::new(MarketId(0), BookScale::new(2, 4));
+        book.apply_snapshot(
+            &[level("101.00", "1.0"), level("102.5", "2")],
+            &[level("100.00", "1.5"), level("99.99", "3.0000")],
+            seq(10),
+        );
+        book
+    }
+
+    #[test]
+    fn test_parse_scaled() {
+        assert_eq!(parse_scaled("3024.66", 2), Some(302_466));
+        assert_eq!(parse_scaled("3024.6", 2), Some(302_460));
+        assert_eq!(parse_scaled("3024", 2), Some(302_400));
+        assert_eq!(parse_scaled("0.0100", 2), Some(1));
+        assert_eq!(parse_scaled("-1.5", 1), Some(-15));
+        assert_eq!(parse_scaled("1.234", 2), None);
+        assert_eq!(parse_scaled("abc", 2), None);
+        assert_eq!(parse_scaled("", 2), None);
+    }
+
+    #[test]
+    fn test_snapshot_orders_levels() {
+        let book = book();
+        assert_eq!(book.best_bid(), Some((10_000, 15_000)));
+        assert_eq!(book.best_ask(), Some((10_100, 10_000)));
+        assert_eq!(book.spread_ticks(), Some(100));
+        assert_eq!(
+            book.bids().map(|(p, _)| p).collect::<Vec<_>>(),
+            vec![10_000, 9_999]
+        );
+        assert!(book.is_synced());
+    }
+
+    #[test]
+    fn test_delta_updates_and_removes_levels() {
+        let mut book = book();
+        let update = book.apply_delta(&[level("101.00", "0")], &[level("100.50", "0.5")], seq(11));
+        assert_eq!(update, BookUpdate::Delta);
+        assert_eq!(book.best_ask(), Some((10_250, 20_000)));
+        assert_eq!(book.best_bid(), Some((10_050, 5_000)));
+    }
+
+    #[test]
+    fn test_offset_gap_and_stale_detection() {
+        let mut book = book();
+        assert_eq!(book.apply_delta(&[], &[], seq(10)), BookUpdate::Skipped);
+        assert_eq!(
+            book.apply_delta(&[], &[], seq(13)),
+            BookUpdate::Gap {
+                expected: 11,
+                actual: 13
+            }
+        );
+        assert!(!book.is_synced());
+        assert_eq!(
+            book.apply_delta(&[], &[], seq(14)),
+            BookUpdate::AwaitingSnapshot
+        );
+    }
+
+    #[test]
+    fn test_begin_nonce_chain() {
+        let mut book = LocalOrderBook::new(MarketId(1), BookScale::new(1, 1));
+        book.apply_snapshot(
+            &[level("10.0", "1")],
+            &[level("9.0", "1")],
+            BookSequence {
+                nonce: Some(100),
+                ..Default::default()
+            },
+        );
+        let chained = BookSequence {
+            nonce: Some(105),
+            begin_nonce: Some(100),
+            offset: None,
+        };
+        assert_eq!(book.apply_delta(&[], &[], chained), BookUpdate::Delta);
+        let broken = BookSequence {
+            nonce: Some(110),
+            begin_nonce: Some(107),
+            offset: None,
+        };
+        assert!(book.apply_delta(&[], &[], broken).needs_resnapshot());
+    }
+
+    #[test]
+    fn test_crossed_book_invalidates() {
+        let mut book = book();
+        let update = book.apply_delta(&[], &[level("101.50", "1")], seq(11));
+        assert_eq!(update, BookUpdate::Crossed);
+        assert!(!book.is_synced());
+    }
+
+    #[test]
+    fn test_rest_snapshot_aggregates_orders() {
+        let mut book = LocalOrderBook::new(MarketId(0), BookScale::new(2, 4));
+        let order = |price: &str, size: &str| models::SimpleOrder {
+            price: price.to_string(),
+            remaining_base_amount: size.to_string(),
+            ..Default::default()
+        };
+        let orders = models::OrderBookOrders {
+            asks: vec![order("101.00", "1"), order("101.00", "0.5")],
+            bids: vec![order("100.00", "2")],
+            ..Default::default()
+        };
+        assert_eq!(book.apply_rest_snapshot(&orders), BookUpdate::Resnapshotted);
+        assert_eq!(book.best_ask(), Some((10_100, 15_000)));
+        assert_eq!(book.last_offset(), None);
+        assert_eq!(book.apply_delta(&[], &[], seq(500)), BookUpdate::Delta);
+    }
+
+    #[test]
+    fn test_rest_snapshot_resumes_after_latest_delta() {
+        let mut book = book();
+        assert!(book.apply_delta(&[], &[], seq(13)).needs_resnapshot());
+        assert_eq!(
+            book.apply_delta(&[], &[], seq(14)),
+            BookUpdate::AwaitingSnapshot
+        );
+
+        let orders = models::OrderBookOrders {
+            asks: vec![models::SimpleOrder {
+                price: "101.00".to_string(),
+                remaining_base_amount: "1".to_string(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        book.apply_rest_snapshot(&orders);
+        assert_eq!(book.last_offset(), Some(14));
+        assert_eq!(book.apply_delta(&[], &[], seq(12)), BookUpdate::Skipped);
+        assert_eq!(
+            book.apply_delta(&[level("101.00", "0")], &[], seq(14)),
+            BookUpdate::Skipped
+        );
+        assert_eq!(book.best_ask(), Some((10_100, 10_000)));
+        assert_eq!(book.apply_delta(&[], &[], seq(15)), BookUpdate::Delta);
+    }
+}
@@ -0,0 +1,783 @@
+//! Order lifecycle tracking.
+//!
+//! Orders registered at sign time are keyed by `client_order_index`; orders
+//! adopted from the account channels are keyed by their exchange
+//! `order_index`, since other sessions and the UI reuse client indices.
+//!
+//! [`OrderManager`] registers orders when they are signed and folds the
+//! account channels (`account_all_orders`, `account_all_trades`,
+//! `account_tx`) into one [`TrackedOrder`] per order. Each order moves forward
+//! through [`OrderState`] only; late or duplicated events never regress it.
+//! Callers can `await` a terminal state through [`OrderHandle`].
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::sync::watch;
+
+use crate::{
+    account_events::{TypedAccountEvent, FEE_RATE_SCALE, TX_STATUS_SUCCESS},
+    batch_outcome::TxHeader,
+    models::{self, order::Status},
+    types::{AccountId, MarketId},
+    ws_client::TradeSide,
+};
+
+/// Fills held for orders whose `order_index` has not been linked yet.
+const MAX_UNLINKED_FILLS: usize = 4_096;
+
+/// Lifecycle state of a tracked order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderState {
+    /// Signed and/or submitted; not yet seen on the book.
+    Pending,
+    Open,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    /// The transaction never made it onto the book.
+    Rejected,
+    Expired,
+}
+
+impl OrderState {
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            Self::Filled | Self::Cancelled | Self::Rejected | Self::Expired
+        )
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Self::Pending => 0,
+            Self::Open => 1,
+            Self::PartiallyFilled => 2,
+            Self::Filled | Self::Cancelled | Self::Rejected | Self::Expired => 3,
+        }
+    }
+}
+
+/// Order details known at sign time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderRegistration {
+    pub client_order_index: i64,
+    pub market: MarketId,
+    pub side: TradeSide,
+    /// Base size in display units.
+    pub size: f64,
+    pub price: Option<f64>,
+    /// API key the create transaction was signed with.
+    pub api_key_index: Option<i32>,
+    /// Nonce the create transaction was signed with. Together with
+    /// `api_key_index` it matches `account_tx` before the hash is known.
+    pub nonce: Option<i64>,
+}
+
+impl OrderRegistration {
+    pub fn new(client_order_index: i64, market: MarketId, side: TradeSide, size: f64) -> Self {
+        Self {
+            client_order_index,
+            market,
+            side,
+            size,
+            price: None,
+            api_key_index: None,
+            nonce: None,
+        }
+    }
+
+    pub fn price(mut self, price: f64) -> Self {
+        self.price = Some(price);
+        self
+    }
+
+    /// Nonces are per API key, so both are needed to identify the transaction.
+    pub fn nonce(mut self, api_key_index: i32, nonce: i64) -> Self {
+        self.api_key_index = Some(api_key_index);
+        self.nonce = Some(nonce);
+        self
+    }
+}
+
+/// Aggregated view of one order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrackedOrder {
+    pub client_order_index: i64,
+    pub market: MarketId,
+    pub side: TradeSide,
+    /// Exchange order index, once an order update has linked it.
+    pub order_index: Option<i64>,
+    pub state: OrderState,
+    pub size: f64,
+    pub price: Option<f64>,
+    pub api_key_index: Option<i32>,
+    pub nonce: Option<i64>,
+    pub tx_hash: Option<String>,
+    /// Cumulative filled base size.
+    pub filled_size: f64,
+    /// Cumulative filled quote notional.
+    pub filled_quote: f64,
+    /// Fees paid across fills, in quote units.
+    pub fees: f64,
+    /// Raw exchange status or rejection message behind a terminal state.
+    pub reason: Option<String>,
+    /// Exchange timestamp of the latest event applied.
+    pub updated_at: Option<i64>,
+}
+
+impl TrackedOrder {
+    fn new(registration: OrderRegistration) -> Self {
+        Self {
+            client_order_index: registration.client_order_index,
+            market: registration.market,
+            side: registration.side,
+            order_index: None,
+            state: OrderState::Pending,
+            size: registration.size,
+            price: registration.price,
+            api_key_index: registration.api_key_index,
+            nonce: registration.nonce,
+            tx_hash: None,
+            filled_size: 0.0,
+            filled_quote: 0.0,
+            fees: 0.0,
+            reason: None,
+            updated_at: None,
+        }
+    }
+
+    fn from_order(order: &models::Order) -> Self {
+        let side = if order.is_ask {
+            TradeSide::Sell
+        } else {
+            TradeSide::Buy
+        };
+        let mut tracked = Self::new(OrderRegistration {
+            client_order_index: order.client_order_index,
+            market: MarketId::new(order.market_index),
+            side,
+            size: parse_amount(&order.initial_base_amount),
+            price: order.price.parse().ok(),
+            api_key_index: None,
+            nonce: None,
+        });
+        tracked.order_index = Some(order.order_index);
+        tracked
+    }
+
+    /// Volume-weighted average fill price.
+    pub fn average_fill_price(&self) -> Option<f64> {
+        (self.filled_size > 0.0).then(|| self.filled_quote / self.filled_size)
+    }
+
+    pub fn remaining_size(&self) -> f64 {
+        (self.size - self.filled_size).max(0.0)
+    }
+
+    pub fn is_terminal(&self) -> bool {
+        self.state.is_terminal()
+    }
+
+    fn advance(&mut self, next: OrderState) -> bool {
+        if next.rank() > self.state.rank() {
+            self.state = next;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn fill_state(&self) -> OrderState {
+        if self.size > 0.0 && self.filled_size >= self.size {
+            OrderState::Filled
+        } else if self.filled_size > 0.0 {
+            OrderState::PartiallyFilled
+        } else {
+            OrderState::Open
+        }
+    }
+}
+
+/// Watch handle for a single order.
+#[derive(Debug, Clone)]
+pub struct OrderHandle {
+    rx: watch::Receiver<TrackedOrder>,
+}
+
+impl OrderHandle {
+    pub fn current(&self) -> TrackedOrder {
+        self.rx.borrow().clone()
+    }
+
+    /// Wait for the next change. Returns `None` once the manager is dropped.
+    pub async fn changed(&mut self) -> Option<TrackedOrder> {
+        self.rx.changed().await.ok()?;
+        Some(self.rx.borrow_and_update().clone())
+    }
+
+    /// Wait until the order reaches a terminal state. Returns the last known
+    /// view if the manager is dropped first.
+    pub async fn terminal(&mut self) -> TrackedOrder {
+        if let Ok(order) = self.rx.wait_for(TrackedOrder::is_terminal).await {
+            return order.clone();
+        }
+        self.current()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct FillTotals {
+    size: f64,
+    quote: f64,
+}
+
+struct Entry {
+    tx: watch::Sender<TrackedOrder>,
+    seen_trades: HashSet<i64>,
+    /// Sum of individual fills seen on the trade channels.
+    traded: FillTotals,
+    /// Latest cumulative totals reported by order updates.
+    reported: FillTotals,
+}
+
+impl Entry {
+    fn new(order: TrackedOrder) -> Self {
+        let (tx, _) = watch::channel(order);
+        Self {
+            tx,
+            seen_trades: HashSet::new(),
+            traded: FillTotals::default(),
+            reported: FillTotals::default(),
+        }
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut TrackedOrder) -> bool) {
+        self.tx.send_if_modified(apply);
+    }
+
+    /// Trades and order updates describe the same fills and either may lag,
+    /// so the aggregate follows whichever has seen more.
+    fn best_fill(&self) -> FillTotals {
+        if self.traded.size >= self.reported.size {
+            self.traded
+        } else {
+            self.reported
+        }
+    }
+
+    fn apply_order(&mut self, order: &models::Order) {
+        let filled = FillTotals {
+            size: parse_amount(&order.filled_base_amount),
+            quote: parse_amount(&order.filled_quote_amount),
+        };
+        if filled.size > self.reported.size {
+            self.reported = filled;
+        }
+        let best = self.best_fill();
+
+        self.update(|tracked| {
+            let before = tracked.clone();
+            tracked.order_index = Some(order.order_index);
+            tracked.updated_at = Some(order.timestamp);
+            let initial = parse_amount(&order.initial_base_amount);
+            if initial > 0.0 {
+                tracked.size = initial;
+            }
+            tracked.filled_size = best.size;
+            tracked.filled_quote = best.quote;
+
+            let next = match order.status {
+                Status::InProgress | Status::Pending => OrderState::Pending,
+                Status::Open => tracked.fill_state(),
+                Status::Filled => OrderState::Filled,
+                Status::CanceledExpired => OrderState::Expired,
+                _ => OrderState::Cancelled,
+            };
+            if tracked.advance(next) && next.is_terminal() {
+                tracked.reason = Some(status_label(order.status));
+            }
+            *tracked != before
+        });
+    }
+
+    fn apply_fill(&mut self, trade: &models::Trade) {
+        if !self.seen_trades.insert(trade.trade_id) {
+            return;
+        }
+        self.traded.size += parse_amount(&trade.size);
+        self.traded.quote += parse_amount(&trade.usd_amount);
+        let best = self.best_fill();
+
+        let is_ask = self.tx.borrow().side == TradeSide::Sell;
+        let fee_rate = if trade.is_maker_ask == is_ask {
+            trade.maker_fee
+        } else {
+            trade.taker_fee
+        };
+        let fee = parse_amount(&trade.usd_amount) * f64::from(fee_rate) / FEE_RATE_SCALE;
+
+        self.update(|tracked| {
+            tracked.fees += fee;
+            tracked.updated_at = Some(trade.timestamp);
+            tracked.filled_size = best.size;
+            tracked.filled_quote = best.quote;
+            let next = tracked.fill_state();
+            tracked.advance(next);
+            true
+        });
+    }
+}
+
+/// Where an order lives in [`Inner::orders`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum OrderKey {
+    /// Registered by this process.
+    Client(i64),
+    /// Adopted from an order update.
+    Exchange(i64),
+}
+
+#[derive(Default)]
+struct Inner {
+    orders: HashMap<OrderKey, Entry>,
+    by_order_index: HashMap<i64, OrderKey>,
+    /// `(api_key_index, nonce)` of each registered create transaction.
+    by_nonce: HashMap<(i32, i64), OrderKey>,
+    by_tx_hash: HashMap<String, OrderKey>,
+    unlinked_fills: HashMap<i64, Vec<models::Trade>>,
+    unlinked_count: usize,
+}
+
+/// Shared order lifecycle tracker. Cheap to clone.
+#[derive(Clone)]
+pub struct OrderManager {
+    account: AccountId,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl std::fmt::Debug for OrderManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OrderManager")
+            .field("account", &self.account)
+            .field("orders", &self.lock().orders.len())
+            .finish()
+    }
+}
+
+impl OrderManager {
+    pub fn new(account: AccountId) -> Self {
+        Self {
+            account,
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// Start tracking an order. Re-registering an index replaces the old entry.
+    pub fn register(&self, registration: OrderRegistration) -> OrderHandle {
+        let mut inner = self.lock();
+        let key = OrderKey::Client(registration.client_order_index);
+        if let (Some(api_key_index), Some(nonce)) = (registration.api_key_index, registration.nonce)
+        {
+            inner.by_nonce.insert((api_key_index, nonce), key);
+        }
+        let entry = Entry::new(TrackedOrder::new(registration));
+        let handle = OrderHandle {
+            rx: entry.tx.subscribe(),
+        };
+        inner.orders.insert(key, entry);
+        handle
+    }
+
+    /// Record the hash returned by `sendTx` so `account_tx` can be matched.
+    pub fn mark_submitted(&self, client_order_index: i64, tx_hash: impl Into<String>) {
+        let tx_hash = tx_hash.into();
+        let mut inner = self.lock();
+        let key = OrderKey::Client(client_order_index);
+        let Some(entry) = inner.orders.get(&key) else {
+            return;
+        };
+        entry.update(|order| {
+            order.tx_hash = Some(tx_hash.clone());
+            true
+        });
+        inner.by_tx_hash.insert(tx_hash, key);
+    }
+
+    /// Mark an order rejected, e.g. after a non-200 `sendTx` response.
+    pub fn mark_rejected(&self, client_order_index: i64, reason: impl Into<String>) {
+        let reason = reason.into();
+        let key = OrderKey::Client(client_order_index);
+        if let Some(entry) = self.lock().orders.get(&key) {
+            entry.update(|order| reject(order, reason));
+        }
+    }
+
+    /// Order registered by this process under `client_order_index`.
+    pub fn get(&self, client_order_index: i64) -> Option<TrackedOrder> {
+        self.lock()
+            .orders
+            .get(&OrderKey::Client(client_order_index))
+            .map(|entry| entry.tx.borrow().clone())
+    }
+
+    /// Any tracked order, registered or adopted, by exchange order index.
+    pub fn get_by_order_index(&self, order_index: i64) -> Option<TrackedOrder> {
+        let inner = self.lock();
+        inner
+            .by_order_index
+            .get(&order_index)
+            .and_then(|key| inner.orders.get(key))
+            .map(|entry| entry.tx.borrow().clone())
+    }
+
+    pub fn watch(&self, client_order_index: i64) -> Option<OrderHandle> {
+        self.lock()
+            .orders
+            .get(&OrderKey::Client(client_order_index))
+            .map(|entry| OrderHandle {
+                rx: entry.tx.subscribe(),
+            })
+    }
+
+    /// Orders that have not reached a terminal state.
+    pub fn active(&self) -> Vec<TrackedOrder> {
+        self.lock()
+            .orders
+            .values()
+            .map(|entry| entry.tx.borrow().clone())
+            .filter(|order| !order.is_terminal())
+            .collect()
+    }
+
+    /// Drop terminal orders and return how many were removed. Existing handles
+    /// keep their final value.
+    pub fn prune_terminal(&self) -> usize {
+        let mut inner = self.lock();
+        let done: HashSet<OrderKey> = inner
+            .orders
+            .iter()
+            .filter(|(_, entry)| entry.tx.borrow().is_terminal())
+            .map(|(key, _)| *key)
+            .collect();
+        for key in &done {
+            inner.orders.remove(key);
+        }
+        inner.by_order_index.retain(|_, key| !done.contains(key));
+        inner.by_nonce.retain(|_, key| !done.contains(key));
+        inner.by_tx_hash.retain(|_, key| !done.contains(key));
+        done.len()
+    }
+
+    /// Apply every order, trade and transaction carried by a decoded account event.
+    pub fn apply(&self, event: &TypedAccountEvent) {
+        match event {
+            TypedAccountEvent::AllOrders(update) | TypedAccountEvent::MarketOrders(update) => {
+                update.iter().for_each(|order| self.on_order(order));
+            }
+            TypedAccountEvent::AllTrades(update) | TypedAccountEvent::MarketTrades(update) => {
+                update.iter().for_each(|trade| self.on_trade(trade));
+            }
+            TypedAccountEvent::AccountAll(update) => {
+                update
+                    .trades
+                    .values()
+                    .flatten()
+                    .for_each(|trade| self.on_trade(trade));
+            }
+            TypedAccountEvent::AccountMarket(update) => {
+                update.orders.iter().for_each(|order| self.on_order(order));
+                update.trades.iter().for_each(|trade| self.on_trade(trade));
+            }
+            TypedAccountEvent::AccountTx(update) => {
+                update.txs.iter().for_each(|tx| self.on_tx(tx));
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply an order snapshot from `account_all_orders` or REST.
+    ///
+    /// The first update for an order links it to the registration with the
+    /// same client index and market, if that registration is still unlinked.
+    /// Anything else (another process, the UI) is adopted under its
+    /// `order_index`.
+    pub fn on_order(&self, order: &models::Order) {
+        if order.owner_account_index != self.account.into_inner() {
+            return;
+        }
+        let mut inner = self.lock();
+        let key = match inner.by_order_index.get(&order.order_index) {
+            Some(key) => *key,
+            None => {
+                let client = OrderKey::Client(order.client_order_index);
+                let market = MarketId::new(order.market_index);
+                let registered = inner.orders.get(&client).is_some_and(|entry| {
+                    let tracked = entry.tx.borrow();
+                    tracked.order_index.is_none() && tracked.market == market
+                });
+                if registered {
+                    client
+                } else {
+                    OrderKey::Exchange(order.order_index)
+                }
+            }
+        };
+        inner
+            .orders
+            .entry(key)
+            .or_insert_with(|| Entry::new(TrackedOrder::from_order(order)));
+        inner.by_order_index.insert(order.order_index, key);
+
+        let fills = inner.unlinked_fills.remove(&order.order_index);
+        if let Some(fills) = &fills {
+            inner.unlinked_count -= fills.len();
+        }
+        let entry = inner.orders.get_mut(&key).expect("entry inserted above");
+        entry.apply_order(order);
+        for trade in fills.iter().flatten() {
+            entry.apply_fill(trade);
+        }
+    }
+
+    /// Apply a fill. Fills for orders not yet linked to an `order_index` are
+    /// held until the matching order update arrives.
+    pub fn on_trade(&self, trade: &models::Trade) {
+        let account = self.account.into_inner();
+        let mut inner = self.lock();
+        for (order_index, party) in [
+            (trade.ask_id, trade.ask_account_id),
+            (trade.bid_id, trade.bid_account_id),
+        ] {
+            if party != account {
+                continue;
+            }
+            match inner.by_order_index.get(&order_index).copied() {
+                Some(key) => {
+                    if let Some(entry) = inner.orders.get_mut(&key) {
+                        entry.apply_fill(trade);
+                    }
+                }
+                None if inner.unlinked_count < MAX_UNLINKED_FILLS => {
+                    inner
+                        .unlinked_fills
+                        .entry(order_index)
+                        .or_default()
+                        .push(trade.clone());
+                    inner.unlinked_count += 1;
+                }
+                None => {
+                    tracing::warn!(order_index, "dropping fill for unlinked order");
+                }
+            }
+        }
+    }
+
+    /// Apply an executed transaction from `account_tx`. Failed create
+    /// transactions reject the matching pending order.
+    pub fn on_tx(&self, tx: &models::Tx) {
+        let inner = self.lock();
+        let key = inner.by_tx_hash.get(&tx.hash).copied().or_else(|| {
+            let api_key_index = TxHeader::parse(&tx.info).api_key_index?;
+            inner.by_nonce.get(&(api_key_index, tx.nonce)).copied()
+        });
+        let Some(entry) = key.and_then(|key| inner.orders.get(&key)) else {
+            return;
+        };
+        entry.update(|order| {
+            let mut changed = order.tx_hash.is_none();
+            order.tx_hash.get_or_insert_with(|| tx.hash.clone());
+            if tx.status != TX_STATUS_SUCCESS && order.state == OrderState::Pending {
+                changed |= reject(order, format!("transaction status {}", tx.status));
+            }
+            changed
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+fn reject(order: &mut TrackedOrder, reason: String) -> bool {
+    if order.advance(OrderState::Rejected) {
+        order.reason = Some(reason);
+        true
+    } else {
+        false
+    }
+}
+
+fn status_label(status: Status) -> String {
+    serde_json::to_value(status)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{status:?}"))
+}
+
+fn parse_amount(value: &str) -> f64 {
+    value.parse().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: i64 = 7;
+
+    fn manager() -> OrderManager {
+        OrderManager::new(AccountId::new(ACCOUNT))
+    }
+
+    fn order(status: Status, filled: &str, filled_quote: &str) -> models::Order {
+        models::Order {
+            order_index: 900,
+            client_order_index: 42,
+            market_index: 1,
+            owner_account_index: ACCOUNT,
+            initial_base_amount: "2.0".into(),
+            price: "100.0".into(),
+            filled_base_amount: filled.into(),
+            filled_quote_amount: filled_quote.into(),
+            status,
+            ..Default::default()
+        }
+    }
+
+    fn fill(trade_id: i64, size: &str, usd: &str) -> models::Trade {
+        models::Trade {
+            trade_id,
+            size: size.into(),
+            usd_amount: usd.into(),
+            bid_id: 900,
+            bid_account_id: ACCOUNT,
+            ask_id: 5,
+            ask_account_id: 99,
+            is_maker_ask: true,
+            taker_fee: 200,
+            maker_fee: 0,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn order_moves_through_fills_to_terminal() {
+        let manager = manager();
+        let mut handle = manager.register(OrderRegistration::new(
+            42,
+            MarketId::new(1),
+            TradeSide::Buy,
+            2.0,
+        ));
+        assert_eq!(handle.current().state, OrderState::Pending);
+
+        manager.on_order(&order(Status::Open, "0", "0"));
+        assert_eq!(handle.current().state, OrderState::Open);
+
+        manager.on_trade(&fill(1, "0.5", "50.0"));
+        manager.on_trade(&fill(1, "0.5", "50.0"));
+        let partial = handle.current();
+        assert_eq!(partial.state, OrderState::PartiallyFilled);
+        assert_eq!(partial.filled_size, 0.5);
+        assert!((partial.fees - 0.01).abs() < 1e-12);
+
+        // The order update catches up with the trade and then overtakes it.
+        manager.on_order(&order(Status::Open, "0.5", "50.0"));
+        manager.on_order(&order(Status::Filled, "2.0", "202.0"));
+        manager.on_trade(&fill(2, "1.5", "152.0"));
+
+        let done = handle.terminal().await;
+        assert_eq!(done.state, OrderState::Filled);
+        assert_eq!(done.filled_size, 2.0);
+        assert_eq!(done.average_fill_price(), Some(101.0));
+        assert_eq!(done.reason.as_deref(), Some("filled"));
+
+        manager.on_order(&order(Status::Canceled, "2.0", "202.0"));
+        assert_eq!(manager.get(42).unwrap().state, OrderState::Filled);
+        assert_eq!(manager.prune_terminal(), 1);
+        assert!(manager.get(42).is_none());
+    }
+
+    #[test]
+    fn fills_before_link_are_replayed() {
+        let manager = manager();
+        manager.register(OrderRegistration::new(
+            42,
+            MarketId::new(1),
+            TradeSide::Buy,
+            2.0,
+        ));
+        manager.on_trade(&fill(1, "0.5", "50.0"));
+        assert_eq!(manager.get(42).unwrap().filled_size, 0.0);
+
+        manager.on_order(&order(Status::Open, "0", "0"));
+        let tracked = manager.get(42).unwrap();
+        assert_eq!(tracked.state, OrderState::PartiallyFilled);
+        assert_eq!(tracked.filled_size, 0.5);
+    }
+
+    #[test]
+    fn failed_tx_rejects_pending_order_and_expiry_maps() {
+        let manager = manager();
+        manager.register(
+            OrderRegistration::new(42, MarketId::new(1), TradeSide::Buy, 2.0).nonce(2, 17),
+        );
+        // Same nonce on another API key belongs to a different transaction.
+        manager.on_tx(&models::Tx {
+            hash: "0xother".into(),
+            info: r#"{"ApiKeyIndex":3,"Nonce":17}"#.into(),
+            nonce: 17,
+            status: 3,
+            ..Default::default()
+        });
+        assert_eq!(manager.get(42).unwrap().state, OrderState::Pending);
+
+        manager.on_tx(&models::Tx {
+            hash: "0xabc".into(),
+            info: r#"{"ApiKeyIndex":2,"Nonce":17}"#.into(),
+            nonce: 17,
+            status: 3,
+            ..Default::default()
+        });
+        let rejected = manager.get(42).unwrap();
+        assert_eq!(rejected.state, OrderState::Rejected);
+        assert_eq!(rejected.tx_hash.as_deref(), Some("0xabc"));
+
+        manager.on_order(&models::Order {
+            client_order_index: 43,
+            ..order(Status::CanceledExpired, "0", "0")
+        });
+        assert!(manager.get(43).is_none());
+        let adopted = manager.get_by_order_index(900).unwrap();
+        assert_eq!(adopted.state, OrderState::Expired);
+        assert_eq!(adopted.reason.as_deref(), Some("canceled-expired"));
+    }
+
+    #[test]
+    fn external_order_reusing_a_client_index_is_tracked_separately() {
+        let manager = manager();
+        manager.register(OrderRegistration::new(
+            42,
+            MarketId::new(1),
+            TradeSide::Buy,
+            2.0,
+        ));
+        manager.on_order(&order(Status::Open, "0", "0"));
+
+        // Another session places an order with the same client index.
+        manager.on_order(&models::Order {
+            order_index: 901,
+            ..order(Status::Filled, "2.0", "200.0")
+        });
+        let ours = manager.get(42).unwrap();
+        assert_eq!(ours.order_index, Some(900));
+        assert_eq!(ours.state, OrderState::Open);
+        let theirs = manager.get_by_order_index(901).unwrap();
+        assert_eq!(theirs.state, OrderState::Filled);
+        assert_eq!(manager.active().len(), 1);
+    }
+}
@@ -0,0 +1,700 @@
+//! Per-market positions with entry price and PnL tracking.
+//!
+//! [`PositionBook`] folds position snapshots (`account_all`,
+//! `account_market`, `account_all_positions`) and fills (`account_all_trades`
+//! and the trades carried by `account_all`) into one [`TrackedPosition`] per
+//! market. Snapshots own size, entry price and funding. Fills are the only
+//! source of realized PnL and fees, so the book keeps a market after its
+//! position is closed. Positions and trades arrive on separate channels, in
+//! either order, so a fill never moves size or entry: its PnL is priced
+//! against the position the exchange reports it was made on
+//! (`*_position_size_before` and `*_entry_quote_before`), and only trades
+//! without those fields fall back to the last snapshot carried forward by
+//! later fills. Every fill counts once by `trade_id`, including the history
+//! in the initial trades snapshot. `market_stats` mark prices drive
+//! unrealized PnL.
+//!
+//! [`PositionBook::reconcile`] compares the book against REST
+//! `AccountHandle::details`, reports every [`PositionDrift`] and adopts the
+//! exchange's figures; [`PositionBook::spawn_reconciler`] does so on a timer
+//! and publishes the drift through [`PositionBook::subscribe_drift`].
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt,
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::{
+    account_events::{TypedAccountEvent, FEE_RATE_SCALE},
+    lighter_client::{LighterClient, Result},
+    models,
+    types::{AccountId, MarketId},
+    ws_client::MarketStats,
+};
+
+/// Sizes closer than this are considered equal.
+const SIZE_EPSILON: f64 = 1e-9;
+/// Relative entry price difference reported as drift.
+const ENTRY_TOLERANCE: f64 = 1e-6;
+/// Trade ids remembered for de-duplication.
+const SEEN_TRADES: usize = 10_000;
+
+/// Direction of a position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PositionDirection {
+    Long,
+    Short,
+    Flat,
+}
+
+impl PositionDirection {
+    /// From the `sign` field of an `AccountPosition`.
+    pub fn from_sign(sign: i32) -> Self {
+        match sign.signum() {
+            1 => Self::Long,
+            -1 => Self::Short,
+            _ => Self::Flat,
+        }
+    }
+
+    fn from_size(size: f64) -> Self {
+        if size > SIZE_EPSILON {
+            Self::Long
+        } else if size < -SIZE_EPSILON {
+            Self::Short
+        } else {
+            Self::Flat
+        }
+    }
+}
+
+impl fmt::Display for PositionDirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Long => "LONG",
+            Self::Short => "SHORT",
+            Self::Flat => "FLAT",
+        })
+    }
+}
+
+/// One market's position as tracked by the [`PositionBook`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct TrackedPosition {
+    pub market: MarketId,
+    pub symbol: String,
+    /// Signed base size: positive long, negative short.
+    pub size: f64,
+    /// Average entry price; 0 when flat.
+    pub entry_price: f64,
+    /// Realized PnL of tracked fills, net of fees.
+    pub realized_pnl: f64,
+    /// Fees paid on tracked fills.
+    pub fees: f64,
+    /// Funding paid out over the position's life, as reported by the
+    /// exchange (`total_funding_paid_out`).
+    pub funding_paid: f64,
+    /// Latest mark price, or the one implied by the last snapshot.
+    pub mark_price: Option<f64>,
+    /// Timestamp of the last fill applied, in milliseconds.
+    pub updated_at: Option<i64>,
+}
+
+impl TrackedPosition {
+    fn new(market: MarketId) -> Self {
+        Self {
+            market,
+            ..Self::default()
+        }
+    }
+
+    pub fn direction(&self) -> PositionDirection {
+        PositionDirection::from_size(self.size)
+    }
+
+    pub fn is_flat(&self) -> bool {
+        self.direction() == PositionDirection::Flat
+    }
+
+    pub fn notional(&self) -> Option<f64> {
+        self.mark_price.map(|mark| self.size.abs() * mark)
+    }
+
+    pub fn unrealized_pnl(&self) -> Option<f64> {
+        self.mark_price
+            .map(|mark| self.size * (mark - self.entry_price))
+    }
+
+    /// Realized plus unrealized PnL, less funding paid. Unrealized PnL counts
+    /// as zero without a mark price.
+    pub fn total_pnl(&self) -> f64 {
+        self.realized_pnl + self.unrealized_pnl().unwrap_or(0.0) - self.funding_paid
+    }
+}
+
+/// Size and entry a fill is priced against: the exchange's pre-fill position
+/// when the trade reports it, else the last snapshot carried forward by the
+/// fills after it. The tracked position keeps the snapshot's.
+#[derive(Debug, Clone, Copy, Default)]
+struct Working {
+    size: f64,
+    entry_price: f64,
+}
+
+impl Working {
+    /// Pre-fill position reported on a trade: a signed size and its entry
+    /// quote (size times entry). `None` if either is missing.
+    fn reported(size_before: &str, entry_quote_before: &str) -> Option<Self> {
+        let size: f64 = size_before.trim().parse().ok()?;
+        let quote: f64 = entry_quote_before.trim().parse().ok()?;
+        let entry_price = if size.abs() < SIZE_EPSILON {
+            0.0
+        } else {
+            quote.abs() / size.abs()
+        };
+        Some(Self { size, entry_price })
+    }
+
+    /// Trade `delta` (signed) base at `price` and return the PnL realized.
+    fn fill(&mut self, delta: f64, price: f64) -> f64 {
+        let size = self.size + delta;
+        let mut realized = 0.0;
+        if PositionDirection::from_size(self.size) == PositionDirection::Flat
+            || self.size.signum() == delta.signum()
+        {
+            self.entry_price =
+                (self.size.abs() * self.entry_price + delta.abs() * price) / size.abs();
+        } else {
+            let closed = delta.abs().min(self.size.abs());
+            realized = closed * (price - self.entry_price) * self.size.signum();
+            if PositionDirection::from_size(size) == PositionDirection::Flat {
+                self.entry_price = 0.0;
+            } else if size.signum() != self.size.signum() {
+                self.entry_price = price;
+            }
+        }
+        self.size = if size.abs() < SIZE_EPSILON { 0.0 } else { size };
+        realized
+    }
+}
+
+/// A market whose book position disagreed with the exchange.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PositionDrift {
+    pub market: MarketId,
+    pub book_size: f64,
+    pub exchange_size: f64,
+    pub book_entry: f64,
+    pub exchange_entry: f64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    positions: BTreeMap<MarketId, TrackedPosition>,
+    working: BTreeMap<MarketId, Working>,
+    /// The latest [`SEEN_TRADES`] trade ids applied.
+    seen_trades: BTreeSet<i64>,
+    /// Highest id pruned from `seen_trades`. Trade ids grow over time, so
+    /// anything at or below it was seen, or is too old to matter.
+    pruned_below: Option<i64>,
+}
+
+impl Inner {
+    fn entry(&mut self, market: MarketId) -> &mut TrackedPosition {
+        self.positions
+            .entry(market)
+            .or_insert_with(|| TrackedPosition::new(market))
+    }
+
+    /// Record `trade_id`; false if it was applied before.
+    fn first_sight(&mut self, trade_id: i64) -> bool {
+        if self.pruned_below.is_some_and(|floor| trade_id <= floor)
+            || !self.seen_trades.insert(trade_id)
+        {
+            return false;
+        }
+        if self.seen_trades.len() > SEEN_TRADES {
+            self.pruned_below = self.seen_trades.pop_first();
+        }
+        true
+    }
+}
+
+/// Shared position tracker for one account. Cheap to clone.
+#[derive(Debug, Clone)]
+pub struct PositionBook {
+    account: AccountId,
+    inner: Arc<Mutex<Inner>>,
+    drift: Arc<watch::Sender<Vec<PositionDrift>>>,
+}
+
+impl PositionBook {
+    pub fn new(account: AccountId) -> Self {
+        let (drift, _) = watch::channel(Vec::new());
+        Self {
+            account,
+            inner: Arc::new(Mutex::new(Inner::default())),
+            drift: Arc::new(drift),
+        }
+    }
+
+    /// Apply the positions and fills carried by a decoded account event.
+    /// Fills are applied before the snapshot that accompanies them, which
+    /// already reflects them.
+    pub fn apply(&self, event: &TypedAccountEvent) {
+        match event {
+            TypedAccountEvent::AccountAll(update) => {
+                update
+                    .trades
+                    .values()
+                    .flatten()
+                    .for_each(|trade| self.on_trade(trade));
+                update
+                    .positions
+                    .values()
+                    .for_each(|position| self.on_position(position));
+            }
+            TypedAccountEvent::AccountMarket(update) => {
+                update.trades.iter().for_each(|trade| self.on_trade(trade));
+                if let Some(position) = &update.position {
+                    self.on_position(position);
+                }
+            }
+            TypedAccountEvent::AllPositions(update)
+            | TypedAccountEvent::MarketPositions(update) => {
+                update
+                    .positions
+                    .values()
+                    .for_each(|position| self.on_position(position));
+            }
+            TypedAccountEvent::AllTrades(update) | TypedAccountEvent::MarketTrades(update) => {
+                update.iter().for_each(|trade| self.on_trade(trade));
+            }
+            _ => {}
+        }
+    }
+
+    /// Apply a position snapshot from the account channels or REST.
+    pub fn on_position(&self, position: &models::AccountPosition) {
+        let market = MarketId::new(position.market_id);
+        let size = parse(&position.position).abs() * f64::from(position.sign.signum());
+        let mut inner = self.lock();
+        let tracked = inner.entry(market);
+        if !position.symbol.is_empty() {
+            tracked.symbol = position.symbol.clone();
+        }
+        tracked.size = size;
+        tracked.entry_price = if size == 0.0 {
+            0.0
+        } else {
+            parse(&position.avg_entry_price)
+        };
+        if let Some(funding) = &position.total_funding_paid_out {
+            tracked.funding_paid = parse(funding);
+        }
+        if tracked.mark_price.is_none() && size != 0.0 {
+            let value = parse(&position.position_value);
+            tracked.mark_price = (value > 0.0).then(|| value / size.abs());
+        }
+        let working = Working {
+            size,
+            entry_price: tracked.entry_price,
+        };
+        inner.working.insert(market, working);
+    }
+
+    /// Apply a fill's realized PnL and fees; size and entry are left to the
+    /// snapshots. Each `trade_id` is applied once and trades of other
+    /// accounts are ignored.
+    pub fn on_trade(&self, trade: &models::Trade) {
+        let account = self.account.into_inner();
+        let is_ask = trade.ask_account_id == account;
+        let is_bid = trade.bid_account_id == account;
+        if !(is_ask || is_bid) {
+            return;
+        }
+        let mut inner = self.lock();
+        if !inner.first_sight(trade.trade_id) {
+            return;
+        }
+        let market = MarketId::new(trade.market_id);
+        let size = parse(&trade.size);
+        let price = parse(&trade.price);
+        let notional = parse(&trade.usd_amount);
+        let mut working = inner.working.get(&market).copied().unwrap_or_default();
+        let tracked = inner.entry(market);
+        // A self-trade fills both sides.
+        for (ours, sign) in [(is_ask, -1.0), (is_bid, 1.0)] {
+            if !ours {
+                continue;
+            }
+            let maker = trade.is_maker_ask == (sign < 0.0);
+            let (rate, before) = if maker {
+                (
+                    trade.maker_fee,
+                    Working::reported(
+                        &trade.maker_position_size_before,
+                        &trade.maker_entry_quote_before,
+                    ),
+                )
+            } else {
+                (
+                    trade.taker_fee,
+                    Working::reported(
+                        &trade.taker_position_size_before,
+                        &trade.taker_entry_quote_before,
+                    ),
+                )
+            };
+            let fee = notional * f64::from(rate) / FEE_RATE_SCALE;
+            if let Some(before) = before {
+                working = before;
+            }
+            tracked.realized_pnl += working.fill(sign * size, price) - fee;
+            tracked.fees += fee;
+        }
+        tracked.updated_at = Some(trade.timestamp);
+        inner.working.insert(market, working);
+    }
+
+    pub fn on_market_stats(&self, stats: &MarketStats) {
+        let mark = parse(&stats.mark_price);
+        if mark > 0.0 {
+            self.set_mark_price(MarketId::new(stats.market_id as i32), mark);
+        }
+    }
+
+    pub fn set_mark_price(&self, market: MarketId, mark: f64) {
+        self.lock().entry(market).mark_price = Some(mark);
+    }
+
+    pub fn position(&self, market: MarketId) -> Option<TrackedPosition> {
+        self.lock().positions.get(&market).cloned()
+    }
+
+    /// Every market the book has seen, flat ones included.
+    pub fn positions(&self) -> Vec<TrackedPosition> {
+        self.lock().positions.values().cloned().collect()
+    }
+
+    /// Positions that are not flat.
+    pub fn open(&self) -> Vec<TrackedPosition> {
+        self.lock()
+            .positions
+            .values()
+            .filter(|position| !position.is_flat())
+            .cloned()
+            .collect()
+    }
+
+    /// Signed size in `market`; 0 when unknown.
+    pub fn size(&self, market: MarketId) -> f64 {
+        self.lock()
+            .positions
+            .get(&market)
+            .map_or(0.0, |position| position.size)
+    }
+
+    pub fn realized_pnl(&self) -> f64 {
+        self.lock()
+            .positions
+            .values()
+            .map(|position| position.realized_pnl)
+            .sum()
+    }
+
+    /// Unrealized PnL of the positions with a mark price.
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.lock()
+            .positions
+            .values()
+            .filter_map(TrackedPosition::unrealized_pnl)
+            .sum()
+    }
+
+    /// Compare the book with `account` as returned by REST, adopt the
+    /// exchange's sizes and entries, and return where they disagreed.
+    pub fn reconcile(&self, account: &models::DetailedAccount) -> Vec<PositionDrift> {
+        let before: BTreeMap<MarketId, (f64, f64)> = self
+            .lock()
+            .positions
+            .iter()
+            .map(|(market, position)| (*market, (position.size, position.entry_price)))
+            .collect();
+
+        let mut reported = HashSet::new();
+        for position in &account.positions {
+            reported.insert(MarketId::new(position.market_id));
+            self.on_position(position);
+        }
+        let mut inner = self.lock();
+        let Inner {
+            positions, working, ..
+        } = &mut *inner;
+        for (market, position) in positions.iter_mut() {
+            if !reported.contains(market) {
+                position.size = 0.0;
+                position.entry_price = 0.0;
+                working.remove(market);
+            }
+        }
+
+        let drift: Vec<PositionDrift> = inner
+            .positions
+            .values()
+            .filter_map(|position| {
+                let (book_size, book_entry) =
+                    before.get(&position.market).copied().unwrap_or_default();
+                let drift = PositionDrift {
+                    market: position.market,
+                    book_size,
+                    exchange_size: position.size,
+                    book_entry,
+                    exchange_entry: position.entry_price,
+                };
+                drift.is_material().then_some(drift)
+            })
+            .collect();
+        drop(inner);
+
+        for drift in &drift {
+            tracing::warn!(
+                market = %drift.market,
+                book_size = drift.book_size,
+                exchange_size = drift.exchange_size,
+                book_entry = drift.book_entry,
+                exchange_entry = drift.exchange_entry,
+                "position book drifted from exchange"
+            );
+        }
+        self.drift.send_replace(drift.clone());
+        drift
+    }
+
+    /// Fetch the account over REST and [`reconcile`](Self::reconcile).
+    pub async fn reconcile_with(&self, client: &LighterClient) -> Result<Vec<PositionDrift>> {
+        let details = client.account().details().await?;
+        let drift = details
+            .accounts
+            .first()
+            .map(|account| self.reconcile(account))
+            .unwrap_or_default();
+        Ok(drift)
+    }
+
+    /// Reconcile against REST every `every`, starting immediately. Stopped
+    /// when the returned task is dropped.
+    pub fn spawn_reconciler(&self, client: Arc<LighterClient>, every: Duration) -> ReconcileTask {
+        let book = self.clone();
+        let task = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                if let Err(err) = book.reconcile_with(&client).await {
+                    tracing::warn!(error = %err, "position reconciliation failed");
+                }
+            }
+        });
+        ReconcileTask { task }
+    }
+
+    /// Drift found by the last reconciliation.
+    pub fn drift(&self) -> Vec<PositionDrift> {
+        self.drift.borrow().clone()
+    }
+
+    /// Receive the drift found by every reconciliation.
+    pub fn subscribe_drift(&self) -> watch::Receiver<Vec<PositionDrift>> {
+        self.drift.subscribe()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("position book lock poisoned")
+    }
+}
+
+impl PositionDrift {
+    fn is_material(&self) -> bool {
+        let entry_scale = self.exchange_entry.abs().max(f64::MIN_POSITIVE);
+        (self.book_size - self.exchange_size).abs() > SIZE_EPSILON
+            || (self.exchange_size != 0.0
+                && (self.book_entry - self.exchange_entry).abs() / entry_scale > ENTRY_TOLERANCE)
+    }
+}
+
+/// Reconciliation loop started by [`PositionBook::spawn_reconciler`].
+/// Dropping it stops the loop.
+#[derive(Debug)]
+pub struct ReconcileTask {
+    task: JoinHandle<()>,
+}
+
+impl Drop for ReconcileTask {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn parse(value: &str) -> f64 {
+    value.trim().parse().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ACCOUNT: i64 = 7;
+    const MARKET: MarketId = MarketId::new(1);
+
+    fn book() -> PositionBook {
+        PositionBook::new(AccountId::new(ACCOUNT))
+    }
+
+    fn snapshot(sign: i32, size: &str, entry: &str) -> models::AccountPosition {
+        models::AccountPosition {
+            market_id: MARKET.into_inner(),
+            sign,
+            position: size.to_string(),
+            avg_entry_price: entry.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// A fill where the account is the taker.
+    fn fill(id: i64, is_ask: bool, size: &str, price: &str) -> models::Trade {
+        let notional = parse(size) * parse(price);
+        models::Trade {
+            trade_id: id,
+            market_id: MARKET.into_inner(),
+            size: size.to_string(),
+            price: price.to_string(),
+            usd_amount: notional.to_string(),
+            ask_account_id: if is_ask { ACCOUNT } else { 99 },
+            bid_account_id: if is_ask { 99 } else { ACCOUNT },
+            is_maker_ask: !is_ask,
+            taker_fee: 500,
+            maker_fee: 0,
+            timestamp: 1_700_000_000_000 + id,
+            ..Default::default()
+        }
+    }
+
+    /// `fill` made on a reported pre-fill position.
+    fn fill_on(
+        id: i64,
+        is_ask: bool,
+        size: &str,
+        price: &str,
+        size_before: &str,
+        entry_quote_before: &str,
+    ) -> models::Trade {
+        models::Trade {
+            taker_position_size_before: size_before.to_string(),
+            taker_entry_quote_before: entry_quote_before.to_string(),
+            ..fill(id, is_ask, size, price)
+        }
+    }
+
+    fn fee(size: f64, price: f64) -> f64 {
+        size * price * 500.0 / FEE_RATE_SCALE
+    }
+
+    #[test]
+    fn fills_realize_pnl_against_the_entry_before_them() {
+        let book = book();
+        book.on_position(&snapshot(1, "4", "105"));
+
+        // Sell 6 at 120: closes 4 for +60 and leaves a short of 2 at 120,
+        // which a buy of 2 at 110 closes for +20.
+        book.on_trade(&fill(1, true, "6", "120"));
+        book.on_trade(&fill(2, false, "2", "110"));
+        book.on_trade(&fill(2, false, "2", "110"));
+        book.set_mark_price(MARKET, 115.0);
+
+        let position = book.position(MARKET).unwrap();
+        let fees = fee(6.0, 120.0) + fee(2.0, 110.0);
+        assert!((position.fees - fees).abs() < 1e-9);
+        assert!((position.realized_pnl - (80.0 - fees)).abs() < 1e-9);
+        assert_eq!(position.updated_at, Some(1_700_000_000_002));
+        // Size and entry stay the snapshot's until the next one.
+        assert_eq!(position.direction(), PositionDirection::Long);
+        assert_eq!((position.size, position.entry_price), (4.0, 105.0));
+        assert_eq!(position.unrealized_pnl(), Some(40.0));
+
+        book.on_position(&snapshot(0, "0", "0"));
+        assert!(book.position(MARKET).unwrap().is_flat());
+        assert!((book.realized_pnl() - (80.0 - fees)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_fill_already_in_the_snapshot_moves_nothing_twice() {
+        let book = book();
+        // Long 1 at 90, then a buy of 1 at 100 takes it to 2 at 95.
+        book.on_position(&snapshot(1, "2", "95"));
+        book.on_trade(&fill_on(1, false, "1", "100", "1", "90"));
+        book.on_trade(&fill_on(1, false, "1", "100", "1", "90"));
+
+        let position = book.position(MARKET).unwrap();
+        assert_eq!((position.size, position.entry_price), (2.0, 95.0));
+        assert!((position.fees - fee(1.0, 100.0)).abs() < 1e-9);
+        assert!((position.realized_pnl + fee(1.0, 100.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_closing_fill_after_its_snapshot_still_realizes() {
+        let book = book();
+        book.on_position(&snapshot(-1, "2", "100"));
+        // The close of the short lands on the positions channel first.
+        book.on_position(&snapshot(0, "0", "0"));
+        book.on_trade(&fill_on(1, false, "2", "90", "-2", "200"));
+
+        let position = book.position(MARKET).unwrap();
+        assert!(position.is_flat());
+        let fees = fee(2.0, 90.0);
+        assert!((position.fees - fees).abs() < 1e-9);
+        assert!((position.realized_pnl - (20.0 - fees)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn seen_trade_ids_stay_bounded() {
+        let book = book();
+        let last = SEEN_TRADES as i64 + 10;
+        for id in 1..=last {
+            book.on_trade(&fill(id, false, "1", "100"));
+        }
+        // Replayed trades, recent or pruned, are not counted again.
+        book.on_trade(&fill(last, false, "1", "100"));
+        book.on_trade(&fill(1, false, "1", "100"));
+
+        let inner = book.lock();
+        assert_eq!(inner.seen_trades.len(), SEEN_TRADES);
+        let fees = inner.positions[&MARKET].fees;
+        assert!((fees - last as f64 * fee(1.0, 100.0)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn reconcile_reports_drift_and_adopts_exchange() {
+        let book = book();
+        book.on_position(&snapshot(1, "1", "100"));
+
+        let mut reported = snapshot(1, "1.5", "100");
+        reported.total_funding_paid_out = Some("0.25".to_string());
+        let account = models::DetailedAccount {
+            positions: vec![reported],
+            ..Default::default()
+        };
+        let drift = book.reconcile(&account);
+        assert_eq!(drift.len(), 1);
+        assert_eq!(drift[0].book_size, 1.0);
+        assert_eq!(drift[0].exchange_size, 1.5);
+        assert_eq!(book.size(MARKET), 1.5);
+        assert_eq!(book.position(MARKET).unwrap().funding_paid, 0.25);
+
+        assert!(book.reconcile(&account).is_empty());
+        assert_eq!(book.drift(), Vec::new());
+    }
+}
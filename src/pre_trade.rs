@@ -0,0 +1,842 @@
+//! Pre-trade checks run before an order is signed.
+//!
+//! A [`PreTradeChain`] converts every create and modify order to market units
+//! with the market's [`MarketSpec`] and runs it through each
+//! [`PreTradeCheck`] in order; the first violation stops the order before a
+//! nonce is reserved. [`SignerClient`](crate::signer_client::SignerClient)
+//! runs the chain for `create_order`, `sign_create_order`, `modify_order`,
+//! `sign_modify_order` and `sign_request`, so the order builder, brackets,
+//! batches and raw signer calls are all covered. Orders signed elsewhere are
+//! decoded and checked again on the way out ([`PreTradeChain::evaluate_tx`]):
+//! by `SignerClient`'s send and batch methods, and by
+//! [`WsConnection`](crate::ws_client::WsConnection) for connections built
+//! from a client, which carries every websocket submission in
+//! [`tx_executor`](crate::tx_executor) and the pool's `TxSender`.
+//!
+//! The chain fails closed: an order for a market without a spec, or a check
+//! missing the price or mark it needs, is rejected rather than let through.
+//! Reduce-only orders cannot add exposure, so the built-in size, exposure
+//! and loss checks let them through. [`PriceBand`] still applies: a close
+//! priced far through the book is as much a fat finger as an opening order.
+//!
+//! Built-in checks: [`MaxOrderNotional`], [`MaxPosition`], [`MaxOpenOrders`],
+//! [`PriceBand`], [`MinNotional`] and [`DailyLossLimit`].
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+    time::UNIX_EPOCH,
+};
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    clock::{system_clock, SharedClock},
+    errors::PreTradeError,
+    market::MarketSpec,
+    models,
+    order_manager::OrderManager,
+    risk::RiskEngine,
+    transactions::{CreateOrderInfo, ModifyOrderInfo},
+    tx_executor::{TX_TYPE_CREATE_ORDER, TX_TYPE_MODIFY_ORDER},
+    types::MarketId,
+    ws_client::TradeSide,
+};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Whether an order creates a new order or modifies a resting one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreTradeAction {
+    Create,
+    Modify,
+}
+
+/// An order in market units, as seen by [`PreTradeCheck`]s.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PreTradeOrder {
+    pub market: MarketId,
+    pub action: PreTradeAction,
+    /// `None` for modifies, which do not carry a side.
+    pub side: Option<TradeSide>,
+    /// Base size.
+    pub size: f64,
+    /// Limit price, the worst acceptable price of a market order, or the
+    /// trigger price of a trigger order without a limit.
+    pub price: Option<f64>,
+    pub reduce_only: bool,
+}
+
+impl PreTradeOrder {
+    pub fn notional(&self) -> Option<f64> {
+        self.price.map(|price| self.size * price)
+    }
+
+    /// Base size with the sign of the side; `None` for modifies.
+    pub fn signed_size(&self) -> Option<f64> {
+        self.side.map(|side| match side {
+            TradeSide::Buy => self.size,
+            TradeSide::Sell => -self.size,
+        })
+    }
+}
+
+/// One rule an order must pass before it is signed.
+pub trait PreTradeCheck: Send + Sync + fmt::Debug {
+    fn name(&self) -> &'static str;
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError>;
+}
+
+/// Market specs plus the checks every order runs through. Cheap to clone;
+/// clones share specs.
+#[derive(Debug, Clone, Default)]
+pub struct PreTradeChain {
+    specs: Arc<RwLock<HashMap<MarketId, MarketSpec>>>,
+    checks: Vec<Arc<dyn PreTradeCheck>>,
+}
+
+impl PreTradeChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn check(mut self, check: impl PreTradeCheck + 'static) -> Self {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    pub fn push(&mut self, check: Arc<dyn PreTradeCheck>) {
+        self.checks.push(check);
+    }
+
+    pub fn checks(&self) -> &[Arc<dyn PreTradeCheck>] {
+        &self.checks
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.checks.is_empty()
+    }
+
+    pub fn set_spec(&self, spec: MarketSpec) {
+        self.specs
+            .write()
+            .expect("pre-trade specs lock poisoned")
+            .insert(spec.market, spec);
+    }
+
+    pub fn set_specs(&self, specs: impl IntoIterator<Item = MarketSpec>) {
+        let mut current = self.specs.write().expect("pre-trade specs lock poisoned");
+        for spec in specs {
+            current.insert(spec.market, spec);
+        }
+    }
+
+    pub fn has_specs(&self) -> bool {
+        !self
+            .specs
+            .read()
+            .expect("pre-trade specs lock poisoned")
+            .is_empty()
+    }
+
+    /// Run every check against an order given in integer units. `price` and
+    /// `trigger_price` are in price ticks; a non-positive value means unset.
+    #[allow(clippy::too_many_arguments)]
+    pub fn evaluate(
+        &self,
+        market: MarketId,
+        action: PreTradeAction,
+        side: Option<TradeSide>,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        reduce_only: bool,
+    ) -> Result<(), PreTradeError> {
+        if self.checks.is_empty() {
+            return Ok(());
+        }
+        let order = {
+            let specs = self.specs.read().expect("pre-trade specs lock poisoned");
+            let spec = specs
+                .get(&market)
+                .ok_or(PreTradeError::UnknownMarket(market))?;
+            let ticks = if price > 0 { price } else { trigger_price };
+            PreTradeOrder {
+                market,
+                action,
+                side,
+                size: base_amount as f64 / 10f64.powi(spec.size_decimals as i32),
+                price: (ticks > 0).then(|| ticks as f64 / 10f64.powi(spec.price_decimals as i32)),
+                reduce_only,
+            }
+        };
+        self.run(&order)
+    }
+
+    /// Run every check against a signed transaction before it is sent.
+    /// `CreateOrder` and `ModifyOrder` payloads are decoded and checked like
+    /// [`evaluate`](Self::evaluate); one that does not decode is rejected.
+    /// Other transaction types pass.
+    pub fn evaluate_tx(&self, tx_type: i32, tx_info: &Value) -> Result<(), PreTradeError> {
+        if self.checks.is_empty() {
+            return Ok(());
+        }
+        let unreadable = |_| PreTradeError::UnreadableOrder { tx_type };
+        match u8::try_from(tx_type) {
+            Ok(TX_TYPE_CREATE_ORDER) => {
+                let info = CreateOrderInfo::deserialize(tx_info).map_err(unreadable)?;
+                let side = if info.is_ask {
+                    TradeSide::Sell
+                } else {
+                    TradeSide::Buy
+                };
+                self.evaluate(
+                    MarketId::new(info.market_index),
+                    PreTradeAction::Create,
+                    Some(side),
+                    info.base_amount,
+                    info.price,
+                    info.trigger_price,
+                    info.reduce_only,
+                )
+            }
+            Ok(TX_TYPE_MODIFY_ORDER) => {
+                let info = ModifyOrderInfo::deserialize(tx_info).map_err(unreadable)?;
+                self.evaluate(
+                    MarketId::new(info.market_index),
+                    PreTradeAction::Modify,
+                    None,
+                    info.base_amount,
+                    info.price,
+                    info.trigger_price,
+                    false,
+                )
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Run every check against an order already in market units.
+    pub fn run(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        for check in &self.checks {
+            if let Err(err) = check.check(order) {
+                tracing::warn!(
+                    check = check.name(),
+                    market = %order.market,
+                    error = %err,
+                    "pre-trade check failed"
+                );
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Caps the notional of a single order. Reduce-only orders pass.
+#[derive(Debug, Clone)]
+pub struct MaxOrderNotional {
+    default: f64,
+    markets: HashMap<MarketId, f64>,
+}
+
+impl MaxOrderNotional {
+    pub fn new(max_notional: f64) -> Self {
+        Self {
+            default: max_notional,
+            markets: HashMap::new(),
+        }
+    }
+
+    pub fn market(mut self, market: MarketId, max_notional: f64) -> Self {
+        self.markets.insert(market, max_notional);
+        self
+    }
+}
+
+impl PreTradeCheck for MaxOrderNotional {
+    fn name(&self) -> &'static str {
+        "max_order_notional"
+    }
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        if order.reduce_only {
+            return Ok(());
+        }
+        let notional = order
+            .notional()
+            .ok_or(PreTradeError::NoPrice(order.market))?;
+        let limit = self
+            .markets
+            .get(&order.market)
+            .copied()
+            .unwrap_or(self.default);
+        if notional > limit {
+            return Err(PreTradeError::MaxOrderNotional {
+                market: order.market,
+                notional,
+                limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Caps the absolute position a filled order would leave, per market.
+/// Orders that shrink the position always pass. Modifies carry no side, so
+/// they are checked as if they grew the position by their full size.
+#[derive(Debug, Clone)]
+pub struct MaxPosition {
+    risk: RiskEngine,
+    default: Option<f64>,
+    markets: HashMap<MarketId, f64>,
+}
+
+impl MaxPosition {
+    /// Positions are read from `risk`. Markets without a limit are only
+    /// capped once [`default_limit`](Self::default_limit) is set.
+    pub fn new(risk: RiskEngine) -> Self {
+        Self {
+            risk,
+            default: None,
+            markets: HashMap::new(),
+        }
+    }
+
+    pub fn default_limit(mut self, max_size: f64) -> Self {
+        self.default = Some(max_size);
+        self
+    }
+
+    pub fn market(mut self, market: MarketId, max_size: f64) -> Self {
+        self.markets.insert(market, max_size);
+        self
+    }
+}
+
+impl PreTradeCheck for MaxPosition {
+    fn name(&self) -> &'static str {
+        "max_position"
+    }
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        let Some(limit) = self.markets.get(&order.market).copied().or(self.default) else {
+            return Ok(());
+        };
+        let current = self
+            .risk
+            .position(order.market)
+            .map(|position| position.size)
+            .unwrap_or(0.0);
+        let projected = match order.signed_size() {
+            Some(delta) => current + delta,
+            // Worst case: the order is on the side the position is on.
+            None if current < 0.0 => current - order.size,
+            None => current + order.size,
+        };
+        if projected.abs() > limit && projected.abs() > current.abs() {
+            return Err(PreTradeError::MaxPosition {
+                market: order.market,
+                projected,
+                limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Caps the number of live orders tracked by an [`OrderManager`]. Reduce-only
+/// orders and modifies pass.
+#[derive(Debug, Clone)]
+pub struct MaxOpenOrders {
+    orders: OrderManager,
+    limit: usize,
+    per_market: bool,
+}
+
+impl MaxOpenOrders {
+    pub fn new(orders: OrderManager, limit: usize) -> Self {
+        Self {
+            orders,
+            limit,
+            per_market: false,
+        }
+    }
+
+    /// Count only orders in the order's market.
+    pub fn per_market(mut self) -> Self {
+        self.per_market = true;
+        self
+    }
+}
+
+impl PreTradeCheck for MaxOpenOrders {
+    fn name(&self) -> &'static str {
+        "max_open_orders"
+    }
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        if order.reduce_only || order.action == PreTradeAction::Modify {
+            return Ok(());
+        }
+        let open = self
+            .orders
+            .active()
+            .iter()
+            .filter(|tracked| !self.per_market || tracked.market == order.market)
+            .count();
+        if open >= self.limit {
+            return Err(PreTradeError::MaxOpenOrders {
+                open,
+                limit: self.limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Rejects prices more than a fraction away from the mark, catching
+/// fat-fingered prices and unit mistakes. Applies to reduce-only orders too.
+#[derive(Debug, Clone)]
+pub struct PriceBand {
+    risk: RiskEngine,
+    max_deviation: f64,
+}
+
+impl PriceBand {
+    /// Marks are read from `risk`. `max_deviation` is a fraction of the mark
+    /// (`0.05` = 5%).
+    pub fn new(risk: RiskEngine, max_deviation: f64) -> Self {
+        Self {
+            risk,
+            max_deviation,
+        }
+    }
+}
+
+impl PreTradeCheck for PriceBand {
+    fn name(&self) -> &'static str {
+        "price_band"
+    }
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        let price = order.price.ok_or(PreTradeError::NoPrice(order.market))?;
+        let mark = self
+            .risk
+            .mark_price(order.market)
+            .ok_or(PreTradeError::NoMarkPrice(order.market))?;
+        let deviation = (price - mark).abs() / mark;
+        if deviation > self.max_deviation {
+            return Err(PreTradeError::PriceBand {
+                market: order.market,
+                price,
+                mark,
+                limit: self.max_deviation,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Minimums {
+    base: f64,
+    quote: f64,
+}
+
+/// Rejects orders below the exchange's minimum base size or quote notional.
+/// Markets without minimums and reduce-only orders pass.
+#[derive(Debug, Clone, Default)]
+pub struct MinNotional {
+    markets: HashMap<MarketId, Minimums>,
+}
+
+impl MinNotional {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Minimums for every market in an `orderBookDetails` reply. An empty
+    /// minimum means none; one that does not parse is an error.
+    pub fn from_details(details: &models::OrderBookDetails) -> Result<Self, PreTradeError> {
+        let markets = details
+            .order_book_details
+            .iter()
+            .map(|detail| {
+                let market = MarketId::new(detail.market_id);
+                let minimums = Minimums {
+                    base: parse_minimum(market, &detail.min_base_amount, "min_base_amount")?,
+                    quote: parse_minimum(market, &detail.min_quote_amount, "min_quote_amount")?,
+                };
+                Ok((market, minimums))
+            })
+            .collect::<Result<_, PreTradeError>>()?;
+        Ok(Self { markets })
+    }
+
+    pub fn market(mut self, market: MarketId, min_base: f64, min_quote: f64) -> Self {
+        self.markets.insert(
+            market,
+            Minimums {
+                base: min_base,
+                quote: min_quote,
+            },
+        );
+        self
+    }
+}
+
+impl PreTradeCheck for MinNotional {
+    fn name(&self) -> &'static str {
+        "min_notional"
+    }
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        let Some(minimums) = self.markets.get(&order.market) else {
+            return Ok(());
+        };
+        if order.reduce_only {
+            return Ok(());
+        }
+        let notional = order.notional().unwrap_or(0.0);
+        if order.size < minimums.base || notional < minimums.quote {
+            return Err(PreTradeError::BelowMinimum {
+                market: order.market,
+                size: order.size,
+                notional,
+                min_size: minimums.base,
+                min_notional: minimums.quote,
+            });
+        }
+        Ok(())
+    }
+}
+
+fn parse_minimum(market: MarketId, text: &str, field: &'static str) -> Result<f64, PreTradeError> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(0.0);
+    }
+    text.parse()
+        .map_err(|_| PreTradeError::InvalidMinimum { market, field })
+}
+
+#[derive(Debug, Default)]
+struct DayPnl {
+    day: u64,
+    pnl: f64,
+}
+
+/// Blocks orders that are not reduce-only once the day's PnL reaches
+/// `-max_loss`. Feed it realized PnL with [`record`](Self::record); the
+/// total resets at UTC midnight.
+#[derive(Debug, Clone)]
+pub struct DailyLossLimit {
+    max_loss: f64,
+    clock: SharedClock,
+    state: Arc<Mutex<DayPnl>>,
+}
+
+impl DailyLossLimit {
+    pub fn new(max_loss: f64) -> Self {
+        Self::with_clock(max_loss, system_clock())
+    }
+
+    pub fn with_clock(max_loss: f64, clock: SharedClock) -> Self {
+        Self {
+            max_loss: max_loss.abs(),
+            clock,
+            state: Arc::new(Mutex::new(DayPnl::default())),
+        }
+    }
+
+    /// Add realized PnL (negative for a loss).
+    pub fn record(&self, pnl: f64) {
+        let mut state = self.current();
+        state.pnl += pnl;
+    }
+
+    /// Replace today's PnL, e.g. from account stats.
+    pub fn set_day_pnl(&self, pnl: f64) {
+        self.current().pnl = pnl;
+    }
+
+    pub fn day_pnl(&self) -> f64 {
+        self.current().pnl
+    }
+
+    fn current(&self) -> std::sync::MutexGuard<'_, DayPnl> {
+        let day = self
+            .clock
+            .system_now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / SECONDS_PER_DAY;
+        let mut state = self.state.lock().expect("daily loss lock poisoned");
+        if state.day != day {
+            *state = DayPnl { day, pnl: 0.0 };
+        }
+        state
+    }
+}
+
+impl PreTradeCheck for DailyLossLimit {
+    fn name(&self) -> &'static str {
+        "daily_loss_limit"
+    }
+
+    fn check(&self, order: &PreTradeOrder) -> Result<(), PreTradeError> {
+        if order.reduce_only {
+            return Ok(());
+        }
+        let pnl = self.day_pnl();
+        if pnl <= -self.max_loss {
+            return Err(PreTradeError::DailyLoss {
+                pnl,
+                limit: self.max_loss,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        clock::ManualClock,
+        errors::SignerClientError,
+        lighter_client::LighterClient,
+        market::Decimal,
+        order_book::BookScale,
+        signer_client::BatchEntry,
+        sim_exchange::SimExchange,
+        tx_executor::{ORDER_TIME_IN_FORCE_GTT, ORDER_TYPE_LIMIT},
+        tx_signer::MockSigner,
+        types::AccountId,
+    };
+
+    fn spec() -> MarketSpec {
+        MarketSpec {
+            market: MarketId::new(1),
+            symbol: "ETH".into(),
+            price_decimals: 2,
+            size_decimals: 4,
+            min_base_amount: Decimal::new(1, 3),
+            min_quote_amount: Decimal::new(10, 0),
+        }
+    }
+
+    #[test]
+    fn chain_converts_units_and_stops_at_first_violation() {
+        let risk = RiskEngine::default();
+        risk.set_mark_price(MarketId::new(1), 2_000.0);
+        let chain = PreTradeChain::new()
+            .check(MinNotional::new().market(MarketId::new(1), 0.001, 10.0))
+            .check(PriceBand::new(risk, 0.05))
+            .check(MaxOrderNotional::new(10_000.0));
+
+        // Unknown market fails closed.
+        let err = chain
+            .evaluate(
+                MarketId::new(1),
+                PreTradeAction::Create,
+                Some(TradeSide::Buy),
+                10_000,
+                200_000,
+                0,
+                false,
+            )
+            .unwrap_err();
+        assert_eq!(err, PreTradeError::UnknownMarket(MarketId::new(1)));
+        chain.set_spec(spec());
+
+        // 1.0 ETH at 2000.00.
+        let buy = |size: i64, price: i64| {
+            chain.evaluate(
+                MarketId::new(1),
+                PreTradeAction::Create,
+                Some(TradeSide::Buy),
+                size,
+                price,
+                0,
+                false,
+            )
+        };
+        assert_eq!(buy(10_000, 200_000), Ok(()));
+        // A price entered in the wrong units is 100x off the mark.
+        assert!(matches!(
+            buy(10_000, 20_000_000),
+            Err(PreTradeError::PriceBand { .. })
+        ));
+        // 100x the size breaks the notional cap.
+        assert!(matches!(
+            buy(1_000_000, 200_000),
+            Err(PreTradeError::MaxOrderNotional { notional, .. }) if notional == 200_000.0
+        ));
+        assert!(matches!(
+            buy(10, 200_000),
+            Err(PreTradeError::BelowMinimum { .. })
+        ));
+
+        // A reduce-only close skips the size checks but not the price band.
+        let close = |price: f64| PreTradeOrder {
+            market: MarketId::new(1),
+            action: PreTradeAction::Create,
+            side: Some(TradeSide::Sell),
+            size: 1_000.0,
+            price: Some(price),
+            reduce_only: true,
+        };
+        assert_eq!(chain.run(&close(1_950.0)), Ok(()));
+        assert!(matches!(
+            chain.run(&close(1_000.0)),
+            Err(PreTradeError::PriceBand { .. })
+        ));
+    }
+
+    #[test]
+    fn position_and_daily_loss_limits_let_reductions_through() {
+        let risk = RiskEngine::default();
+        let clock =
+            ManualClock::starting_at(UNIX_EPOCH + Duration::from_secs(SECONDS_PER_DAY * 10));
+        let loss = DailyLossLimit::with_clock(500.0, clock.shared());
+        let chain = PreTradeChain::new()
+            .check(MaxPosition::new(risk).market(MarketId::new(1), 2.0))
+            .check(loss.clone());
+        chain.set_spec(spec());
+        let order = |side, size: i64, reduce_only| {
+            chain.evaluate(
+                MarketId::new(1),
+                PreTradeAction::Create,
+                Some(side),
+                size,
+                200_000,
+                0,
+                reduce_only,
+            )
+        };
+
+        assert_eq!(order(TradeSide::Buy, 20_000, false), Ok(()));
+        assert!(matches!(
+            order(TradeSide::Sell, 30_000, false),
+            Err(PreTradeError::MaxPosition { projected, .. }) if projected == -3.0
+        ));
+
+        loss.record(-300.0);
+        loss.record(-250.0);
+        assert!(matches!(
+            order(TradeSide::Buy, 10_000, false),
+            Err(PreTradeError::DailyLoss { .. })
+        ));
+        assert_eq!(order(TradeSide::Sell, 10_000, true), Ok(()));
+
+        clock.advance(Duration::from_secs(SECONDS_PER_DAY));
+        assert_eq!(loss.day_pnl(), 0.0);
+        assert_eq!(order(TradeSide::Buy, 10_000, false), Ok(()));
+    }
+
+    #[test]
+    fn modifies_are_capped_as_the_worst_case() {
+        let chain = PreTradeChain::new()
+            .check(MaxPosition::new(RiskEngine::default()).market(MarketId::new(1), 2.0));
+        chain.set_spec(spec());
+        let modify = |size: i64| {
+            chain.evaluate(
+                MarketId::new(1),
+                PreTradeAction::Modify,
+                None,
+                size,
+                200_000,
+                0,
+                false,
+            )
+        };
+        assert_eq!(modify(10_000), Ok(()));
+        assert!(matches!(
+            modify(30_000),
+            Err(PreTradeError::MaxPosition { projected, .. }) if projected == 3.0
+        ));
+    }
+
+    #[test]
+    fn unparseable_minimums_are_rejected() {
+        let detail = |min_base: &str| models::OrderBookDetail {
+            market_id: 1,
+            min_base_amount: min_base.into(),
+            min_quote_amount: "10".into(),
+            ..Default::default()
+        };
+        let details = models::OrderBookDetails::new(200, vec![detail("0.001")]);
+        assert!(MinNotional::from_details(&details).is_ok());
+
+        let details = models::OrderBookDetails::new(200, vec![detail("n/a")]);
+        assert_eq!(
+            MinNotional::from_details(&details).unwrap_err(),
+            PreTradeError::InvalidMinimum {
+                market: MarketId::new(1),
+                field: "min_base_amount",
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn presigned_batches_are_checked_before_sending() {
+        let chain = PreTradeChain::new().check(MaxOrderNotional::new(10_000.0));
+        chain.set_spec(spec());
+        let mut sim = SimExchange::new(AccountId::new(42));
+        sim.add_market(MarketId::new(1), BookScale::new(2, 4));
+        let client = LighterClient::builder()
+            .simulated(sim)
+            .tx_signer(Arc::new(MockSigner::new(42)))
+            .api_key_index(2)
+            .account_index(42)
+            .pre_trade(chain)
+            .build()
+            .await
+            .unwrap();
+        let signer = client.signer().unwrap();
+        // Signed on the raw backend, so the chain has not seen them yet.
+        let buy = |nonce: i64, base_amount: i64| {
+            let (tx_info, _) = signer
+                .unchecked_signer()
+                .sign_create_order(
+                    1,
+                    nonce,
+                    base_amount,
+                    200_000,
+                    false,
+                    ORDER_TYPE_LIMIT,
+                    ORDER_TIME_IN_FORCE_GTT,
+                    false,
+                    0,
+                    -1,
+                    nonce,
+                )
+                .unwrap();
+            BatchEntry::new(TX_TYPE_CREATE_ORDER.into(), tx_info.unwrap())
+        };
+
+        // 1 ETH, then a fat-fingered 100 ETH, at 2000.00.
+        let batch = [buy(1, 10_000), buy(2, 1_000_000)];
+        assert!(matches!(
+            signer.send_tx_batch(&batch).await,
+            Err(SignerClientError::PreTrade(
+                PreTradeError::MaxOrderNotional { .. }
+            ))
+        ));
+        assert!(matches!(
+            signer.send_tx_batch_outcome(&batch).await,
+            Err(SignerClientError::PreTrade(
+                PreTradeError::MaxOrderNotional { .. }
+            ))
+        ));
+        assert!(signer.send_tx_batch(&batch[..1]).await.is_ok());
+    }
+}
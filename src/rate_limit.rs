@@ -0,0 +1,411 @@
+//! Client-side REST rate limiting and retries.
+//!
+//! A [`RateLimiter`] keeps two token buckets: one for weighted read endpoints
+//! and one for `sendTx`/`sendTxBatch`. Every REST call waits for its weight
+//! before it is sent, and a `429` pauses both buckets for the server's
+//! `retry_after`.
+//!
+//! A [`RestRetryPolicy`] retries reads that failed with a `429`, a `5xx` or a
+//! transport error, with jittered exponential backoff that never undercuts
+//! `retry_after`. Transaction submissions are not idempotent, so they are
+//! only resent after a lookup by tx hash shows the failed attempt was not
+//! accepted; a failure that carries no tx hash is returned as is.
+//!
+//! Both are opt-in through
+//! [`LighterClientBuilder`](crate::lighter_client::LighterClientBuilder).
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+use tokio::time::sleep;
+
+use crate::{
+    apis,
+    clock::{system_clock, SharedClock},
+};
+
+/// Which budget a request draws from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    Read,
+    /// `sendTx` and `sendTxBatch`.
+    Send,
+}
+
+/// Token bucket size and refill rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BucketConfig {
+    /// Most weight that can be spent at once.
+    pub capacity: u32,
+    /// Weight restored every `interval`, spread evenly.
+    pub refill: u32,
+    pub interval: Duration,
+}
+
+impl BucketConfig {
+    pub const fn per_minute(weight: u32) -> Self {
+        Self {
+            capacity: weight,
+            refill: weight,
+            interval: Duration::from_secs(60),
+        }
+    }
+
+    fn refill_per_sec(&self) -> f64 {
+        self.refill as f64 / self.interval.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// Budgets and endpoint weights for a [`RateLimiter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateLimitConfig {
+    pub read: BucketConfig,
+    pub send: BucketConfig,
+    /// Weight per endpoint, keyed by its path after `/api/v1/`
+    /// (`orderBookOrders`, `sendTx`, `deposit/history`, ...).
+    pub weights: HashMap<String, u32>,
+    /// Weight of endpoints missing from `weights`.
+    pub default_weight: u32,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self::standard()
+    }
+}
+
+impl RateLimitConfig {
+    /// Standard accounts: 60 requests a minute, every request weighing one.
+    pub fn standard() -> Self {
+        Self {
+            read: BucketConfig::per_minute(60),
+            send: BucketConfig::per_minute(60),
+            weights: HashMap::new(),
+            default_weight: 1,
+        }
+    }
+
+    /// Premium accounts: 24000 weight a minute with the exchange's
+    /// per-endpoint weights.
+    pub fn premium() -> Self {
+        let weights = [
+            ("sendTx", 6),
+            ("sendTxBatch", 6),
+            ("nextNonce", 6),
+            ("publicPools", 50),
+            ("txFromL1TxHash", 50),
+            ("accountInactiveOrders", 100),
+            ("apikeys", 150),
+        ]
+        .into_iter()
+        .map(|(endpoint, weight)| (endpoint.to_string(), weight))
+        .collect();
+        Self {
+            read: BucketConfig::per_minute(24_000),
+            send: BucketConfig::per_minute(24_000),
+            weights,
+            default_weight: 300,
+        }
+    }
+
+    pub fn weight(mut self, endpoint: impl Into<String>, weight: u32) -> Self {
+        self.weights.insert(endpoint.into(), weight);
+        self
+    }
+
+    pub fn weight_of(&self, endpoint: &str) -> u32 {
+        self.weights
+            .get(endpoint)
+            .copied()
+            .unwrap_or(self.default_weight)
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    config: BucketConfig,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn new(config: BucketConfig, now: Instant) -> Self {
+        Self {
+            config,
+            tokens: config.capacity as f64,
+            updated: now,
+        }
+    }
+
+    /// Take `weight` tokens, or return how long until they are available.
+    fn take(&mut self, weight: u32, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        let capacity = self.config.capacity as f64;
+        self.tokens = (self.tokens + elapsed * self.config.refill_per_sec()).min(capacity);
+        self.updated = now;
+
+        // A request heavier than the bucket waits for a full bucket.
+        let weight = (weight as f64).min(capacity);
+        if self.tokens >= weight {
+            self.tokens -= weight;
+            return Ok(());
+        }
+        let rate = self.config.refill_per_sec();
+        if rate <= 0.0 {
+            return Err(self.config.interval);
+        }
+        Err(Duration::from_secs_f64((weight - self.tokens) / rate))
+    }
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    read: Bucket,
+    send: Bucket,
+    paused_until: Option<Instant>,
+}
+
+/// Token-bucket limiter shared by every REST call of a client.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    clock: SharedClock,
+    state: Mutex<LimiterState>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self::with_clock(config, system_clock())
+    }
+
+    pub fn with_clock(config: RateLimitConfig, clock: SharedClock) -> Self {
+        let now = clock.now();
+        let state = LimiterState {
+            read: Bucket::new(config.read, now),
+            send: Bucket::new(config.send, now),
+            paused_until: None,
+        };
+        Self {
+            config,
+            clock,
+            state: Mutex::new(state),
+        }
+    }
+
+    pub fn config(&self) -> &RateLimitConfig {
+        &self.config
+    }
+
+    /// Spend `weight` from `class`'s bucket, or return how long to wait
+    /// before trying again. Nothing is spent while paused.
+    pub fn try_acquire(&self, class: RequestClass, weight: u32) -> Result<(), Duration> {
+        let now = self.clock.now();
+        let mut state = self.state.lock().expect("rate limiter lock poisoned");
+        if let Some(until) = state.paused_until {
+            if now < until {
+                return Err(until - now);
+            }
+            state.paused_until = None;
+        }
+        match class {
+            RequestClass::Read => state.read.take(weight, now),
+            RequestClass::Send => state.send.take(weight, now),
+        }
+    }
+
+    /// Wait until `endpoint`'s weight is available in `class`'s bucket.
+    pub async fn acquire(&self, class: RequestClass, endpoint: &str) {
+        let weight = self.config.weight_of(endpoint);
+        while let Err(wait) = self.try_acquire(class, weight) {
+            sleep(wait).await;
+        }
+    }
+
+    /// Hold every request back for `duration`, e.g. a `429`'s `retry_after`.
+    pub fn pause_for(&self, duration: Duration) {
+        let until = self.clock.now() + duration;
+        let mut state = self.state.lock().expect("rate limiter lock poisoned");
+        if state.paused_until.is_none_or(|current| current < until) {
+            state.paused_until = Some(until);
+        }
+    }
+}
+
+/// How failed REST calls are retried.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RestRetryPolicy {
+    /// Attempts in total, including the first.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Fraction of each backoff that is randomised away, in `[0, 1]`.
+    pub jitter: f64,
+}
+
+impl Default for RestRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 4,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RestRetryPolicy {
+    /// Delay before retry number `retry` (starting at 1), or `None` once the
+    /// attempts are used up. Never shorter than `retry_after`.
+    pub fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if retry == 0 || retry >= self.max_attempts {
+            return None;
+        }
+        let exponent = (retry - 1).min(16);
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(1 << exponent)
+            .min(self.max_backoff);
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let backoff = if jitter > 0.0 {
+            backoff.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..=jitter))
+        } else {
+            backoff
+        };
+        Some(retry_after.map_or(backoff, |after| after.max(backoff)))
+    }
+}
+
+/// The limiter and retry policy a REST-calling client was configured with.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct RequestPolicy {
+    pub(crate) limiter: Option<Arc<RateLimiter>>,
+    pub(crate) retry: Option<RestRetryPolicy>,
+}
+
+impl RequestPolicy {
+    pub(crate) async fn acquire(&self, class: RequestClass, endpoint: &str) {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(class, endpoint).await;
+        }
+    }
+
+    pub(crate) fn rate_limited(&self, retry_after: Option<Duration>) {
+        if let (Some(limiter), Some(after)) = (&self.limiter, retry_after) {
+            limiter.pause_for(after);
+        }
+    }
+
+    pub(crate) fn retry_delay(
+        &self,
+        retry: u32,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        self.retry?.delay(retry, retry_after)
+    }
+}
+
+/// How a failed request may be handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Failure {
+    /// `429`: the server asked us to slow down.
+    RateLimited,
+    /// `5xx` or a transport error; the request may or may not have been
+    /// processed.
+    Transient,
+    Permanent,
+}
+
+pub(crate) fn classify<E>(err: &apis::Error<E>) -> Failure {
+    match err {
+        apis::Error::ResponseError(response) if response.status.as_u16() == 429 => {
+            Failure::RateLimited
+        }
+        apis::Error::ResponseError(response) if response.status.is_server_error() => {
+            Failure::Transient
+        }
+        apis::Error::Reqwest(err) if err.status().is_some_and(|s| s.as_u16() == 429) => {
+            Failure::RateLimited
+        }
+        apis::Error::Reqwest(err)
+            if err.is_timeout()
+                || err.is_connect()
+                || err.status().is_some_and(|s| s.is_server_error()) =>
+        {
+            Failure::Transient
+        }
+        _ => Failure::Permanent,
+    }
+}
+
+/// `retry_after` from a `429` body, in seconds.
+pub(crate) fn retry_after<E>(err: &apis::Error<E>) -> Option<Duration> {
+    let apis::Error::ResponseError(response) = err else {
+        return None;
+    };
+    let body: serde_json::Value = serde_json::from_str(&response.content).ok()?;
+    body.get("retry_after")
+        .and_then(serde_json::Value::as_u64)
+        .map(Duration::from_secs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    #[test]
+    fn buckets_refill_and_pause_independently() {
+        let clock = ManualClock::new();
+        let config = RateLimitConfig {
+            read: BucketConfig {
+                capacity: 2,
+                refill: 2,
+                interval: Duration::from_secs(1),
+            },
+            send: BucketConfig::per_minute(1),
+            ..RateLimitConfig::standard()
+        };
+        let limiter = RateLimiter::with_clock(config, clock.shared());
+
+        assert!(limiter.try_acquire(RequestClass::Read, 1).is_ok());
+        assert!(limiter.try_acquire(RequestClass::Read, 1).is_ok());
+        let wait = limiter.try_acquire(RequestClass::Read, 1).unwrap_err();
+        assert_eq!(wait, Duration::from_millis(500));
+        assert!(limiter.try_acquire(RequestClass::Send, 1).is_ok());
+        assert!(limiter.try_acquire(RequestClass::Send, 1).is_err());
+
+        clock.advance(Duration::from_millis(500));
+        assert!(limiter.try_acquire(RequestClass::Read, 1).is_ok());
+
+        limiter.pause_for(Duration::from_secs(3));
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(
+            limiter.try_acquire(RequestClass::Read, 1),
+            Err(Duration::from_secs(2))
+        );
+        clock.advance(Duration::from_secs(2));
+        assert!(limiter.try_acquire(RequestClass::Read, 1).is_ok());
+    }
+
+    #[test]
+    fn retry_delay_backs_off_and_honours_retry_after() {
+        let policy = RestRetryPolicy {
+            jitter: 0.0,
+            ..RestRetryPolicy::default()
+        };
+        assert_eq!(policy.delay(1, None), Some(Duration::from_millis(200)));
+        assert_eq!(policy.delay(2, None), Some(Duration::from_millis(400)));
+        assert_eq!(
+            policy.delay(2, Some(Duration::from_secs(2))),
+            Some(Duration::from_secs(2))
+        );
+        assert_eq!(policy.delay(4, None), None);
+
+        let jittered = RestRetryPolicy::default().delay(3, None).unwrap();
+        assert!(jittered >= Duration::from_millis(400) && jittered <= Duration::from_millis(800));
+    }
+}
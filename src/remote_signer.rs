@@ -0,0 +1,376 @@
+//! Signing over a local Unix socket so private keys can live in a separate
+//! process.
+//!
+//! The wire format is one JSON object per line. Requests carry an `id` and a
+//! [`SignRequest`]; replies echo the `id` with either a [`SignReply`] or a
+//! `failure` string for errors raised before the signer produced a result.
+//!
+//! ```text
+//! -> {"id":1,"request":{"method":"switch_api_key","params":{"api_key_index":3}}}
+//! <- {"id":1,"reply":{}}
+//! ```
+//!
+//! The key-holding process runs [`serve`] in front of any [`TxSigner`]
+//! (usually a [`SignerLibrary`](crate::signer::SignerLibrary)); the trading
+//! process hands a [`RemoteSigner`] to
+//! [`SignerClient::with_signer`](crate::signer_client::SignerClient::with_signer).
+//! [`ServeOptions`] limits which methods the server answers and which user
+//! may connect.
+
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt},
+    net::UnixListener,
+    runtime::{Handle, RuntimeFlavor},
+};
+
+use crate::{
+    errors::{Result, SignerClientError},
+    tx_signer::{SignBackend, SignReply, SignRequest, TxSigner},
+};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Methods [`ServeOptions::default`] answers: order placement and
+/// cancellation, plus the session calls the client makes before signing.
+const DEFAULT_METHODS: [&str; 6] = [
+    "check_client",
+    "switch_api_key",
+    "sign_create_order",
+    "sign_modify_order",
+    "sign_cancel_order",
+    "sign_cancel_all_orders",
+];
+
+/// Access rules for [`serve`].
+#[derive(Debug, Clone)]
+pub struct ServeOptions {
+    allowed_methods: HashSet<&'static str>,
+    peer_uid: u32,
+}
+
+impl Default for ServeOptions {
+    /// Order and cancel requests only, from peers running as this process's
+    /// user.
+    fn default() -> Self {
+        Self {
+            allowed_methods: DEFAULT_METHODS.into_iter().collect(),
+            // SAFETY: geteuid has no preconditions and cannot fail.
+            peer_uid: unsafe { libc::geteuid() },
+        }
+    }
+}
+
+impl ServeOptions {
+    /// Also answer `method`, named as in [`SignRequest::method`].
+    pub fn allow(mut self, method: &'static str) -> Self {
+        self.allowed_methods.insert(method);
+        self
+    }
+
+    /// Accept connections from `uid` instead of this process's user.
+    pub fn peer_uid(mut self, uid: u32) -> Self {
+        self.peer_uid = uid;
+        self
+    }
+
+    pub fn allows(&self, method: &str) -> bool {
+        self.allowed_methods.contains(method)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WireRequest {
+    id: u64,
+    request: SignRequest,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WireReply {
+    id: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    reply: Option<SignReply>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    failure: Option<String>,
+}
+
+/// [`TxSigner`] that forwards every call to a signer process over a Unix socket.
+///
+/// Calls are blocking and serialised over a single connection, matching the
+/// native library they replace. Inside a multi-threaded tokio runtime each
+/// call runs under [`tokio::task::block_in_place`] so other tasks move off the
+/// waiting worker; on a current-thread runtime the call blocks the runtime.
+/// A broken connection is re-dialled on the next call.
+#[derive(Debug)]
+pub struct RemoteSigner {
+    path: PathBuf,
+    timeout: Duration,
+    conn: Mutex<Option<Connection>>,
+}
+
+#[derive(Debug)]
+struct Connection {
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+    next_id: u64,
+}
+
+impl Connection {
+    fn open(path: &Path, timeout: Duration) -> io::Result<Self> {
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: stream,
+            next_id: 1,
+        })
+    }
+
+    fn call(&mut self, request: SignRequest) -> Result<SignReply> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let mut line = serde_json::to_vec(&WireRequest { id, request })?;
+        line.push(b'\n');
+        self.writer.write_all(&line)?;
+        self.writer.flush()?;
+
+        let mut buf = String::new();
+        if self.reader.read_line(&mut buf)? == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let reply: WireReply = serde_json::from_str(&buf)?;
+        if reply.id != id {
+            return Err(SignerClientError::Signer(format!(
+                "remote signer replied to request {} while {id} was pending",
+                reply.id
+            )));
+        }
+        match (reply.reply, reply.failure) {
+            (_, Some(failure)) => Err(SignerClientError::Signer(failure)),
+            (Some(reply), None) => Ok(reply),
+            (None, None) => Err(SignerClientError::InvalidResponse),
+        }
+    }
+}
+
+impl RemoteSigner {
+    /// Connect to the signer listening at `path`.
+    pub fn connect(path: impl Into<PathBuf>) -> Result<Self> {
+        let signer = Self {
+            path: path.into(),
+            timeout: DEFAULT_TIMEOUT,
+            conn: Mutex::new(None),
+        };
+        let conn = Connection::open(&signer.path, signer.timeout)?;
+        *signer.lock() = Some(conn);
+        Ok(signer)
+    }
+
+    /// Read/write timeout applied to each call (5s by default).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        if let Some(conn) = self.lock().as_mut() {
+            let stream = &conn.writer;
+            let _ = stream.set_read_timeout(Some(timeout));
+            let _ = stream.set_write_timeout(Some(timeout));
+        }
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Option<Connection>> {
+        self.conn
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl SignBackend for RemoteSigner {
+    fn handle(&self, request: SignRequest) -> Result<SignReply> {
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                tokio::task::block_in_place(|| self.call(request))
+            }
+            _ => self.call(request),
+        }
+    }
+}
+
+impl RemoteSigner {
+    fn call(&self, request: SignRequest) -> Result<SignReply> {
+        let mut guard = self.lock();
+        let conn = match guard.as_mut() {
+            Some(conn) => conn,
+            None => guard.insert(Connection::open(&self.path, self.timeout)?),
+        };
+        let result = conn.call(request);
+        if matches!(
+            result,
+            Err(SignerClientError::Io(_) | SignerClientError::Json(_))
+        ) {
+            // The stream may hold a half-read reply; start fresh next time.
+            *guard = None;
+        }
+        result
+    }
+}
+
+/// Serve `signer` on `listener`, one task per connection.
+///
+/// Connections from a user other than [`ServeOptions::peer_uid`] are closed
+/// unanswered, and requests for methods the options do not allow are refused
+/// without reaching the signer. The active API key is signer state shared by
+/// every connection, so serve a single trading process per listener. Runs
+/// until accepting a connection fails.
+pub async fn serve(
+    listener: UnixListener,
+    signer: Arc<dyn TxSigner>,
+    options: ServeOptions,
+) -> io::Result<()> {
+    let options = Arc::new(options);
+    loop {
+        let (stream, _) = listener.accept().await?;
+        match stream.peer_cred() {
+            Ok(cred) if cred.uid() == options.peer_uid => {}
+            Ok(cred) => {
+                tracing::warn!(uid = cred.uid(), "rejected remote signer peer");
+                continue;
+            }
+            Err(err) => {
+                tracing::warn!(error = %err, "could not read remote signer peer credentials");
+                continue;
+            }
+        }
+        let signer = Arc::clone(&signer);
+        let options = Arc::clone(&options);
+        tokio::spawn(async move {
+            if let Err(err) = serve_connection(stream, signer.as_ref(), &options).await {
+                tracing::warn!(error = %err, "remote signer connection closed");
+            }
+        });
+    }
+}
+
+/// Answer requests from a single connection until the peer hangs up.
+async fn serve_connection(
+    stream: tokio::net::UnixStream,
+    signer: &dyn TxSigner,
+    options: &ServeOptions,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<WireRequest>(&line) {
+            Ok(WireRequest { id, request }) if !options.allows(request.method()) => WireReply {
+                id,
+                reply: None,
+                failure: Some(format!("{}: not allowed by this signer", request.method())),
+            },
+            Ok(WireRequest { id, request }) => match request.dispatch(signer) {
+                Ok(reply) => WireReply {
+                    id,
+                    reply: Some(reply),
+                    failure: None,
+                },
+                Err(err) => WireReply {
+                    id,
+                    reply: None,
+                    failure: Some(format!("{}: {err}", request.method())),
+                },
+            },
+            Err(err) => WireReply {
+                id: 0,
+                reply: None,
+                failure: Some(format!("malformed request: {err}")),
+            },
+        };
+        let mut out = serde_json::to_vec(&reply)?;
+        out.push(b'\n');
+        writer.write_all(&out).await?;
+        writer.flush().await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tx_signer::MockSigner;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "lighter-signer-{}-{}.sock",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn remote_calls_round_trip_through_socket() {
+        let path = socket_path("roundtrip");
+        let listener = UnixListener::bind(&path).unwrap();
+        let backend = Arc::new(MockSigner::new(9));
+        let served: Arc<dyn TxSigner> = backend.clone();
+        let options = ServeOptions::default().allow("create_auth_token");
+        tokio::spawn(serve(listener, served, options));
+
+        let remote = RemoteSigner::connect(&path).unwrap();
+        assert_eq!(remote.switch_api_key(4).unwrap(), None);
+        let local = MockSigner::new(9);
+        local.switch_api_key(4).unwrap();
+        assert_eq!(
+            remote.sign_cancel_order(1, 77, 12).unwrap(),
+            local.sign_cancel_order(1, 77, 12).unwrap()
+        );
+
+        backend.reject_next("key not loaded");
+        let (value, error) = remote.create_auth_token(1_700_000_000).unwrap();
+        assert!(value.is_none());
+        assert_eq!(error.as_deref(), Some("key not loaded"));
+
+        let err = remote.sign_withdraw(10, 13).unwrap_err();
+        assert!(err.to_string().contains("sign_withdraw: not allowed"));
+        assert_eq!(backend.calls().len(), 3);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn connections_from_other_users_are_closed() {
+        let path = socket_path("peer");
+        let listener = UnixListener::bind(&path).unwrap();
+        let backend = Arc::new(MockSigner::new(9));
+        let served: Arc<dyn TxSigner> = backend.clone();
+        let other_uid = unsafe { libc::geteuid() }.wrapping_add(1);
+        tokio::spawn(serve(
+            listener,
+            served,
+            ServeOptions::default().peer_uid(other_uid),
+        ));
+
+        let remote = RemoteSigner::connect(&path).unwrap();
+        assert!(remote.switch_api_key(4).is_err());
+        assert!(backend.calls().is_empty());
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
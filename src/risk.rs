@@ -0,0 +1,772 @@
+//! Local margin, leverage and liquidation estimates.
+//!
+//! [`RiskEngine`] folds the account channels (`account_all`,
+//! `account_market`, `account_positions`, `user_stats`) and `market_stats`
+//! mark prices into cross and isolated margin state, using the margin
+//! fractions from `orderBookDetails`. [`RiskEngine::what_if`] answers what an
+//! order would do to the margin and liquidation price of its position before
+//! it is signed, and every update re-checks maintenance headroom against
+//! [`RiskConfig::warn_headroom`], publishing breaches through
+//! [`RiskEngine::subscribe`].
+//!
+//! The model follows the exchange's rules without fees or funding: an account
+//! (or isolated position) is liquidatable once its value falls to its
+//! maintenance margin, and an order that grows a position needs the value to
+//! cover the initial margin afterwards.
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::sync::watch;
+
+use crate::{
+    account_events::TypedAccountEvent,
+    errors::RiskError,
+    lighter_client::MarginMode,
+    models,
+    signer_client::ISOLATED_MARGIN_MODE,
+    types::MarketId,
+    ws_client::{MarketStats, TradeSide},
+};
+
+/// `orderBookDetails` margin fractions are reported in hundredths of a percent.
+const MARGIN_FRACTION_SCALE: f64 = 10_000.0;
+
+/// Which pool of margin a figure refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MarginScope {
+    Cross,
+    Isolated(MarketId),
+}
+
+/// Margin fractions of a market, as fractions of notional (`0.05` = 5%).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MarketRisk {
+    /// Initial margin for positions without their own leverage setting.
+    pub initial: f64,
+    pub maintenance: f64,
+    pub close_out: f64,
+}
+
+impl MarketRisk {
+    pub fn from_detail(detail: &models::OrderBookDetail) -> Self {
+        Self {
+            initial: detail.default_initial_margin_fraction as f64 / MARGIN_FRACTION_SCALE,
+            maintenance: detail.maintenance_margin_fraction as f64 / MARGIN_FRACTION_SCALE,
+            close_out: detail.closeout_margin_fraction as f64 / MARGIN_FRACTION_SCALE,
+        }
+    }
+}
+
+/// Thresholds for [`RiskEngine`] warnings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskConfig {
+    /// Warn once maintenance headroom drops below this fraction of the
+    /// account (or isolated position) value.
+    pub warn_headroom: f64,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        Self { warn_headroom: 0.2 }
+    }
+}
+
+/// A position as the engine sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionRisk {
+    pub market: MarketId,
+    pub mode: MarginMode,
+    /// Signed base size: positive long, negative short.
+    pub size: f64,
+    pub entry_price: f64,
+    pub mark_price: f64,
+    /// Initial margin fraction chosen for this position, if set.
+    pub initial_margin_fraction: Option<f64>,
+    /// Margin held by an isolated position.
+    pub allocated_margin: f64,
+}
+
+impl PositionRisk {
+    pub fn from_model(position: &models::AccountPosition) -> Self {
+        let size = parse(&position.position) * if position.sign < 0 { -1.0 } else { 1.0 };
+        let value = parse(&position.position_value);
+        let mark_price = if size != 0.0 {
+            value.abs() / size.abs()
+        } else {
+            0.0
+        };
+        // Reported as a percentage.
+        let fraction = parse(&position.initial_margin_fraction) / 100.0;
+        Self {
+            market: MarketId::new(position.market_id),
+            mode: if position.margin_mode == ISOLATED_MARGIN_MODE {
+                MarginMode::Isolated
+            } else {
+                MarginMode::Cross
+            },
+            size,
+            entry_price: parse(&position.avg_entry_price),
+            mark_price,
+            initial_margin_fraction: (fraction > 0.0).then_some(fraction),
+            allocated_margin: parse(&position.allocated_margin),
+        }
+    }
+
+    pub fn notional(&self) -> f64 {
+        self.size.abs() * self.mark_price
+    }
+
+    pub fn unrealized_pnl(&self) -> f64 {
+        self.size * (self.mark_price - self.entry_price)
+    }
+
+    /// The position after trading `delta` (signed) base at `price`, and the
+    /// PnL realized by the part of the fill that closed it.
+    fn after_fill(&self, delta: f64, price: f64) -> (Self, f64) {
+        let closed = if delta.signum() == self.size.signum() {
+            0.0
+        } else {
+            delta.abs().min(self.size.abs())
+        };
+        let realized = (price - self.entry_price) * closed * self.size.signum();
+        let size = self.size + delta;
+        let entry_price = if size == 0.0 {
+            0.0
+        } else if self.size == 0.0 || self.size.signum() != size.signum() {
+            price
+        } else if delta.signum() == self.size.signum() {
+            (self.size * self.entry_price + delta * price) / size
+        } else {
+            self.entry_price
+        };
+        let position = Self {
+            size,
+            entry_price,
+            ..self.clone()
+        };
+        (position, realized)
+    }
+}
+
+/// Margin figures for the cross account or one isolated position.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct MarginSnapshot {
+    /// Collateral plus unrealized PnL.
+    pub account_value: f64,
+    pub notional: f64,
+    pub initial_margin: f64,
+    pub maintenance_margin: f64,
+    pub close_out_margin: f64,
+}
+
+impl MarginSnapshot {
+    pub fn leverage(&self) -> f64 {
+        if self.account_value > 0.0 {
+            self.notional / self.account_value
+        } else if self.notional > 0.0 {
+            f64::INFINITY
+        } else {
+            0.0
+        }
+    }
+
+    /// Value left above the maintenance requirement.
+    pub fn headroom(&self) -> f64 {
+        self.account_value - self.maintenance_margin
+    }
+
+    /// [`headroom`](Self::headroom) as a fraction of the account value;
+    /// `1.0` with nothing at risk, `0.0` or below when liquidatable.
+    pub fn headroom_ratio(&self) -> f64 {
+        if self.maintenance_margin <= 0.0 {
+            1.0
+        } else if self.account_value <= 0.0 {
+            0.0
+        } else {
+            self.headroom() / self.account_value
+        }
+    }
+
+    /// Maintenance requirement over account value; liquidation at `1.0`.
+    pub fn margin_ratio(&self) -> f64 {
+        if self.maintenance_margin <= 0.0 {
+            0.0
+        } else if self.account_value <= 0.0 {
+            f64::INFINITY
+        } else {
+            self.maintenance_margin / self.account_value
+        }
+    }
+
+    pub fn is_liquidatable(&self) -> bool {
+        self.maintenance_margin > 0.0 && self.headroom() <= 0.0
+    }
+
+    fn add(&mut self, position: &PositionRisk, market: &MarketRisk) {
+        let notional = position.notional();
+        let initial = position.initial_margin_fraction.unwrap_or(market.initial);
+        self.account_value += position.unrealized_pnl();
+        self.notional += notional;
+        self.initial_margin += notional * initial;
+        self.maintenance_margin += notional * market.maintenance;
+        self.close_out_margin += notional * market.close_out;
+    }
+}
+
+/// Margin state of the whole account.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct RiskSnapshot {
+    pub cross: MarginSnapshot,
+    pub isolated: BTreeMap<MarketId, MarginSnapshot>,
+    pub positions: Vec<PositionRisk>,
+}
+
+impl RiskSnapshot {
+    pub fn scope(&self, scope: MarginScope) -> Option<&MarginSnapshot> {
+        match scope {
+            MarginScope::Cross => Some(&self.cross),
+            MarginScope::Isolated(market) => self.isolated.get(&market),
+        }
+    }
+}
+
+/// An order to evaluate with [`RiskEngine::what_if`], assumed fully filled.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OrderIntent {
+    pub market: MarketId,
+    pub side: TradeSide,
+    /// Base size, positive.
+    pub size: f64,
+    pub price: f64,
+}
+
+impl OrderIntent {
+    pub fn new(market: MarketId, side: TradeSide, size: f64, price: f64) -> Self {
+        Self {
+            market,
+            side,
+            size,
+            price,
+        }
+    }
+
+    fn signed_size(&self) -> f64 {
+        match self.side {
+            TradeSide::Buy => self.size,
+            TradeSide::Sell => -self.size,
+        }
+    }
+}
+
+/// Effect of an [`OrderIntent`] on the margin backing its position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WhatIf {
+    pub scope: MarginScope,
+    pub before: MarginSnapshot,
+    pub after: MarginSnapshot,
+    pub position_before: f64,
+    pub position_after: f64,
+    pub liquidation_price_before: Option<f64>,
+    pub liquidation_price_after: Option<f64>,
+}
+
+impl WhatIf {
+    /// Whether the order only shrinks the position.
+    pub fn reduces_position(&self) -> bool {
+        self.position_after.abs() < self.position_before.abs()
+            && self.position_after * self.position_before >= 0.0
+    }
+
+    /// Whether the exchange would accept the order's margin: the value
+    /// covers the initial margin afterwards, or the order only reduces.
+    pub fn meets_initial_margin(&self) -> bool {
+        self.reduces_position() || self.after.account_value >= self.after.initial_margin
+    }
+}
+
+/// Maintenance headroom below [`RiskConfig::warn_headroom`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskWarning {
+    pub scope: MarginScope,
+    pub headroom_ratio: f64,
+    pub margin: MarginSnapshot,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    markets: BTreeMap<MarketId, MarketRisk>,
+    marks: BTreeMap<MarketId, f64>,
+    positions: BTreeMap<MarketId, PositionRisk>,
+    cross_collateral: f64,
+}
+
+impl Inner {
+    fn market(&self, market: MarketId) -> Result<&MarketRisk, RiskError> {
+        self.markets
+            .get(&market)
+            .ok_or(RiskError::UnknownMarket(market))
+    }
+
+    fn mark(&self, market: MarketId) -> Option<f64> {
+        self.marks
+            .get(&market)
+            .copied()
+            .or_else(|| self.positions.get(&market).map(|p| p.mark_price))
+            .filter(|mark| *mark > 0.0)
+    }
+
+    fn snapshot(&self) -> Result<RiskSnapshot, RiskError> {
+        let mut snapshot = RiskSnapshot {
+            cross: MarginSnapshot {
+                account_value: self.cross_collateral,
+                ..MarginSnapshot::default()
+            },
+            ..RiskSnapshot::default()
+        };
+        for position in self.positions.values() {
+            let market = self.market(position.market)?;
+            match position.mode {
+                MarginMode::Cross => snapshot.cross.add(position, market),
+                MarginMode::Isolated => {
+                    let mut margin = MarginSnapshot {
+                        account_value: position.allocated_margin,
+                        ..MarginSnapshot::default()
+                    };
+                    margin.add(position, market);
+                    snapshot.isolated.insert(position.market, margin);
+                }
+            }
+            snapshot.positions.push(position.clone());
+        }
+        Ok(snapshot)
+    }
+}
+
+/// Shared account risk tracker. Cheap to clone.
+#[derive(Clone)]
+pub struct RiskEngine {
+    config: RiskConfig,
+    inner: Arc<Mutex<Inner>>,
+    warnings: Arc<watch::Sender<Vec<RiskWarning>>>,
+}
+
+impl std::fmt::Debug for RiskEngine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RiskEngine")
+            .field("config", &self.config)
+            .field("positions", &self.lock().positions.len())
+            .finish()
+    }
+}
+
+impl Default for RiskEngine {
+    fn default() -> Self {
+        Self::new(RiskConfig::default())
+    }
+}
+
+impl RiskEngine {
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            inner: Arc::new(Mutex::new(Inner::default())),
+            warnings: Arc::new(watch::channel(Vec::new()).0),
+        }
+    }
+
+    pub fn config(&self) -> &RiskConfig {
+        &self.config
+    }
+
+    pub fn set_market(&self, market: MarketId, risk: MarketRisk) {
+        self.lock().markets.insert(market, risk);
+        self.check();
+    }
+
+    /// Load margin fractions for every market in an `orderBookDetails` reply.
+    pub fn load_markets(&self, details: &models::OrderBookDetails) {
+        let mut inner = self.lock();
+        for detail in &details.order_book_details {
+            inner.markets.insert(
+                MarketId::new(detail.market_id),
+                MarketRisk::from_detail(detail),
+            );
+        }
+        drop(inner);
+        self.check();
+    }
+
+    /// Replace the whole account state from REST account details.
+    pub fn load_account(&self, account: &models::DetailedAccount) {
+        let mut inner = self.lock();
+        inner.cross_collateral = parse(&account.collateral);
+        inner.positions.clear();
+        for position in &account.positions {
+            upsert_position(&mut inner, position);
+        }
+        drop(inner);
+        self.check();
+    }
+
+    /// Apply positions and collateral carried by a decoded account event.
+    pub fn apply(&self, event: &TypedAccountEvent) {
+        let mut inner = self.lock();
+        match event {
+            TypedAccountEvent::AccountAll(update) => {
+                update
+                    .positions
+                    .values()
+                    .for_each(|position| upsert_position(&mut inner, position));
+            }
+            TypedAccountEvent::AccountMarket(update) => {
+                if let Some(position) = &update.position {
+                    upsert_position(&mut inner, position);
+                }
+            }
+            TypedAccountEvent::AllPositions(update)
+            | TypedAccountEvent::MarketPositions(update) => {
+                update
+                    .positions
+                    .values()
+                    .for_each(|position| upsert_position(&mut inner, position));
+            }
+            TypedAccountEvent::UserStats(update) => {
+                inner.cross_collateral = parse(&update.stats.cross_stats.collateral);
+            }
+            _ => return,
+        }
+        drop(inner);
+        self.check();
+    }
+
+    /// Track the mark price from a `market_stats` update.
+    pub fn on_market_stats(&self, stats: &MarketStats) {
+        let mark = parse(&stats.mark_price);
+        if mark <= 0.0 {
+            return;
+        }
+        let market = MarketId::new(stats.market_id as i32);
+        self.set_mark_price(market, mark);
+    }
+
+    pub fn set_mark_price(&self, market: MarketId, mark: f64) {
+        let mut inner = self.lock();
+        inner.marks.insert(market, mark);
+        if let Some(position) = inner.positions.get_mut(&market) {
+            position.mark_price = mark;
+        }
+        drop(inner);
+        self.check();
+    }
+
+    /// Latest mark price, falling back to the position's reported value.
+    pub fn mark_price(&self, market: MarketId) -> Option<f64> {
+        self.lock().mark(market)
+    }
+
+    pub fn position(&self, market: MarketId) -> Option<PositionRisk> {
+        self.lock().positions.get(&market).cloned()
+    }
+
+    /// Current margin state. Fails if a position's market has no margin
+    /// fractions loaded.
+    pub fn snapshot(&self) -> Result<RiskSnapshot, RiskError> {
+        self.lock().snapshot()
+    }
+
+    /// Estimated liquidation price of the position in `market`, holding every
+    /// other price still. `None` when flat or when no positive price would
+    /// liquidate it.
+    pub fn liquidation_price(&self, market: MarketId) -> Result<Option<f64>, RiskError> {
+        let inner = self.lock();
+        let Some(position) = inner.positions.get(&market) else {
+            return Ok(None);
+        };
+        let snapshot = inner.snapshot()?;
+        let scope = scope_of(position);
+        let margin = snapshot.scope(scope).copied().unwrap_or_default();
+        Ok(liquidation_price(position, inner.market(market)?, &margin))
+    }
+
+    /// What `order` would do to the margin and liquidation price of its
+    /// position if it filled completely at its price.
+    ///
+    /// Growing an isolated position moves the added initial margin from
+    /// cross collateral into the position, as the exchange does. PnL
+    /// realized by reducing or flipping it is credited to the margin backing
+    /// the position.
+    pub fn what_if(&self, order: &OrderIntent) -> Result<WhatIf, RiskError> {
+        if !(order.size > 0.0 && order.price > 0.0) {
+            return Err(RiskError::InvalidOrder(
+                "size and price must be positive".into(),
+            ));
+        }
+        let mut inner = self.lock();
+        let market = *inner.market(order.market)?;
+        let mark = inner
+            .mark(order.market)
+            .ok_or(RiskError::NoMarkPrice(order.market))?;
+
+        let before_position = inner
+            .positions
+            .get(&order.market)
+            .cloned()
+            .unwrap_or(PositionRisk {
+                market: order.market,
+                mode: MarginMode::Cross,
+                size: 0.0,
+                entry_price: 0.0,
+                mark_price: mark,
+                initial_margin_fraction: None,
+                allocated_margin: 0.0,
+            });
+        let scope = scope_of(&before_position);
+        let before = inner.snapshot()?;
+
+        let delta = order.signed_size();
+        let (mut after_position, realized) = before_position.after_fill(delta, order.price);
+        after_position.mark_price = mark;
+        let added = (after_position.size.abs() - before_position.size.abs()).max(0.0);
+        let mut cross_collateral = inner.cross_collateral;
+        if before_position.mode == MarginMode::Isolated {
+            after_position.allocated_margin += realized;
+            let transfer = added
+                * order.price
+                * before_position
+                    .initial_margin_fraction
+                    .unwrap_or(market.initial);
+            after_position.allocated_margin += transfer;
+            cross_collateral -= transfer;
+        } else {
+            cross_collateral += realized;
+        }
+
+        let saved = (
+            inner.positions.insert(order.market, after_position.clone()),
+            std::mem::replace(&mut inner.cross_collateral, cross_collateral),
+        );
+        let after = inner.snapshot();
+        match saved.0 {
+            Some(position) => inner.positions.insert(order.market, position),
+            None => inner.positions.remove(&order.market),
+        };
+        inner.cross_collateral = saved.1;
+        let after = after?;
+
+        let margin_before = before.scope(scope).copied().unwrap_or_default();
+        let margin_after = after.scope(scope).copied().unwrap_or_default();
+        Ok(WhatIf {
+            scope,
+            before: margin_before,
+            after: margin_after,
+            position_before: before_position.size,
+            position_after: after_position.size,
+            liquidation_price_before: liquidation_price(&before_position, &market, &margin_before),
+            liquidation_price_after: liquidation_price(&after_position, &market, &margin_after),
+        })
+    }
+
+    /// Breaches found by the latest update.
+    pub fn warnings(&self) -> Vec<RiskWarning> {
+        self.warnings.borrow().clone()
+    }
+
+    /// Receive the list of breaches whenever it changes.
+    pub fn subscribe(&self) -> watch::Receiver<Vec<RiskWarning>> {
+        self.warnings.subscribe()
+    }
+
+    fn check(&self) {
+        let Ok(snapshot) = self.snapshot() else {
+            return;
+        };
+        let scopes = std::iter::once((MarginScope::Cross, snapshot.cross)).chain(
+            snapshot
+                .isolated
+                .iter()
+                .map(|(market, margin)| (MarginScope::Isolated(*market), *margin)),
+        );
+        let warnings: Vec<RiskWarning> = scopes
+            .filter(|(_, margin)| margin.maintenance_margin > 0.0)
+            .map(|(scope, margin)| RiskWarning {
+                scope,
+                headroom_ratio: margin.headroom_ratio(),
+                margin,
+            })
+            .filter(|warning| warning.headroom_ratio < self.config.warn_headroom)
+            .collect();
+
+        self.warnings.send_if_modified(|current| {
+            for warning in &warnings {
+                if !current.iter().any(|known| known.scope == warning.scope) {
+                    tracing::warn!(
+                        scope = ?warning.scope,
+                        headroom_ratio = warning.headroom_ratio,
+                        account_value = warning.margin.account_value,
+                        maintenance_margin = warning.margin.maintenance_margin,
+                        "maintenance margin headroom below threshold"
+                    );
+                }
+            }
+            if *current == warnings {
+                return false;
+            }
+            *current = warnings;
+            true
+        });
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().expect("risk engine lock poisoned")
+    }
+}
+
+fn upsert_position(inner: &mut Inner, position: &models::AccountPosition) {
+    let mut position = PositionRisk::from_model(position);
+    if position.size == 0.0 {
+        inner.positions.remove(&position.market);
+        return;
+    }
+    if let Some(mark) = inner.marks.get(&position.market) {
+        position.mark_price = *mark;
+    }
+    inner.positions.insert(position.market, position);
+}
+
+fn scope_of(position: &PositionRisk) -> MarginScope {
+    match position.mode {
+        MarginMode::Cross => MarginScope::Cross,
+        MarginMode::Isolated => MarginScope::Isolated(position.market),
+    }
+}
+
+/// Price at which `margin`'s value meets its maintenance requirement when
+/// only `position`'s mark moves.
+fn liquidation_price(
+    position: &PositionRisk,
+    market: &MarketRisk,
+    margin: &MarginSnapshot,
+) -> Option<f64> {
+    let size = position.size;
+    if size == 0.0 {
+        return None;
+    }
+    // value(p) = value + size (p - mark); maintenance(p) grows by
+    // |size| mmf (p - mark).
+    let slope = size - size.abs() * market.maintenance;
+    if slope == 0.0 {
+        return None;
+    }
+    let price = position.mark_price + (margin.maintenance_margin - margin.account_value) / slope;
+    (price > 0.0).then_some(price)
+}
+
+fn parse(value: &str) -> f64 {
+    value.trim().parse().unwrap_or(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market() -> MarketRisk {
+        MarketRisk {
+            initial: 0.1,
+            maintenance: 0.05,
+            close_out: 0.025,
+        }
+    }
+
+    /// Cross long of 10 at 100 on `collateral`.
+    fn engine_with_long(collateral: f64) -> RiskEngine {
+        let engine = RiskEngine::new(RiskConfig { warn_headroom: 0.5 });
+        engine.set_market(MarketId::new(1), market());
+        let mut inner = engine.lock();
+        inner.cross_collateral = collateral;
+        inner.positions.insert(
+            MarketId::new(1),
+            PositionRisk {
+                market: MarketId::new(1),
+                mode: MarginMode::Cross,
+                size: 10.0,
+                entry_price: 100.0,
+                mark_price: 100.0,
+                initial_margin_fraction: None,
+                allocated_margin: 0.0,
+            },
+        );
+        drop(inner);
+        engine
+    }
+
+    #[test]
+    fn cross_liquidation_price_and_headroom_warning() {
+        // Fully collateralised: no positive price liquidates it.
+        assert_eq!(
+            engine_with_long(1_000.0)
+                .liquidation_price(MarketId::new(1))
+                .unwrap(),
+            None
+        );
+
+        let engine = engine_with_long(100.0);
+        let snapshot = engine.snapshot().unwrap();
+        assert_eq!(snapshot.cross.maintenance_margin, 50.0);
+        assert_eq!(snapshot.cross.leverage(), 10.0);
+        assert_eq!(snapshot.cross.headroom_ratio(), 0.5);
+        assert!(engine.warnings().is_empty());
+
+        // 100 + 10 (p - 100) = 0.5 p  =>  p = 900 / 9.5.
+        let liquidation = engine.liquidation_price(MarketId::new(1)).unwrap().unwrap();
+        assert!((liquidation - 900.0 / 9.5).abs() < 1e-9);
+
+        let mut warnings = engine.subscribe();
+        engine.set_mark_price(MarketId::new(1), 99.0);
+        assert!(warnings.has_changed().unwrap());
+        let current = warnings.borrow_and_update().clone();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].scope, MarginScope::Cross);
+        assert!((current[0].headroom_ratio - 40.5 / 90.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn what_if_reports_margin_and_liquidation_effect() {
+        let engine = engine_with_long(1_000.0);
+        let order = OrderIntent::new(MarketId::new(1), TradeSide::Buy, 90.0, 100.0);
+        let what_if = engine.what_if(&order).unwrap();
+        assert_eq!(what_if.position_after, 100.0);
+        assert_eq!(what_if.after.notional, 10_000.0);
+        assert_eq!(what_if.after.initial_margin, 1_000.0);
+        assert!(what_if.meets_initial_margin());
+        // 1000 + 100 (p - 100) = 5 p  =>  p = 9000 / 95.
+        let liquidation = what_if.liquidation_price_after.unwrap();
+        assert!((liquidation - 9_000.0 / 95.0).abs() < 1e-9);
+
+        let too_big = OrderIntent::new(MarketId::new(1), TradeSide::Buy, 100.0, 100.0);
+        assert!(!engine.what_if(&too_big).unwrap().meets_initial_margin());
+        let reduce = OrderIntent::new(MarketId::new(1), TradeSide::Sell, 5.0, 100.0);
+        assert!(engine.what_if(&reduce).unwrap().reduces_position());
+
+        // The engine's own state is untouched.
+        assert_eq!(engine.position(MarketId::new(1)).unwrap().size, 10.0);
+    }
+
+    #[test]
+    fn reducing_credits_realized_pnl() {
+        let engine = engine_with_long(1_000.0);
+        engine.set_mark_price(MarketId::new(1), 110.0);
+        let reduce = OrderIntent::new(MarketId::new(1), TradeSide::Sell, 5.0, 110.0);
+        let what_if = engine.what_if(&reduce).unwrap();
+        // 50 realized plus 50 still unrealized: the value is unchanged.
+        assert_eq!(what_if.before.account_value, 1_100.0);
+        assert_eq!(what_if.after.account_value, 1_100.0);
+
+        let flip = OrderIntent::new(MarketId::new(1), TradeSide::Sell, 15.0, 110.0);
+        let what_if = engine.what_if(&flip).unwrap();
+        assert_eq!(what_if.position_after, -5.0);
+        assert_eq!(what_if.after.account_value, 1_100.0);
+    }
+}
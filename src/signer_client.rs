@@ -14,14 +14,20 @@ use tokio::sync::Mutex;
 
 use crate::{
     apis::{self, configuration, order_api, transaction_api},
+    batch_outcome::BatchOutcome,
     errors::{Result, SignerClientError},
     models,
     nonce_manager::{self, NonceManager, NonceManagerType},
+    pre_trade::{PreTradeAction, PreTradeChain},
+    rate_limit::{self, Failure, RateLimiter, RequestClass, RequestPolicy, RestRetryPolicy},
     signer::SignerLibrary,
     timings, transactions,
+    tx_signer::{SignRequest, TxSigner},
+    ws_client::TradeSide,
 };
 
-const CODE_OK: i32 = 200;
+pub(crate) const CODE_OK: i32 = 200;
+pub(crate) const CODE_INVALID_NONCE: i32 = 21104;
 
 const TX_TYPE_CHANGE_PUB_KEY: i32 = 8;
 const TX_TYPE_CREATE_SUB_ACCOUNT: i32 = 9;
@@ -49,6 +55,12 @@ const ORDER_TIME_IN_FORCE_IMMEDIATE_OR_CANCEL: i32 = 0;
 const ORDER_TIME_IN_FORCE_GOOD_TILL_TIME: i32 = 1;
 const ORDER_TIME_IN_FORCE_POST_ONLY: i32 = 2;
 
+const CROSS_MARGIN_MODE: i32 = 0;
+pub(crate) const ISOLATED_MARGIN_MODE: i32 = 1;
+
+const ISOLATED_MARGIN_REMOVE_COLLATERAL: i32 = 0;
+const ISOLATED_MARGIN_ADD_COLLATERAL: i32 = 1;
+
 const DEFAULT_28_DAY_ORDER_EXPIRY: i64 = -1;
 const DEFAULT_IOC_EXPIRY: i64 = 0;
 const DEFAULT_10_MIN_AUTH_EXPIRY: i64 = -1;
@@ -58,12 +70,22 @@ const USDC_TICKER_SCALE: f64 = 1_000_000.0;
 const MINUTE: i64 = 60;
 
 pub struct SignerClient {
-    signer: SignerLibrary,
+    signer: Arc<dyn TxSigner>,
     configuration: configuration::Configuration,
     start_api_key_index: i32,
     end_api_key_index: i32,
     account_index: i64,
     nonce_manager: Arc<Mutex<Box<dyn NonceManager>>>,
+    policy: RequestPolicy,
+    pre_trade: PreTradeChain,
+}
+
+/// What to do after a failed submission.
+enum Resend {
+    Again,
+    /// The lookup found the tx; the failure was only in the response.
+    Accepted(String),
+    No,
 }
 
 struct SigningContext {
@@ -198,11 +220,65 @@ impl SignerClient {
             }
         }
 
+        Self::from_parts(
+            Arc::new(signer),
+            configuration,
+            api_key_index,
+            end_api_key_index,
+            account_index,
+            nonce_management_type,
+        )
+        .await
+    }
+
+    /// Build a client around an already configured signing backend.
+    ///
+    /// No private keys are handled here: each API key in range must already be
+    /// loaded by `signer`, which is verified with `check_client`.
+    pub async fn with_signer(
+        url: impl Into<String>,
+        signer: Arc<dyn TxSigner>,
+        api_key_index: i32,
+        account_index: i64,
+        max_api_key_index: Option<i32>,
+        nonce_management_type: NonceManagerType,
+    ) -> Result<Self> {
+        let end_api_key_index = max_api_key_index.unwrap_or(api_key_index);
+        for idx in api_key_index..=end_api_key_index {
+            if let Some(err) = signer.check_client(idx, account_index)? {
+                return Err(SignerClientError::Signer(format!("api key {idx}: {err}")));
+            }
+        }
+
+        let configuration = configuration::Configuration {
+            base_path: url.into(),
+            ..Default::default()
+        };
+
+        Self::from_parts(
+            signer,
+            configuration,
+            api_key_index,
+            end_api_key_index,
+            account_index,
+            nonce_management_type,
+        )
+        .await
+    }
+
+    async fn from_parts(
+        signer: Arc<dyn TxSigner>,
+        configuration: configuration::Configuration,
+        start_api_key_index: i32,
+        end_api_key_index: i32,
+        account_index: i64,
+        nonce_management_type: NonceManagerType,
+    ) -> Result<Self> {
         let nonce_manager = nonce_manager::nonce_manager_factory(
             nonce_management_type,
             configuration.clone(),
             account_index,
-            api_key_index,
+            start_api_key_index,
             Some(end_api_key_index),
         )
         .await?;
@@ -210,13 +286,50 @@ impl SignerClient {
         Ok(Self {
             signer,
             configuration,
-            start_api_key_index: api_key_index,
+            start_api_key_index,
             end_api_key_index,
             account_index,
             nonce_manager: Arc::new(Mutex::new(nonce_manager)),
+            policy: RequestPolicy::default(),
+            pre_trade: PreTradeChain::default(),
         })
     }
 
+    /// Run `chain` on every order before it is signed.
+    pub fn with_pre_trade(mut self, chain: PreTradeChain) -> Self {
+        self.pre_trade = chain;
+        self
+    }
+
+    pub fn pre_trade(&self) -> &PreTradeChain {
+        &self.pre_trade
+    }
+
+    /// Draw `sendTx` and `sendTxBatch` calls from `limiter`'s send budget.
+    pub fn with_rate_limit(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.policy.limiter = Some(limiter);
+        self
+    }
+
+    /// Retry rate limited submissions, and submissions that failed in
+    /// transit once a lookup of their tx hash shows they were not accepted.
+    pub fn with_retry_policy(mut self, policy: RestRetryPolicy) -> Self {
+        self.policy.retry = Some(policy);
+        self
+    }
+
+    pub(crate) fn set_request_policy(&mut self, policy: RequestPolicy) {
+        self.policy = policy;
+    }
+
+    /// Signing backend used for every transaction. Orders signed on it
+    /// directly bypass the pre-trade chain; they are checked only when sent
+    /// through this client or a websocket connection built from a
+    /// `LighterClient`.
+    pub fn unchecked_signer(&self) -> &Arc<dyn TxSigner> {
+        &self.signer
+    }
+
     pub fn configuration(&self) -> configuration::Configuration {
         self.configuration.clone()
     }
@@ -257,6 +370,22 @@ impl SignerClient {
         ORDER_TIME_IN_FORCE_IMMEDIATE_OR_CANCEL
     }
 
+    pub fn margin_mode_cross(&self) -> i32 {
+        CROSS_MARGIN_MODE
+    }
+
+    pub fn margin_mode_isolated(&self) -> i32 {
+        ISOLATED_MARGIN_MODE
+    }
+
+    pub fn margin_direction_add(&self) -> i32 {
+        ISOLATED_MARGIN_ADD_COLLATERAL
+    }
+
+    pub fn margin_direction_remove(&self) -> i32 {
+        ISOLATED_MARGIN_REMOVE_COLLATERAL
+    }
+
     pub async fn check_client(&self) -> Result<Option<String>> {
         self.signer
             .check_client(self.start_api_key_index, self.account_index)
@@ -352,6 +481,14 @@ impl SignerClient {
         nonce: Option<i64>,
         api_key_index: Option<i32>,
     ) -> Result<(transactions::CreateOrder, models::RespSendTx)> {
+        self.check_create_order(
+            market_index,
+            base_amount,
+            price,
+            is_ask,
+            reduce_only,
+            trigger_price,
+        )?;
         let context = self.prepare_context(api_key_index, nonce, true).await?;
 
         let signed = timings::time_block("sign_create_order", || {
@@ -395,6 +532,14 @@ impl SignerClient {
         nonce: Option<i64>,
         api_key_index: Option<i32>,
     ) -> Result<SignedPayload<transactions::CreateOrder>> {
+        self.check_create_order(
+            market_index,
+            base_amount,
+            price,
+            is_ask,
+            reduce_only,
+            trigger_price,
+        )?;
         let context = self.prepare_context(api_key_index, nonce, false).await?;
         self.sign_create_order_with_context(
             market_index,
@@ -537,9 +682,8 @@ impl SignerClient {
         tx_info: &str,
         price_protection: Option<bool>,
     ) -> Result<models::RespSendTx> {
-        match transaction_api::send_tx(&self.configuration, tx_type, tx_info, price_protection)
-            .await
-        {
+        self.check_signed_tx(tx_type, tx_info)?;
+        match self.post_tx(tx_type, tx_info, price_protection).await {
             Ok(response) => Ok(response),
             Err(err) => match classify_send_tx_error(err) {
                 TxSendError::InvalidNonce(message) => Err(SignerClientError::Nonce(message)),
@@ -563,18 +707,86 @@ impl SignerClient {
                 "no transactions provided for batch submission".into(),
             ));
         }
+        self.check_batch(entries)?;
 
-        let tx_types: Vec<i32> = entries.iter().map(|entry| entry.tx_type).collect();
-        let tx_infos: Vec<&str> = entries.iter().map(|entry| entry.tx_info.as_str()).collect();
-        let types_json = serde_json::to_string(&tx_types)?;
-        let infos_json = serde_json::to_string(&tx_infos)?;
-
-        match transaction_api::send_tx_batch(&self.configuration, &types_json, &infos_json).await {
+        let (types_json, infos_json) = batch_params(entries)?;
+        match self.post_tx_batch(&types_json, &infos_json).await {
             Ok(response) => Ok(response),
             Err(err) => Err(map_api_error(err)),
         }
     }
 
+    /// Submit a batch and report one outcome per entry. A timed-out request
+    /// may still have reached the exchange, so its entries are left
+    /// [`TxOutcome::Unknown`](crate::batch_outcome::TxOutcome::Unknown) for
+    /// `account_tx` reconciliation instead of failing the call.
+    /// A `400` carrying a result code rejects every entry with that code.
+    pub async fn send_tx_batch_outcome(&self, entries: &[BatchEntry]) -> Result<BatchOutcome> {
+        if entries.is_empty() {
+            return Ok(BatchOutcome::default());
+        }
+        self.check_batch(entries)?;
+        let (types_json, infos_json) = batch_params(entries)?;
+        let mut outcome = BatchOutcome::from_entries(entries);
+        match self.post_tx_batch(&types_json, &infos_json).await {
+            Ok(response) => outcome.apply_response(&response),
+            Err(apis::Error::Reqwest(err)) if err.is_timeout() => {}
+            Err(apis::Error::ResponseError(apis::ResponseContent {
+                entity: Some(transaction_api::SendTxBatchError::Status400(result)),
+                ..
+            })) => outcome.apply_reply(
+                0..entries.len(),
+                result.code,
+                result.message.as_deref(),
+                &[],
+            ),
+            Err(err) => return Err(map_api_error(err)),
+        }
+        Ok(outcome)
+    }
+
+    /// Sign a captured request under `api_key_index`, keeping its nonce.
+    pub async fn sign_request(
+        &self,
+        request: &SignRequest,
+        api_key_index: i32,
+    ) -> Result<BatchEntry> {
+        let tx_type = request.tx_type().ok_or_else(|| {
+            SignerClientError::InvalidInput(format!("{} is not a transaction", request.method()))
+        })?;
+        match *request {
+            SignRequest::SignCreateOrder {
+                market_index,
+                base_amount,
+                price,
+                is_ask,
+                reduce_only,
+                trigger_price,
+                ..
+            } => self.check_create_order(
+                market_index,
+                base_amount,
+                price,
+                is_ask,
+                reduce_only,
+                trigger_price,
+            )?,
+            SignRequest::SignModifyOrder {
+                market_index,
+                base_amount,
+                price,
+                trigger_price,
+                ..
+            } => self.check_modify_order(market_index, base_amount, price, trigger_price)?,
+            _ => {}
+        }
+        self.prepare_context(Some(api_key_index), request.nonce(), false)
+            .await?;
+        let reply = request.dispatch(self.signer.as_ref())?;
+        let tx_info = parse_sign_output(reply.value, reply.error, request.method())?;
+        Ok(BatchEntry::new(i32::from(tx_type), tx_info))
+    }
+
     pub async fn send_signed_batch<T>(
         &self,
         payloads: &[SignedPayload<T>],
@@ -597,6 +809,13 @@ impl SignerClient {
         Ok(())
     }
 
+    /// Return nonces `first..=last` of a key when nothing later was issued;
+    /// see [`NonceManager::release`].
+    pub async fn release_nonces(&self, api_key_index: i32, first: i64, last: i64) -> bool {
+        let mut manager = self.nonce_manager.lock().await;
+        manager.release(api_key_index, first, last)
+    }
+
     pub async fn create_market_order(
         &self,
         market_index: i32,
@@ -926,22 +1145,74 @@ impl SignerClient {
         nonce: Option<i64>,
         api_key_index: Option<i32>,
     ) -> Result<(String, models::RespSendTx)> {
+        self.check_modify_order(market_index, base_amount, price, trigger_price)?;
         let context = self.prepare_context(api_key_index, nonce, true).await?;
-        let (tx_info, error) = self.signer.sign_modify_order(
+        let tx_info = self.modify_order_tx_info(
             market_index,
             order_index,
             base_amount,
             price,
             trigger_price,
-            context.nonce,
+            &context,
         )?;
-        let tx_info = parse_sign_output(tx_info, error, "sign_modify_order")?;
         let response = self
             .submit_signed_tx(&context, TX_TYPE_MODIFY_ORDER, &tx_info, None)
             .await?;
         Ok((tx_info, response))
     }
 
+    /// [`modify_order`](Self::modify_order) returning the decoded transaction.
+    pub async fn modify_order_typed(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::ModifyOrder, models::RespSendTx)> {
+        self.check_modify_order(market_index, base_amount, price, trigger_price)?;
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let tx_info = self.modify_order_tx_info(
+            market_index,
+            order_index,
+            base_amount,
+            price,
+            trigger_price,
+            &context,
+        )?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_MODIFY_ORDER,
+            tx_info,
+            transactions::ModifyOrder::from_json_str,
+        )
+        .await
+    }
+
+    pub async fn sign_modify_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<SignedPayload<transactions::ModifyOrder>> {
+        self.check_modify_order(market_index, base_amount, price, trigger_price)?;
+        let context = self.prepare_context(api_key_index, nonce, false).await?;
+        self.sign_modify_order_with_context(
+            market_index,
+            order_index,
+            base_amount,
+            price,
+            trigger_price,
+            &context,
+        )
+    }
+
     pub async fn withdraw(
         &self,
         usdc_amount: f64,
@@ -970,6 +1241,25 @@ impl SignerClient {
         Ok((tx_info, response))
     }
 
+    /// [`create_sub_account`](Self::create_sub_account) returning the decoded
+    /// transaction, optionally signed with a specific API key.
+    pub async fn create_sub_account_typed(
+        &self,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::CreateSubAccount, models::RespSendTx)> {
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let (tx_info, error) = self.signer.sign_create_sub_account(context.nonce)?;
+        let tx_info = parse_sign_output(tx_info, error, "sign_create_sub_account")?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_CREATE_SUB_ACCOUNT,
+            tx_info,
+            transactions::CreateSubAccount::from_json_str,
+        )
+        .await
+    }
+
     pub async fn transfer(
         &self,
         eth_private_key: &str,
@@ -981,27 +1271,49 @@ impl SignerClient {
         api_key_index: Option<i32>,
     ) -> Result<(String, models::RespSendTx)> {
         let context = self.prepare_context(api_key_index, nonce, true).await?;
-        let scaled_amount = (usdc_amount * USDC_TICKER_SCALE) as i64;
-        let (tx_info, error) =
-            self.signer
-                .sign_transfer(to_account_index, scaled_amount, fee, memo, context.nonce)?;
-        let tx_info = parse_sign_output(tx_info, error, "sign_transfer")?;
-
-        let mut payload: Map<String, Value> = serde_json::from_str(&tx_info)?;
-        let msg = payload
-            .remove("MessageToSign")
-            .ok_or(SignerClientError::InvalidResponse)?;
-        let message = msg.as_str().ok_or(SignerClientError::InvalidResponse)?;
-        let signature = sign_personal_message(eth_private_key, message)?;
-        payload.insert("L1Sig".into(), Value::String(signature));
-
-        let payload_string = serde_json::to_string(&payload)?;
+        let payload_string = self.transfer_tx_info(
+            eth_private_key,
+            to_account_index,
+            usdc_amount,
+            fee,
+            memo,
+            &context,
+        )?;
         let response = self
             .submit_signed_tx(&context, TX_TYPE_TRANSFER, &payload_string, None)
             .await?;
         Ok((payload_string, response))
     }
 
+    /// [`transfer`](Self::transfer) returning the decoded transaction.
+    pub async fn transfer_typed(
+        &self,
+        eth_private_key: &str,
+        to_account_index: i64,
+        usdc_amount: f64,
+        fee: i64,
+        memo: &str,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::Transfer, models::RespSendTx)> {
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let payload_string = self.transfer_tx_info(
+            eth_private_key,
+            to_account_index,
+            usdc_amount,
+            fee,
+            memo,
+            &context,
+        )?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_TRANSFER,
+            payload_string,
+            transactions::Transfer::from_json_str,
+        )
+        .await
+    }
+
     pub async fn create_public_pool(
         &self,
         operator_fee: i64,
@@ -1066,6 +1378,28 @@ impl SignerClient {
         Ok((tx_info, response))
     }
 
+    /// [`mint_shares`](Self::mint_shares) returning the decoded transaction.
+    pub async fn mint_shares_typed(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::MintShares, models::RespSendTx)> {
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let (tx_info, error) =
+            self.signer
+                .sign_mint_shares(public_pool_index, share_amount, context.nonce)?;
+        let tx_info = parse_sign_output(tx_info, error, "sign_mint_shares")?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_MINT_SHARES,
+            tx_info,
+            transactions::MintShares::from_json_str,
+        )
+        .await
+    }
+
     pub async fn burn_shares(
         &self,
         public_pool_index: i64,
@@ -1084,6 +1418,28 @@ impl SignerClient {
         Ok((tx_info, response))
     }
 
+    /// [`burn_shares`](Self::burn_shares) returning the decoded transaction.
+    pub async fn burn_shares_typed(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::BurnShares, models::RespSendTx)> {
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let (tx_info, error) =
+            self.signer
+                .sign_burn_shares(public_pool_index, share_amount, context.nonce)?;
+        let tx_info = parse_sign_output(tx_info, error, "sign_burn_shares")?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_BURN_SHARES,
+            tx_info,
+            transactions::BurnShares::from_json_str,
+        )
+        .await
+    }
+
     pub async fn update_leverage(
         &self,
         market_index: i32,
@@ -1093,17 +1449,35 @@ impl SignerClient {
         api_key_index: Option<i32>,
     ) -> Result<(String, models::RespSendTx)> {
         let context = self.prepare_context(api_key_index, nonce, true).await?;
-        let fraction = (10_000f64 / leverage as f64).round() as i32;
-        let (tx_info, error) =
-            self.signer
-                .sign_update_leverage(market_index, fraction, margin_mode, context.nonce)?;
-        let tx_info = parse_sign_output(tx_info, error, "sign_update_leverage")?;
+        let tx_info =
+            self.update_leverage_tx_info(market_index, margin_mode, leverage, &context)?;
         let response = self
             .submit_signed_tx(&context, TX_TYPE_UPDATE_LEVERAGE, &tx_info, None)
             .await?;
         Ok((tx_info, response))
     }
 
+    /// [`update_leverage`](Self::update_leverage) returning the decoded transaction.
+    pub async fn update_leverage_typed(
+        &self,
+        market_index: i32,
+        margin_mode: i32,
+        leverage: i32,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::UpdateLeverage, models::RespSendTx)> {
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let tx_info =
+            self.update_leverage_tx_info(market_index, margin_mode, leverage, &context)?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_UPDATE_LEVERAGE,
+            tx_info,
+            transactions::UpdateLeverage::from_json_str,
+        )
+        .await
+    }
+
     pub async fn update_margin(
         &self,
         market_index: i32,
@@ -1113,20 +1487,95 @@ impl SignerClient {
         api_key_index: Option<i32>,
     ) -> Result<(String, models::RespSendTx)> {
         let context = self.prepare_context(api_key_index, nonce, true).await?;
-        let scaled_amount = (usdc_amount * USDC_TICKER_SCALE) as i64;
-        let (tx_info, error) = self.signer.sign_update_margin(
-            market_index,
-            scaled_amount,
-            direction,
-            context.nonce,
-        )?;
-        let tx_info = parse_sign_output(tx_info, error, "sign_update_margin")?;
+        let tx_info = self.update_margin_tx_info(market_index, usdc_amount, direction, &context)?;
         let response = self
             .submit_signed_tx(&context, TX_TYPE_UPDATE_MARGIN, &tx_info, None)
             .await?;
         Ok((tx_info, response))
     }
 
+    /// [`update_margin`](Self::update_margin) returning the decoded transaction.
+    pub async fn update_margin_typed(
+        &self,
+        market_index: i32,
+        usdc_amount: f64,
+        direction: i32,
+        nonce: Option<i64>,
+        api_key_index: Option<i32>,
+    ) -> Result<(transactions::UpdateMargin, models::RespSendTx)> {
+        let context = self.prepare_context(api_key_index, nonce, true).await?;
+        let tx_info = self.update_margin_tx_info(market_index, usdc_amount, direction, &context)?;
+        self.submit_typed_tx(
+            &context,
+            TX_TYPE_UPDATE_MARGIN,
+            tx_info,
+            transactions::UpdateMargin::from_json_str,
+        )
+        .await
+    }
+
+    fn check_create_order(
+        &self,
+        market_index: i32,
+        base_amount: i64,
+        price: i32,
+        is_ask: bool,
+        reduce_only: bool,
+        trigger_price: i32,
+    ) -> Result<()> {
+        let side = if is_ask {
+            TradeSide::Sell
+        } else {
+            TradeSide::Buy
+        };
+        self.pre_trade.evaluate(
+            market_index.into(),
+            PreTradeAction::Create,
+            Some(side),
+            base_amount,
+            i64::from(price),
+            i64::from(trigger_price),
+            reduce_only,
+        )?;
+        Ok(())
+    }
+
+    fn check_modify_order(
+        &self,
+        market_index: i32,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+    ) -> Result<()> {
+        self.pre_trade.evaluate(
+            market_index.into(),
+            PreTradeAction::Modify,
+            None,
+            base_amount,
+            price,
+            trigger_price,
+            false,
+        )?;
+        Ok(())
+    }
+
+    /// Run the pre-trade chain on an order signed before it reached us.
+    fn check_signed_tx(&self, tx_type: i32, tx_info: &str) -> Result<()> {
+        if self.pre_trade.is_empty() {
+            return Ok(());
+        }
+        let tx_info = serde_json::from_str(tx_info).unwrap_or(Value::Null);
+        self.pre_trade.evaluate_tx(tx_type, &tx_info)?;
+        Ok(())
+    }
+
+    /// A batch is rejected whole if any entry fails the chain.
+    fn check_batch(&self, entries: &[BatchEntry]) -> Result<()> {
+        entries
+            .iter()
+            .try_for_each(|entry| self.check_signed_tx(entry.tx_type, &entry.tx_info))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn sign_create_order_with_context(
         &self,
@@ -1174,6 +1623,104 @@ impl SignerClient {
         Ok(SignedPayload::new(TX_TYPE_CANCEL_ORDER, tx_info, parsed))
     }
 
+    fn sign_modify_order_with_context(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        context: &SigningContext,
+    ) -> Result<SignedPayload<transactions::ModifyOrder>> {
+        let tx_info = self.modify_order_tx_info(
+            market_index,
+            order_index,
+            base_amount,
+            price,
+            trigger_price,
+            context,
+        )?;
+        let parsed = transactions::ModifyOrder::from_json_str(&tx_info)?;
+        Ok(SignedPayload::new(TX_TYPE_MODIFY_ORDER, tx_info, parsed))
+    }
+
+    fn modify_order_tx_info(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        context: &SigningContext,
+    ) -> Result<String> {
+        let (tx_info, error) = self.signer.sign_modify_order(
+            market_index,
+            order_index,
+            base_amount,
+            price,
+            trigger_price,
+            context.nonce,
+        )?;
+        parse_sign_output(tx_info, error, "sign_modify_order")
+    }
+
+    fn transfer_tx_info(
+        &self,
+        eth_private_key: &str,
+        to_account_index: i64,
+        usdc_amount: f64,
+        fee: i64,
+        memo: &str,
+        context: &SigningContext,
+    ) -> Result<String> {
+        let scaled_amount = (usdc_amount * USDC_TICKER_SCALE) as i64;
+        let (tx_info, error) =
+            self.signer
+                .sign_transfer(to_account_index, scaled_amount, fee, memo, context.nonce)?;
+        let tx_info = parse_sign_output(tx_info, error, "sign_transfer")?;
+
+        let mut payload: Map<String, Value> = serde_json::from_str(&tx_info)?;
+        let msg = payload
+            .remove("MessageToSign")
+            .ok_or(SignerClientError::InvalidResponse)?;
+        let message = msg.as_str().ok_or(SignerClientError::InvalidResponse)?;
+        let signature = sign_personal_message(eth_private_key, message)?;
+        payload.insert("L1Sig".into(), Value::String(signature));
+
+        Ok(serde_json::to_string(&payload)?)
+    }
+
+    fn update_leverage_tx_info(
+        &self,
+        market_index: i32,
+        margin_mode: i32,
+        leverage: i32,
+        context: &SigningContext,
+    ) -> Result<String> {
+        let fraction = (10_000f64 / leverage as f64).round() as i32;
+        let (tx_info, error) =
+            self.signer
+                .sign_update_leverage(market_index, fraction, margin_mode, context.nonce)?;
+        parse_sign_output(tx_info, error, "sign_update_leverage")
+    }
+
+    fn update_margin_tx_info(
+        &self,
+        market_index: i32,
+        usdc_amount: f64,
+        direction: i32,
+        context: &SigningContext,
+    ) -> Result<String> {
+        let scaled_amount = (usdc_amount * USDC_TICKER_SCALE) as i64;
+        let (tx_info, error) = self.signer.sign_update_margin(
+            market_index,
+            scaled_amount,
+            direction,
+            context.nonce,
+        )?;
+        parse_sign_output(tx_info, error, "sign_update_margin")
+    }
+
     fn sign_withdraw_with_context(
         &self,
         usdc_amount: f64,
@@ -1225,6 +1772,31 @@ impl SignerClient {
         })
     }
 
+    /// Decode `tx_info` and send it. Decoding happens first so that once the
+    /// exchange has accepted a transaction the caller always gets the response;
+    /// a payload that fails to decode is never sent and its nonce is handed back.
+    async fn submit_typed_tx<T>(
+        &self,
+        context: &SigningContext,
+        tx_type: i32,
+        tx_info: String,
+        decode: fn(&str) -> serde_json::Result<T>,
+    ) -> Result<(T, models::RespSendTx)> {
+        let parsed = match decode(&tx_info) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                if context.nonce_reserved {
+                    self.acknowledge_failure(context.api_key_index).await;
+                }
+                return Err(err.into());
+            }
+        };
+        let response = self
+            .submit_signed_tx(context, tx_type, &tx_info, None)
+            .await?;
+        Ok((parsed, response))
+    }
+
     async fn submit_signed_tx(
         &self,
         context: &SigningContext,
@@ -1232,12 +1804,16 @@ impl SignerClient {
         tx_info: &str,
         price_protection: Option<bool>,
     ) -> Result<models::RespSendTx> {
-        match transaction_api::send_tx(&self.configuration, tx_type, tx_info, price_protection)
-            .await
-        {
+        match self.post_tx(tx_type, tx_info, price_protection).await {
             Ok(response) => {
                 if response.code != CODE_OK && context.nonce_reserved {
-                    self.acknowledge_failure(context.api_key_index).await;
+                    if is_invalid_nonce(response.code, response.message.as_deref()) {
+                        // Only this key drifted; the others keep their local counters.
+                        self.refresh_after_invalid_nonce(context.api_key_index)
+                            .await;
+                    } else {
+                        self.acknowledge_failure(context.api_key_index).await;
+                    }
                 }
                 Ok(response)
             }
@@ -1248,7 +1824,8 @@ impl SignerClient {
                 match classify_send_tx_error(err) {
                     TxSendError::InvalidNonce(message) => {
                         if context.nonce_reserved {
-                            self.hard_refresh_nonce(context.api_key_index).await?;
+                            self.refresh_after_invalid_nonce(context.api_key_index)
+                                .await;
                         }
                         Err(SignerClientError::Nonce(message))
                     }
@@ -1258,11 +1835,117 @@ impl SignerClient {
         }
     }
 
+    /// `sendTx` within the send budget. A `429` is retried per the retry
+    /// policy; any other failure is only resent after looking up the tx hash
+    /// the server replied with shows the exchange does not have the tx.
+    async fn post_tx(
+        &self,
+        tx_type: i32,
+        tx_info: &str,
+        price_protection: Option<bool>,
+    ) -> std::result::Result<models::RespSendTx, apis::Error<transaction_api::SendTxError>> {
+        let mut retry = 0;
+        loop {
+            self.policy.acquire(RequestClass::Send, "sendTx").await;
+            let err = match transaction_api::send_tx(
+                &self.configuration,
+                tx_type,
+                tx_info,
+                price_protection,
+            )
+            .await
+            {
+                Ok(response) => return Ok(response),
+                Err(err) => err,
+            };
+            retry += 1;
+            match self.resend(&err, retry, true).await {
+                Resend::Again => {}
+                Resend::Accepted(tx_hash) => {
+                    return Ok(models::RespSendTx::new(CODE_OK, tx_hash, 0))
+                }
+                Resend::No => return Err(err),
+            }
+        }
+    }
+
+    /// `sendTxBatch` within the send budget. Only a `429` is retried: the
+    /// entries of a batch that failed in transit are left for reconciliation.
+    async fn post_tx_batch(
+        &self,
+        types_json: &str,
+        infos_json: &str,
+    ) -> std::result::Result<models::RespSendTxBatch, apis::Error<transaction_api::SendTxBatchError>>
+    {
+        let mut retry = 0;
+        loop {
+            self.policy.acquire(RequestClass::Send, "sendTxBatch").await;
+            let err =
+                match transaction_api::send_tx_batch(&self.configuration, types_json, infos_json)
+                    .await
+                {
+                    Ok(response) => return Ok(response),
+                    Err(err) => err,
+                };
+            retry += 1;
+            match self.resend(&err, retry, false).await {
+                Resend::Again => {}
+                Resend::Accepted(_) | Resend::No => return Err(err),
+            }
+        }
+    }
+
+    /// Decide whether a failed submission may be sent again, waiting out the
+    /// backoff first when it may. `confirm` allows resending a transient
+    /// failure whose tx hash the exchange does not know.
+    async fn resend<E>(&self, err: &apis::Error<E>, retry: u32, confirm: bool) -> Resend {
+        let failure = rate_limit::classify(err);
+        let delay = match failure {
+            Failure::RateLimited => {
+                let retry_after = rate_limit::retry_after(err);
+                self.policy.rate_limited(retry_after);
+                self.policy.retry_delay(retry, retry_after)
+            }
+            Failure::Transient if confirm => self.policy.retry_delay(retry, None),
+            _ => None,
+        };
+        let Some(delay) = delay else {
+            return Resend::No;
+        };
+        tokio::time::sleep(delay).await;
+        if failure == Failure::RateLimited {
+            return Resend::Again;
+        }
+
+        let Some(tx_hash) = response_tx_hash(err) else {
+            return Resend::No;
+        };
+        self.policy.acquire(RequestClass::Read, "tx").await;
+        match transaction_api::tx(&self.configuration, "hash", &tx_hash).await {
+            Ok(_) => Resend::Accepted(tx_hash),
+            Err(apis::Error::ResponseError(response))
+                if matches!(response.status.as_u16(), 400 | 404) =>
+            {
+                Resend::Again
+            }
+            Err(_) => Resend::No,
+        }
+    }
+
     async fn acknowledge_failure(&self, api_key_index: i32) {
         let mut manager = self.nonce_manager.lock().await;
         manager.acknowledge_failure(api_key_index);
     }
 
+    /// Resync a key the exchange rejected for its nonce. A failed refresh is
+    /// only logged so it never hides the exchange's answer; the next invalid
+    /// nonce retries it.
+    async fn refresh_after_invalid_nonce(&self, api_key_index: i32) {
+        if let Err(err) = self.hard_refresh_nonce(api_key_index).await {
+            tracing::warn!(api_key_index, error = %err, "failed to refresh nonce after rejection");
+        }
+    }
+
     async fn hard_refresh_nonce(&self, api_key_index: i32) -> Result<()> {
         let mut manager = self.nonce_manager.lock().await;
         manager.hard_refresh_nonce(api_key_index).await
@@ -1288,10 +1971,15 @@ fn classify_send_tx_error(err: apis::Error<transaction_api::SendTxError>) -> TxS
         apis::Error::Io(e) => TxSendError::Other(SignerClientError::Io(e)),
         apis::Error::ResponseError(mut response) => {
             let message = response.entity.as_ref().and_then(extract_send_tx_message);
-            if let Some(msg) = message.clone() {
-                if msg.to_ascii_lowercase().contains("invalid nonce") {
-                    return TxSendError::InvalidNonce(msg);
-                }
+            let code = response
+                .entity
+                .as_ref()
+                .and_then(extract_send_tx_code)
+                .unwrap_or_default();
+            if is_invalid_nonce(code, message.as_deref()) {
+                return TxSendError::InvalidNonce(
+                    message.unwrap_or_else(|| "invalid nonce".to_string()),
+                );
             }
 
             let entity_string = response
@@ -1307,6 +1995,21 @@ fn classify_send_tx_error(err: apis::Error<transaction_api::SendTxError>) -> TxS
     }
 }
 
+pub(crate) fn is_invalid_nonce(code: i32, message: Option<&str>) -> bool {
+    code == CODE_INVALID_NONCE
+        || message.is_some_and(|msg| msg.to_ascii_lowercase().contains("invalid nonce"))
+}
+
+fn extract_send_tx_code(error: &transaction_api::SendTxError) -> Option<i32> {
+    match error {
+        transaction_api::SendTxError::Status400(result) => Some(result.code),
+        transaction_api::SendTxError::UnknownValue(value) => value
+            .get("code")
+            .and_then(|code| code.as_i64())
+            .and_then(|code| i32::try_from(code).ok()),
+    }
+}
+
 fn extract_send_tx_message(error: &transaction_api::SendTxError) -> Option<String> {
     match error {
         transaction_api::SendTxError::Status400(result) => result.message.clone(),
@@ -1364,6 +2067,15 @@ fn unix_timestamp_to_system_time(value: i64) -> Option<SystemTime> {
     base.checked_add(Duration::from_nanos(nanos))
 }
 
+fn batch_params(entries: &[BatchEntry]) -> Result<(String, String)> {
+    let tx_types: Vec<i32> = entries.iter().map(|entry| entry.tx_type).collect();
+    let tx_infos: Vec<&str> = entries.iter().map(|entry| entry.tx_info.as_str()).collect();
+    Ok((
+        serde_json::to_string(&tx_types)?,
+        serde_json::to_string(&tx_infos)?,
+    ))
+}
+
 fn parse_sign_output(
     value: Option<String>,
     error: Option<String>,
@@ -1449,6 +2161,18 @@ fn are_keys_equal(a: &str, b: &str) -> bool {
     sanitize_private_key(a) == sanitize_private_key(b)
 }
 
+/// Tx hash in the body of a failed `sendTx` response, if the server sent one.
+fn response_tx_hash<E>(err: &apis::Error<E>) -> Option<String> {
+    let apis::Error::ResponseError(response) = err else {
+        return None;
+    };
+    let body: Value = serde_json::from_str(&response.content).ok()?;
+    body.get("tx_hash")
+        .and_then(Value::as_str)
+        .filter(|hash| !hash.is_empty())
+        .map(str::to_string)
+}
+
 pub(crate) fn map_api_error<E>(err: apis::Error<E>) -> SignerClientError
 where
     E: serde::Serialize + std::fmt::Debug,
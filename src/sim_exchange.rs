@@ -0,0 +1,1751 @@
+//! Local matching engine for paper trading.
+//!
+//! [`SimExchange`] accepts the same signed `tx_info` payloads the exchange
+//! does (from [`SignerLibrary`](crate::signer::SignerLibrary) or any other
+//! [`TxSigner`](crate::tx_signer::TxSigner)) and answers with the same
+//! [`WsEvent`] stream: `account_tx` acks, `account_all_orders`,
+//! `account_all_trades`, `account_all_positions` and order book updates that
+//! include the simulated resting orders.
+//!
+//! The public book is mirrored from market data fed through
+//! [`SimExchange::on_market_event`], live or replayed. Aggressive orders
+//! consume the mirrored levels. A resting order joins the back of the public
+//! queue at its price: prints at that price fill it only once the public size
+//! ahead of it has traded, and cancellations can only move it forward. Prints
+//! through its price, or a public book that crosses it, fill it outright.
+//! Simulated orders never trade with each other.
+//!
+//! ```ignore
+//! let mut sim = SimExchange::new(AccountId::new(42));
+//! sim.add_market(MarketId::new(0), BookScale::new(2, 4));
+//! sim.on_market_event(&event);
+//! let ack = sim.submit_tx(TX_TYPE_CREATE_ORDER.into(), &tx_info);
+//! for event in sim.drain_events() { /* feed the strategy */ }
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    account_events::{
+        AccountChannel, AccountTxUpdate, OrdersUpdate, PositionsUpdate, TradesUpdate,
+        FEE_RATE_SCALE, TX_STATUS_FAILED, TX_STATUS_SUCCESS,
+    },
+    market::Decimal,
+    models::{
+        self,
+        order::{Status, TimeInForce, TriggerStatus, Type},
+    },
+    order_book::BookScale,
+    signer_client::{BatchEntry, CODE_INVALID_NONCE, CODE_OK},
+    transactions::{CreateOrderInfo, ModifyOrderInfo},
+    tx_executor::{
+        ORDER_TIME_IN_FORCE_GTT, ORDER_TIME_IN_FORCE_IOC, ORDER_TIME_IN_FORCE_POST_ONLY,
+        ORDER_TYPE_LIMIT, ORDER_TYPE_MARKET, ORDER_TYPE_STOP_LOSS, ORDER_TYPE_STOP_LOSS_LIMIT,
+        ORDER_TYPE_TAKE_PROFIT, ORDER_TYPE_TAKE_PROFIT_LIMIT, TX_TYPE_CANCEL_ALL_ORDERS,
+        TX_TYPE_CANCEL_ORDER, TX_TYPE_CREATE_ORDER, TX_TYPE_MODIFY_ORDER,
+    },
+    types::{AccountId, MarketId},
+    ws_client::{
+        AccountEvent, AccountEventEnvelope, OrderBookDelta, OrderBookEvent, OrderBookLevel,
+        OrderBookState, TradeData, WsEvent,
+    },
+};
+
+const CODE_INVALID_TX: i32 = 21120;
+
+const CANCEL_ALL_IMMEDIATE: i32 = 0;
+const CANCEL_ALL_SCHEDULED: i32 = 1;
+const CANCEL_ALL_ABORT: i32 = 2;
+
+/// Counterparty account reported on fills against mirrored public liquidity.
+pub const EXTERNAL_ACCOUNT: i64 = -1;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct TxHeader {
+    #[serde(default)]
+    account_index: Option<i64>,
+    #[serde(default)]
+    api_key_index: i32,
+    nonce: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CancelOrderInfo {
+    #[serde(alias = "OrderBookIndex")]
+    market_index: i32,
+    #[serde(alias = "OrderIndex")]
+    index: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CancelAllInfo {
+    time_in_force: i32,
+    #[serde(default)]
+    time: i64,
+}
+
+#[derive(Debug)]
+enum TxBody {
+    Create(CreateOrderInfo),
+    Cancel(CancelOrderInfo),
+    CancelAll(CancelAllInfo),
+    Modify(ModifyOrderInfo),
+}
+
+#[derive(Debug)]
+struct Admitted {
+    tx_type: i32,
+    info: String,
+    header: TxHeader,
+    body: TxBody,
+}
+
+type Rejection = (i32, String);
+
+#[derive(Debug, Clone)]
+struct SimOrder {
+    order_index: i64,
+    client_order_index: i64,
+    market: i32,
+    is_ask: bool,
+    price: i64,
+    trigger_price: i64,
+    order_type: i32,
+    time_in_force: i32,
+    reduce_only: bool,
+    expiry: i64,
+    nonce: i64,
+    initial: i64,
+    remaining: i64,
+    filled: i64,
+    /// Filled quote in units of `10^-(price_decimals + size_decimals)`.
+    filled_quote: i128,
+    status: Status,
+    triggered: bool,
+    updated_at: i64,
+    /// Public size resting ahead of this order at its price.
+    queue_ahead: i64,
+}
+
+impl SimOrder {
+    fn is_live(&self) -> bool {
+        matches!(self.status, Status::Open | Status::Pending)
+    }
+
+    fn is_trigger(&self) -> bool {
+        matches!(
+            self.order_type,
+            ORDER_TYPE_STOP_LOSS
+                | ORDER_TYPE_STOP_LOSS_LIMIT
+                | ORDER_TYPE_TAKE_PROFIT
+                | ORDER_TYPE_TAKE_PROFIT_LIMIT
+        )
+    }
+
+    fn is_market(&self) -> bool {
+        matches!(
+            self.order_type,
+            ORDER_TYPE_MARKET | ORDER_TYPE_STOP_LOSS | ORDER_TYPE_TAKE_PROFIT
+        )
+    }
+
+    fn awaiting_trigger(&self) -> bool {
+        self.is_trigger() && !self.triggered
+    }
+
+    /// Limit applied while taking liquidity; market orders priced at zero
+    /// take at any price.
+    fn limit(&self) -> i64 {
+        match (self.price > 0, self.is_ask) {
+            (true, _) => self.price,
+            (false, true) => 0,
+            (false, false) => i64::MAX,
+        }
+    }
+
+    /// Whether a trigger order fires at `reference`.
+    fn trigger_hit(&self, reference: i64) -> bool {
+        let stop = matches!(
+            self.order_type,
+            ORDER_TYPE_STOP_LOSS | ORDER_TYPE_STOP_LOSS_LIMIT
+        );
+        // Sell stops and buy take-profits fire on the way down.
+        if self.is_ask == stop {
+            reference <= self.trigger_price
+        } else {
+            reference >= self.trigger_price
+        }
+    }
+
+    fn to_model(&self, account: i64, scale: BookScale) -> models::Order {
+        let order_type = match self.order_type {
+            ORDER_TYPE_MARKET => Type::Market,
+            ORDER_TYPE_STOP_LOSS => Type::StopLoss,
+            ORDER_TYPE_STOP_LOSS_LIMIT => Type::StopLossLimit,
+            ORDER_TYPE_TAKE_PROFIT => Type::TakeProfit,
+            ORDER_TYPE_TAKE_PROFIT_LIMIT => Type::TakeProfitLimit,
+            _ => Type::Limit,
+        };
+        let time_in_force = match self.time_in_force {
+            ORDER_TIME_IN_FORCE_IOC => TimeInForce::ImmediateOrCancel,
+            ORDER_TIME_IN_FORCE_POST_ONLY => TimeInForce::PostOnly,
+            _ => TimeInForce::GoodTillTime,
+        };
+        models::Order {
+            order_index: self.order_index,
+            client_order_index: self.client_order_index,
+            order_id: self.order_index.to_string(),
+            client_order_id: self.client_order_index.to_string(),
+            market_index: self.market,
+            owner_account_index: account,
+            initial_base_amount: size_text(scale, self.initial),
+            price: price_text(scale, self.price),
+            nonce: self.nonce,
+            remaining_base_amount: size_text(scale, self.remaining),
+            is_ask: self.is_ask,
+            base_size: self.remaining,
+            base_price: i32::try_from(self.price).unwrap_or(i32::MAX),
+            filled_base_amount: size_text(scale, self.filled),
+            filled_quote_amount: quote_text(scale, self.filled_quote),
+            side: if self.is_ask { "sell" } else { "buy" }.to_string(),
+            r#type: order_type,
+            time_in_force,
+            reduce_only: self.reduce_only,
+            trigger_price: price_text(scale, self.trigger_price),
+            order_expiry: self.expiry,
+            status: self.status,
+            trigger_status: if self.awaiting_trigger() {
+                TriggerStatus::MarkPrice
+            } else {
+                TriggerStatus::Na
+            },
+            timestamp: self.updated_at,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct SimPosition {
+    /// Signed size in base units; positive is long.
+    size: i64,
+    /// Cost of the open position in quote currency.
+    entry_quote: f64,
+    realized: f64,
+}
+
+#[derive(Debug)]
+struct SimMarket {
+    scale: BookScale,
+    ext_bids: BTreeMap<i64, i64>,
+    ext_asks: BTreeMap<i64, i64>,
+    bids: BTreeMap<i64, VecDeque<i64>>,
+    asks: BTreeMap<i64, VecDeque<i64>>,
+    triggers: Vec<i64>,
+    last_price: Option<i64>,
+    position: SimPosition,
+    published: Option<(BTreeMap<i64, i64>, BTreeMap<i64, i64>)>,
+    book_nonce: u64,
+}
+
+impl SimMarket {
+    fn new(scale: BookScale) -> Self {
+        Self {
+            scale,
+            ext_bids: BTreeMap::new(),
+            ext_asks: BTreeMap::new(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            triggers: Vec::new(),
+            last_price: None,
+            position: SimPosition::default(),
+            published: None,
+            book_nonce: 0,
+        }
+    }
+
+    fn external(&self, is_ask: bool) -> &BTreeMap<i64, i64> {
+        if is_ask {
+            &self.ext_asks
+        } else {
+            &self.ext_bids
+        }
+    }
+
+    fn best_external(&self, is_ask: bool) -> Option<(i64, i64)> {
+        if is_ask {
+            self.ext_asks.iter().next().map(|(p, s)| (*p, *s))
+        } else {
+            self.ext_bids.iter().next_back().map(|(p, s)| (*p, *s))
+        }
+    }
+
+    fn consume_external(&mut self, is_ask: bool, price: i64, qty: i64) {
+        let levels = if is_ask {
+            &mut self.ext_asks
+        } else {
+            &mut self.ext_bids
+        };
+        if let Some(size) = levels.get_mut(&price) {
+            *size -= qty;
+            if *size <= 0 {
+                levels.remove(&price);
+            }
+        }
+    }
+
+    /// Last trade price, falling back to the mirrored mid.
+    fn reference_price(&self) -> Option<i64> {
+        self.last_price.or_else(|| {
+            let (bid, _) = self.best_external(false)?;
+            let (ask, _) = self.best_external(true)?;
+            Some((bid + ask) / 2)
+        })
+    }
+
+    fn queue(&mut self, is_ask: bool) -> &mut BTreeMap<i64, VecDeque<i64>> {
+        if is_ask {
+            &mut self.asks
+        } else {
+            &mut self.bids
+        }
+    }
+
+    fn unrest(&mut self, order: &SimOrder) {
+        let queue = self.queue(order.is_ask);
+        if let Some(level) = queue.get_mut(&order.price) {
+            level.retain(|index| *index != order.order_index);
+            if level.is_empty() {
+                queue.remove(&order.price);
+            }
+        }
+        self.triggers.retain(|index| *index != order.order_index);
+    }
+
+    /// Size that a reduce-only order on `is_ask` may still trade.
+    fn reducible(&self, is_ask: bool) -> i64 {
+        match (is_ask, self.position.size) {
+            (true, size) if size > 0 => size,
+            (false, size) if size < 0 => -size,
+            _ => 0,
+        }
+    }
+
+    fn apply_fill(&mut self, is_ask: bool, price: i64, qty: i64) {
+        let price = self.scale.ticks_to_price(price);
+        let qty_value = self.scale.units_to_size(qty);
+        let signed = if is_ask { -qty } else { qty };
+        let position = &mut self.position;
+        if position.size == 0 || (position.size > 0) == (signed > 0) {
+            position.entry_quote += qty_value * price;
+            position.size += signed;
+            return;
+        }
+
+        let open = position.size.abs();
+        let closing = qty.min(open);
+        let avg = position.entry_quote / self.scale.units_to_size(open);
+        let closing_value = self.scale.units_to_size(closing);
+        let direction = if position.size > 0 { 1.0 } else { -1.0 };
+        position.realized += closing_value * (price - avg) * direction;
+        position.entry_quote -= closing_value * avg;
+        position.size += signed;
+        if qty > closing {
+            position.entry_quote = self.scale.units_to_size(qty - closing) * price;
+        } else if position.size == 0 {
+            position.entry_quote = 0.0;
+        }
+    }
+
+    fn to_position(&self, market: i32, open_orders: i64) -> models::AccountPosition {
+        let position = &self.position;
+        let size = self.scale.units_to_size(position.size.abs());
+        let avg = if size > 0.0 {
+            position.entry_quote / size
+        } else {
+            0.0
+        };
+        let mark = self
+            .reference_price()
+            .map(|ticks| self.scale.ticks_to_price(ticks))
+            .unwrap_or(avg);
+        let unrealized = (mark - avg) * size * f64::from(position.size.signum() as i32);
+        let price_decimals = self.scale.price_decimals as usize;
+        models::AccountPosition {
+            market_id: market,
+            open_order_count: open_orders,
+            sign: position.size.signum() as i32,
+            position: size_text(self.scale, position.size.abs()),
+            avg_entry_price: format!("{avg:.price_decimals$}"),
+            position_value: format!("{:.6}", size * mark),
+            unrealized_pnl: format!("{unrealized:.6}"),
+            realized_pnl: format!("{:.6}", position.realized),
+            ..Default::default()
+        }
+    }
+
+    fn combined_levels(
+        &self,
+        orders: &HashMap<i64, SimOrder>,
+    ) -> (BTreeMap<i64, i64>, BTreeMap<i64, i64>) {
+        let mut bids = self.ext_bids.clone();
+        let mut asks = self.ext_asks.clone();
+        for (levels, queue) in [(&mut bids, &self.bids), (&mut asks, &self.asks)] {
+            for (price, indices) in queue {
+                let size: i64 = indices
+                    .iter()
+                    .filter_map(|index| orders.get(index))
+                    .map(|order| order.remaining)
+                    .sum();
+                *levels.entry(*price).or_default() += size;
+            }
+        }
+        (bids, asks)
+    }
+}
+
+/// Paper trading exchange driven by public market data.
+///
+/// All methods are synchronous; share one instance behind a mutex when
+/// several tasks submit. Every call queues the resulting events, which are
+/// collected with [`drain_events`](Self::drain_events).
+#[derive(Debug)]
+pub struct SimExchange {
+    account: AccountId,
+    maker_fee: i32,
+    taker_fee: i32,
+    now_ms: i64,
+    markets: BTreeMap<i32, SimMarket>,
+    orders: HashMap<i64, SimOrder>,
+    by_client: HashMap<i64, i64>,
+    nonces: HashMap<i32, i64>,
+    next_order_index: i64,
+    next_trade_id: i64,
+    next_tx: i64,
+    scheduled_cancel_all: Option<i64>,
+    fees_paid: f64,
+    pending_txs: Vec<models::Tx>,
+    pending_trades: BTreeMap<i32, Vec<models::Trade>>,
+    dirty_orders: BTreeSet<i64>,
+    dirty_positions: BTreeSet<i32>,
+    dirty_books: BTreeSet<i32>,
+    events: VecDeque<WsEvent>,
+}
+
+impl SimExchange {
+    pub fn new(account: AccountId) -> Self {
+        Self {
+            account,
+            maker_fee: 0,
+            taker_fee: 0,
+            now_ms: 0,
+            markets: BTreeMap::new(),
+            orders: HashMap::new(),
+            by_client: HashMap::new(),
+            nonces: HashMap::new(),
+            next_order_index: 1,
+            next_trade_id: 1,
+            next_tx: 1,
+            scheduled_cancel_all: None,
+            fees_paid: 0.0,
+            pending_txs: Vec::new(),
+            pending_trades: BTreeMap::new(),
+            dirty_orders: BTreeSet::new(),
+            dirty_positions: BTreeSet::new(),
+            dirty_books: BTreeSet::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Fee rates in millionths of notional, as reported on `models::Trade`.
+    pub fn with_fees(mut self, maker_fee: i32, taker_fee: i32) -> Self {
+        self.maker_fee = maker_fee;
+        self.taker_fee = taker_fee;
+        self
+    }
+
+    /// Register a market. Transactions for unregistered markets are rejected.
+    pub fn add_market(&mut self, market: MarketId, scale: BookScale) {
+        self.markets
+            .entry(market.into_inner())
+            .or_insert_with(|| SimMarket::new(scale));
+    }
+
+    pub fn account(&self) -> AccountId {
+        self.account
+    }
+
+    /// Simulated time in milliseconds.
+    pub fn now_ms(&self) -> i64 {
+        self.now_ms
+    }
+
+    /// Total fees charged so far, in quote currency.
+    pub fn fees_paid(&self) -> f64 {
+        self.fees_paid
+    }
+
+    pub fn order(&self, order_index: i64) -> Option<models::Order> {
+        let order = self.orders.get(&order_index)?;
+        let market = self.markets.get(&order.market)?;
+        Some(order.to_model(self.account.into_inner(), market.scale))
+    }
+
+    /// Live orders (resting or awaiting a trigger) in `market`.
+    pub fn open_orders(&self, market: MarketId) -> Vec<models::Order> {
+        let Some(state) = self.markets.get(&market.into_inner()) else {
+            return Vec::new();
+        };
+        let mut orders: Vec<_> = self
+            .orders
+            .values()
+            .filter(|order| order.market == market.into_inner() && order.is_live())
+            .map(|order| order.to_model(self.account.into_inner(), state.scale))
+            .collect();
+        orders.sort_by_key(|order| order.order_index);
+        orders
+    }
+
+    pub fn position(&self, market: MarketId) -> Option<models::AccountPosition> {
+        let state = self.markets.get(&market.into_inner())?;
+        Some(state.to_position(
+            market.into_inner(),
+            self.open_order_count(market.into_inner()),
+        ))
+    }
+
+    /// Registered markets, in id order.
+    pub fn markets(&self) -> Vec<MarketId> {
+        self.markets.keys().copied().map(MarketId::new).collect()
+    }
+
+    /// Nonce the next transaction from `api_key_index` must use, as
+    /// `nextNonce` reports it.
+    pub fn next_nonce(&self, api_key_index: i32) -> i64 {
+        self.nonces.get(&api_key_index).map_or(0, |last| last + 1)
+    }
+
+    /// Current combined book of `market` as a snapshot.
+    pub fn book(&self, market: MarketId) -> Option<OrderBookEvent> {
+        let state = self.markets.get(&market.into_inner())?;
+        let (bids, asks) = state.combined_levels(&self.orders);
+        let nonce = state.book_nonce;
+        Some(OrderBookEvent {
+            market,
+            state: book_state(state.scale, &bids, &asks),
+            delta: None,
+            nonce: Some(nonce),
+            begin_nonce: None,
+            offset: Some(nonce as i64),
+        })
+    }
+
+    /// Account channel snapshot in the shape the exchange sends on
+    /// subscribe (`subscribed/...`): open orders, positions, or an empty
+    /// trade or transaction list. `None` for channels that are not simulated.
+    pub fn account_snapshot(&self, channel: AccountChannel) -> Option<Value> {
+        let account = self.account.into_inner();
+        let payload = match channel {
+            AccountChannel::AllOrders => {
+                let mut update = OrdersUpdate {
+                    account: Some(account),
+                    ..Default::default()
+                };
+                for market in self.markets() {
+                    update
+                        .orders
+                        .insert(market.into_inner(), self.open_orders(market));
+                }
+                serde_json::to_value(update)
+            }
+            AccountChannel::AllPositions => {
+                let mut update = PositionsUpdate::default();
+                for market in self.markets() {
+                    if let Some(position) = self.position(market) {
+                        update.positions.insert(market.into_inner(), position);
+                    }
+                }
+                serde_json::to_value(update)
+            }
+            AccountChannel::AllTrades => serde_json::to_value(TradesUpdate::default()),
+            AccountChannel::AccountTx => serde_json::to_value(AccountTxUpdate::default()),
+            _ => return None,
+        };
+        match payload {
+            Ok(payload) => Some(self.account_message(channel, "subscribed", payload)),
+            Err(err) => {
+                tracing::warn!(error = %err, channel = channel.as_str(), "failed to encode simulated snapshot");
+                None
+            }
+        }
+    }
+
+    /// Take every event produced since the last call, oldest first.
+    pub fn drain_events(&mut self) -> Vec<WsEvent> {
+        self.events.drain(..).collect()
+    }
+
+    /// Move the clock forward, expiring GTT orders and running a scheduled
+    /// cancel-all that has come due. Time never moves backwards.
+    pub fn advance_time(&mut self, now_ms: i64) {
+        self.now_ms = self.now_ms.max(now_ms);
+        self.expire();
+        self.flush();
+    }
+
+    /// Feed public market data. Order book snapshots and deltas update the
+    /// mirrored book; trades set the last price and fill resting orders.
+    /// Other events are ignored.
+    pub fn on_market_event(&mut self, event: &WsEvent) {
+        match event {
+            WsEvent::OrderBook(book) => self.on_book(book),
+            WsEvent::Trade(trades) => {
+                for trade in &trades.trades {
+                    self.on_public_trade(trade);
+                }
+            }
+            _ => return,
+        }
+        self.expire();
+        self.flush();
+    }
+
+    /// Submit one signed transaction, answering like `sendTx`.
+    pub fn submit_tx(&mut self, tx_type: i32, tx_info: &str) -> models::RespSendTx {
+        let mut nonces = self.nonces.clone();
+        let response = match self.admit(&mut nonces, tx_type, tx_info) {
+            Ok(admitted) => {
+                self.nonces = nonces;
+                let hash = self.execute(admitted);
+                models::RespSendTx::new(CODE_OK, hash, 0)
+            }
+            Err((code, message)) => models::RespSendTx {
+                code,
+                message: Some(message),
+                ..Default::default()
+            },
+        };
+        self.flush();
+        response
+    }
+
+    /// Submit a batch, answering like `sendTxBatch`. The batch is rejected as
+    /// a whole if any entry fails validation; otherwise entries execute in
+    /// order and individual failures show up as failed `account_tx` entries.
+    pub fn submit_batch(&mut self, entries: &[BatchEntry]) -> models::RespSendTxBatch {
+        let mut nonces = self.nonces.clone();
+        let mut admitted = Vec::with_capacity(entries.len());
+        for (position, entry) in entries.iter().enumerate() {
+            match self.admit(&mut nonces, entry.tx_type(), entry.tx_info()) {
+                Ok(tx) => admitted.push(tx),
+                Err((code, message)) => {
+                    return models::RespSendTxBatch {
+                        code,
+                        message: Some(format!("entry {position}: {message}")),
+                        ..Default::default()
+                    };
+                }
+            }
+        }
+        self.nonces = nonces;
+        let hashes = admitted.into_iter().map(|tx| self.execute(tx)).collect();
+        self.flush();
+        models::RespSendTxBatch::new(CODE_OK, hashes, 0)
+    }
+
+    fn admit(
+        &self,
+        nonces: &mut HashMap<i32, i64>,
+        tx_type: i32,
+        tx_info: &str,
+    ) -> Result<Admitted, Rejection> {
+        let invalid = |err: serde_json::Error| (CODE_INVALID_TX, format!("invalid tx info: {err}"));
+        let header: TxHeader = serde_json::from_str(tx_info).map_err(invalid)?;
+        let body = match u8::try_from(tx_type) {
+            Ok(TX_TYPE_CREATE_ORDER) => {
+                TxBody::Create(serde_json::from_str(tx_info).map_err(invalid)?)
+            }
+            Ok(TX_TYPE_CANCEL_ORDER) => {
+                TxBody::Cancel(serde_json::from_str(tx_info).map_err(invalid)?)
+            }
+            Ok(TX_TYPE_CANCEL_ALL_ORDERS) => {
+                TxBody::CancelAll(serde_json::from_str(tx_info).map_err(invalid)?)
+            }
+            Ok(TX_TYPE_MODIFY_ORDER) => {
+                TxBody::Modify(serde_json::from_str(tx_info).map_err(invalid)?)
+            }
+            _ => {
+                return Err((
+                    CODE_INVALID_TX,
+                    format!("tx type {tx_type} is not simulated"),
+                ))
+            }
+        };
+
+        if let Some(account) = header.account_index {
+            if account != self.account.into_inner() {
+                return Err((CODE_INVALID_TX, format!("unknown account {account}")));
+            }
+        }
+        let market = match &body {
+            TxBody::Create(info) => Some(info.market_index),
+            TxBody::Cancel(info) => Some(info.market_index),
+            TxBody::Modify(info) => Some(info.market_index),
+            TxBody::CancelAll(_) => None,
+        };
+        if let Some(market) = market {
+            if !self.markets.contains_key(&market) {
+                return Err((CODE_INVALID_TX, format!("unknown market {market}")));
+            }
+        }
+        if let TxBody::Create(info) = &body {
+            if info.base_amount <= 0 {
+                return Err((CODE_INVALID_TX, "base amount must be positive".into()));
+            }
+        }
+
+        let last = nonces.entry(header.api_key_index).or_insert(-1);
+        if header.nonce <= *last {
+            return Err((
+                CODE_INVALID_NONCE,
+                format!(
+                    "invalid nonce {} for api key {}, expected > {}",
+                    header.nonce, header.api_key_index, *last
+                ),
+            ));
+        }
+        *last = header.nonce;
+
+        Ok(Admitted {
+            tx_type,
+            info: tx_info.to_string(),
+            header,
+            body,
+        })
+    }
+
+    fn execute(&mut self, tx: Admitted) -> String {
+        let hash = format!("{:064x}", self.next_tx);
+        self.next_tx += 1;
+        let nonce = tx.header.nonce;
+        let outcome = match tx.body {
+            TxBody::Create(info) => self.create_order(info, nonce),
+            TxBody::Cancel(info) => self.cancel_order(info),
+            TxBody::CancelAll(info) => self.cancel_all(info),
+            TxBody::Modify(info) => self.modify_order(info),
+        };
+        let (status, event_info) = match outcome {
+            Ok(()) => (TX_STATUS_SUCCESS, String::new()),
+            Err(reason) => (TX_STATUS_FAILED, reason),
+        };
+        self.pending_txs.push(models::Tx {
+            hash: hash.clone(),
+            r#type: tx.tx_type,
+            info: tx.info,
+            event_info,
+            status,
+            account_index: self.account.into_inner(),
+            nonce,
+            queued_at: self.now_ms,
+            executed_at: self.now_ms,
+            sequence_index: self.next_tx - 1,
+            ..Default::default()
+        });
+        hash
+    }
+
+    fn create_order(&mut self, info: CreateOrderInfo, nonce: i64) -> Result<(), String> {
+        let order_index = self.next_order_index;
+        self.next_order_index += 1;
+        let order = SimOrder {
+            order_index,
+            client_order_index: info.client_order_index,
+            market: info.market_index,
+            is_ask: info.is_ask,
+            price: info.price,
+            trigger_price: info.trigger_price,
+            order_type: info.order_type,
+            time_in_force: info.time_in_force,
+            reduce_only: info.reduce_only,
+            expiry: info.order_expiry,
+            nonce,
+            initial: info.base_amount,
+            remaining: info.base_amount,
+            filled: 0,
+            filled_quote: 0,
+            status: Status::Pending,
+            triggered: false,
+            updated_at: self.now_ms,
+            queue_ahead: 0,
+        };
+        let awaiting_trigger = order.awaiting_trigger();
+        if info.client_order_index != 0 {
+            self.by_client.insert(info.client_order_index, order_index);
+        }
+        self.orders.insert(order_index, order);
+        self.dirty_orders.insert(order_index);
+
+        if awaiting_trigger {
+            if let Some(market) = self.markets.get_mut(&info.market_index) {
+                market.triggers.push(order_index);
+            }
+            self.check_triggers(info.market_index);
+        } else {
+            self.place(order_index);
+        }
+        Ok(())
+    }
+
+    fn cancel_order(&mut self, info: CancelOrderInfo) -> Result<(), String> {
+        let order_index = self.resolve(info.market_index, info.index)?;
+        self.finish(order_index, Status::Canceled);
+        Ok(())
+    }
+
+    fn cancel_all(&mut self, info: CancelAllInfo) -> Result<(), String> {
+        match info.time_in_force {
+            CANCEL_ALL_IMMEDIATE => self.cancel_all_live(),
+            CANCEL_ALL_SCHEDULED => self.scheduled_cancel_all = Some(info.time),
+            CANCEL_ALL_ABORT => self.scheduled_cancel_all = None,
+            other => return Err(format!("unsupported cancel-all mode {other}")),
+        }
+        Ok(())
+    }
+
+    fn modify_order(&mut self, info: ModifyOrderInfo) -> Result<(), String> {
+        if info.base_amount <= 0 {
+            return Err("base amount must be positive".into());
+        }
+        let order_index = self.resolve(info.market_index, info.index)?;
+        let Some(order) = self.orders.get_mut(&order_index) else {
+            return Err(format!("order {} not found", info.index));
+        };
+        let previous = order.clone();
+        order.initial += info.base_amount - order.remaining;
+        order.remaining = info.base_amount;
+        order.price = info.price;
+        order.trigger_price = info.trigger_price;
+        order.updated_at = self.now_ms;
+        self.dirty_orders.insert(order_index);
+        self.dirty_books.insert(previous.market);
+
+        // Priority is kept only when the price is unchanged and the size
+        // does not grow.
+        let keeps_priority = info.price == previous.price && info.base_amount <= previous.remaining;
+        if previous.awaiting_trigger() {
+            self.check_triggers(previous.market);
+        } else if !keeps_priority {
+            if let Some(market) = self.markets.get_mut(&previous.market) {
+                market.unrest(&previous);
+            }
+            self.place(order_index);
+        }
+        Ok(())
+    }
+
+    /// Find a live order by exchange index, falling back to client index.
+    fn resolve(&self, market: i32, index: i64) -> Result<i64, String> {
+        [Some(index), self.by_client.get(&index).copied()]
+            .into_iter()
+            .flatten()
+            .find(|order_index| {
+                self.orders
+                    .get(order_index)
+                    .is_some_and(|order| order.market == market && order.is_live())
+            })
+            .ok_or_else(|| format!("order {index} not found in market {market}"))
+    }
+
+    /// Run an order that is not awaiting a trigger against the mirrored book
+    /// and rest, cancel or reject whatever is left.
+    fn place(&mut self, order_index: i64) {
+        let Some(order) = self.orders.get(&order_index).cloned() else {
+            return;
+        };
+        let Some(market) = self.markets.get(&order.market) else {
+            return;
+        };
+        self.dirty_orders.insert(order_index);
+
+        if order.reduce_only && market.reducible(order.is_ask) == 0 {
+            self.finish(order_index, Status::CanceledReduceOnly);
+            return;
+        }
+        let crosses = market
+            .best_external(!order.is_ask)
+            .is_some_and(|(price, _)| crosses(order.is_ask, order.limit(), price));
+        if crosses && order.time_in_force == ORDER_TIME_IN_FORCE_POST_ONLY {
+            self.finish(order_index, Status::CanceledPostOnly);
+            return;
+        }
+        if crosses {
+            self.take(order_index);
+        }
+
+        let Some(order) = self.orders.get_mut(&order_index) else {
+            return;
+        };
+        if !order.is_live() || order.remaining == 0 {
+            return;
+        }
+        if order.is_market() || order.time_in_force == ORDER_TIME_IN_FORCE_IOC {
+            self.finish(order_index, Status::Canceled);
+            return;
+        }
+        order.status = Status::Open;
+        order.updated_at = self.now_ms;
+        order.queue_ahead = self
+            .markets
+            .get(&order.market)
+            .and_then(|market| market.external(order.is_ask).get(&order.price).copied())
+            .unwrap_or(0);
+        let (market_id, is_ask, price) = (order.market, order.is_ask, order.price);
+        if let Some(market) = self.markets.get_mut(&market_id) {
+            market
+                .queue(is_ask)
+                .entry(price)
+                .or_default()
+                .push_back(order_index);
+        }
+        self.dirty_books.insert(market_id);
+    }
+
+    /// Fill an aggressive order against mirrored liquidity, best price first.
+    fn take(&mut self, order_index: i64) {
+        loop {
+            let Some(order) = self.orders.get(&order_index) else {
+                return;
+            };
+            if !order.is_live() || order.remaining == 0 {
+                return;
+            }
+            let (market_id, is_ask, limit, remaining) =
+                (order.market, order.is_ask, order.limit(), order.remaining);
+            let Some(market) = self.markets.get(&market_id) else {
+                return;
+            };
+            let Some((price, size)) = market.best_external(!is_ask) else {
+                return;
+            };
+            if !crosses(is_ask, limit, price) {
+                return;
+            }
+            let filled = self.fill(order_index, price, remaining.min(size), false);
+            if filled == 0 {
+                return;
+            }
+            if let Some(market) = self.markets.get_mut(&market_id) {
+                market.consume_external(!is_ask, price, filled);
+            }
+        }
+    }
+
+    /// Record a fill of up to `qty` at `price`, capped for reduce-only
+    /// orders. Returns the size actually filled.
+    fn fill(&mut self, order_index: i64, price: i64, qty: i64, maker: bool) -> i64 {
+        let Some(order) = self.orders.get(&order_index).cloned() else {
+            return 0;
+        };
+        let Some(market) = self.markets.get_mut(&order.market) else {
+            return 0;
+        };
+        let qty = if order.reduce_only {
+            qty.min(market.reducible(order.is_ask))
+        } else {
+            qty
+        };
+        if qty <= 0 {
+            if order.reduce_only {
+                self.finish(order_index, Status::CanceledReduceOnly);
+            }
+            return 0;
+        }
+
+        market.apply_fill(order.is_ask, price, qty);
+        market.last_price = Some(price);
+        let scale = market.scale;
+        let quote = i128::from(price) * i128::from(qty);
+        let fee_rate = if maker {
+            self.maker_fee
+        } else {
+            self.taker_fee
+        };
+        let notional = scale.ticks_to_price(price) * scale.units_to_size(qty);
+        self.fees_paid += notional * f64::from(fee_rate) / FEE_RATE_SCALE;
+
+        let account = self.account.into_inner();
+        let (ask_id, bid_id, ask_account_id, bid_account_id) = if order.is_ask {
+            (order_index, 0, account, EXTERNAL_ACCOUNT)
+        } else {
+            (0, order_index, EXTERNAL_ACCOUNT, account)
+        };
+        let trade = models::Trade {
+            trade_id: self.next_trade_id,
+            tx_hash: format!("{:064x}", self.next_tx - 1),
+            market_id: order.market,
+            size: size_text(scale, qty),
+            price: price_text(scale, price),
+            usd_amount: quote_text(scale, quote),
+            ask_id,
+            bid_id,
+            ask_account_id,
+            bid_account_id,
+            is_maker_ask: order.is_ask == maker,
+            timestamp: self.now_ms,
+            taker_fee: self.taker_fee,
+            maker_fee: self.maker_fee,
+            ..Default::default()
+        };
+        self.next_trade_id += 1;
+        self.pending_trades
+            .entry(order.market)
+            .or_default()
+            .push(trade);
+
+        let now = self.now_ms;
+        if let Some(order) = self.orders.get_mut(&order_index) {
+            order.remaining -= qty;
+            order.filled += qty;
+            order.filled_quote += quote;
+            order.updated_at = now;
+            if order.remaining == 0 {
+                order.status = Status::Filled;
+            }
+        }
+        if order.remaining == qty {
+            if let Some(market) = self.markets.get_mut(&order.market) {
+                market.unrest(&order);
+            }
+        }
+        self.dirty_orders.insert(order_index);
+        self.dirty_positions.insert(order.market);
+        self.dirty_books.insert(order.market);
+        qty
+    }
+
+    /// Close an order with a terminal status.
+    fn finish(&mut self, order_index: i64, status: Status) {
+        let Some(order) = self.orders.get_mut(&order_index) else {
+            return;
+        };
+        order.status = status;
+        order.updated_at = self.now_ms;
+        let order = order.clone();
+        if let Some(market) = self.markets.get_mut(&order.market) {
+            market.unrest(&order);
+        }
+        self.dirty_orders.insert(order_index);
+        self.dirty_books.insert(order.market);
+        self.dirty_positions.insert(order.market);
+    }
+
+    fn cancel_all_live(&mut self) {
+        let mut live: Vec<i64> = self
+            .orders
+            .values()
+            .filter(|order| order.is_live())
+            .map(|order| order.order_index)
+            .collect();
+        live.sort_unstable();
+        for order_index in live {
+            self.finish(order_index, Status::Canceled);
+        }
+    }
+
+    fn expire(&mut self) {
+        if self
+            .scheduled_cancel_all
+            .is_some_and(|at| at <= self.now_ms)
+        {
+            self.scheduled_cancel_all = None;
+            self.cancel_all_live();
+        }
+        let now = self.now_ms;
+        let mut expired: Vec<i64> = self
+            .orders
+            .values()
+            .filter(|order| order.is_live() && order.expiry > 0 && order.expiry <= now)
+            .map(|order| order.order_index)
+            .collect();
+        expired.sort_unstable();
+        for order_index in expired {
+            self.finish(order_index, Status::CanceledExpired);
+        }
+    }
+
+    fn check_triggers(&mut self, market_id: i32) {
+        let Some(market) = self.markets.get(&market_id) else {
+            return;
+        };
+        let Some(reference) = market.reference_price() else {
+            return;
+        };
+        let fired: Vec<i64> = market
+            .triggers
+            .iter()
+            .copied()
+            .filter(|index| {
+                self.orders
+                    .get(index)
+                    .is_some_and(|order| order.trigger_hit(reference))
+            })
+            .collect();
+        for order_index in fired {
+            if let Some(market) = self.markets.get_mut(&market_id) {
+                market.triggers.retain(|index| *index != order_index);
+            }
+            if let Some(order) = self.orders.get_mut(&order_index) {
+                order.triggered = true;
+            }
+            self.place(order_index);
+        }
+    }
+
+    fn on_book(&mut self, event: &OrderBookEvent) {
+        let market_id = event.market.into_inner();
+        let Some(market) = self.markets.get_mut(&market_id) else {
+            return;
+        };
+        let scale = market.scale;
+        let (asks, bids) = match &event.delta {
+            Some(delta) => (&delta.asks, &delta.bids),
+            None => {
+                market.ext_asks.clear();
+                market.ext_bids.clear();
+                (&event.state.asks, &event.state.bids)
+            }
+        };
+        for (levels, updates) in [(&mut market.ext_asks, asks), (&mut market.ext_bids, bids)] {
+            for level in updates {
+                let (Some(price), Some(size)) = (
+                    scale.price_to_ticks(&level.price),
+                    scale.size_to_units(&level.size),
+                ) else {
+                    continue;
+                };
+                if size > 0 {
+                    levels.insert(price, size);
+                } else {
+                    levels.remove(&price);
+                }
+            }
+        }
+        self.dirty_books.insert(market_id);
+        self.shrink_queues(market_id);
+        self.sweep_crossed(market_id);
+        self.check_triggers(market_id);
+    }
+
+    /// Public size can only leave the queue ahead of a resting order, so cap
+    /// each order's place in line at the size now shown at its price.
+    fn shrink_queues(&mut self, market_id: i32) {
+        let Some(market) = self.markets.get(&market_id) else {
+            return;
+        };
+        for is_ask in [false, true] {
+            let public = market.external(is_ask);
+            let queue = if is_ask { &market.asks } else { &market.bids };
+            for (price, indices) in queue {
+                let shown = public.get(price).copied().unwrap_or(0);
+                for index in indices {
+                    if let Some(order) = self.orders.get_mut(index) {
+                        order.queue_ahead = order.queue_ahead.min(shown);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fill resting orders the mirrored book has moved through.
+    fn sweep_crossed(&mut self, market_id: i32) {
+        for is_ask in [false, true] {
+            loop {
+                let Some(market) = self.markets.get(&market_id) else {
+                    return;
+                };
+                let resting = if is_ask {
+                    market.asks.iter().next()
+                } else {
+                    market.bids.iter().next_back()
+                };
+                let Some((&price, queue)) = resting else {
+                    break;
+                };
+                let Some(&order_index) = queue.front() else {
+                    break;
+                };
+                let Some((ext_price, ext_size)) = market.best_external(!is_ask) else {
+                    break;
+                };
+                if !crosses(is_ask, price, ext_price) {
+                    break;
+                }
+                let remaining = self
+                    .orders
+                    .get(&order_index)
+                    .map_or(0, |order| order.remaining);
+                let filled = self.fill(order_index, price, remaining.min(ext_size), true);
+                if filled == 0 {
+                    // A capped reduce-only order was cancelled; anything
+                    // else would loop forever.
+                    if self.orders.get(&order_index).is_some_and(SimOrder::is_live) {
+                        break;
+                    }
+                    continue;
+                }
+                if let Some(market) = self.markets.get_mut(&market_id) {
+                    market.consume_external(!is_ask, ext_price, filled);
+                }
+            }
+        }
+    }
+
+    fn on_public_trade(&mut self, trade: &TradeData) {
+        let market_id = trade.market_id as i32;
+        let Some(market) = self.markets.get_mut(&market_id) else {
+            return;
+        };
+        let (Some(price), Some(size)) = (
+            market.scale.price_to_ticks(&trade.price),
+            market.scale.size_to_units(&trade.base_size),
+        ) else {
+            return;
+        };
+        market.last_price = Some(price);
+        self.now_ms = self.now_ms.max(trade.timestamp);
+
+        // A print through a resting order's price reaches it; one at its price
+        // first works through the public size queued ahead of it. What is left
+        // is shared by our orders in price-time priority.
+        for is_ask in [false, true] {
+            let Some(market) = self.markets.get(&market_id) else {
+                return;
+            };
+            let queue: Vec<(i64, i64)> = if is_ask {
+                market
+                    .asks
+                    .range(..=price)
+                    .flat_map(|(level, indices)| indices.iter().map(move |index| (*level, *index)))
+                    .collect()
+            } else {
+                market
+                    .bids
+                    .range(price..)
+                    .rev()
+                    .flat_map(|(level, indices)| indices.iter().map(move |index| (*level, *index)))
+                    .collect()
+            };
+            let mut available = size;
+            for (level, order_index) in queue {
+                let Some(order) = self.orders.get_mut(&order_index) else {
+                    continue;
+                };
+                let reach = if level == price {
+                    let reach = available.saturating_sub(order.queue_ahead);
+                    order.queue_ahead = order.queue_ahead.saturating_sub(size);
+                    reach
+                } else {
+                    available
+                };
+                let qty = order.remaining.min(reach);
+                if qty > 0 {
+                    available -= self.fill(order_index, level, qty, true);
+                }
+            }
+        }
+        // The mark moved, so an open position's unrealized PnL did too.
+        if self
+            .markets
+            .get(&market_id)
+            .is_some_and(|market| market.position.size != 0)
+        {
+            self.dirty_positions.insert(market_id);
+        }
+        self.check_triggers(market_id);
+    }
+
+    fn open_order_count(&self, market: i32) -> i64 {
+        self.orders
+            .values()
+            .filter(|order| order.market == market && order.is_live())
+            .count() as i64
+    }
+
+    /// Turn the changes accumulated by the last call into events.
+    fn flush(&mut self) {
+        let account = self.account.into_inner();
+        if !self.pending_txs.is_empty() {
+            let update = AccountTxUpdate {
+                txs: std::mem::take(&mut self.pending_txs),
+            };
+            self.push_account(AccountChannel::AccountTx, &update);
+        }
+
+        if !self.dirty_orders.is_empty() {
+            let mut update = OrdersUpdate {
+                account: Some(account),
+                ..Default::default()
+            };
+            for order_index in std::mem::take(&mut self.dirty_orders) {
+                let Some(order) = self.orders.get(&order_index) else {
+                    continue;
+                };
+                let Some(market) = self.markets.get(&order.market) else {
+                    continue;
+                };
+                update
+                    .orders
+                    .entry(order.market)
+                    .or_default()
+                    .push(order.to_model(account, market.scale));
+            }
+            self.push_account(AccountChannel::AllOrders, &update);
+        }
+
+        if !self.pending_trades.is_empty() {
+            let update = TradesUpdate {
+                trades: std::mem::take(&mut self.pending_trades),
+                ..Default::default()
+            };
+            self.push_account(AccountChannel::AllTrades, &update);
+        }
+
+        if !self.dirty_positions.is_empty() {
+            let mut update = PositionsUpdate::default();
+            for market_id in std::mem::take(&mut self.dirty_positions) {
+                if let Some(market) = self.markets.get(&market_id) {
+                    update.positions.insert(
+                        market_id,
+                        market.to_position(market_id, self.open_order_count(market_id)),
+                    );
+                }
+            }
+            self.push_account(AccountChannel::AllPositions, &update);
+        }
+
+        for market_id in std::mem::take(&mut self.dirty_books) {
+            if let Some(event) = self.publish_book(market_id) {
+                self.events.push_back(WsEvent::OrderBook(event));
+            }
+        }
+    }
+
+    fn push_account<T: Serialize>(&mut self, channel: AccountChannel, payload: &T) {
+        let value = match serde_json::to_value(payload) {
+            Ok(value) => value,
+            Err(err) => {
+                tracing::warn!(error = %err, channel = channel.as_str(), "failed to encode simulated event");
+                return;
+            }
+        };
+        let value = self.account_message(channel, "update", value);
+        self.events
+            .push_back(WsEvent::Account(AccountEventEnvelope {
+                account: self.account,
+                snapshot: false,
+                event: AccountEvent::new(value),
+            }));
+    }
+
+    /// Add the `type` and `channel` fields of an account channel message.
+    fn account_message(&self, channel: AccountChannel, kind: &str, mut value: Value) -> Value {
+        let account = self.account.into_inner();
+        if let Value::Object(map) = &mut value {
+            map.insert(
+                "channel".into(),
+                Value::from(format!("{}:{account}", channel.as_str())),
+            );
+            map.insert(
+                "type".into(),
+                Value::from(format!("{kind}/{}", channel.as_str())),
+            );
+        }
+        value
+    }
+
+    /// Combined public and simulated book, as a delta against the last
+    /// published state (a snapshot the first time).
+    fn publish_book(&mut self, market_id: i32) -> Option<OrderBookEvent> {
+        let market = self.markets.get(&market_id)?;
+        let (bids, asks) = market.combined_levels(&self.orders);
+        let scale = market.scale;
+        let delta = match &market.published {
+            Some((old_bids, old_asks)) => {
+                let delta = OrderBookDelta {
+                    asks: diff_levels(scale, old_asks, &asks),
+                    bids: diff_levels(scale, old_bids, &bids),
+                };
+                if delta.asks.is_empty() && delta.bids.is_empty() {
+                    return None;
+                }
+                Some(delta)
+            }
+            None => None,
+        };
+        let state = book_state(scale, &bids, &asks);
+
+        let market = self.markets.get_mut(&market_id)?;
+        let begin_nonce = market.published.as_ref().map(|_| market.book_nonce);
+        market.book_nonce += 1;
+        market.published = Some((bids, asks));
+        Some(OrderBookEvent {
+            market: MarketId::new(market_id),
+            state,
+            delta,
+            nonce: Some(market.book_nonce),
+            begin_nonce,
+            offset: Some(market.book_nonce as i64),
+        })
+    }
+}
+
+/// Whether an order on `is_ask` limited at `limit` trades with a level at
+/// `price` on the other side.
+fn crosses(is_ask: bool, limit: i64, price: i64) -> bool {
+    if is_ask {
+        price >= limit
+    } else {
+        price <= limit
+    }
+}
+
+fn book_state(
+    scale: BookScale,
+    bids: &BTreeMap<i64, i64>,
+    asks: &BTreeMap<i64, i64>,
+) -> OrderBookState {
+    OrderBookState {
+        asks: asks.iter().map(|(p, s)| level(scale, *p, *s)).collect(),
+        bids: bids
+            .iter()
+            .rev()
+            .map(|(p, s)| level(scale, *p, *s))
+            .collect(),
+    }
+}
+
+fn diff_levels(
+    scale: BookScale,
+    old: &BTreeMap<i64, i64>,
+    new: &BTreeMap<i64, i64>,
+) -> Vec<OrderBookLevel> {
+    let mut changed: Vec<_> = new
+        .iter()
+        .filter(|(price, size)| old.get(price) != Some(size))
+        .map(|(price, size)| level(scale, *price, *size))
+        .collect();
+    changed.extend(
+        old.keys()
+            .filter(|price| !new.contains_key(price))
+            .map(|price| level(scale, *price, 0)),
+    );
+    changed
+}
+
+fn level(scale: BookScale, price: i64, size: i64) -> OrderBookLevel {
+    OrderBookLevel {
+        price: price_text(scale, price),
+        size: size_text(scale, size),
+        remaining_base_amount: None,
+        extra: HashMap::new(),
+    }
+}
+
+fn price_text(scale: BookScale, ticks: i64) -> String {
+    Decimal::from_scaled(ticks, scale.price_decimals).to_string()
+}
+
+fn size_text(scale: BookScale, units: i64) -> String {
+    Decimal::from_scaled(units, scale.size_decimals).to_string()
+}
+
+fn quote_text(scale: BookScale, quote: i128) -> String {
+    Decimal::new(quote, scale.price_decimals + scale.size_decimals).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        account_events::TypedAccountEvent,
+        tx_signer::{MockSigner, TxSigner},
+    };
+
+    const MARKET: i32 = 0;
+
+    fn exchange() -> SimExchange {
+        let mut sim = SimExchange::new(AccountId::new(42)).with_fees(0, 200);
+        sim.add_market(MarketId::new(MARKET), BookScale::new(2, 2));
+        sim.on_market_event(&WsEvent::OrderBook(OrderBookEvent {
+            market: MarketId::new(MARKET),
+            state: OrderBookState {
+                asks: vec![level(BookScale::new(2, 2), 10_010, 100)],
+                bids: vec![level(BookScale::new(2, 2), 9_990, 100)],
+            },
+            delta: None,
+            nonce: None,
+            begin_nonce: None,
+            offset: None,
+        }));
+        sim.drain_events();
+        sim
+    }
+
+    fn create(
+        sim: &mut SimExchange,
+        signer: &MockSigner,
+        nonce: i64,
+        is_ask: bool,
+        price: i32,
+        size: i64,
+        time_in_force: i32,
+    ) -> models::RespSendTx {
+        let (info, _) = signer
+            .sign_create_order(
+                MARKET,
+                nonce,
+                size,
+                price,
+                is_ask,
+                ORDER_TYPE_LIMIT,
+                time_in_force,
+                false,
+                0,
+                -1,
+                nonce,
+            )
+            .unwrap();
+        sim.submit_tx(TX_TYPE_CREATE_ORDER.into(), &info.unwrap())
+    }
+
+    fn print(sim: &mut SimExchange, price: &str, size: &str, timestamp: i64) {
+        let trade: TradeData = serde_json::from_value(serde_json::json!({
+            "market_id": MARKET,
+            "price": price,
+            "base_size": size,
+            "timestamp": timestamp,
+        }))
+        .unwrap();
+        sim.on_market_event(&WsEvent::Trade(crate::ws_client::TradeEvent {
+            channel: format!("trade/{MARKET}"),
+            trades: vec![trade],
+        }));
+    }
+
+    #[test]
+    fn post_only_crossing_is_rejected_and_nonces_are_checked() {
+        let mut sim = exchange();
+        let signer = MockSigner::new(42);
+        let ack = create(
+            &mut sim,
+            &signer,
+            1,
+            false,
+            10_010,
+            50,
+            ORDER_TIME_IN_FORCE_POST_ONLY,
+        );
+        assert_eq!(ack.code, CODE_OK);
+        assert_eq!(sim.order(1).unwrap().status, Status::CanceledPostOnly);
+
+        let replay = create(
+            &mut sim,
+            &signer,
+            1,
+            false,
+            10_000,
+            50,
+            ORDER_TIME_IN_FORCE_GTT,
+        );
+        assert_eq!(replay.code, CODE_INVALID_NONCE);
+    }
+
+    #[test]
+    fn ioc_fills_against_book_and_cancels_remainder() {
+        let mut sim = exchange();
+        let signer = MockSigner::new(42);
+        create(
+            &mut sim,
+            &signer,
+            1,
+            false,
+            10_020,
+            150,
+            ORDER_TIME_IN_FORCE_IOC,
+        );
+
+        let order = sim.order(1).unwrap();
+        assert_eq!(order.status, Status::Canceled);
+        assert_eq!(order.filled_base_amount, "1.00");
+        assert_eq!(sim.position(MarketId::new(MARKET)).unwrap().sign, 1);
+        assert!((sim.fees_paid() - 100.10 * 200.0 / 1e6).abs() < 1e-9);
+
+        let events: Vec<_> = sim
+            .drain_events()
+            .into_iter()
+            .filter_map(|event| match event {
+                WsEvent::Account(envelope) => Some(envelope.decode().unwrap()),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            matches!(&events[0], TypedAccountEvent::AccountTx(update) if update.txs[0].status == TX_STATUS_SUCCESS)
+        );
+        let TypedAccountEvent::AllTrades(trades) = &events[2] else {
+            panic!("expected trades, got {events:?}");
+        };
+        let trade = &trades.trades_for(MarketId::new(MARKET))[0];
+        assert_eq!((trade.price.as_str(), trade.bid_id), ("100.10", 1));
+    }
+
+    #[test]
+    fn resting_orders_fill_from_prints_in_price_time_priority() {
+        let mut sim = exchange();
+        let signer = MockSigner::new(42);
+        create(
+            &mut sim,
+            &signer,
+            1,
+            false,
+            9_995,
+            30,
+            ORDER_TIME_IN_FORCE_GTT,
+        );
+        create(
+            &mut sim,
+            &signer,
+            2,
+            false,
+            9_995,
+            30,
+            ORDER_TIME_IN_FORCE_GTT,
+        );
+        create(
+            &mut sim,
+            &signer,
+            3,
+            false,
+            10_000,
+            30,
+            ORDER_TIME_IN_FORCE_GTT,
+        );
+        sim.drain_events();
+
+        print(&mut sim, "99.95", "0.70", 1_000);
+        assert_eq!(sim.order(3).unwrap().status, Status::Filled);
+        assert_eq!(sim.order(1).unwrap().status, Status::Filled);
+        assert_eq!(sim.order(2).unwrap().remaining_base_amount, "0.20");
+
+        let book = sim
+            .drain_events()
+            .into_iter()
+            .find_map(|event| match event {
+                WsEvent::OrderBook(book) => Some(book),
+                _ => None,
+            })
+            .unwrap();
+        let delta = book.delta.unwrap();
+        assert!(delta
+            .bids
+            .iter()
+            .any(|l| l.price == "100.00" && l.size == "0.00"));
+    }
+
+    #[test]
+    fn resting_orders_wait_behind_the_public_queue() {
+        let mut sim = exchange();
+        let signer = MockSigner::new(42);
+        create(
+            &mut sim,
+            &signer,
+            1,
+            false,
+            9_990,
+            30,
+            ORDER_TIME_IN_FORCE_GTT,
+        );
+
+        // 1.00 of public size was resting at 99.90 first.
+        print(&mut sim, "99.90", "0.50", 1_000);
+        assert_eq!(sim.order(1).unwrap().filled_base_amount, "0.00");
+
+        // Cancellations shrink the level to 0.20, all of it still ahead.
+        sim.on_market_event(&WsEvent::OrderBook(OrderBookEvent {
+            market: MarketId::new(MARKET),
+            state: OrderBookState {
+                asks: Vec::new(),
+                bids: Vec::new(),
+            },
+            delta: Some(OrderBookDelta {
+                asks: Vec::new(),
+                bids: vec![level(BookScale::new(2, 2), 9_990, 20)],
+            }),
+            nonce: None,
+            begin_nonce: None,
+            offset: None,
+        }));
+        print(&mut sim, "99.90", "0.40", 2_000);
+        let order = sim.order(1).unwrap();
+        assert_eq!(order.filled_base_amount, "0.20");
+        assert_eq!(order.remaining_base_amount, "0.10");
+
+        print(&mut sim, "99.80", "0.10", 3_000);
+        assert_eq!(sim.order(1).unwrap().status, Status::Filled);
+    }
+
+    #[test]
+    fn gtt_orders_expire_and_stops_trigger() {
+        let mut sim = exchange();
+        let signer = MockSigner::new(42);
+        let (info, _) = signer
+            .sign_create_order(
+                MARKET,
+                1,
+                40,
+                9_000,
+                false,
+                ORDER_TYPE_LIMIT,
+                ORDER_TIME_IN_FORCE_GTT,
+                false,
+                0,
+                5_000,
+                1,
+            )
+            .unwrap();
+        sim.submit_tx(TX_TYPE_CREATE_ORDER.into(), &info.unwrap());
+        let (info, _) = signer
+            .sign_create_order(
+                MARKET,
+                2,
+                40,
+                0,
+                false,
+                ORDER_TYPE_STOP_LOSS,
+                ORDER_TIME_IN_FORCE_IOC,
+                false,
+                10_050,
+                0,
+                2,
+            )
+            .unwrap();
+        sim.submit_tx(TX_TYPE_CREATE_ORDER.into(), &info.unwrap());
+        assert_eq!(
+            sim.order(2).unwrap().trigger_status,
+            TriggerStatus::MarkPrice
+        );
+
+        sim.advance_time(5_000);
+        assert_eq!(sim.order(1).unwrap().status, Status::CanceledExpired);
+
+        print(&mut sim, "100.50", "0.01", 6_000);
+        let stop = sim.order(2).unwrap();
+        assert_eq!(stop.status, Status::Filled);
+        assert_eq!(stop.filled_quote_amount, "40.0400");
+    }
+}
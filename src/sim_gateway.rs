@@ -0,0 +1,1138 @@
+//! Loopback exchange serving a [`SimExchange`] over the REST and websocket
+//! wire protocol.
+//!
+//! [`SimGateway`] listens on one local port for both transports, so a
+//! [`LighterClient`] pointed at [`SimGateway::url`] signs, submits and
+//! streams exactly as it does against the exchange while orders fill in the
+//! simulator. [`LighterClientBuilder::simulated`] sets this up. The gateway
+//! answers itself:
+//!
+//! - `sendTx`, `sendTxBatch` and `nextNonce`, and `orderBookOrders` for
+//!   simulated markets;
+//! - websocket `jsonapi/sendtx` and `jsonapi/sendtxbatch`;
+//! - `order_book` subscriptions for simulated markets, and
+//!   `account_all_orders`, `account_all_trades`, `account_all_positions` and
+//!   `account_tx` for the simulated account.
+//!
+//! Everything else goes to the upstream exchange when one is configured. The
+//! upstream also feeds the simulated markets their public order book and
+//! trades, and the simulated clock follows the client's. Without an
+//! upstream the gateway is offline: market data and time are fed through
+//! [`SimGateway::with_sim`].
+//!
+//! ```ignore
+//! let mut sim = SimExchange::new(AccountId::new(42));
+//! sim.add_market(MarketId::new(0), BookScale::new(2, 4));
+//! let client = LighterClient::builder()
+//!     .api_url("https://mainnet.zklighter.elliot.ai")
+//!     .simulated(sim)
+//!     .private_key(key)
+//!     .api_key_index(2)
+//!     .account_index(42)
+//!     .build()
+//!     .await?;
+//! let fees = client.simulator().unwrap().with_sim(|sim| sim.fees_paid());
+//! ```
+//!
+//! [`LighterClient`]: crate::lighter_client::LighterClient
+//! [`LighterClientBuilder::simulated`]: crate::lighter_client::LighterClientBuilder::simulated
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{broadcast, mpsc},
+    task::{JoinHandle, JoinSet},
+};
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+use crate::{
+    account_events::AccountChannel,
+    clock::SharedClock,
+    lighter_client::LighterClient,
+    models,
+    signer_client::{BatchEntry, CODE_OK},
+    sim_exchange::{SimExchange, EXTERNAL_ACCOUNT},
+    types::MarketId,
+    ws_client::{Channel, OrderBookEvent, WsEvent},
+};
+
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+/// Simulator events buffered per websocket connection before it resyncs.
+const EVENT_BUFFER: usize = 4096;
+const CLOCK_TICK: Duration = Duration::from_millis(100);
+
+/// A [`SimExchange`] served on a loopback port.
+///
+/// Dropping the gateway stops the listener, its connections and the
+/// upstream market data feed.
+pub struct SimGateway {
+    url: String,
+    shared: Arc<Shared>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl SimGateway {
+    /// Serve `sim` on a free loopback port. With an `upstream` REST URL the
+    /// gateway mirrors its market data into the simulated markets, moves
+    /// the simulated clock with `clock`, and forwards every request it does
+    /// not simulate.
+    pub async fn start(
+        sim: SimExchange,
+        upstream: Option<&str>,
+        clock: SharedClock,
+    ) -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("http://{}", listener.local_addr()?);
+        let markets = sim.markets();
+        let upstream = upstream.map(Upstream::new).transpose()?;
+        let (events, _) = broadcast::channel(EVENT_BUFFER);
+        let shared = Arc::new(Shared {
+            sim: Mutex::new(sim),
+            events,
+            upstream,
+        });
+
+        let mut tasks = vec![tokio::spawn(accept(listener, Arc::clone(&shared)))];
+        if let Some(upstream) = &shared.upstream {
+            tasks.push(tokio::spawn(mirror(
+                Arc::clone(&shared),
+                upstream.rest.clone(),
+                markets,
+            )));
+            tasks.push(tokio::spawn(follow_clock(Arc::clone(&shared), clock)));
+        }
+        Ok(Self { url, shared, tasks })
+    }
+
+    /// Base URL to use as the client's REST endpoint; the websocket is served
+    /// on the same port at `/stream`.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Run `f` against the simulator, then publish the events it produced to
+    /// connected websockets. Use it to feed market data and time when the
+    /// gateway is offline, and to inspect the simulated account.
+    pub fn with_sim<R>(&self, f: impl FnOnce(&mut SimExchange) -> R) -> R {
+        self.shared.run(f)
+    }
+}
+
+impl Drop for SimGateway {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+impl std::fmt::Debug for SimGateway {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SimGateway")
+            .field("url", &self.url)
+            .field("upstream", &self.shared.upstream.as_ref().map(|u| &u.rest))
+            .finish()
+    }
+}
+
+struct Shared {
+    sim: Mutex<SimExchange>,
+    events: broadcast::Sender<WsEvent>,
+    upstream: Option<Upstream>,
+}
+
+impl Shared {
+    fn lock(&self) -> std::sync::MutexGuard<'_, SimExchange> {
+        self.sim.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Apply `f` and broadcast the resulting events. Events are sent under
+    /// the lock, so every receiver sees them in simulator order.
+    fn run<R>(&self, f: impl FnOnce(&mut SimExchange) -> R) -> R {
+        let mut sim = self.lock();
+        let result = f(&mut sim);
+        for event in sim.drain_events() {
+            // No receivers is fine: nobody is connected yet.
+            let _ = self.events.send(event);
+        }
+        result
+    }
+}
+
+struct Upstream {
+    rest: String,
+    stream: String,
+    http: reqwest::Client,
+}
+
+impl Upstream {
+    fn new(rest: &str) -> io::Result<Self> {
+        let rest = rest.trim_end_matches('/').to_string();
+        let mut stream = url::Url::parse(&rest)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+        let scheme = if stream.scheme() == "http" {
+            "ws"
+        } else {
+            "wss"
+        };
+        stream
+            .set_scheme(scheme)
+            .map_err(|()| io::Error::new(io::ErrorKind::InvalidInput, "unsupported upstream"))?;
+        stream.set_path("/stream");
+        Ok(Self {
+            rest,
+            stream: stream.to_string(),
+            http: reqwest::Client::new(),
+        })
+    }
+
+    async fn forward(&self, request: &Request) -> Response {
+        let method =
+            reqwest::Method::from_bytes(request.method.as_bytes()).unwrap_or(reqwest::Method::GET);
+        let mut builder = self
+            .http
+            .request(method, format!("{}{}", self.rest, request.target))
+            .body(request.body.clone());
+        for (name, value) in &request.headers {
+            if !matches!(
+                name.as_str(),
+                "host" | "content-length" | "connection" | "accept-encoding"
+            ) {
+                builder = builder.header(name.as_str(), value.as_str());
+            }
+        }
+        let response = match builder.send().await {
+            Ok(response) => response,
+            Err(err) => return Response::error(502, format!("upstream: {err}")),
+        };
+        let status = response.status().as_u16();
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or("application/json")
+            .to_string();
+        match response.bytes().await {
+            Ok(body) => Response {
+                status,
+                content_type,
+                body: body.to_vec(),
+            },
+            Err(err) => Response::error(502, format!("upstream: {err}")),
+        }
+    }
+}
+
+async fn accept(listener: TcpListener, shared: Arc<Shared>) {
+    // Connections live in the set so that aborting this task ends them too.
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    connections.spawn(serve_connection(stream, Arc::clone(&shared)));
+                }
+                Err(err) => {
+                    tracing::warn!(error = %err, "simulator gateway accept failed");
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                }
+            },
+            Some(_) = connections.join_next() => {}
+        }
+    }
+}
+
+/// Feed the upstream order books and trades of the simulated markets into
+/// the simulator.
+async fn mirror(shared: Arc<Shared>, upstream: String, markets: Vec<MarketId>) {
+    if markets.is_empty() {
+        return;
+    }
+    let client = match LighterClient::new(upstream).await {
+        Ok(client) => client,
+        Err(err) => {
+            tracing::warn!(error = %err, "simulator market data client failed");
+            return;
+        }
+    };
+    let mut builder = client
+        .ws()
+        .subscribe_order_books(markets.iter().copied())
+        .supervised();
+    for market in &markets {
+        builder = builder.subscribe_trade(*market);
+    }
+    let mut stream = match builder.connect().await {
+        Ok(stream) => stream,
+        Err(err) => {
+            tracing::warn!(error = %err, "simulator market data connection failed");
+            return;
+        }
+    };
+    while let Some(event) = stream.next().await {
+        match event {
+            Ok(event @ (WsEvent::OrderBook(_) | WsEvent::Trade(_))) => {
+                shared.run(|sim| sim.on_market_event(&event));
+            }
+            Ok(_) => {}
+            Err(err) => tracing::warn!(error = %err, "simulator market data error"),
+        }
+    }
+    tracing::warn!("simulator market data stream ended");
+}
+
+/// Move the simulated clock with `clock` so GTT orders and scheduled
+/// cancels come due in real time.
+async fn follow_clock(shared: Arc<Shared>, clock: SharedClock) {
+    let mut ticker = tokio::time::interval(CLOCK_TICK);
+    loop {
+        ticker.tick().await;
+        let now_ms = clock
+            .system_now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64;
+        shared.run(|sim| sim.advance_time(now_ms));
+    }
+}
+
+async fn serve_connection(mut stream: TcpStream, shared: Arc<Shared>) {
+    let request = match read_request(&mut stream).await {
+        Ok(Some(request)) => request,
+        Ok(None) => return,
+        Err(err) => {
+            tracing::debug!(error = %err, "simulator gateway dropped a malformed request");
+            let _ = stream
+                .write_all(&Response::error(400, err.to_string()).encode())
+                .await;
+            return;
+        }
+    };
+
+    let upgrade = request
+        .header("upgrade")
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    if !upgrade {
+        let response = answer(&shared, &request).await;
+        let _ = stream.write_all(&response.encode()).await;
+        return;
+    }
+
+    let Some(key) = request.header("sec-websocket-key") else {
+        let response = Response::error(400, "missing sec-websocket-key".into());
+        let _ = stream.write_all(&response.encode()).await;
+        return;
+    };
+    let head = format!(
+        "HTTP/1.1 101 Switching Protocols\r\nupgrade: websocket\r\nconnection: Upgrade\r\nsec-websocket-accept: {}\r\n\r\n",
+        derive_accept_key(key.as_bytes())
+    );
+    if stream.write_all(head.as_bytes()).await.is_err() {
+        return;
+    }
+    let socket = WebSocketStream::from_raw_socket(stream, Role::Server, None).await;
+    Session::new(socket, shared).run().await;
+}
+
+// ---------------------------------------------------------------------------
+// REST
+// ---------------------------------------------------------------------------
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    target: String,
+    /// Lowercased names.
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn path(&self) -> &str {
+        self.target
+            .split_once('?')
+            .map_or(self.target.as_str(), |(path, _)| path)
+    }
+
+    fn query(&self) -> HashMap<String, String> {
+        let query = self.target.split_once('?').map_or("", |(_, query)| query);
+        url::form_urlencoded::parse(query.as_bytes())
+            .into_owned()
+            .collect()
+    }
+
+    /// Fields of a `multipart/form-data` or urlencoded body.
+    fn form(&self) -> HashMap<String, String> {
+        let content_type = self.header("content-type").unwrap_or_default();
+        let Some(boundary) = content_type
+            .split(';')
+            .find_map(|param| param.trim().strip_prefix("boundary="))
+        else {
+            return url::form_urlencoded::parse(&self.body)
+                .into_owned()
+                .collect();
+        };
+        let delimiter = format!("--{}", boundary.trim_matches('"'));
+        let body = String::from_utf8_lossy(&self.body);
+        body.split(delimiter.as_str())
+            .filter_map(|part| {
+                let (head, value) = part.split_once("\r\n\r\n")?;
+                let start = head.find("name=\"")? + "name=\"".len();
+                let end = start + head[start..].find('"')?;
+                let value = value.strip_suffix("\r\n").unwrap_or(value);
+                Some((head[start..end].to_string(), value.to_string()))
+            })
+            .collect()
+    }
+}
+
+async fn read_request(stream: &mut TcpStream) -> io::Result<Option<Request>> {
+    let invalid = |why: &str| io::Error::new(io::ErrorKind::InvalidData, why.to_string());
+    let mut buffer = Vec::new();
+    let mut chunk = [0u8; 8192];
+    let head_end = loop {
+        if let Some(end) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(invalid("request head too large"));
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = std::str::from_utf8(&buffer[..head_end])
+        .map_err(|_| invalid("request head is not UTF-8"))?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(invalid("malformed request line"));
+    };
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value.trim().to_string()))
+        .collect();
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .map(|(_, value)| value.parse::<usize>())
+        .transpose()
+        .map_err(|_| invalid("invalid content-length"))?
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err(invalid("request body too large"));
+    }
+
+    let method = method.to_string();
+    let target = target.to_string();
+    let mut body = buffer.split_off(head_end + 4);
+    while body.len() < length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(length);
+    Ok(Some(Request {
+        method,
+        target,
+        headers,
+        body,
+    }))
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    content_type: String,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn json(status: u16, body: &Value) -> Self {
+        Self {
+            status,
+            content_type: "application/json".into(),
+            body: body.to_string().into_bytes(),
+        }
+    }
+
+    fn error(status: u16, message: String) -> Self {
+        Self::json(status, &json!({ "code": status, "message": message }))
+    }
+
+    /// `200` with the body on success; otherwise `400` with the code and
+    /// message, as the exchange answers failed transactions.
+    fn tx<T: serde::Serialize>(code: i32, message: Option<String>, body: &T) -> Self {
+        if code == CODE_OK {
+            match serde_json::to_value(body) {
+                Ok(body) => Self::json(200, &body),
+                Err(err) => Self::error(500, err.to_string()),
+            }
+        } else {
+            Self::json(400, &json!({ "code": code, "message": message }))
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let reason = reqwest::StatusCode::from_u16(self.status)
+            .ok()
+            .and_then(|status| status.canonical_reason())
+            .unwrap_or("Unknown");
+        let mut bytes = format!(
+            "HTTP/1.1 {} {reason}\r\ncontent-type: {}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+async fn answer(shared: &Shared, request: &Request) -> Response {
+    let simulated = match (request.method.as_str(), request.path()) {
+        ("POST", "/api/v1/sendTx") => Some(send_tx(shared, request)),
+        ("POST", "/api/v1/sendTxBatch") => Some(send_tx_batch(shared, request)),
+        ("GET", "/api/v1/nextNonce") => Some(next_nonce(shared, request)),
+        ("GET", "/api/v1/orderBookOrders") => order_book_orders(shared, request),
+        _ => None,
+    };
+    match (simulated, &shared.upstream) {
+        (Some(response), _) => response,
+        (None, Some(upstream)) => upstream.forward(request).await,
+        (None, None) => Response::error(404, format!("{} is not simulated", request.path())),
+    }
+}
+
+fn send_tx(shared: &Shared, request: &Request) -> Response {
+    let form = request.form();
+    let (Some(tx_type), Some(tx_info)) = (
+        form.get("tx_type")
+            .and_then(|value| value.parse::<i32>().ok()),
+        form.get("tx_info"),
+    ) else {
+        return Response::error(400, "tx_type and tx_info are required".into());
+    };
+    let response = shared.run(|sim| sim.submit_tx(tx_type, tx_info));
+    Response::tx(response.code, response.message.clone(), &response)
+}
+
+fn send_tx_batch(shared: &Shared, request: &Request) -> Response {
+    let form = request.form();
+    let entries = form
+        .get("tx_types")
+        .zip(form.get("tx_infos"))
+        .and_then(|(types, infos)| {
+            batch_entries(&Value::String(types.clone()), &Value::String(infos.clone()))
+        });
+    let Some(entries) = entries else {
+        return Response::error(400, "tx_types and tx_infos must be matching lists".into());
+    };
+    let response = shared.run(|sim| sim.submit_batch(&entries));
+    Response::tx(response.code, response.message.clone(), &response)
+}
+
+fn next_nonce(shared: &Shared, request: &Request) -> Response {
+    let Some(api_key_index) = request
+        .query()
+        .get("api_key_index")
+        .and_then(|value| value.parse::<i32>().ok())
+    else {
+        return Response::error(400, "api_key_index is required".into());
+    };
+    let nonce = shared.lock().next_nonce(api_key_index);
+    Response::json(200, &json!(models::NextNonce::new(CODE_OK, nonce)))
+}
+
+/// The combined book as resting "orders", one per level. `None` for markets
+/// the simulator does not know.
+fn order_book_orders(shared: &Shared, request: &Request) -> Option<Response> {
+    let query = request.query();
+    let market = query.get("market_id")?.parse::<i32>().ok()?;
+    let limit = query
+        .get("limit")
+        .and_then(|value| value.parse::<usize>().ok())
+        .unwrap_or(usize::MAX);
+    let book = shared.lock().book(MarketId::new(market))?;
+    let orders = |levels: &[crate::ws_client::OrderBookLevel]| -> Vec<models::SimpleOrder> {
+        levels
+            .iter()
+            .take(limit)
+            .map(|level| models::SimpleOrder {
+                owner_account_index: EXTERNAL_ACCOUNT,
+                initial_base_amount: level.size.clone(),
+                remaining_base_amount: level.size.clone(),
+                price: level.price.clone(),
+                ..Default::default()
+            })
+            .collect()
+    };
+    let asks = orders(&book.state.asks);
+    let bids = orders(&book.state.bids);
+    let body =
+        models::OrderBookOrders::new(CODE_OK, asks.len() as i64, asks, bids.len() as i64, bids);
+    Some(Response::json(200, &json!(body)))
+}
+
+/// Pair `tx_types` with `tx_infos`, each given as a JSON list or a string
+/// holding one.
+fn batch_entries(tx_types: &Value, tx_infos: &Value) -> Option<Vec<BatchEntry>> {
+    let list = |value: &Value| match value {
+        Value::String(text) => serde_json::from_str::<Vec<Value>>(text).ok(),
+        Value::Array(items) => Some(items.clone()),
+        _ => None,
+    };
+    let types = list(tx_types)?;
+    let infos = list(tx_infos)?;
+    if types.len() != infos.len() {
+        return None;
+    }
+    types
+        .iter()
+        .zip(&infos)
+        .map(|(tx_type, tx_info)| {
+            let tx_type = i32::try_from(tx_type.as_i64()?).ok()?;
+            Some(BatchEntry::new(tx_type, tx_info_text(tx_info)))
+        })
+        .collect()
+}
+
+/// `tx_info` is sent either as the signed JSON text or as the object itself.
+fn tx_info_text(tx_info: &Value) -> String {
+    match tx_info {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Websocket
+// ---------------------------------------------------------------------------
+
+struct Session {
+    socket: WebSocketStream<TcpStream>,
+    shared: Arc<Shared>,
+    events: broadcast::Receiver<WsEvent>,
+    /// Channels served from the simulator.
+    channels: HashSet<Channel>,
+    relay: Option<Relay>,
+    relayed_tx: mpsc::UnboundedSender<String>,
+    relayed: mpsc::UnboundedReceiver<String>,
+}
+
+impl Session {
+    fn new(socket: WebSocketStream<TcpStream>, shared: Arc<Shared>) -> Self {
+        let events = shared.events.subscribe();
+        let (relayed_tx, relayed) = mpsc::unbounded_channel();
+        Self {
+            socket,
+            shared,
+            events,
+            channels: HashSet::new(),
+            relay: None,
+            relayed_tx,
+            relayed,
+        }
+    }
+
+    async fn run(mut self) {
+        if self.send(&json!({ "type": "connected" })).await.is_err() {
+            return;
+        }
+        loop {
+            let result = tokio::select! {
+                frame = self.socket.next() => match frame {
+                    Some(Ok(Message::Text(text))) => self.on_text(&text).await,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    // Protocol pings are answered by tungstenite.
+                    Some(Ok(_)) => Ok(()),
+                },
+                event = self.events.recv() => match event {
+                    Ok(event) => match self.encode(&event) {
+                        Some(message) => self.send(&message).await,
+                        None => Ok(()),
+                    },
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "simulator gateway client lagged, resending snapshots");
+                        self.resync().await
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                },
+                Some(text) = self.relayed.recv() => {
+                    self.socket.send(Message::Text(text)).await.map_err(|_| ())
+                }
+            };
+            if result.is_err() {
+                break;
+            }
+        }
+    }
+
+    async fn send(&mut self, message: &Value) -> Result<(), ()> {
+        self.socket
+            .send(Message::Text(message.to_string()))
+            .await
+            .map_err(|_| ())
+    }
+
+    async fn on_text(&mut self, text: &str) -> Result<(), ()> {
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return self
+                .send(&json!({ "type": "error", "error": "invalid json" }))
+                .await;
+        };
+        let kind = message
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let channel = message
+            .get("channel")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        match kind {
+            "ping" => self.send(&json!({ "type": "pong" })).await,
+            "pong" => Ok(()),
+            "subscribe" => self.subscribe(channel, text).await,
+            "unsubscribe" => self.unsubscribe(channel, text).await,
+            "jsonapi/sendtx" => {
+                let reply = self.send_tx(message.get("data").unwrap_or(&Value::Null));
+                self.send(&reply).await
+            }
+            "jsonapi/sendtxbatch" => {
+                let reply = self.send_tx_batch(message.get("data").unwrap_or(&Value::Null));
+                self.send(&reply).await
+            }
+            _ => {
+                self.pass_upstream(text, format!("{kind} is not simulated"))
+                    .await
+            }
+        }
+    }
+
+    /// Whether the simulator serves `channel`.
+    fn simulates(&self, channel: Channel) -> bool {
+        let sim = self.shared.lock();
+        match channel {
+            Channel::OrderBook(market) => sim.markets().contains(&market),
+            Channel::AccountAllOrders(account)
+            | Channel::AccountAllTrades(account)
+            | Channel::AccountAllPositions(account)
+            | Channel::AccountTx(account) => account == sim.account(),
+            _ => false,
+        }
+    }
+
+    async fn subscribe(&mut self, name: &str, text: &str) -> Result<(), ()> {
+        match Channel::parse(name) {
+            Some(channel) if self.simulates(channel) => {
+                for message in self.snapshot(channel) {
+                    self.send(&message).await?;
+                }
+                Ok(())
+            }
+            Some(Channel::Trade(market)) if self.shared.upstream.is_none() => {
+                // Offline there are no public prints; acknowledge with none.
+                self.send(&json!({
+                    "type": "subscribed/trade",
+                    "channel": format!("trade:{}", market.into_inner()),
+                    "trades": [],
+                }))
+                .await
+            }
+            _ => {
+                self.pass_upstream(text, format!("cannot subscribe to {name}: not simulated"))
+                    .await
+            }
+        }
+    }
+
+    async fn unsubscribe(&mut self, name: &str, text: &str) -> Result<(), ()> {
+        match Channel::parse(name) {
+            Some(channel) if self.channels.remove(&channel) => {
+                self.send(&json!({
+                    "type": "unsubscribed",
+                    "channel": name.replacen('/', ":", 1),
+                }))
+                .await
+            }
+            _ => {
+                self.pass_upstream(text, format!("not subscribed to {name}"))
+                    .await
+            }
+        }
+    }
+
+    /// Messages to send for a new subscription to `channel`: events queued
+    /// for channels already served, then the snapshot.
+    ///
+    /// The snapshot is taken under the simulator lock after draining the
+    /// broadcast queue, so no event older than the snapshot reaches the new
+    /// channel and none newer is missed.
+    fn snapshot(&mut self, channel: Channel) -> Vec<Value> {
+        let mut messages = Vec::new();
+        {
+            let sim = self.shared.lock();
+            while let Ok(event) = self.events.try_recv() {
+                messages.extend(self.encode(&event));
+            }
+            let snapshot = match channel {
+                Channel::OrderBook(market) => sim.book(market).as_ref().map(order_book_message),
+                Channel::AccountAllOrders(_) => sim.account_snapshot(AccountChannel::AllOrders),
+                Channel::AccountAllTrades(_) => sim.account_snapshot(AccountChannel::AllTrades),
+                Channel::AccountAllPositions(_) => {
+                    sim.account_snapshot(AccountChannel::AllPositions)
+                }
+                Channel::AccountTx(_) => sim.account_snapshot(AccountChannel::AccountTx),
+                _ => None,
+            };
+            messages.extend(snapshot);
+        }
+        self.channels.insert(channel);
+        messages
+    }
+
+    /// Send fresh snapshots of every simulated channel after this
+    /// connection fell behind the event stream.
+    async fn resync(&mut self) -> Result<(), ()> {
+        self.events = self.events.resubscribe();
+        let channels: Vec<Channel> = self.channels.drain().collect();
+        for channel in channels {
+            for message in self.snapshot(channel) {
+                self.send(&message).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Wire message for a simulator event, if this connection subscribed to
+    /// its channel.
+    fn encode(&self, event: &WsEvent) -> Option<Value> {
+        match event {
+            WsEvent::OrderBook(book)
+                if self.channels.contains(&Channel::OrderBook(book.market)) =>
+            {
+                Some(order_book_message(book))
+            }
+            WsEvent::Account(envelope) => {
+                let value = envelope.event.as_value();
+                let channel = value
+                    .get("channel")
+                    .and_then(Value::as_str)
+                    .and_then(Channel::parse)?;
+                self.channels.contains(&channel).then(|| value.clone())
+            }
+            _ => None,
+        }
+    }
+
+    fn send_tx(&self, data: &Value) -> Value {
+        let id = data.get("id").cloned().unwrap_or(Value::Null);
+        let tx_type = data
+            .get("tx_type")
+            .and_then(Value::as_i64)
+            .and_then(|tx_type| i32::try_from(tx_type).ok());
+        let (Some(tx_type), Some(tx_info)) = (tx_type, data.get("tx_info")) else {
+            return tx_reply(
+                "jsonapi/sendtx",
+                id,
+                400,
+                Some("tx_type and tx_info are required".into()),
+                json!({}),
+            );
+        };
+        let tx_info = tx_info_text(tx_info);
+        let response = self.shared.run(|sim| sim.submit_tx(tx_type, &tx_info));
+        let hashes = json!({ "tx_hash": response.tx_hash });
+        tx_reply(
+            "jsonapi/sendtx",
+            id,
+            response.code,
+            response.message,
+            hashes,
+        )
+    }
+
+    fn send_tx_batch(&self, data: &Value) -> Value {
+        let id = data.get("id").cloned().unwrap_or(Value::Null);
+        let entries = data
+            .get("tx_types")
+            .zip(data.get("tx_infos"))
+            .and_then(|(types, infos)| batch_entries(types, infos));
+        let Some(entries) = entries else {
+            return tx_reply(
+                "jsonapi/sendtxbatch",
+                id,
+                400,
+                Some("tx_types and tx_infos must be matching lists".into()),
+                json!({}),
+            );
+        };
+        let response = self.shared.run(|sim| sim.submit_batch(&entries));
+        let hashes = json!({ "tx_hashes": response.tx_hash });
+        tx_reply(
+            "jsonapi/sendtxbatch",
+            id,
+            response.code,
+            response.message,
+            hashes,
+        )
+    }
+
+    /// Hand a request the simulator does not serve to the upstream socket,
+    /// or refuse it with `refusal` when offline.
+    async fn pass_upstream(&mut self, text: &str, refusal: String) -> Result<(), ()> {
+        let Some(upstream) = &self.shared.upstream else {
+            return self
+                .send(&json!({ "type": "error", "error": refusal }))
+                .await;
+        };
+        if self.relay.is_none() {
+            match Relay::connect(&upstream.stream, self.relayed_tx.clone()).await {
+                Ok(relay) => self.relay = Some(relay),
+                Err(err) => {
+                    let message = format!("upstream: {err}");
+                    return self
+                        .send(&json!({ "type": "error", "error": message }))
+                        .await;
+                }
+            }
+        }
+        if let Some(relay) = &self.relay {
+            let _ = relay.commands.send(text.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn tx_reply(kind: &str, id: Value, code: i32, message: Option<String>, hashes: Value) -> Value {
+    let mut data = json!({ "id": id, "code": code, "message": message });
+    if code == CODE_OK {
+        if let (Value::Object(data), Value::Object(hashes)) = (&mut data, hashes) {
+            data.extend(hashes);
+        }
+    }
+    json!({ "type": kind, "data": data })
+}
+
+fn order_book_message(book: &OrderBookEvent) -> Value {
+    let (kind, asks, bids) = match &book.delta {
+        Some(delta) => ("update/order_book", &delta.asks, &delta.bids),
+        None => ("subscribed/order_book", &book.state.asks, &book.state.bids),
+    };
+    json!({
+        "type": kind,
+        "channel": format!("order_book:{}", book.market.into_inner()),
+        "offset": book.offset,
+        "order_book": {
+            "code": 0,
+            "asks": asks,
+            "bids": bids,
+            "offset": book.offset,
+            "nonce": book.nonce,
+            "begin_nonce": book.begin_nonce,
+        },
+    })
+}
+
+/// Upstream socket carrying one client connection's unsimulated channels.
+/// Its frames, except the greeting and pings, go back to the client.
+struct Relay {
+    commands: mpsc::UnboundedSender<String>,
+    task: JoinHandle<()>,
+}
+
+impl Relay {
+    async fn connect(
+        url: &str,
+        out: mpsc::UnboundedSender<String>,
+    ) -> Result<Self, tokio_tungstenite::tungstenite::Error> {
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await?;
+        let (commands, mut pending) = mpsc::unbounded_channel::<String>();
+        let task = tokio::spawn(async move {
+            // Requests wait for the upstream greeting, as the client does.
+            let mut ready = false;
+            let mut queued = Vec::new();
+            loop {
+                tokio::select! {
+                    frame = socket.next() => {
+                        let Some(Ok(Message::Text(text))) = frame else {
+                            match frame {
+                                Some(Ok(_)) => continue,
+                                _ => break,
+                            }
+                        };
+                        let kind = serde_json::from_str::<Value>(&text)
+                            .ok()
+                            .and_then(|value| value.get("type").and_then(Value::as_str).map(str::to_owned));
+                        match kind.as_deref() {
+                            Some("connected") => {
+                                ready = true;
+                                for text in queued.drain(..) {
+                                    if socket.send(Message::Text(text)).await.is_err() {
+                                        return;
+                                    }
+                                }
+                            }
+                            Some("ping") => {
+                                let pong = json!({ "type": "pong" }).to_string();
+                                if socket.send(Message::Text(pong)).await.is_err() {
+                                    break;
+                                }
+                            }
+                            _ => {
+                                if out.send(text).is_err() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                    command = pending.recv() => match command {
+                        Some(text) if ready => {
+                            if socket.send(Message::Text(text)).await.is_err() {
+                                break;
+                            }
+                        }
+                        Some(text) => queued.push(text),
+                        None => break,
+                    },
+                }
+            }
+        });
+        Ok(Self { commands, task })
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        account_events::TypedAccountEvent,
+        order_book::BookScale,
+        tx_signer::MockSigner,
+        types::{AccountId, BaseQty, Price},
+    };
+
+    async fn simulated_client() -> LighterClient {
+        let mut sim = SimExchange::new(AccountId::new(42));
+        sim.add_market(MarketId::new(0), BookScale::new(2, 4));
+        LighterClient::builder()
+            .simulated(sim)
+            .tx_signer(Arc::new(MockSigner::new(42)))
+            .api_key_index(2)
+            .account_index(42)
+            .build()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn client_orders_fill_in_the_simulator() {
+        let client = simulated_client().await;
+        let gateway = client.simulator().expect("built with a simulator");
+        assert!(gateway.url().starts_with("http://127.0.0.1:"));
+
+        let mut stream = client
+            .ws()
+            .subscribe_order_book(MarketId::new(0))
+            .subscribe_account_all_orders(AccountId::new(42))
+            .connect()
+            .await
+            .unwrap();
+
+        let submission = client
+            .order(MarketId::new(0))
+            .buy()
+            .qty(BaseQty::from_i64(500).unwrap())
+            .limit(Price::ticks(300_000))
+            .submit()
+            .await
+            .unwrap();
+        assert_eq!(submission.response().code, 200);
+        assert_eq!(
+            gateway.with_sim(|sim| sim.open_orders(MarketId::new(0)).len()),
+            1
+        );
+
+        let (order, bid) = tokio::time::timeout(Duration::from_secs(5), async {
+            let (mut order, mut bid) = (None, None);
+            while order.is_none() || bid.is_none() {
+                match stream.connection_mut().next_event().await.unwrap() {
+                    Some(WsEvent::Account(envelope)) => {
+                        if let Ok(TypedAccountEvent::AllOrders(update)) = envelope.decode() {
+                            order = order.or(update.orders_for(MarketId::new(0)).first().cloned());
+                        }
+                    }
+                    Some(WsEvent::OrderBook(book)) => {
+                        bid = bid.or(book.state.bids.first().cloned());
+                    }
+                    _ => {}
+                }
+            }
+            (order.unwrap(), bid.unwrap())
+        })
+        .await
+        .expect("order and book updates over the websocket");
+        assert_eq!(order.price, "3000.00");
+        assert_eq!(
+            (bid.price.as_str(), bid.size.as_str()),
+            ("3000.00", "0.0500")
+        );
+
+        let missing = reqwest::get(format!("{}/api/v1/account", gateway.url()))
+            .await
+            .unwrap();
+        assert_eq!(missing.status().as_u16(), 404);
+    }
+
+    #[tokio::test]
+    async fn rejected_transactions_answer_like_the_exchange() {
+        let client = simulated_client().await;
+        let gateway = client.simulator().unwrap();
+        let config = client.configuration();
+
+        let stale = crate::apis::transaction_api::send_tx(&config, 14, r#"{"Nonce":-5}"#, None)
+            .await
+            .unwrap_err();
+        let crate::apis::Error::ResponseError(content) = stale else {
+            panic!("expected a response error, got {stale:?}");
+        };
+        assert_eq!(content.status.as_u16(), 400);
+
+        let nonce = crate::apis::transaction_api::next_nonce(&config, 42, 2)
+            .await
+            .unwrap();
+        assert_eq!(nonce.nonce, gateway.with_sim(|sim| sim.next_nonce(2)));
+    }
+}
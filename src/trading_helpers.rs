@@ -130,6 +130,10 @@ pub fn calculate_max_position_size(available_margin: f64, price: f64, leverage:
 
 /// Calculate liquidation price for a position
 ///
+/// Assumes an isolated position margined at exactly `1 / leverage`. For cross
+/// positions and live margin, use
+/// [`RiskEngine::liquidation_price`](crate::risk::RiskEngine::liquidation_price).
+///
 /// # Arguments
 /// * `entry_price` - Entry price of the position
 /// * `leverage` - Leverage multiplier
@@ -1,4 +1,6 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::tx_executor::ORDER_TIME_IN_FORCE_GTT;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "PascalCase")]
@@ -113,3 +115,232 @@ impl UpdateMargin {
         serde_json::to_string(self)
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct ModifyOrder {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub market_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trigger_price: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl ModifyOrder {
+    pub fn from_json_str(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct Transfer {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from_account_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to_account_index: Option<i64>,
+    #[serde(rename = "USDCAmount", skip_serializing_if = "Option::is_none")]
+    pub usdc_amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fee: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub memo: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+    #[serde(rename = "L1Sig", skip_serializing_if = "Option::is_none")]
+    pub l1_sig: Option<String>,
+}
+
+impl Transfer {
+    pub fn from_json_str(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct UpdateLeverage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub market_index: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub initial_margin_fraction: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub margin_mode: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl UpdateLeverage {
+    pub fn from_json_str(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct CreateSubAccount {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl CreateSubAccount {
+    pub fn from_json_str(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct MintShares {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_pool_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl MintShares {
+    pub fn from_json_str(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "PascalCase")]
+pub struct BurnShares {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub public_pool_index: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub share_amount: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expired_at: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nonce: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl BurnShares {
+    pub fn from_json_str(value: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(value)
+    }
+
+    pub fn to_json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+/// Order fields of a signed `CreateOrder` `tx_info`, as the native signer
+/// writes them.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct CreateOrderInfo {
+    #[serde(alias = "OrderBookIndex")]
+    pub(crate) market_index: i32,
+    #[serde(default)]
+    pub(crate) client_order_index: i64,
+    pub(crate) base_amount: i64,
+    pub(crate) price: i64,
+    #[serde(deserialize_with = "flag")]
+    pub(crate) is_ask: bool,
+    #[serde(rename = "Type", alias = "OrderType", default)]
+    pub(crate) order_type: i32,
+    #[serde(default = "default_time_in_force")]
+    pub(crate) time_in_force: i32,
+    #[serde(default, deserialize_with = "flag")]
+    pub(crate) reduce_only: bool,
+    #[serde(default)]
+    pub(crate) trigger_price: i64,
+    #[serde(default)]
+    pub(crate) order_expiry: i64,
+}
+
+/// Order fields of a signed `ModifyOrder` `tx_info`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub(crate) struct ModifyOrderInfo {
+    #[serde(alias = "OrderBookIndex")]
+    pub(crate) market_index: i32,
+    #[serde(alias = "OrderIndex")]
+    pub(crate) index: i64,
+    pub(crate) base_amount: i64,
+    pub(crate) price: i64,
+    #[serde(default)]
+    pub(crate) trigger_price: i64,
+}
+
+fn default_time_in_force() -> i32 {
+    ORDER_TIME_IN_FORCE_GTT
+}
+
+/// The native signer encodes flags as 0/1; accept plain booleans too.
+fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Int(i64),
+    }
+    Ok(match Flag::deserialize(deserializer)? {
+        Flag::Bool(value) => value,
+        Flag::Int(value) => value != 0,
+    })
+}
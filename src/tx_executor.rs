@@ -10,13 +10,13 @@
 //! - Short position (sign=-1): Close with BUY (is_ask=0)
 
 use crate::{
+    batch_outcome::BatchOutcome,
     errors::{Result as SignerResult, WsClientError, WsResult},
-    signer::SignerLibrary,
-    ws_client::WsConnection,
+    tx_signer::TxSigner,
+    ws_client::{TxResponse, WsConnection, WsEvent},
 };
 use serde_json::json;
 use std::time::Duration;
-use tokio::time::timeout;
 use tracing::{debug, error, info, warn};
 
 // Transaction type constants (matching signer_client.rs)
@@ -54,83 +54,147 @@ pub enum BatchAckMode {
     Optimistic { wait_timeout: Duration },
 }
 
-async fn send_batch_tx_ws_with_mode_internal(
+impl BatchAckMode {
+    fn wait_timeout(self, chunk_len: usize) -> Duration {
+        match self {
+            BatchAckMode::Strict => Duration::from_secs(chunk_len as u64),
+            BatchAckMode::Optimistic { wait_timeout } => wait_timeout,
+        }
+    }
+}
+
+/// Send a batch over WebSocket and report what happened to each entry.
+///
+/// Chunks of up to 50 are sent in order and each chunk's reply is applied to
+/// its entries. Entries whose reply timed out stay [`TxOutcome::Unknown`];
+/// pass the result to [`reconcile_batch_ws`] to settle them (and to confirm
+/// accepted entries) from `account_tx`. If a chunk cannot be sent, it and
+/// every later chunk are rejected as unsent; when nothing was sent the error
+/// is returned instead.
+///
+/// [`TxOutcome::Unknown`]: crate::batch_outcome::TxOutcome::Unknown
+pub async fn send_batch_tx_ws_outcome(
     ws: &mut WsConnection,
-    txs: Vec<(u8, String)>,
+    txs: &[(u8, String)],
     mode: BatchAckMode,
-) -> WsResult<Vec<bool>> {
+) -> WsResult<BatchOutcome> {
     const MAX_BATCH_SIZE: usize = 50;
 
+    let mut outcome = BatchOutcome::new(
+        txs.iter()
+            .map(|(tx_type, tx_info)| (i32::from(*tx_type), tx_info.as_str())),
+    );
     if txs.is_empty() {
-        return Ok(vec![]);
+        return Ok(outcome);
     }
 
     info!("Sending batch of {} transactions", txs.len());
 
-    let mut results = Vec::with_capacity(txs.len());
-
+    let chunk_count = (txs.len() + MAX_BATCH_SIZE - 1) / MAX_BATCH_SIZE;
     for (chunk_idx, chunk) in txs.chunks(MAX_BATCH_SIZE).enumerate() {
         debug!(
             "Sending chunk {}/{} ({} txs)",
             chunk_idx + 1,
-            (txs.len() + MAX_BATCH_SIZE - 1) / MAX_BATCH_SIZE,
+            chunk_count,
             chunk.len()
         );
 
+        let range = chunk_idx * MAX_BATCH_SIZE..chunk_idx * MAX_BATCH_SIZE + chunk.len();
         let tx_types: Vec<u8> = chunk.iter().map(|(tx_type, _)| *tx_type).collect();
         let tx_infos: Vec<serde_json::Value> = chunk
             .iter()
             .map(|(_, tx_info)| serde_json::from_str(tx_info).unwrap_or_else(|_| json!({})))
             .collect();
+        let wait_timeout = mode.wait_timeout(chunk.len());
 
-        let wait_timeout = match mode {
-            BatchAckMode::Strict => Duration::from_secs(chunk.len() as u64),
-            BatchAckMode::Optimistic { wait_timeout } => wait_timeout,
+        let mut pending = match ws.send_batch_transaction(tx_types, tx_infos).await {
+            Ok(pending) => pending,
+            Err(e) if chunk_idx == 0 => {
+                error!("Failed to send batch: {}", e);
+                return Err(e);
+            }
+            Err(e) => {
+                error!("Failed to send batch chunk {}: {}", chunk_idx + 1, e);
+                outcome.mark_unsent(range.start..txs.len(), &e.to_string());
+                break;
+            }
         };
 
-        match ws.send_batch_transaction(tx_types, tx_infos).await {
-            Ok(_) => match timeout(wait_timeout, ws.wait_for_tx_response(wait_timeout)).await {
-                Ok(Ok(success)) => {
-                    results.extend(vec![success; chunk.len()]);
+        match ws.wait_for_tx(&mut pending, wait_timeout).await {
+            Ok(response) => {
+                if !response.is_success() {
+                    error!(
+                        "Batch {} rejected: code {} {:?}",
+                        response.id, response.code, response.message
+                    );
                 }
-                Ok(Err(e)) => match mode {
-                    BatchAckMode::Strict => {
-                        error!("Batch error: {}", e);
-                        results.extend(vec![false; chunk.len()]);
-                        continue;
-                    }
-                    BatchAckMode::Optimistic { .. } => {
-                        warn!(
-                                "Fast execution: error while waiting for batch ack ({e}); assuming success"
-                            );
-                        results.extend(vec![true; chunk.len()]);
-                    }
-                },
-                Err(_) => match mode {
-                    BatchAckMode::Strict => {
-                        error!(
-                            "Batch timeout after {:?} while waiting for transaction response",
-                            wait_timeout
-                        );
-                        results.extend(vec![false; chunk.len()]);
-                        continue;
-                    }
-                    BatchAckMode::Optimistic { .. } => {
-                        debug!(
-                            "Fast execution: batch timeout after {:?}; assuming success",
-                            wait_timeout
-                        );
-                        results.extend(vec![true; chunk.len()]);
-                    }
-                },
-            },
+                outcome.apply_reply(
+                    range,
+                    response.code,
+                    response.message.as_deref(),
+                    &response.tx_hashes,
+                );
+            }
+            Err(WsClientError::TxTimeout(_)) => {
+                warn!(
+                    "Batch timeout after {:?}; {} outcomes unknown",
+                    wait_timeout,
+                    chunk.len()
+                );
+            }
             Err(e) => {
-                error!("Failed to send batch: {}", e);
-                return Err(e);
+                warn!(
+                    "Error while waiting for batch ack ({e}); {} outcomes unknown",
+                    chunk.len()
+                );
             }
         }
     }
 
+    Ok(outcome)
+}
+
+/// Settle a batch from `account_tx` events read off `ws` for up to `timeout`.
+///
+/// The connection must be subscribed to `account_tx` for the signing account.
+/// Events read here stay queued for [`WsConnection::next_event`]. Returns
+/// whether every entry is `Confirmed` or `Rejected`.
+pub async fn reconcile_batch_ws(
+    ws: &mut WsConnection,
+    outcome: &mut BatchOutcome,
+    timeout: Duration,
+) -> WsResult<bool> {
+    if outcome.is_settled() {
+        return Ok(true);
+    }
+    let settled = ws
+        .observe_until(timeout, |event| {
+            if let WsEvent::Account(envelope) = event {
+                if let Ok(event) = envelope.decode() {
+                    outcome.apply(&event);
+                }
+            }
+            outcome.is_settled()
+        })
+        .await?;
+    if !settled {
+        debug!(
+            "Batch reconciliation timed out with {} entries unsettled",
+            outcome.unsettled().count()
+        );
+    }
+    Ok(settled)
+}
+
+async fn send_batch_tx_ws_with_mode_internal(
+    ws: &mut WsConnection,
+    txs: Vec<(u8, String)>,
+    mode: BatchAckMode,
+) -> WsResult<Vec<bool>> {
+    let outcome = send_batch_tx_ws_outcome(ws, &txs, mode).await?;
+
+    // Optimistic mode keeps its old contract of treating a missing ack as success.
+    let results = outcome.acks(matches!(mode, BatchAckMode::Optimistic { .. }));
     let success_count = results.iter().filter(|&&r| r).count();
     info!(
         "Batch complete: {}/{} successful",
@@ -141,6 +205,11 @@ async fn send_batch_tx_ws_with_mode_internal(
     Ok(results)
 }
 
+/// Send a batch and collapse each entry's outcome to a flag.
+///
+/// Entries with no reply count as failed in [`BatchAckMode::Strict`] and as
+/// succeeded in [`BatchAckMode::Optimistic`]. Use
+/// [`send_batch_tx_ws_outcome`] to tell them apart.
 pub async fn send_batch_tx_ws_with_mode(
     ws: &mut WsConnection,
     txs: Vec<(u8, String)>,
@@ -171,49 +240,58 @@ pub struct Position {
 /// - `Err(_)` - WebSocket communication error
 ///
 /// # Error Detection
-/// Properly detects errors via:
-/// - Response contains `"type":"error"`
-/// - Response contains `"error":` key
-/// - Response contains non-200 status code
+/// The reply is matched to the request by id (see [`WsConnection::wait_for_tx`]);
+/// market data read while waiting stays queued on the connection.
+/// - Non-200 code or `error` payload is treated as FAILURE
 /// - Timeout (1s) is treated as FAILURE
-/// - Parse errors are treated as FAILURE
 /// - Connection closed is treated as FAILURE
+///
+/// Use [`send_tx_ws_with_response`] for the code, message and tx hash.
 pub async fn send_tx_ws(ws: &mut WsConnection, tx_type: u8, tx_info: &str) -> WsResult<bool> {
-    // Parse tx_info as JSON (should already be JSON from SignerLibrary)
-    let tx_info_json: serde_json::Value = serde_json::from_str(tx_info).map_err(|e| {
-        WsClientError::InvalidMessage(format!("Failed to parse tx_info as JSON: {}", e))
-    })?;
-
-    // Use WsConnection's built-in send_transaction method
-    ws.send_transaction(tx_type, tx_info_json).await?;
-
-    // Wait for response with timeout (1 second)
-    match timeout(
-        Duration::from_secs(1),
-        ws.wait_for_tx_response(Duration::from_secs(1)),
-    )
-    .await
-    {
-        Ok(Ok(success)) => {
-            if success {
-                info!("Transaction submitted successfully");
-                Ok(true)
-            } else {
-                error!("Transaction failed");
-                Ok(false)
-            }
+    let tx_info_json = serde_json::from_str(tx_info).map_err(invalid_tx_info)?;
+    let mut pending = ws.send_transaction(tx_type, tx_info_json).await?;
+
+    // Wait for the matching response (1 second)
+    match ws.wait_for_tx(&mut pending, Duration::from_secs(1)).await {
+        Ok(response) if response.is_success() => {
+            info!("Transaction submitted successfully");
+            Ok(true)
         }
-        Ok(Err(e)) => {
-            error!("WebSocket error while waiting for response: {}", e);
+        Ok(response) => {
+            error!(
+                "Transaction failed: code {} {:?}",
+                response.code, response.message
+            );
             Ok(false)
         }
-        Err(_) => {
+        Err(WsClientError::TxTimeout(_)) => {
             error!("Timeout: No WebSocket response within 1000ms");
             Ok(false)
         }
+        Err(e) => {
+            error!("WebSocket error while waiting for response: {}", e);
+            Ok(false)
+        }
     }
 }
 
+/// Send a transaction and wait up to `wait_timeout` for its typed reply.
+pub async fn send_tx_ws_with_response(
+    ws: &mut WsConnection,
+    tx_type: u8,
+    tx_info: &str,
+    wait_timeout: Duration,
+) -> WsResult<TxResponse> {
+    let tx_info_json = serde_json::from_str(tx_info).map_err(invalid_tx_info)?;
+    let mut pending = ws.send_transaction(tx_type, tx_info_json).await?;
+    ws.wait_for_tx(&mut pending, wait_timeout).await
+}
+
+/// tx_info should already be JSON from SignerLibrary
+fn invalid_tx_info(e: serde_json::Error) -> WsClientError {
+    WsClientError::InvalidMessage(format!("Failed to parse tx_info as JSON: {}", e))
+}
+
 /// Send batch transactions via WebSocket (max 50 per batch per Lighter docs)
 ///
 /// # Arguments
@@ -226,7 +304,9 @@ pub async fn send_tx_ws(ws: &mut WsConnection, tx_type: u8, tx_info: &str) -> Ws
 /// # Notes
 /// - Lighter DEX supports max 50 transactions per WebSocket batch
 /// - This function will chunk larger batches automatically
-/// - Each transaction result is tracked independently
+/// - A chunk's reply applies to each of its entries; entries without a reply
+///   count as failed. Use [`send_batch_tx_ws_outcome`] for hashes, rejection
+///   codes and `account_tx` reconciliation
 pub async fn send_batch_tx_ws(
     ws: &mut WsConnection,
     txs: Vec<(u8, String)>,
@@ -238,7 +318,7 @@ pub async fn send_batch_tx_ws(
 ///
 /// # Arguments
 /// * `ws` - WebSocket connection
-/// * `signer` - SignerLibrary instance for transaction signing
+/// * `signer` - Signing backend (e.g. `SignerLibrary`)
 /// * `market_id` - Market ID (0 for ETH, 1 for BTC, etc.)
 /// * `position_size` - Position size (string from API, always positive)
 /// * `position_sign` - Position sign (-1 for short, 1 for long)
@@ -260,7 +340,7 @@ pub async fn send_batch_tx_ws(
 /// DO NOT use `position_size > 0` for direction - it's ALWAYS positive!
 pub async fn close_position(
     ws: &mut WsConnection,
-    signer: &SignerLibrary,
+    signer: &dyn TxSigner,
     market_id: u32,
     position_size: &str,
     position_sign: i8,
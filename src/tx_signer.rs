@@ -0,0 +1,1208 @@
+//! Pluggable signing backends for [`SignerClient`](crate::signer_client::SignerClient).
+//!
+//! [`TxSigner`] mirrors the surface of [`SignerLibrary`], which remains the
+//! default backend. Backends that work on requests as data (the deterministic
+//! [`MockSigner`] and the Unix socket
+//! [`RemoteSigner`](crate::remote_signer::RemoteSigner)) implement
+//! [`SignBackend`] instead and get [`TxSigner`] for free.
+
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::{
+    errors::Result,
+    signer::SignerLibrary,
+    tx_executor::{
+        TX_TYPE_BURN_SHARES, TX_TYPE_CANCEL_ALL_ORDERS, TX_TYPE_CANCEL_ORDER,
+        TX_TYPE_CHANGE_PUB_KEY, TX_TYPE_CREATE_ORDER, TX_TYPE_CREATE_PUBLIC_POOL,
+        TX_TYPE_CREATE_SUB_ACCOUNT, TX_TYPE_MINT_SHARES, TX_TYPE_MODIFY_ORDER, TX_TYPE_TRANSFER,
+        TX_TYPE_UPDATE_LEVERAGE, TX_TYPE_UPDATE_MARGIN, TX_TYPE_UPDATE_PUBLIC_POOL,
+        TX_TYPE_WITHDRAW,
+    },
+};
+
+/// Signs transactions and auth tokens for the active API key.
+///
+/// Every `sign_*` method returns `(tx_info, error)` exactly like the native
+/// library: an `Err` is reserved for transport or decoding failures, while a
+/// signer-side rejection comes back as `Ok((None, Some(message)))`.
+pub trait TxSigner: Send + Sync {
+    fn check_client(&self, api_key_index: i32, account_index: i64) -> Result<Option<String>>;
+
+    fn switch_api_key(&self, api_key_index: i32) -> Result<Option<String>>;
+
+    fn generate_api_key(
+        &self,
+        seed: Option<&str>,
+    ) -> Result<(Option<String>, Option<String>, Option<String>)>;
+
+    fn sign_change_pub_key(
+        &self,
+        new_pubkey: &str,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_create_order(
+        &self,
+        market_index: i32,
+        client_order_index: i64,
+        base_amount: i64,
+        price: i32,
+        is_ask: bool,
+        order_type: i32,
+        time_in_force: i32,
+        reduce_only: bool,
+        trigger_price: i32,
+        order_expiry: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_cancel_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_withdraw(
+        &self,
+        usdc_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_create_sub_account(&self, nonce: i64) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_cancel_all_orders(
+        &self,
+        time_in_force: i32,
+        time: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_modify_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_transfer(
+        &self,
+        to_account_index: i64,
+        usdc_amount: i64,
+        fee: i64,
+        memo: &str,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_create_public_pool(
+        &self,
+        operator_fee: i64,
+        initial_total_shares: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_update_public_pool(
+        &self,
+        public_pool_index: i64,
+        status: i32,
+        operator_fee: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_mint_shares(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_burn_shares(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_update_leverage(
+        &self,
+        market_index: i32,
+        fraction: i32,
+        margin_mode: i32,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn sign_update_margin(
+        &self,
+        market_index: i32,
+        usdc_amount: i64,
+        direction: i32,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)>;
+
+    fn create_auth_token(&self, deadline: i64) -> Result<(Option<String>, Option<String>)>;
+}
+
+impl std::fmt::Debug for dyn TxSigner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TxSigner")
+    }
+}
+
+impl TxSigner for SignerLibrary {
+    fn check_client(&self, api_key_index: i32, account_index: i64) -> Result<Option<String>> {
+        SignerLibrary::check_client(self, api_key_index, account_index)
+    }
+
+    fn switch_api_key(&self, api_key_index: i32) -> Result<Option<String>> {
+        SignerLibrary::switch_api_key(self, api_key_index)
+    }
+
+    fn generate_api_key(
+        &self,
+        seed: Option<&str>,
+    ) -> Result<(Option<String>, Option<String>, Option<String>)> {
+        SignerLibrary::generate_api_key(self, seed)
+    }
+
+    fn sign_change_pub_key(
+        &self,
+        new_pubkey: &str,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_change_pub_key(self, new_pubkey, nonce)
+    }
+
+    fn sign_create_order(
+        &self,
+        market_index: i32,
+        client_order_index: i64,
+        base_amount: i64,
+        price: i32,
+        is_ask: bool,
+        order_type: i32,
+        time_in_force: i32,
+        reduce_only: bool,
+        trigger_price: i32,
+        order_expiry: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_create_order(
+            self,
+            market_index,
+            client_order_index,
+            base_amount,
+            price,
+            is_ask,
+            order_type,
+            time_in_force,
+            reduce_only,
+            trigger_price,
+            order_expiry,
+            nonce,
+        )
+    }
+
+    fn sign_cancel_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_cancel_order(self, market_index, order_index, nonce)
+    }
+
+    fn sign_withdraw(
+        &self,
+        usdc_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_withdraw(self, usdc_amount, nonce)
+    }
+
+    fn sign_create_sub_account(&self, nonce: i64) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_create_sub_account(self, nonce)
+    }
+
+    fn sign_cancel_all_orders(
+        &self,
+        time_in_force: i32,
+        time: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_cancel_all_orders(self, time_in_force, time, nonce)
+    }
+
+    fn sign_modify_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_modify_order(
+            self,
+            market_index,
+            order_index,
+            base_amount,
+            price,
+            trigger_price,
+            nonce,
+        )
+    }
+
+    fn sign_transfer(
+        &self,
+        to_account_index: i64,
+        usdc_amount: i64,
+        fee: i64,
+        memo: &str,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_transfer(self, to_account_index, usdc_amount, fee, memo, nonce)
+    }
+
+    fn sign_create_public_pool(
+        &self,
+        operator_fee: i64,
+        initial_total_shares: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_create_public_pool(
+            self,
+            operator_fee,
+            initial_total_shares,
+            min_operator_share_rate,
+            nonce,
+        )
+    }
+
+    fn sign_update_public_pool(
+        &self,
+        public_pool_index: i64,
+        status: i32,
+        operator_fee: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_update_public_pool(
+            self,
+            public_pool_index,
+            status,
+            operator_fee,
+            min_operator_share_rate,
+            nonce,
+        )
+    }
+
+    fn sign_mint_shares(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_mint_shares(self, public_pool_index, share_amount, nonce)
+    }
+
+    fn sign_burn_shares(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_burn_shares(self, public_pool_index, share_amount, nonce)
+    }
+
+    fn sign_update_leverage(
+        &self,
+        market_index: i32,
+        fraction: i32,
+        margin_mode: i32,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_update_leverage(self, market_index, fraction, margin_mode, nonce)
+    }
+
+    fn sign_update_margin(
+        &self,
+        market_index: i32,
+        usdc_amount: i64,
+        direction: i32,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::sign_update_margin(self, market_index, usdc_amount, direction, nonce)
+    }
+
+    fn create_auth_token(&self, deadline: i64) -> Result<(Option<String>, Option<String>)> {
+        SignerLibrary::create_auth_token(self, deadline)
+    }
+}
+
+/// One [`TxSigner`] call captured as data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "method", content = "params", rename_all = "snake_case")]
+pub enum SignRequest {
+    CheckClient {
+        api_key_index: i32,
+        account_index: i64,
+    },
+    SwitchApiKey {
+        api_key_index: i32,
+    },
+    GenerateApiKey {
+        seed: Option<String>,
+    },
+    SignChangePubKey {
+        new_pubkey: String,
+        nonce: i64,
+    },
+    SignCreateOrder {
+        market_index: i32,
+        client_order_index: i64,
+        base_amount: i64,
+        price: i32,
+        is_ask: bool,
+        order_type: i32,
+        time_in_force: i32,
+        reduce_only: bool,
+        trigger_price: i32,
+        order_expiry: i64,
+        nonce: i64,
+    },
+    SignCancelOrder {
+        market_index: i32,
+        order_index: i64,
+        nonce: i64,
+    },
+    SignWithdraw {
+        usdc_amount: i64,
+        nonce: i64,
+    },
+    SignCreateSubAccount {
+        nonce: i64,
+    },
+    SignCancelAllOrders {
+        time_in_force: i32,
+        time: i64,
+        nonce: i64,
+    },
+    SignModifyOrder {
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        nonce: i64,
+    },
+    SignTransfer {
+        to_account_index: i64,
+        usdc_amount: i64,
+        fee: i64,
+        memo: String,
+        nonce: i64,
+    },
+    SignCreatePublicPool {
+        operator_fee: i64,
+        initial_total_shares: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    },
+    SignUpdatePublicPool {
+        public_pool_index: i64,
+        status: i32,
+        operator_fee: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    },
+    SignMintShares {
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    },
+    SignBurnShares {
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    },
+    SignUpdateLeverage {
+        market_index: i32,
+        fraction: i32,
+        margin_mode: i32,
+        nonce: i64,
+    },
+    SignUpdateMargin {
+        market_index: i32,
+        usdc_amount: i64,
+        direction: i32,
+        nonce: i64,
+    },
+    CreateAuthToken {
+        deadline: i64,
+    },
+}
+
+impl SignRequest {
+    /// Method name as it appears on the wire.
+    pub fn method(&self) -> &'static str {
+        match self {
+            Self::CheckClient { .. } => "check_client",
+            Self::SwitchApiKey { .. } => "switch_api_key",
+            Self::GenerateApiKey { .. } => "generate_api_key",
+            Self::SignChangePubKey { .. } => "sign_change_pub_key",
+            Self::SignCreateOrder { .. } => "sign_create_order",
+            Self::SignCancelOrder { .. } => "sign_cancel_order",
+            Self::SignWithdraw { .. } => "sign_withdraw",
+            Self::SignCreateSubAccount { .. } => "sign_create_sub_account",
+            Self::SignCancelAllOrders { .. } => "sign_cancel_all_orders",
+            Self::SignModifyOrder { .. } => "sign_modify_order",
+            Self::SignTransfer { .. } => "sign_transfer",
+            Self::SignCreatePublicPool { .. } => "sign_create_public_pool",
+            Self::SignUpdatePublicPool { .. } => "sign_update_public_pool",
+            Self::SignMintShares { .. } => "sign_mint_shares",
+            Self::SignBurnShares { .. } => "sign_burn_shares",
+            Self::SignUpdateLeverage { .. } => "sign_update_leverage",
+            Self::SignUpdateMargin { .. } => "sign_update_margin",
+            Self::CreateAuthToken { .. } => "create_auth_token",
+        }
+    }
+
+    /// Nonce carried by a transaction request.
+    pub fn nonce(&self) -> Option<i64> {
+        match self {
+            Self::SignChangePubKey { nonce, .. }
+            | Self::SignCreateOrder { nonce, .. }
+            | Self::SignCancelOrder { nonce, .. }
+            | Self::SignWithdraw { nonce, .. }
+            | Self::SignCreateSubAccount { nonce }
+            | Self::SignCancelAllOrders { nonce, .. }
+            | Self::SignModifyOrder { nonce, .. }
+            | Self::SignTransfer { nonce, .. }
+            | Self::SignCreatePublicPool { nonce, .. }
+            | Self::SignUpdatePublicPool { nonce, .. }
+            | Self::SignMintShares { nonce, .. }
+            | Self::SignBurnShares { nonce, .. }
+            | Self::SignUpdateLeverage { nonce, .. }
+            | Self::SignUpdateMargin { nonce, .. } => Some(*nonce),
+            Self::CheckClient { .. }
+            | Self::SwitchApiKey { .. }
+            | Self::GenerateApiKey { .. }
+            | Self::CreateAuthToken { .. } => None,
+        }
+    }
+
+    /// Transaction type the signed payload is submitted as.
+    pub fn tx_type(&self) -> Option<u8> {
+        let tx_type = match self {
+            Self::SignChangePubKey { .. } => TX_TYPE_CHANGE_PUB_KEY,
+            Self::SignCreateSubAccount { .. } => TX_TYPE_CREATE_SUB_ACCOUNT,
+            Self::SignCreatePublicPool { .. } => TX_TYPE_CREATE_PUBLIC_POOL,
+            Self::SignUpdatePublicPool { .. } => TX_TYPE_UPDATE_PUBLIC_POOL,
+            Self::SignTransfer { .. } => TX_TYPE_TRANSFER,
+            Self::SignWithdraw { .. } => TX_TYPE_WITHDRAW,
+            Self::SignCreateOrder { .. } => TX_TYPE_CREATE_ORDER,
+            Self::SignCancelOrder { .. } => TX_TYPE_CANCEL_ORDER,
+            Self::SignCancelAllOrders { .. } => TX_TYPE_CANCEL_ALL_ORDERS,
+            Self::SignModifyOrder { .. } => TX_TYPE_MODIFY_ORDER,
+            Self::SignMintShares { .. } => TX_TYPE_MINT_SHARES,
+            Self::SignBurnShares { .. } => TX_TYPE_BURN_SHARES,
+            Self::SignUpdateLeverage { .. } => TX_TYPE_UPDATE_LEVERAGE,
+            Self::SignUpdateMargin { .. } => TX_TYPE_UPDATE_MARGIN,
+            Self::CheckClient { .. }
+            | Self::SwitchApiKey { .. }
+            | Self::GenerateApiKey { .. }
+            | Self::CreateAuthToken { .. } => return None,
+        };
+        Some(tx_type)
+    }
+
+    /// The same transaction request under a different nonce. Requests that
+    /// carry no nonce are returned unchanged.
+    pub fn with_nonce(mut self, new_nonce: i64) -> Self {
+        match &mut self {
+            Self::SignChangePubKey { nonce, .. }
+            | Self::SignCreateOrder { nonce, .. }
+            | Self::SignCancelOrder { nonce, .. }
+            | Self::SignWithdraw { nonce, .. }
+            | Self::SignCreateSubAccount { nonce }
+            | Self::SignCancelAllOrders { nonce, .. }
+            | Self::SignModifyOrder { nonce, .. }
+            | Self::SignTransfer { nonce, .. }
+            | Self::SignCreatePublicPool { nonce, .. }
+            | Self::SignUpdatePublicPool { nonce, .. }
+            | Self::SignMintShares { nonce, .. }
+            | Self::SignBurnShares { nonce, .. }
+            | Self::SignUpdateLeverage { nonce, .. }
+            | Self::SignUpdateMargin { nonce, .. } => *nonce = new_nonce,
+            Self::CheckClient { .. }
+            | Self::SwitchApiKey { .. }
+            | Self::GenerateApiKey { .. }
+            | Self::CreateAuthToken { .. } => {}
+        }
+        self
+    }
+
+    /// Run the request against a concrete signer.
+    pub fn dispatch(&self, signer: &dyn TxSigner) -> Result<SignReply> {
+        let reply = match self {
+            Self::CheckClient {
+                api_key_index,
+                account_index,
+            } => SignReply::from_error(signer.check_client(*api_key_index, *account_index)?),
+            Self::SwitchApiKey { api_key_index } => {
+                SignReply::from_error(signer.switch_api_key(*api_key_index)?)
+            }
+            Self::GenerateApiKey { seed } => {
+                let (value, public_key, error) = signer.generate_api_key(seed.as_deref())?;
+                SignReply {
+                    value,
+                    public_key,
+                    error,
+                }
+            }
+            Self::SignChangePubKey { new_pubkey, nonce } => {
+                SignReply::from_pair(signer.sign_change_pub_key(new_pubkey, *nonce)?)
+            }
+            Self::SignCreateOrder {
+                market_index,
+                client_order_index,
+                base_amount,
+                price,
+                is_ask,
+                order_type,
+                time_in_force,
+                reduce_only,
+                trigger_price,
+                order_expiry,
+                nonce,
+            } => SignReply::from_pair(signer.sign_create_order(
+                *market_index,
+                *client_order_index,
+                *base_amount,
+                *price,
+                *is_ask,
+                *order_type,
+                *time_in_force,
+                *reduce_only,
+                *trigger_price,
+                *order_expiry,
+                *nonce,
+            )?),
+            Self::SignCancelOrder {
+                market_index,
+                order_index,
+                nonce,
+            } => SignReply::from_pair(signer.sign_cancel_order(
+                *market_index,
+                *order_index,
+                *nonce,
+            )?),
+            Self::SignWithdraw { usdc_amount, nonce } => {
+                SignReply::from_pair(signer.sign_withdraw(*usdc_amount, *nonce)?)
+            }
+            Self::SignCreateSubAccount { nonce } => {
+                SignReply::from_pair(signer.sign_create_sub_account(*nonce)?)
+            }
+            Self::SignCancelAllOrders {
+                time_in_force,
+                time,
+                nonce,
+            } => SignReply::from_pair(signer.sign_cancel_all_orders(
+                *time_in_force,
+                *time,
+                *nonce,
+            )?),
+            Self::SignModifyOrder {
+                market_index,
+                order_index,
+                base_amount,
+                price,
+                trigger_price,
+                nonce,
+            } => SignReply::from_pair(signer.sign_modify_order(
+                *market_index,
+                *order_index,
+                *base_amount,
+                *price,
+                *trigger_price,
+                *nonce,
+            )?),
+            Self::SignTransfer {
+                to_account_index,
+                usdc_amount,
+                fee,
+                memo,
+                nonce,
+            } => SignReply::from_pair(signer.sign_transfer(
+                *to_account_index,
+                *usdc_amount,
+                *fee,
+                memo,
+                *nonce,
+            )?),
+            Self::SignCreatePublicPool {
+                operator_fee,
+                initial_total_shares,
+                min_operator_share_rate,
+                nonce,
+            } => SignReply::from_pair(signer.sign_create_public_pool(
+                *operator_fee,
+                *initial_total_shares,
+                *min_operator_share_rate,
+                *nonce,
+            )?),
+            Self::SignUpdatePublicPool {
+                public_pool_index,
+                status,
+                operator_fee,
+                min_operator_share_rate,
+                nonce,
+            } => SignReply::from_pair(signer.sign_update_public_pool(
+                *public_pool_index,
+                *status,
+                *operator_fee,
+                *min_operator_share_rate,
+                *nonce,
+            )?),
+            Self::SignMintShares {
+                public_pool_index,
+                share_amount,
+                nonce,
+            } => SignReply::from_pair(signer.sign_mint_shares(
+                *public_pool_index,
+                *share_amount,
+                *nonce,
+            )?),
+            Self::SignBurnShares {
+                public_pool_index,
+                share_amount,
+                nonce,
+            } => SignReply::from_pair(signer.sign_burn_shares(
+                *public_pool_index,
+                *share_amount,
+                *nonce,
+            )?),
+            Self::SignUpdateLeverage {
+                market_index,
+                fraction,
+                margin_mode,
+                nonce,
+            } => SignReply::from_pair(signer.sign_update_leverage(
+                *market_index,
+                *fraction,
+                *margin_mode,
+                *nonce,
+            )?),
+            Self::SignUpdateMargin {
+                market_index,
+                usdc_amount,
+                direction,
+                nonce,
+            } => SignReply::from_pair(signer.sign_update_margin(
+                *market_index,
+                *usdc_amount,
+                *direction,
+                *nonce,
+            )?),
+            Self::CreateAuthToken { deadline } => {
+                SignReply::from_pair(signer.create_auth_token(*deadline)?)
+            }
+        };
+        Ok(reply)
+    }
+}
+
+/// Outcome of a [`SignRequest`], shaped like the native library's return values.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignReply {
+    /// Signed payload, auth token or generated private key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// Only set by `generate_api_key`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_key: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl SignReply {
+    pub fn signed(value: impl Into<String>) -> Self {
+        Self {
+            value: Some(value.into()),
+            ..Self::default()
+        }
+    }
+
+    pub fn rejected(error: impl Into<String>) -> Self {
+        Self {
+            error: Some(error.into()),
+            ..Self::default()
+        }
+    }
+
+    fn from_pair((value, error): (Option<String>, Option<String>)) -> Self {
+        Self {
+            value,
+            public_key: None,
+            error,
+        }
+    }
+
+    fn from_error(error: Option<String>) -> Self {
+        Self {
+            error,
+            ..Self::default()
+        }
+    }
+
+    fn into_pair(self) -> (Option<String>, Option<String>) {
+        (self.value, self.error)
+    }
+}
+
+/// A signer that handles [`SignRequest`]s as data.
+///
+/// Implementors get the full [`TxSigner`] surface through a blanket impl.
+pub trait SignBackend: Send + Sync {
+    fn handle(&self, request: SignRequest) -> Result<SignReply>;
+}
+
+impl<B: SignBackend> TxSigner for B {
+    fn check_client(&self, api_key_index: i32, account_index: i64) -> Result<Option<String>> {
+        let reply = self.handle(SignRequest::CheckClient {
+            api_key_index,
+            account_index,
+        })?;
+        Ok(reply.error)
+    }
+
+    fn switch_api_key(&self, api_key_index: i32) -> Result<Option<String>> {
+        let reply = self.handle(SignRequest::SwitchApiKey { api_key_index })?;
+        Ok(reply.error)
+    }
+
+    fn generate_api_key(
+        &self,
+        seed: Option<&str>,
+    ) -> Result<(Option<String>, Option<String>, Option<String>)> {
+        let reply = self.handle(SignRequest::GenerateApiKey {
+            seed: seed.map(str::to_string),
+        })?;
+        Ok((reply.value, reply.public_key, reply.error))
+    }
+
+    fn sign_change_pub_key(
+        &self,
+        new_pubkey: &str,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignChangePubKey {
+            new_pubkey: new_pubkey.to_string(),
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_create_order(
+        &self,
+        market_index: i32,
+        client_order_index: i64,
+        base_amount: i64,
+        price: i32,
+        is_ask: bool,
+        order_type: i32,
+        time_in_force: i32,
+        reduce_only: bool,
+        trigger_price: i32,
+        order_expiry: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignCreateOrder {
+            market_index,
+            client_order_index,
+            base_amount,
+            price,
+            is_ask,
+            order_type,
+            time_in_force,
+            reduce_only,
+            trigger_price,
+            order_expiry,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_cancel_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignCancelOrder {
+            market_index,
+            order_index,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_withdraw(
+        &self,
+        usdc_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignWithdraw { usdc_amount, nonce })
+            .map(SignReply::into_pair)
+    }
+
+    fn sign_create_sub_account(&self, nonce: i64) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignCreateSubAccount { nonce })
+            .map(SignReply::into_pair)
+    }
+
+    fn sign_cancel_all_orders(
+        &self,
+        time_in_force: i32,
+        time: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignCancelAllOrders {
+            time_in_force,
+            time,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_modify_order(
+        &self,
+        market_index: i32,
+        order_index: i64,
+        base_amount: i64,
+        price: i64,
+        trigger_price: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignModifyOrder {
+            market_index,
+            order_index,
+            base_amount,
+            price,
+            trigger_price,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_transfer(
+        &self,
+        to_account_index: i64,
+        usdc_amount: i64,
+        fee: i64,
+        memo: &str,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignTransfer {
+            to_account_index,
+            usdc_amount,
+            fee,
+            memo: memo.to_string(),
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_create_public_pool(
+        &self,
+        operator_fee: i64,
+        initial_total_shares: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignCreatePublicPool {
+            operator_fee,
+            initial_total_shares,
+            min_operator_share_rate,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_update_public_pool(
+        &self,
+        public_pool_index: i64,
+        status: i32,
+        operator_fee: i64,
+        min_operator_share_rate: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignUpdatePublicPool {
+            public_pool_index,
+            status,
+            operator_fee,
+            min_operator_share_rate,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_mint_shares(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignMintShares {
+            public_pool_index,
+            share_amount,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_burn_shares(
+        &self,
+        public_pool_index: i64,
+        share_amount: i64,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignBurnShares {
+            public_pool_index,
+            share_amount,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_update_leverage(
+        &self,
+        market_index: i32,
+        fraction: i32,
+        margin_mode: i32,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignUpdateLeverage {
+            market_index,
+            fraction,
+            margin_mode,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn sign_update_margin(
+        &self,
+        market_index: i32,
+        usdc_amount: i64,
+        direction: i32,
+        nonce: i64,
+    ) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::SignUpdateMargin {
+            market_index,
+            usdc_amount,
+            direction,
+            nonce,
+        })
+        .map(SignReply::into_pair)
+    }
+
+    fn create_auth_token(&self, deadline: i64) -> Result<(Option<String>, Option<String>)> {
+        self.handle(SignRequest::CreateAuthToken { deadline })
+            .map(SignReply::into_pair)
+    }
+}
+
+/// Deterministic, key-less signer for tests.
+///
+/// Payloads echo the request parameters as PascalCase JSON together with
+/// `AccountIndex`, `ApiKeyIndex` and a `Sig` derived from the request, so
+/// they parse into the [`transactions`](crate::transactions) types but are
+/// never accepted by the exchange. Every request is recorded.
+#[derive(Debug)]
+pub struct MockSigner {
+    account_index: i64,
+    state: Mutex<MockState>,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    api_key_index: i32,
+    calls: Vec<SignRequest>,
+    reject_next: Option<String>,
+}
+
+impl MockSigner {
+    pub fn new(account_index: i64) -> Self {
+        Self {
+            account_index,
+            state: Mutex::new(MockState::default()),
+        }
+    }
+
+    /// Requests handled so far, oldest first.
+    pub fn calls(&self) -> Vec<SignRequest> {
+        self.lock().calls.clone()
+    }
+
+    /// Make the next request come back as a signer-side rejection.
+    pub fn reject_next(&self, error: impl Into<String>) {
+        self.lock().reject_next = Some(error.into());
+    }
+
+    /// API key selected by the last `switch_api_key`.
+    pub fn api_key_index(&self) -> i32 {
+        self.lock().api_key_index
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn payload(&self, api_key_index: i32, request: &SignRequest) -> Result<String> {
+        let mut fields = Map::new();
+        fields.insert("AccountIndex".into(), Value::from(self.account_index));
+        fields.insert("ApiKeyIndex".into(), Value::from(api_key_index));
+        if let Value::Object(params) = serde_json::to_value(request)?
+            .get_mut("params")
+            .map(Value::take)
+            .unwrap_or_default()
+        {
+            for (key, value) in params {
+                // The native signer encodes flags as 0/1.
+                let value = match value {
+                    Value::Bool(flag) => Value::from(i32::from(flag)),
+                    other => other,
+                };
+                fields.insert(pascal_case(&key), value);
+            }
+        }
+        let body = serde_json::to_string(&fields)?;
+        fields.insert(
+            "Sig".into(),
+            Value::from(format!("{:016x}", fnv1a(body.as_bytes()))),
+        );
+        Ok(serde_json::to_string(&fields)?)
+    }
+}
+
+impl SignBackend for MockSigner {
+    fn handle(&self, request: SignRequest) -> Result<SignReply> {
+        let mut state = self.lock();
+        state.calls.push(request.clone());
+        if let Some(error) = state.reject_next.take() {
+            return Ok(SignReply::rejected(error));
+        }
+
+        let reply = match &request {
+            SignRequest::CheckClient { .. } => SignReply::default(),
+            SignRequest::SwitchApiKey { api_key_index } => {
+                state.api_key_index = *api_key_index;
+                SignReply::default()
+            }
+            SignRequest::GenerateApiKey { seed } => {
+                let private = fnv1a(seed.as_deref().unwrap_or_default().as_bytes());
+                SignReply {
+                    value: Some(format!("{private:016x}")),
+                    public_key: Some(format!("{:016x}", fnv1a(&private.to_be_bytes()))),
+                    error: None,
+                }
+            }
+            SignRequest::CreateAuthToken { deadline } => {
+                let body = format!("{deadline}:{}:{}", self.account_index, state.api_key_index);
+                let sig = fnv1a(body.as_bytes());
+                SignReply::signed(format!("{body}:{sig:016x}"))
+            }
+            other => SignReply::signed(self.payload(state.api_key_index, other)?),
+        };
+        Ok(reply)
+    }
+}
+
+fn pascal_case(snake: &str) -> String {
+    snake
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transactions;
+
+    #[test]
+    fn mock_payload_parses_into_transaction_types() {
+        let signer = MockSigner::new(42);
+        assert_eq!(signer.switch_api_key(3).unwrap(), None);
+
+        let (tx_info, error) = signer
+            .sign_create_order(1, 7, 1_000, 3_500, true, 0, 1, false, 0, -1, 11)
+            .unwrap();
+        assert!(error.is_none());
+        let parsed = transactions::CreateOrder::from_json_str(&tx_info.unwrap()).unwrap();
+        assert_eq!(parsed.account_index, Some(42));
+        assert_eq!(parsed.base_amount, Some(1_000));
+        assert_eq!(parsed.price, Some(3_500));
+        assert_eq!(parsed.nonce, Some(11));
+        assert!(parsed.sig.is_some());
+
+        assert_eq!(signer.calls().len(), 2);
+        assert_eq!(signer.calls()[1].nonce(), Some(11));
+    }
+
+    #[test]
+    fn mock_is_deterministic_and_can_reject() {
+        let a = MockSigner::new(1);
+        let b = MockSigner::new(1);
+        assert_eq!(
+            a.sign_cancel_order(2, 99, 5).unwrap(),
+            b.sign_cancel_order(2, 99, 5).unwrap()
+        );
+
+        a.reject_next("invalid nonce");
+        let (value, error) = a.sign_withdraw(10, 6).unwrap();
+        assert!(value.is_none());
+        assert_eq!(error.as_deref(), Some("invalid nonce"));
+        assert!(a.sign_withdraw(10, 6).unwrap().0.is_some());
+    }
+
+    #[test]
+    fn request_round_trips_through_json() {
+        let request = SignRequest::SignTransfer {
+            to_account_index: 8,
+            usdc_amount: 1_000_000,
+            fee: 0,
+            memo: "rent".into(),
+            nonce: 3,
+        };
+        let wire = serde_json::to_string(&request).unwrap();
+        assert!(wire.contains("\"method\":\"sign_transfer\""));
+        let decoded: SignRequest = serde_json::from_str(&wire).unwrap();
+        assert_eq!(decoded, request);
+        assert_eq!(decoded.method(), "sign_transfer");
+    }
+}
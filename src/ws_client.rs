@@ -1,23 +1,31 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
+    future::Future,
     pin::Pin,
     task::{Context, Poll},
     time::{Duration, Instant},
 };
 
-use futures_util::{FutureExt, SinkExt, Stream, StreamExt};
+use futures_util::{FutureExt, Sink, SinkExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tokio::net::TcpStream;
+use tokio::{net::TcpStream, sync::oneshot};
 use tokio_tungstenite::{
-    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+    connect_async,
+    tungstenite::{self, protocol::Message},
+    MaybeTlsStream, WebSocketStream,
 };
 use url::Url;
 
 use crate::{
-    errors::{WsClientError, WsResult},
-    lighter_client::LighterClient,
+    account_events::{AccountChannel, TypedAccountEvent},
+    errors::{DecodeResult, WsClientError, WsResult},
+    lighter_client::{auth::AuthTokenSource, LighterClient},
+    pre_trade::PreTradeChain,
+    signer_client::CODE_OK,
     types::{AccountId, MarketId},
+    ws_health::{HealthConfig, HealthMonitor, HealthSnapshot},
+    ws_recorder::RecorderTap,
 };
 
 #[derive(Debug, Clone)]
@@ -45,6 +53,8 @@ pub struct WsConfig {
 }
 
 const SUPPRESS_ALREADY_SUB_DURATION: Duration = Duration::from_secs(2);
+/// Longest a read waits before the loop comes around again.
+const READ_IDLE_TIMEOUT: Duration = Duration::from_secs(30);
 
 impl Default for WsConfig {
     fn default() -> Self {
@@ -56,6 +66,17 @@ impl Default for WsConfig {
     }
 }
 
+/// Settings for a supervised [`WsStream`] session.
+///
+/// A supervised stream reconnects on its own when the socket drops, using the
+/// connection's [`ExponentialBackoff`], and reports progress through
+/// [`WsEvent::Reconnecting`] and [`WsEvent::Resynced`].
+#[derive(Debug, Clone, Default)]
+pub struct SupervisorConfig {
+    /// Maximum reconnection attempts per outage (None = retry forever).
+    pub max_attempts: Option<u32>,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SubscriptionSet {
     pub order_books: Vec<MarketId>,
@@ -163,6 +184,39 @@ impl SubscriptionSet {
             && self.account_market_trades.is_empty()
     }
 
+    /// Add every channel of `other` that is not already in this set.
+    pub fn extend(&mut self, other: SubscriptionSet) {
+        fn merge<T: PartialEq>(into: &mut Vec<T>, from: Vec<T>) {
+            for item in from {
+                if !into.contains(&item) {
+                    into.push(item);
+                }
+            }
+        }
+
+        merge(&mut self.order_books, other.order_books);
+        merge(&mut self.accounts, other.accounts);
+        merge(&mut self.bbo, other.bbo);
+        merge(&mut self.trades, other.trades);
+        merge(&mut self.market_stats, other.market_stats);
+        self.subscribe_height |= other.subscribe_height;
+        merge(&mut self.account_all_positions, other.account_all_positions);
+        merge(&mut self.account_all_trades, other.account_all_trades);
+        merge(&mut self.account_all_orders, other.account_all_orders);
+        merge(&mut self.user_stats, other.user_stats);
+        merge(&mut self.account_tx, other.account_tx);
+        merge(&mut self.pool_data, other.pool_data);
+        merge(&mut self.pool_info, other.pool_info);
+        merge(&mut self.notifications, other.notifications);
+        merge(&mut self.account_market, other.account_market);
+        merge(&mut self.account_market_orders, other.account_market_orders);
+        merge(
+            &mut self.account_market_positions,
+            other.account_market_positions,
+        );
+        merge(&mut self.account_market_trades, other.account_market_trades);
+    }
+
     pub fn requires_auth(&self) -> bool {
         !self.accounts.is_empty()
             || !self.account_all_positions.is_empty()
@@ -178,12 +232,310 @@ impl SubscriptionSet {
             || !self.account_market_positions.is_empty()
             || !self.account_market_trades.is_empty()
     }
+
+    pub fn contains(&self, channel: Channel) -> bool {
+        match channel {
+            Channel::OrderBook(m) => self.order_books.contains(&m),
+            Channel::Bbo(m) => self.bbo.contains(&m),
+            Channel::Trade(m) => self.trades.contains(&m),
+            Channel::MarketStats(m) => self.market_stats.contains(&m),
+            Channel::Height => self.subscribe_height,
+            Channel::Account(a) => self.accounts.contains(&a),
+            Channel::AccountAllPositions(a) => self.account_all_positions.contains(&a),
+            Channel::AccountAllTrades(a) => self.account_all_trades.contains(&a),
+            Channel::AccountAllOrders(a) => self.account_all_orders.contains(&a),
+            Channel::UserStats(a) => self.user_stats.contains(&a),
+            Channel::AccountTx(a) => self.account_tx.contains(&a),
+            Channel::PoolData(a) => self.pool_data.contains(&a),
+            Channel::PoolInfo(a) => self.pool_info.contains(&a),
+            Channel::Notification(a) => self.notifications.contains(&a),
+            Channel::AccountMarket(m, a) => self.account_market.contains(&(m, a)),
+            Channel::AccountMarketOrders(m, a) => self.account_market_orders.contains(&(m, a)),
+            Channel::AccountMarketPositions(m, a) => {
+                self.account_market_positions.contains(&(m, a))
+            }
+            Channel::AccountMarketTrades(m, a) => self.account_market_trades.contains(&(m, a)),
+        }
+    }
+
+    /// Add `channel`; returns `false` if it was already in the set.
+    pub fn insert(&mut self, channel: Channel) -> bool {
+        if self.contains(channel) {
+            return false;
+        }
+        match channel {
+            Channel::OrderBook(m) => self.order_books.push(m),
+            Channel::Bbo(m) => self.bbo.push(m),
+            Channel::Trade(m) => self.trades.push(m),
+            Channel::MarketStats(m) => self.market_stats.push(m),
+            Channel::Height => self.subscribe_height = true,
+            Channel::Account(a) => self.accounts.push(a),
+            Channel::AccountAllPositions(a) => self.account_all_positions.push(a),
+            Channel::AccountAllTrades(a) => self.account_all_trades.push(a),
+            Channel::AccountAllOrders(a) => self.account_all_orders.push(a),
+            Channel::UserStats(a) => self.user_stats.push(a),
+            Channel::AccountTx(a) => self.account_tx.push(a),
+            Channel::PoolData(a) => self.pool_data.push(a),
+            Channel::PoolInfo(a) => self.pool_info.push(a),
+            Channel::Notification(a) => self.notifications.push(a),
+            Channel::AccountMarket(m, a) => self.account_market.push((m, a)),
+            Channel::AccountMarketOrders(m, a) => self.account_market_orders.push((m, a)),
+            Channel::AccountMarketPositions(m, a) => self.account_market_positions.push((m, a)),
+            Channel::AccountMarketTrades(m, a) => self.account_market_trades.push((m, a)),
+        }
+        true
+    }
+
+    /// Drop `channel`; returns `false` if it was not in the set.
+    pub fn remove(&mut self, channel: Channel) -> bool {
+        if !self.contains(channel) {
+            return false;
+        }
+        match channel {
+            Channel::OrderBook(m) => self.order_books.retain(|x| *x != m),
+            Channel::Bbo(m) => self.bbo.retain(|x| *x != m),
+            Channel::Trade(m) => self.trades.retain(|x| *x != m),
+            Channel::MarketStats(m) => self.market_stats.retain(|x| *x != m),
+            Channel::Height => self.subscribe_height = false,
+            Channel::Account(a) => self.accounts.retain(|x| *x != a),
+            Channel::AccountAllPositions(a) => self.account_all_positions.retain(|x| *x != a),
+            Channel::AccountAllTrades(a) => self.account_all_trades.retain(|x| *x != a),
+            Channel::AccountAllOrders(a) => self.account_all_orders.retain(|x| *x != a),
+            Channel::UserStats(a) => self.user_stats.retain(|x| *x != a),
+            Channel::AccountTx(a) => self.account_tx.retain(|x| *x != a),
+            Channel::PoolData(a) => self.pool_data.retain(|x| *x != a),
+            Channel::PoolInfo(a) => self.pool_info.retain(|x| *x != a),
+            Channel::Notification(a) => self.notifications.retain(|x| *x != a),
+            Channel::AccountMarket(m, a) => self.account_market.retain(|x| *x != (m, a)),
+            Channel::AccountMarketOrders(m, a) => {
+                self.account_market_orders.retain(|x| *x != (m, a))
+            }
+            Channel::AccountMarketPositions(m, a) => {
+                self.account_market_positions.retain(|x| *x != (m, a))
+            }
+            Channel::AccountMarketTrades(m, a) => {
+                self.account_market_trades.retain(|x| *x != (m, a))
+            }
+        }
+        true
+    }
+
+    /// Whether any channel in the set is carried by `source` on the wire
+    /// (see [`Channel::source`]). Synthetic channels never hold it open.
+    pub fn carries(&self, source: Channel) -> bool {
+        self.channels()
+            .into_iter()
+            .any(|channel| !channel.is_synthetic() && channel.source() == source)
+    }
+
+    /// Every channel in the set, in subscription order.
+    pub fn channels(&self) -> Vec<Channel> {
+        let mut channels = Vec::new();
+        channels.extend(self.order_books.iter().map(|m| Channel::OrderBook(*m)));
+        channels.extend(self.accounts.iter().map(|a| Channel::Account(*a)));
+        channels.extend(self.bbo.iter().map(|m| Channel::Bbo(*m)));
+        channels.extend(self.trades.iter().map(|m| Channel::Trade(*m)));
+        channels.extend(self.market_stats.iter().map(|m| Channel::MarketStats(*m)));
+        if self.subscribe_height {
+            channels.push(Channel::Height);
+        }
+        channels.extend(
+            self.account_all_positions
+                .iter()
+                .map(|a| Channel::AccountAllPositions(*a)),
+        );
+        channels.extend(
+            self.account_all_trades
+                .iter()
+                .map(|a| Channel::AccountAllTrades(*a)),
+        );
+        channels.extend(
+            self.account_all_orders
+                .iter()
+                .map(|a| Channel::AccountAllOrders(*a)),
+        );
+        channels.extend(self.user_stats.iter().map(|a| Channel::UserStats(*a)));
+        channels.extend(self.account_tx.iter().map(|a| Channel::AccountTx(*a)));
+        channels.extend(self.pool_data.iter().map(|a| Channel::PoolData(*a)));
+        channels.extend(self.pool_info.iter().map(|a| Channel::PoolInfo(*a)));
+        channels.extend(self.notifications.iter().map(|a| Channel::Notification(*a)));
+        channels.extend(
+            self.account_market
+                .iter()
+                .map(|(m, a)| Channel::AccountMarket(*m, *a)),
+        );
+        channels.extend(
+            self.account_market_orders
+                .iter()
+                .map(|(m, a)| Channel::AccountMarketOrders(*m, *a)),
+        );
+        channels.extend(
+            self.account_market_positions
+                .iter()
+                .map(|(m, a)| Channel::AccountMarketPositions(*m, *a)),
+        );
+        channels.extend(
+            self.account_market_trades
+                .iter()
+                .map(|(m, a)| Channel::AccountMarketTrades(*m, *a)),
+        );
+        channels
+    }
+}
+
+/// One subscribable channel.
+///
+/// `Display` gives the name used in subscribe requests (`order_book/1`, and
+/// the same scheme for client-side channels); [`Channel::parse`] also
+/// accepts the `order_book:1` form the server puts in its messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Channel {
+    OrderBook(MarketId),
+    /// Synthetic: computed client-side from the market's order book.
+    Bbo(MarketId),
+    Trade(MarketId),
+    MarketStats(MarketId),
+    Height,
+    /// The `account_all` channel.
+    Account(AccountId),
+    AccountAllPositions(AccountId),
+    AccountAllTrades(AccountId),
+    AccountAllOrders(AccountId),
+    UserStats(AccountId),
+    AccountTx(AccountId),
+    PoolData(AccountId),
+    PoolInfo(AccountId),
+    Notification(AccountId),
+    AccountMarket(MarketId, AccountId),
+    AccountMarketOrders(MarketId, AccountId),
+    /// Filtered client-side from `account_all_positions`.
+    AccountMarketPositions(MarketId, AccountId),
+    /// Filtered client-side from `account_all_trades`.
+    AccountMarketTrades(MarketId, AccountId),
+}
+
+impl Channel {
+    /// Parse a channel name in either the `kind/id` or `kind:id` form.
+    pub fn parse(name: &str) -> Option<Self> {
+        let mut parts = name.split(['/', ':']);
+        let kind = parts.next()?;
+        let ids = parts
+            .map(|part| part.parse::<i64>().ok())
+            .collect::<Option<Vec<_>>>()?;
+        let market = |id: i64| i32::try_from(id).ok().map(MarketId::new);
+        let account = AccountId::new;
+        let channel = match (kind, ids.as_slice()) {
+            ("order_book", [m]) => Self::OrderBook(market(*m)?),
+            ("bbo", [m]) => Self::Bbo(market(*m)?),
+            ("trade", [m]) => Self::Trade(market(*m)?),
+            ("market_stats", [m]) => Self::MarketStats(market(*m)?),
+            ("height", []) => Self::Height,
+            ("account_all", [a]) => Self::Account(account(*a)),
+            ("account_all_positions", [a]) => Self::AccountAllPositions(account(*a)),
+            ("account_all_trades", [a]) => Self::AccountAllTrades(account(*a)),
+            ("account_all_orders", [a]) => Self::AccountAllOrders(account(*a)),
+            ("user_stats", [a]) => Self::UserStats(account(*a)),
+            ("account_tx", [a]) => Self::AccountTx(account(*a)),
+            ("pool_data", [a]) => Self::PoolData(account(*a)),
+            ("pool_info", [a]) => Self::PoolInfo(account(*a)),
+            ("notification", [a]) => Self::Notification(account(*a)),
+            ("account_market", [m, a]) => Self::AccountMarket(market(*m)?, account(*a)),
+            ("account_orders", [m, a]) => Self::AccountMarketOrders(market(*m)?, account(*a)),
+            ("account_positions", [m, a]) => Self::AccountMarketPositions(market(*m)?, account(*a)),
+            ("account_trades", [m, a]) => Self::AccountMarketTrades(market(*m)?, account(*a)),
+            _ => return None,
+        };
+        Some(channel)
+    }
+
+    /// The channel whose messages carry this one's data. Synthetic and
+    /// client-filtered channels map to their source; the rest to themselves.
+    pub fn source(self) -> Self {
+        match self {
+            Self::Bbo(market) => Self::OrderBook(market),
+            Self::AccountMarketPositions(_, account) => Self::AccountAllPositions(account),
+            Self::AccountMarketTrades(_, account) => Self::AccountAllTrades(account),
+            other => other,
+        }
+    }
+
+    /// Computed client-side from another channel and never subscribed on the
+    /// wire. A BBO only produces events while its order book is subscribed.
+    pub fn is_synthetic(self) -> bool {
+        matches!(self, Self::Bbo(_))
+    }
+
+    /// Public market data rather than an authenticated account channel.
+    pub fn is_market_data(self) -> bool {
+        matches!(
+            self,
+            Self::OrderBook(_)
+                | Self::Bbo(_)
+                | Self::Trade(_)
+                | Self::MarketStats(_)
+                | Self::Height
+        )
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OrderBook(m) => write!(f, "order_book/{}", m.into_inner()),
+            Self::Bbo(m) => write!(f, "bbo/{}", m.into_inner()),
+            Self::Trade(m) => write!(f, "trade/{}", m.into_inner()),
+            Self::MarketStats(m) => write!(f, "market_stats/{}", m.into_inner()),
+            Self::Height => write!(f, "height"),
+            Self::Account(a) => write!(f, "account_all/{}", a.into_inner()),
+            Self::AccountAllPositions(a) => write!(f, "account_all_positions/{}", a.into_inner()),
+            Self::AccountAllTrades(a) => write!(f, "account_all_trades/{}", a.into_inner()),
+            Self::AccountAllOrders(a) => write!(f, "account_all_orders/{}", a.into_inner()),
+            Self::UserStats(a) => write!(f, "user_stats/{}", a.into_inner()),
+            Self::AccountTx(a) => write!(f, "account_tx/{}", a.into_inner()),
+            Self::PoolData(a) => write!(f, "pool_data/{}", a.into_inner()),
+            Self::PoolInfo(a) => write!(f, "pool_info/{}", a.into_inner()),
+            Self::Notification(a) => write!(f, "notification/{}", a.into_inner()),
+            Self::AccountMarket(m, a) => {
+                write!(f, "account_market/{}/{}", m.into_inner(), a.into_inner())
+            }
+            Self::AccountMarketOrders(m, a) => {
+                write!(f, "account_orders/{}/{}", m.into_inner(), a.into_inner())
+            }
+            Self::AccountMarketPositions(m, a) => {
+                write!(f, "account_positions/{}/{}", m.into_inner(), a.into_inner())
+            }
+            Self::AccountMarketTrades(m, a) => {
+                write!(f, "account_trades/{}/{}", m.into_inner(), a.into_inner())
+            }
+        }
+    }
+}
+
+/// Server acknowledgement state of a channel subscription.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SubscriptionStatus {
+    /// Subscribe request sent, no ack yet.
+    Subscribing,
+    Subscribed,
+    /// Unsubscribe request sent, no ack yet.
+    Unsubscribing,
+    Unsubscribed,
+    /// The server answered with an error naming the channel.
+    Rejected(String),
+}
+
+impl SubscriptionStatus {
+    pub fn is_pending(&self) -> bool {
+        matches!(self, Self::Subscribing | Self::Unsubscribing)
+    }
 }
 
 pub struct WsBuilder<'a> {
     client: &'a LighterClient,
     subscriptions: SubscriptionSet,
     config: WsConfig,
+    supervisor: Option<SupervisorConfig>,
+    health: Option<HealthConfig>,
 }
 
 impl<'a> WsBuilder<'a> {
@@ -196,6 +548,8 @@ impl<'a> WsBuilder<'a> {
             client,
             subscriptions: SubscriptionSet::default(),
             config,
+            supervisor: None,
+            health: None,
         }
     }
 
@@ -335,6 +689,30 @@ impl<'a> WsBuilder<'a> {
         self
     }
 
+    /// Replace every subscription with `subscriptions`.
+    pub(crate) fn with_subscriptions(mut self, subscriptions: SubscriptionSet) -> Self {
+        self.subscriptions = subscriptions;
+        self
+    }
+
+    /// Run the resulting stream as a self-healing session that reconnects,
+    /// resubscribes and refreshes its auth token without caller involvement.
+    pub fn supervised(self) -> Self {
+        self.supervise(SupervisorConfig::default())
+    }
+
+    /// Like [`WsBuilder::supervised`] with explicit supervisor settings.
+    pub fn supervise(mut self, supervisor: SupervisorConfig) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Send heartbeats and report stale channels as [`WsEvent::Stale`].
+    pub fn health(mut self, health: HealthConfig) -> Self {
+        self.health = Some(health);
+        self
+    }
+
     pub fn build(self) -> WsResult<WsClient> {
         WsClient::new(self.client, self.config, self.subscriptions)
     }
@@ -344,6 +722,8 @@ impl<'a> WsBuilder<'a> {
             client,
             subscriptions,
             config,
+            supervisor,
+            health,
         } = self;
 
         let auth_token = if subscriptions.requires_auth() {
@@ -362,6 +742,18 @@ impl<'a> WsBuilder<'a> {
         if let Some(token) = auth_token {
             connection.set_auth_token(token);
         }
+        if let Some(health) = health {
+            connection.set_health_config(health);
+        }
+
+        if let Some(supervisor) = supervisor {
+            let auth = if connection.subscriptions.requires_auth() {
+                client.auth_token_source()
+            } else {
+                None
+            };
+            connection.enable_supervision(supervisor, auth);
+        }
 
         Ok(WsStream::new(connection))
     }
@@ -372,6 +764,7 @@ pub struct WsClient {
     config: WsConfig,
     subscriptions: SubscriptionSet,
     url: Url,
+    pre_trade: PreTradeChain,
 }
 
 impl WsClient {
@@ -393,6 +786,7 @@ impl WsClient {
             config,
             subscriptions,
             url,
+            pre_trade: client.pre_trade().clone(),
         })
     }
 
@@ -406,19 +800,17 @@ impl WsClient {
 
     pub async fn connect(self) -> WsResult<WsConnection> {
         let (stream, _) = connect_async(self.url.as_str()).await?;
-        Ok(WsConnection::new(
-            self.url,
-            stream,
-            self.subscriptions,
-            self.config.backoff,
-        ))
+        let mut connection =
+            WsConnection::new(self.url, stream, self.subscriptions, self.config.backoff);
+        connection.pre_trade = self.pre_trade;
+        Ok(connection)
     }
 }
 
 #[derive(Debug)]
 pub struct WsConnection {
     url: Url,
-    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    stream: WsTransport,
     subscriptions: SubscriptionSet,
     state: WsState,
     backoff: ExponentialBackoff,
@@ -427,6 +819,101 @@ pub struct WsConnection {
     generation: u64,
     suppress_already_subscribed_until: Option<Instant>,
     pending_events: std::collections::VecDeque<WsEvent>,
+    pending_txs: std::collections::VecDeque<InFlightTx>,
+    supervisor: Option<Supervisor>,
+    health: HealthMonitor,
+    /// Ack state per wire channel (see [`Channel::source`]).
+    acks: HashMap<Channel, SubscriptionStatus>,
+    recorder: Option<RecorderTap>,
+    /// The client's pre-trade chain, run on every order sent.
+    pre_trade: PreTradeChain,
+}
+
+/// Where a connection's frames come from: a live socket, or recorded frames
+/// queued by a [`ReplayStream`](crate::ws_recorder::ReplayStream). A replay
+/// transport ends (yields `None`) whenever its queue runs dry and discards
+/// everything sent to it.
+#[derive(Debug)]
+enum WsTransport {
+    Socket(Box<WebSocketStream<MaybeTlsStream<TcpStream>>>),
+    Replay(std::collections::VecDeque<Message>),
+}
+
+impl WsTransport {
+    async fn close(
+        &mut self,
+        frame: Option<tungstenite::protocol::CloseFrame<'static>>,
+    ) -> Result<(), tungstenite::Error> {
+        match self {
+            Self::Socket(stream) => (**stream).close(frame).await,
+            Self::Replay(frames) => {
+                frames.clear();
+                Ok(())
+            }
+        }
+    }
+}
+
+impl From<WebSocketStream<MaybeTlsStream<TcpStream>>> for WsTransport {
+    fn from(stream: WebSocketStream<MaybeTlsStream<TcpStream>>) -> Self {
+        Self::Socket(Box::new(stream))
+    }
+}
+
+impl Stream for WsTransport {
+    type Item = Result<Message, tungstenite::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut() {
+            Self::Socket(stream) => stream.poll_next_unpin(cx),
+            Self::Replay(frames) => Poll::Ready(frames.pop_front().map(Ok)),
+        }
+    }
+}
+
+impl Sink<Message> for WsTransport {
+    type Error = tungstenite::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Socket(stream) => stream.poll_ready_unpin(cx),
+            Self::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Message) -> Result<(), Self::Error> {
+        match self.get_mut() {
+            Self::Socket(stream) => stream.start_send_unpin(item),
+            Self::Replay(_) => Ok(()),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Socket(stream) => stream.poll_flush_unpin(cx),
+            Self::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match self.get_mut() {
+            Self::Socket(stream) => stream.poll_close_unpin(cx),
+            Self::Replay(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// A sent transaction whose reply has not been read yet.
+#[derive(Debug)]
+struct InFlightTx {
+    id: String,
+    waiters: Vec<oneshot::Sender<TxResponse>>,
+}
+
+impl InFlightTx {
+    fn is_abandoned(&self) -> bool {
+        self.waiters.iter().all(oneshot::Sender::is_closed)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -438,12 +925,109 @@ struct WsState {
     bbo_cache: HashMap<MarketId, (Option<String>, Option<String>)>,
 }
 
+impl WsState {
+    fn clear(&mut self) {
+        self.order_books.clear();
+        self.order_book_offsets.clear();
+        self.accounts.clear();
+        self.bbo_cache.clear();
+    }
+}
+
+type DialFuture = Pin<
+    Box<
+        dyn Future<Output = WsResult<(WebSocketStream<MaybeTlsStream<TcpStream>>, Option<String>)>>
+            + Send,
+    >,
+>;
+
+#[derive(Debug)]
+struct Supervisor {
+    config: SupervisorConfig,
+    auth: Option<AuthTokenSource>,
+    reconnect: Option<ReconnectState>,
+    resync: Option<PendingResync>,
+    exhausted: bool,
+}
+
+/// Progress of an in-flight reconnect. The sleep and dial futures are kept
+/// here so the reconnect survives the caller dropping a pending poll.
+struct ReconnectState {
+    attempt: u32,
+    delay: Duration,
+    sleep: Pin<Box<tokio::time::Sleep>>,
+    dial: Option<DialFuture>,
+}
+
+impl std::fmt::Debug for ReconnectState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ReconnectState")
+            .field("attempt", &self.attempt)
+            .field("delay", &self.delay)
+            .field("dialing", &self.dial.is_some())
+            .finish()
+    }
+}
+
+impl ReconnectState {
+    fn new(attempt: u32, delay: Duration) -> Self {
+        use rand::Rng;
+
+        // Add jitter: ±10% randomization to prevent thundering herd
+        let jitter = rand::thread_rng().gen_range(0.9..1.1);
+        Self {
+            attempt,
+            delay,
+            sleep: Box::pin(tokio::time::sleep(delay.mul_f64(jitter))),
+            dial: None,
+        }
+    }
+}
+
+/// Tracks what a reconnected session still needs before it is resynced:
+/// the server's `connected` acknowledgement (which replays subscriptions) and
+/// a fresh snapshot for every subscribed order book.
+#[derive(Debug)]
+struct PendingResync {
+    generation: u64,
+    connected: bool,
+    markets: HashSet<MarketId>,
+}
+
+impl PendingResync {
+    fn is_complete(&self) -> bool {
+        self.connected && self.markets.is_empty()
+    }
+}
+
 impl WsConnection {
     fn new(
         url: Url,
         stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
         subscriptions: SubscriptionSet,
         backoff: ExponentialBackoff,
+    ) -> Self {
+        Self::with_transport(url, stream.into(), subscriptions, backoff)
+    }
+
+    /// A connection fed from recorded frames instead of a socket.
+    pub(crate) fn replay(subscriptions: SubscriptionSet) -> Self {
+        let url = Url::parse("wss://replay.invalid/stream").expect("static url");
+        let mut connection = Self::with_transport(
+            url,
+            WsTransport::Replay(std::collections::VecDeque::new()),
+            subscriptions,
+            ExponentialBackoff::default(),
+        );
+        connection.suppress_already_subscribed_until = None;
+        connection
+    }
+
+    fn with_transport(
+        url: Url,
+        stream: WsTransport,
+        subscriptions: SubscriptionSet,
+        backoff: ExponentialBackoff,
     ) -> Self {
         Self {
             url,
@@ -456,6 +1040,43 @@ impl WsConnection {
             generation: 0,
             suppress_already_subscribed_until: Some(Instant::now() + SUPPRESS_ALREADY_SUB_DURATION),
             pending_events: std::collections::VecDeque::new(),
+            pending_txs: std::collections::VecDeque::new(),
+            supervisor: None,
+            health: HealthMonitor::new(HealthConfig::passive(), Instant::now()),
+            acks: HashMap::new(),
+            recorder: None,
+            pre_trade: PreTradeChain::default(),
+        }
+    }
+
+    /// Queue a recorded frame on a replay connection; ignored on a socket.
+    pub(crate) fn push_replay_frame(&mut self, message: Message) {
+        if let WsTransport::Replay(frames) = &mut self.stream {
+            frames.push_back(message);
+        }
+    }
+
+    /// Swap the subscription set of a replay connection as recorded.
+    pub(crate) fn replace_subscriptions(&mut self, subscriptions: SubscriptionSet) {
+        self.subscriptions = subscriptions;
+    }
+
+    /// Copy every frame this connection receives to `tap`, along with its
+    /// subscriptions whenever they change (see
+    /// [`MarketRecorder`](crate::ws_recorder::MarketRecorder)).
+    pub fn attach_recorder(&mut self, tap: RecorderTap) {
+        tap.subscriptions(&self.subscriptions);
+        self.recorder = Some(tap);
+    }
+
+    /// Stop copying frames to the attached recorder, if any.
+    pub fn detach_recorder(&mut self) -> Option<RecorderTap> {
+        self.recorder.take()
+    }
+
+    fn note_subscriptions(&self) {
+        if let Some(recorder) = &self.recorder {
+            recorder.subscriptions(&self.subscriptions);
         }
     }
 
@@ -479,15 +1100,162 @@ impl WsConnection {
         &self.backoff
     }
 
+    /// Number of successful reconnects performed by this connection.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// Heartbeat, RTT and per-channel activity of this connection.
+    pub fn health(&self) -> HealthSnapshot {
+        self.health.snapshot(&self.subscriptions, Instant::now())
+    }
+
+    /// Start heartbeats and staleness detection (see [`HealthConfig`]).
+    pub fn set_health_config(&mut self, config: HealthConfig) {
+        self.health.set_config(config, Instant::now());
+    }
+
+    /// Whether this connection reconnects on its own (see [`SupervisorConfig`]).
+    pub fn is_supervised(&self) -> bool {
+        self.supervisor.is_some()
+    }
+
+    pub(crate) fn enable_supervision(
+        &mut self,
+        config: SupervisorConfig,
+        auth: Option<AuthTokenSource>,
+    ) {
+        self.supervisor = Some(Supervisor {
+            config,
+            auth,
+            reconnect: None,
+            resync: None,
+            exhausted: false,
+        });
+    }
+
     pub fn suppressing_already_subscribed(&self) -> bool {
         self.suppress_already_subscribed_until
             .map(|until| Instant::now() <= until)
             .unwrap_or(false)
     }
 
+    /// Subscribe to `channel` on the live socket.
+    ///
+    /// The channel is added to [`subscriptions`](Self::subscriptions) first,
+    /// so a reconnect replays it. Nothing is sent for a synthetic channel
+    /// (see [`Channel::is_synthetic`]) or when another subscribed channel
+    /// already carries its data. A synthetic channel whose source is not
+    /// subscribed fails with [`WsClientError::MissingSource`], since it would
+    /// never emit. Use [`wait_for_ack`](Self::wait_for_ack) to wait for the
+    /// server's confirmation.
+    pub async fn subscribe(&mut self, channel: Channel) -> WsResult<()> {
+        let source = channel.source();
+        let on_wire = self.subscriptions.carries(source);
+        if channel.is_synthetic() && !on_wire {
+            return Err(WsClientError::MissingSource {
+                channel: channel.to_string(),
+                carrier: source.to_string(),
+            });
+        }
+        if !self.subscriptions.insert(channel) {
+            return Ok(());
+        }
+        self.note_subscriptions();
+        if on_wire || channel.is_synthetic() {
+            return Ok(());
+        }
+        self.send_channel_request("subscribe", source).await?;
+        self.acks.insert(source, SubscriptionStatus::Subscribing);
+        Ok(())
+    }
+
+    /// Unsubscribe from `channel` and drop its cached state.
+    ///
+    /// The wire channel stays open while another subscribed channel still
+    /// needs it.
+    pub async fn unsubscribe(&mut self, channel: Channel) -> WsResult<()> {
+        if !self.subscriptions.remove(channel) {
+            return Ok(());
+        }
+        self.note_subscriptions();
+        let source = channel.source();
+        match channel {
+            Channel::Bbo(market) => {
+                self.state.bbo_cache.remove(&market);
+                return Ok(());
+            }
+            Channel::Account(account) => {
+                self.state.accounts.remove(&account);
+            }
+            _ => {}
+        }
+        if self.subscriptions.carries(source) {
+            return Ok(());
+        }
+        if let Channel::OrderBook(market) = source {
+            self.state.order_books.remove(&market);
+            self.state.order_book_offsets.remove(&market);
+            self.state.bbo_cache.remove(&market);
+        }
+        self.send_channel_request("unsubscribe", source).await?;
+        self.acks.insert(source, SubscriptionStatus::Unsubscribing);
+        Ok(())
+    }
+
+    /// Ack state of the wire channel carrying `channel`, if any request for
+    /// it was sent on this socket.
+    pub fn subscription_status(&self, channel: Channel) -> Option<&SubscriptionStatus> {
+        self.acks.get(&channel.source())
+    }
+
+    /// Read frames until the pending subscribe or unsubscribe for `channel`
+    /// is acknowledged, queueing every other event for
+    /// [`next_event`](Self::next_event). Returns immediately when nothing is
+    /// pending.
+    pub async fn wait_for_ack(&mut self, channel: Channel, timeout: Duration) -> WsResult<()> {
+        let source = channel.source();
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            match self.acks.get(&source) {
+                Some(SubscriptionStatus::Rejected(message)) => {
+                    return Err(WsClientError::SubscriptionRejected {
+                        channel: source.to_string(),
+                        message: message.clone(),
+                    })
+                }
+                Some(status) if status.is_pending() => {}
+                _ => return Ok(()),
+            }
+            let queued = self.pending_events.len();
+            match tokio::time::timeout_at(deadline, self.read_event()).await {
+                Err(_) | Ok(Ok(None)) => {
+                    return Err(WsClientError::SubscriptionTimeout(source.to_string()))
+                }
+                Ok(Err(err)) => return Err(err),
+                Ok(Ok(Some(event))) => self.pending_events.insert(queued, event),
+            }
+        }
+    }
+
+    async fn send_channel_request(&mut self, kind: &str, channel: Channel) -> WsResult<()> {
+        let mut payload = json!({
+            "type": kind,
+            "channel": channel.to_string(),
+        });
+        if !channel.is_market_data() {
+            if let Some(token) = &self.auth_token {
+                payload["auth"] = json!(token);
+            }
+        }
+        self.stream.send(Message::Text(payload.to_string())).await?;
+        Ok(())
+    }
+
     pub async fn unsubscribe_order_book_channel(&mut self, market: MarketId) -> WsResult<()> {
         self.subscriptions.order_books.retain(|m| *m != market);
         self.state.order_books.remove(&market);
+        self.note_subscriptions();
         let payload = json!({
             "type": "unsubscribe",
             "channel": format!("order_book/{}", market.into_inner()),
@@ -500,6 +1268,7 @@ impl WsConnection {
     pub async fn subscribe_order_book_channel(&mut self, market: MarketId) -> WsResult<()> {
         if !self.subscriptions.order_books.iter().any(|m| *m == market) {
             self.subscriptions.order_books.push(market);
+            self.note_subscriptions();
         }
         let payload = json!({
             "type": "subscribe",
@@ -519,36 +1288,57 @@ impl WsConnection {
     }
 
     pub async fn next_event(&mut self) -> WsResult<Option<WsEvent>> {
-        use tokio::time::timeout;
-
         // First check if we have pending events to emit
         if let Some(event) = self.pending_events.pop_front() {
             return Ok(Some(event));
         }
 
+        self.read_event().await
+    }
+
+    /// Read the next event from the socket, bypassing `pending_events`.
+    /// Transaction replies are routed to their [`PendingTx`]; replies no
+    /// request is waiting for surface as [`WsEvent::UnmatchedReply`].
+    /// `Ok(None)` only means the stream ended.
+    async fn read_event(&mut self) -> WsResult<Option<WsEvent>> {
+        use tokio::time::timeout;
+
         loop {
+            if let Some(event) = self.poll_health().await? {
+                return Ok(Some(event));
+            }
+
             // Add 30-second timeout to keep event loop responsive during quiet periods
             // This prevents WebSocket staleness by allowing the loop to continue even
-            // when no messages are received, enabling proper ping/pong handling
-            match timeout(Duration::from_secs(30), self.stream.next()).await {
+            // when no messages are received, enabling proper ping/pong handling.
+            // Heartbeats and staleness checks shorten the wait when configured.
+            let wait = self
+                .health
+                .next_deadline()
+                .map_or(READ_IDLE_TIMEOUT, |deadline| {
+                    deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(READ_IDLE_TIMEOUT)
+                });
+            match timeout(wait, self.stream.next()).await {
                 Ok(Some(Ok(message))) => {
+                    self.health.record_message(Instant::now());
+                    if let Some(recorder) = &self.recorder {
+                        recorder.frame(&message);
+                    }
                     // Got a message - process it normally
-                    match message {
-                        Message::Text(text) => {
-                            return self.handle_text_message(text).await;
-                        }
-                        Message::Binary(binary) => {
-                            let text = String::from_utf8(binary).map_err(|_| {
-                                WsClientError::InvalidMessage("invalid utf8 payload".to_string())
-                            })?;
-                            return self.handle_text_message(text).await;
-                        }
+                    let text = match message {
+                        Message::Text(text) => text,
+                        Message::Binary(binary) => String::from_utf8(binary).map_err(|_| {
+                            WsClientError::InvalidMessage("invalid utf8 payload".to_string())
+                        })?,
                         Message::Ping(payload) => {
                             tracing::info!("🏓 Received WebSocket PING, sending PONG");
                             self.stream.send(Message::Pong(payload)).await?;
                             return Ok(Some(WsEvent::Pong));
                         }
-                        Message::Pong(_) => {
+                        Message::Pong(payload) => {
+                            self.health.record_pong(&payload, Instant::now());
                             return Ok(Some(WsEvent::Pong));
                         }
                         Message::Close(frame) => {
@@ -556,34 +1346,77 @@ impl WsConnection {
                                 code: u16::from(frame.code),
                                 reason: frame.reason.into_owned(),
                             });
+                            self.pending_txs.clear();
                             return Ok(Some(WsEvent::Closed(info)));
                         }
                         Message::Frame(_) => {
                             // Ignore frame messages, continue loop
+                            continue;
                         }
+                    };
+                    if self.resolve_tx_response(&text) {
+                        continue;
+                    }
+                    // Frames that produce no event (e.g. a delta dropped
+                    // before its snapshot) must not read as end of stream.
+                    if let Some(event) = self.handle_text_message(text).await? {
+                        return Ok(Some(event));
                     }
                 }
                 Ok(Some(Err(e))) => {
                     // WebSocket error
+                    self.pending_txs.clear();
                     return Err(e.into());
                 }
                 Ok(None) => {
                     // Connection closed by server
+                    self.pending_txs.clear();
                     return Ok(None);
                 }
                 Err(_) => {
                     // Timeout occurred - connection is quiet but OK
                     // Continue loop to check for messages again
                     // This prevents the blocking issue that causes staleness
-                    tracing::debug!(
-                        "WebSocket message read timeout (connection quiet, continuing...)"
-                    );
+                    if wait >= READ_IDLE_TIMEOUT {
+                        tracing::debug!(
+                            "WebSocket message read timeout (connection quiet, continuing...)"
+                        );
+                    }
                     continue;
                 }
             }
         }
     }
 
+    /// Send a due heartbeat and surface channels that just went stale,
+    /// resubscribing stale order books when configured to.
+    async fn poll_health(&mut self) -> WsResult<Option<WsEvent>> {
+        let now = Instant::now();
+        let actions = self.health.poll(&self.subscriptions, now);
+        if let Some(payload) = actions.ping {
+            self.stream.send(Message::Ping(payload)).await?;
+        }
+
+        let mut stale = Vec::with_capacity(actions.stale.len());
+        for channel in actions.stale {
+            tracing::warn!(%channel, "websocket channel stale");
+            if let Channel::OrderBook(market) = channel {
+                if self.health.config().resubscribe_stale_books
+                    && self.subscriptions.order_books.contains(&market)
+                {
+                    self.resubscribe_order_book_channel(market).await?;
+                    self.health.restart_channel(channel, Instant::now());
+                }
+            }
+            stale.push(WsEvent::Stale { channel });
+        }
+
+        let mut stale = stale.into_iter();
+        let first = stale.next();
+        self.pending_events.extend(stale);
+        Ok(first)
+    }
+
     pub async fn close(mut self) -> WsResult<()> {
         self.stream.close(None).await?;
         Ok(())
@@ -610,6 +1443,56 @@ impl WsConnection {
         self.message_id
     }
 
+    /// Register a sent request so its reply can be routed back by id.
+    fn track_tx(&mut self, id: String) -> PendingTx {
+        let (sender, receiver) = oneshot::channel();
+        self.pending_txs.retain(|tx| !tx.is_abandoned());
+        self.pending_txs.push_back(InFlightTx {
+            id: id.clone(),
+            waiters: vec![sender],
+        });
+        PendingTx { id, receiver }
+    }
+
+    /// Hand a `sendtx` / `sendtxbatch` reply to the request with the same
+    /// id. Returns `false` for every other frame, including replies without
+    /// an id or for requests this connection is no longer tracking.
+    fn resolve_tx_response(&mut self, text: &str) -> bool {
+        if self.pending_txs.is_empty() {
+            return false;
+        }
+        let Ok(message) = serde_json::from_str::<Value>(text) else {
+            return false;
+        };
+        let Some(reply) = TxReply::parse(&message) else {
+            return false;
+        };
+        let Some(tx) = reply
+            .id
+            .as_ref()
+            .and_then(|id| self.pending_txs.iter().position(|tx| &tx.id == id))
+            .and_then(|index| self.pending_txs.remove(index))
+        else {
+            return false;
+        };
+
+        let response = TxResponse {
+            id: tx.id,
+            code: reply.code,
+            message: reply.message,
+            tx_hashes: reply.tx_hashes,
+        };
+        if response.is_success() {
+            tracing::debug!(id = %response.id, tx_hash = ?response.tx_hash(), "transaction accepted");
+        } else {
+            tracing::warn!(id = %response.id, code = response.code, message = ?response.message, "transaction rejected");
+        }
+        for waiter in tx.waiters {
+            let _ = waiter.send(response.clone());
+        }
+        true
+    }
+
     /// Send a single transaction via WebSocket
     ///
     /// # Arguments
@@ -617,23 +1500,31 @@ impl WsConnection {
     /// * `tx_info` - Signed transaction data as JSON value
     ///
     /// # Returns
-    /// Result indicating success or failure
+    /// A [`PendingTx`] resolved with the exchange's [`TxResponse`] once the
+    /// reply carrying the same id is read (see [`PendingTx`] for who reads it).
+    /// Orders failing the client's pre-trade chain are not sent
+    /// ([`WsClientError::PreTrade`]).
     ///
     /// # Example
     /// ```ignore
-    /// connection.send_transaction(14, tx_info).await?;
+    /// let mut pending = connection.send_transaction(14, tx_info).await?;
+    /// let response = connection.wait_for_tx(&mut pending, Duration::from_secs(1)).await?;
+    /// if !response.is_success() {
+    ///     eprintln!("rejected: {} {:?}", response.code, response.message);
+    /// }
     /// ```
-    pub async fn send_transaction(&mut self, tx_type: u8, tx_info: Value) -> WsResult<()> {
+    pub async fn send_transaction(&mut self, tx_type: u8, tx_info: Value) -> WsResult<PendingTx> {
+        self.pre_trade.evaluate_tx(i32::from(tx_type), &tx_info)?;
         let token = self.auth_token.clone().ok_or_else(|| {
             WsClientError::InvalidMessage("Authentication token not set".to_string())
         })?;
 
-        let msg_id = self.next_message_id();
+        let id = format!("tx_{}", self.next_message_id());
 
         let msg = json!({
             "type": "jsonapi/sendtx",
             "data": {
-                "id": format!("tx_{}", msg_id),
+                "id": id,
                 "tx_type": tx_type,
                 "tx_info": tx_info,
                 "token": token,
@@ -642,7 +1533,7 @@ impl WsConnection {
 
         self.stream.send(Message::Text(msg.to_string())).await?;
 
-        Ok(())
+        Ok(self.track_tx(id))
     }
 
     /// Send multiple transactions in a single batch (max 50)
@@ -652,19 +1543,20 @@ impl WsConnection {
     /// * `tx_infos` - Vector of signed transaction data
     ///
     /// # Returns
-    /// Result indicating success or failure
+    /// A [`PendingTx`] resolved with the reply to the whole batch
     ///
     /// # Example
     /// ```ignore
     /// let tx_types = vec![14, 14, 15]; // Create, Create, Cancel
     /// let tx_infos = vec![order1, order2, cancel1];
-    /// connection.send_batch_transaction(tx_types, tx_infos).await?;
+    /// let mut pending = connection.send_batch_transaction(tx_types, tx_infos).await?;
+    /// let response = connection.wait_for_tx(&mut pending, Duration::from_secs(3)).await?;
     /// ```
     pub async fn send_batch_transaction(
         &mut self,
         tx_types: Vec<u8>,
         tx_infos: Vec<Value>,
-    ) -> WsResult<()> {
+    ) -> WsResult<PendingTx> {
         if tx_types.len() != tx_infos.len() {
             return Err(WsClientError::InvalidMessage(
                 "tx_types and tx_infos must have same length".to_string(),
@@ -677,11 +1569,15 @@ impl WsConnection {
             ));
         }
 
+        for (tx_type, tx_info) in tx_types.iter().zip(&tx_infos) {
+            self.pre_trade.evaluate_tx(i32::from(*tx_type), tx_info)?;
+        }
+
         let token = self.auth_token.clone().ok_or_else(|| {
             WsClientError::InvalidMessage("Authentication token not set".to_string())
         })?;
 
-        let msg_id = self.next_message_id();
+        let id = format!("batch_{}", self.next_message_id());
 
         let tx_infos_as_strings: Vec<String> = tx_infos.iter().map(|v| v.to_string()).collect();
         let tx_types_json = serde_json::to_string(&tx_types)?;
@@ -690,7 +1586,7 @@ impl WsConnection {
         let msg = json!({
             "type": "jsonapi/sendtxbatch",
             "data": {
-                "id": format!("batch_{}", msg_id),
+                "id": id,
                 "tx_types": tx_types_json,
                 "tx_infos": tx_infos_json,
                 "token": token,
@@ -699,13 +1595,44 @@ impl WsConnection {
 
         self.stream.send(Message::Text(msg.to_string())).await?;
 
-        Ok(())
+        Ok(self.track_tx(id))
     }
 
-    /// Wait for transaction response with timeout
+    /// Read frames until `pending` is answered or `timeout` elapses.
     ///
-    /// # Arguments
-    /// * `timeout` - Maximum time to wait for response
+    /// Every other frame is queued for [`next_event`](Self::next_event)
+    /// rather than dropped, so market data keeps flowing while a strategy
+    /// waits for an ack. Fails with [`WsClientError::TxTimeout`] or, if the
+    /// socket goes away first, [`WsClientError::TxAbandoned`]; after a timeout
+    /// `pending` can still be awaited for a late reply.
+    pub async fn wait_for_tx(
+        &mut self,
+        pending: &mut PendingTx,
+        timeout: Duration,
+    ) -> WsResult<TxResponse> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(result) = pending.try_result() {
+                return result;
+            }
+            let queued = self.pending_events.len();
+            match tokio::time::timeout_at(deadline, self.read_event()).await {
+                Err(_) => return Err(WsClientError::TxTimeout(pending.id.clone())),
+                Ok(Err(err)) => return Err(err),
+                Ok(Ok(None)) => return Err(WsClientError::TxAbandoned(pending.id.clone())),
+                // `handle_text_message` may already have queued follow-up
+                // events (e.g. synthetic BBO) that belong after this one.
+                Ok(Ok(Some(event))) => self.pending_events.insert(queued, event),
+            }
+        }
+    }
+
+    /// Wait for the reply to the most recent [`send_transaction`](Self::send_transaction)
+    /// or [`send_batch_transaction`](Self::send_batch_transaction) call.
+    ///
+    /// Prefer [`wait_for_tx`](Self::wait_for_tx) with the returned
+    /// [`PendingTx`], which reports the code, message and tx hashes. Frames
+    /// read while waiting are queued for [`next_event`](Self::next_event).
     ///
     /// # Returns
     /// Ok(true) if transaction succeeded, Ok(false) if failed or timeout, Err if connection error
@@ -719,56 +1646,59 @@ impl WsConnection {
     /// }
     /// ```
     pub async fn wait_for_tx_response(&mut self, timeout: Duration) -> WsResult<bool> {
-        use tokio::time::timeout as tokio_timeout;
-
-        let result = tokio_timeout(timeout, async {
-            while let Some(message) = self.stream.next().await {
-                let message = message?;
-                if let Message::Text(text) = message {
-                    // Skip non-transaction messages (order book updates, etc)
-                    if text.contains(r#""type":"connected""#)
-                        || text.contains(r#""type":"subscribed"#)  // Matches "subscribed" AND "subscribed/..."
-                        || text.contains(r#""type":"unsubscribed""#)
-                        || text.contains(r#""type":"pong""#)
-                        || text.contains(r#""type":"update/order_book""#)
-                        || text.contains(r#""type":"update/bbo""#)
-                        || text.contains(r#""type":"update/trade""#)
-                        || text.contains(r#""type":"update/account""#)
-                        || text.contains(r#""type":"update/fills""#)
-                        || text.contains(r#""type":"update/orders""#)
-                    {
-                        tracing::debug!("Skipping non-transaction message in wait_for_tx_response");
-                        continue;
-                    }
+        let Some(tx) = self.pending_txs.back_mut() else {
+            return Ok(false);
+        };
+        let (sender, receiver) = oneshot::channel();
+        tx.waiters.push(sender);
+        let mut pending = PendingTx {
+            id: tx.id.clone(),
+            receiver,
+        };
 
-                    // Check if this is an error response
-                    let is_error = text.contains(r#""type":"error""#)
-                        || text.contains(r#""error":"#)
-                        || (text.contains(r#""code":"#) && !text.contains(r#""code":200"#));
+        match self.wait_for_tx(&mut pending, timeout).await {
+            Ok(response) => Ok(response.is_success()),
+            Err(WsClientError::TxTimeout(_) | WsClientError::TxAbandoned(_)) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
 
-                    if is_error {
-                        tracing::error!("Transaction error response: {}", text);
-                    } else {
-                        tracing::info!("Transaction success response: {}", text);
+    /// Show `inspect` every queued event and then each new one until it
+    /// returns `true` or `timeout` elapses. Nothing is consumed: all events
+    /// stay queued for [`next_event`](Self::next_event).
+    ///
+    /// Returns `Ok(false)` on timeout or when the stream ends.
+    pub async fn observe_until<F>(&mut self, timeout: Duration, mut inspect: F) -> WsResult<bool>
+    where
+        F: FnMut(&WsEvent) -> bool,
+    {
+        if self.pending_events.iter().any(&mut inspect) {
+            return Ok(true);
+        }
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            let queued = self.pending_events.len();
+            match tokio::time::timeout_at(deadline, self.read_event()).await {
+                Err(_) | Ok(Ok(None)) => return Ok(false),
+                Ok(Err(err)) => return Err(err),
+                Ok(Ok(Some(event))) => {
+                    let closed = matches!(event, WsEvent::Closed(_));
+                    self.pending_events.insert(queued, event);
+                    if self.pending_events.range(queued..).any(&mut inspect) {
+                        return Ok(true);
+                    }
+                    if closed {
+                        return Ok(false);
                     }
-
-                    return Ok(!is_error);
                 }
             }
-            Ok(false)
-        })
-        .await;
-
-        match result {
-            Ok(Ok(success)) => Ok(success),
-            Ok(Err(e)) => Err(e),
-            Err(_) => Ok(false), // Timeout = failure
         }
     }
 
     /// Send a ping message to keep connection alive
     pub async fn ping(&mut self) -> WsResult<()> {
-        self.stream.send(Message::Ping(vec![])).await?;
+        let payload = self.health.begin_ping(Instant::now());
+        self.stream.send(Message::Ping(payload)).await?;
         Ok(())
     }
 
@@ -825,7 +1755,9 @@ impl WsConnection {
                 Ok((stream, _)) => {
                     let dial_elapsed = dial_start.elapsed();
                     tracing::info!(attempts, ?dial_elapsed, "reconnect_dial_success");
-                    self.stream = stream;
+                    self.stream = stream.into();
+                    self.pending_txs.clear();
+                    self.health.reset(Instant::now());
                     self.generation = self.generation.wrapping_add(1);
                     self.suppress_already_subscribed_until =
                         Some(Instant::now() + SUPPRESS_ALREADY_SUB_DURATION);
@@ -867,6 +1799,180 @@ impl WsConnection {
         }
     }
 
+    /// Next event for a supervised session.
+    ///
+    /// Behaves like [`WsConnection::next_event`] when supervision is disabled.
+    /// Otherwise a dropped socket is redialled with backoff, the auth token is
+    /// refreshed, subscriptions are replayed and cached book/account state is
+    /// discarded until fresh snapshots arrive. Progress is reported through
+    /// [`WsEvent::Reconnecting`] and [`WsEvent::Resynced`]. Once
+    /// `max_attempts` is exhausted the error is returned and the session ends.
+    pub async fn next_session_event(&mut self) -> WsResult<Option<WsEvent>> {
+        if self.supervisor.is_none() {
+            return self.next_event().await;
+        }
+
+        loop {
+            if let Some(event) = self.pending_events.pop_front() {
+                return Ok(Some(event));
+            }
+
+            let (exhausted, reconnecting) = match &self.supervisor {
+                Some(supervisor) => (supervisor.exhausted, supervisor.reconnect.is_some()),
+                None => (false, false),
+            };
+            if exhausted {
+                return Ok(None);
+            }
+            if reconnecting {
+                self.drive_reconnect().await?;
+                continue;
+            }
+
+            match self.next_event().await {
+                Ok(Some(WsEvent::Closed(info))) => {
+                    tracing::warn!(?info, "supervised websocket closed by server");
+                    self.begin_reconnect();
+                    return Ok(Some(WsEvent::Closed(info)));
+                }
+                Ok(None) => {
+                    tracing::warn!("supervised websocket stream ended");
+                    self.begin_reconnect();
+                }
+                Err(WsClientError::WebSocket(err)) => {
+                    tracing::warn!("supervised websocket error: {}", err);
+                    self.begin_reconnect();
+                }
+                Ok(Some(event)) => {
+                    self.track_resync(&event);
+                    return Ok(Some(event));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn begin_reconnect(&mut self) {
+        let initial = self.backoff.initial;
+        let Some(supervisor) = self.supervisor.as_mut() else {
+            return;
+        };
+        if supervisor.reconnect.is_some() {
+            return;
+        }
+
+        tracing::info!(generation = self.generation, "reconnect_begin");
+        supervisor.reconnect = Some(ReconnectState::new(1, initial));
+        supervisor.resync = None;
+        self.state.clear();
+        self.pending_events.clear();
+        self.pending_txs.clear();
+        self.pending_events
+            .push_back(WsEvent::Reconnecting { attempt: 1 });
+    }
+
+    async fn drive_reconnect(&mut self) -> WsResult<()> {
+        let url = self.url.clone();
+        let Some(supervisor) = self.supervisor.as_mut() else {
+            return Ok(());
+        };
+        let auth = supervisor.auth.clone();
+        let Some(reconnect) = supervisor.reconnect.as_mut() else {
+            return Ok(());
+        };
+
+        if reconnect.dial.is_none() {
+            reconnect.sleep.as_mut().await;
+            reconnect.dial = Some(Box::pin(dial_session(url, auth)));
+        }
+        let result = match reconnect.dial.as_mut() {
+            Some(dial) => dial.await,
+            None => return Ok(()),
+        };
+        reconnect.dial = None;
+        let attempt = reconnect.attempt;
+
+        match result {
+            Ok((stream, token)) => {
+                self.stream = stream.into();
+                if token.is_some() {
+                    self.auth_token = token;
+                }
+                self.generation = self.generation.wrapping_add(1);
+                self.suppress_already_subscribed_until =
+                    Some(Instant::now() + SUPPRESS_ALREADY_SUB_DURATION);
+                self.state.clear();
+                self.health.reset(Instant::now());
+
+                // Subscriptions are replayed when the server greets the new
+                // socket with `connected` (see `handle_text_message`).
+                let markets = self.subscriptions.order_books.iter().copied().collect();
+                if let Some(supervisor) = self.supervisor.as_mut() {
+                    supervisor.reconnect = None;
+                    supervisor.resync = Some(PendingResync {
+                        generation: self.generation,
+                        connected: false,
+                        markets,
+                    });
+                }
+                tracing::info!(
+                    attempts = attempt,
+                    generation = self.generation,
+                    "reconnect_dial_success"
+                );
+                Ok(())
+            }
+            Err(err) => {
+                tracing::error!("Reconnection failed (attempt {}): {}", attempt, err);
+                let max_attempts = supervisor.config.max_attempts;
+                if max_attempts.is_some_and(|max| attempt >= max) {
+                    supervisor.reconnect = None;
+                    supervisor.exhausted = true;
+                    return Err(WsClientError::InvalidMessage(format!(
+                        "Max reconnection attempts ({}) exceeded",
+                        attempt
+                    )));
+                }
+
+                // Exponential backoff with cap
+                let delay = reconnect
+                    .delay
+                    .mul_f64(self.backoff.multiplier)
+                    .min(self.backoff.max);
+                *reconnect = ReconnectState::new(attempt + 1, delay);
+                self.pending_events.push_back(WsEvent::Reconnecting {
+                    attempt: attempt + 1,
+                });
+                Ok(())
+            }
+        }
+    }
+
+    fn track_resync(&mut self, event: &WsEvent) {
+        let Some(supervisor) = self.supervisor.as_mut() else {
+            return;
+        };
+        let Some(resync) = supervisor.resync.as_mut() else {
+            return;
+        };
+
+        match event {
+            WsEvent::Connected => resync.connected = true,
+            WsEvent::OrderBook(book) if book.delta.is_none() => {
+                resync.markets.remove(&book.market);
+            }
+            _ => return,
+        }
+
+        if resync.is_complete() {
+            let generation = resync.generation;
+            supervisor.resync = None;
+            tracing::info!(generation, "websocket session resynced");
+            self.pending_events
+                .push_back(WsEvent::Resynced { generation });
+        }
+    }
+
     /// Compute BBO (Best Bid/Offer) from order book state
     /// Returns (best_bid, best_ask) as strings, or None if book is empty
     ///
@@ -917,6 +2023,17 @@ impl WsConnection {
     async fn handle_text_message(&mut self, text: String) -> WsResult<Option<WsEvent>> {
         let message: Value = serde_json::from_str(&text)?;
 
+        if let Some(channel) = message
+            .get("channel")
+            .and_then(Value::as_str)
+            .and_then(Channel::parse)
+        {
+            self.health.record_channel(channel, Instant::now());
+        }
+        if self.acks.values().any(SubscriptionStatus::is_pending) {
+            apply_subscription_ack(&mut self.acks, &message);
+        }
+
         // Gracefully handle messages without a "type" field
         let message_type = match message.get("type").and_then(|value| value.as_str()) {
             Some(msg_type) => msg_type.to_owned(),
@@ -957,6 +2074,7 @@ impl WsConnection {
                 let order_book_payload = payload.order_book;
                 let book_code = order_book_payload.code;
                 let offset = order_book_payload.offset.or(envelope_offset);
+                let nonce = order_book_payload.nonce;
 
                 if let Some(code) = payload_code.or(book_code) {
                     if code != 200 && code != 0 {
@@ -1004,7 +2122,9 @@ impl WsConnection {
                     market,
                     state: snapshot,
                     delta: None,
-                    nonce: None, // Snapshots don't have nonce
+                    nonce,
+                    begin_nonce: None,
+                    offset,
                 })))
             }
             "update/order_book" => {
@@ -1017,6 +2137,7 @@ impl WsConnection {
                 let book_code = order_book_payload.code;
                 let offset = order_book_payload.offset.or(envelope_offset);
                 let nonce = order_book_payload.nonce;
+                let begin_nonce = order_book_payload.begin_nonce;
 
                 if let Some(code) = payload_code.or(book_code) {
                     if code != 200 && code != 0 {
@@ -1076,6 +2197,8 @@ impl WsConnection {
                         state: state.clone(),
                         delta: Some(delta),
                         nonce,
+                        begin_nonce,
+                        offset,
                     })))
                 } else {
                     tracing::warn!(
@@ -1157,6 +2280,8 @@ impl WsConnection {
                     }
 
                     Ok(Some(WsEvent::Account(event)))
+                } else if let Some(reply) = TxReply::parse(&message) {
+                    Ok(Some(WsEvent::UnmatchedReply(reply.into_response())))
                 } else {
                     Ok(Some(WsEvent::Unknown(text)))
                 }
@@ -1165,8 +2290,30 @@ impl WsConnection {
     }
 
     async fn send_subscriptions(&mut self) -> WsResult<()> {
+        self.acks.clear();
+        let subscriptions = self.subscriptions.clone();
+        self.send_subscription_set(&subscriptions).await
+    }
+
+    /// Subscribe to `subscriptions` on the live socket and keep them for
+    /// replay after a reconnect. Private channels need the auth token.
+    pub async fn add_subscriptions(&mut self, subscriptions: SubscriptionSet) -> WsResult<()> {
+        self.send_subscription_set(&subscriptions).await?;
+        self.subscriptions.extend(subscriptions);
+        self.note_subscriptions();
+        Ok(())
+    }
+
+    async fn send_subscription_set(&mut self, subscriptions: &SubscriptionSet) -> WsResult<()> {
+        for channel in subscriptions.channels() {
+            if !channel.is_synthetic() {
+                self.acks
+                    .insert(channel.source(), SubscriptionStatus::Subscribing);
+            }
+        }
+
         // Order books
-        for market in &self.subscriptions.order_books {
+        for market in &subscriptions.order_books {
             let payload = json!({
                 "type": "subscribe",
                 "channel": format!("order_book/{}", market.into_inner()),
@@ -1176,7 +2323,7 @@ impl WsConnection {
         }
 
         // Accounts (account_all channel) - requires authentication
-        for account in &self.subscriptions.accounts {
+        for account in &subscriptions.accounts {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_all/{}", account.into_inner()),
@@ -1194,7 +2341,7 @@ impl WsConnection {
         // and emit synthetic BBO events when order book updates arrive.
 
         // Trades
-        for market in &self.subscriptions.trades {
+        for market in &subscriptions.trades {
             let payload = json!({
                 "type": "subscribe",
                 "channel": format!("trade/{}", market.into_inner()),
@@ -1204,7 +2351,7 @@ impl WsConnection {
         }
 
         // Market stats
-        for market in &self.subscriptions.market_stats {
+        for market in &subscriptions.market_stats {
             let payload = json!({
                 "type": "subscribe",
                 "channel": format!("market_stats/{}", market.into_inner()),
@@ -1215,7 +2362,7 @@ impl WsConnection {
 
 
         // Height
-        if self.subscriptions.subscribe_height {
+        if subscriptions.subscribe_height {
             let payload = json!({
                 "type": "subscribe",
                 "channel": "height",
@@ -1225,7 +2372,7 @@ impl WsConnection {
         }
 
         // Account all positions - requires authentication
-        for account in &self.subscriptions.account_all_positions {
+        for account in &subscriptions.account_all_positions {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_all_positions/{}", account.into_inner()),
@@ -1237,7 +2384,7 @@ impl WsConnection {
         }
 
         // Account all trades - requires authentication
-        for account in &self.subscriptions.account_all_trades {
+        for account in &subscriptions.account_all_trades {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_all_trades/{}", account.into_inner()),
@@ -1249,7 +2396,7 @@ impl WsConnection {
         }
 
         // Account all orders - requires authentication
-        for account in &self.subscriptions.account_all_orders {
+        for account in &subscriptions.account_all_orders {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_all_orders/{}", account.into_inner()),
@@ -1261,7 +2408,7 @@ impl WsConnection {
         }
 
         // User stats - requires authentication
-        for account in &self.subscriptions.user_stats {
+        for account in &subscriptions.user_stats {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("user_stats/{}", account.into_inner()),
@@ -1273,7 +2420,7 @@ impl WsConnection {
         }
 
         // Account transactions - requires authentication
-        for account in &self.subscriptions.account_tx {
+        for account in &subscriptions.account_tx {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_tx/{}", account.into_inner()),
@@ -1285,7 +2432,7 @@ impl WsConnection {
         }
 
         // Pool data - requires authentication
-        for account in &self.subscriptions.pool_data {
+        for account in &subscriptions.pool_data {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("pool_data/{}", account.into_inner()),
@@ -1297,7 +2444,7 @@ impl WsConnection {
         }
 
         // Pool info - requires authentication
-        for account in &self.subscriptions.pool_info {
+        for account in &subscriptions.pool_info {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("pool_info/{}", account.into_inner()),
@@ -1309,7 +2456,7 @@ impl WsConnection {
         }
 
         // Notifications - requires authentication
-        for account in &self.subscriptions.notifications {
+        for account in &subscriptions.notifications {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("notification/{}", account.into_inner()),
@@ -1321,7 +2468,7 @@ impl WsConnection {
         }
 
         // Account market combined channel - requires authentication
-        for (market, account) in &self.subscriptions.account_market {
+        for (market, account) in &subscriptions.account_market {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_market/{}/{}", market.into_inner(), account.into_inner()),
@@ -1333,7 +2480,7 @@ impl WsConnection {
         }
 
         // Market-specific account orders - requires authentication
-        for (market, account) in &self.subscriptions.account_market_orders {
+        for (market, account) in &subscriptions.account_market_orders {
             let mut payload = json!({
                 "type": "subscribe",
                 "channel": format!("account_orders/{}/{}", market.into_inner(), account.into_inner()),
@@ -1348,7 +2495,7 @@ impl WsConnection {
         // Note: Lighter doesn't have account_positions/{market}/{account} channel.
         // We subscribe to account_all_positions once per account and filter by market_id in the event handler.
         let mut subscribed_position_accounts = std::collections::HashSet::new();
-        for (_market, account) in &self.subscriptions.account_market_positions {
+        for (_market, account) in &subscriptions.account_market_positions {
             if subscribed_position_accounts.insert(*account) {
                 let mut payload = json!({
                     "type": "subscribe",
@@ -1365,7 +2512,7 @@ impl WsConnection {
         // Note: Lighter doesn't have account_trades/{market}/{account} channel.
         // We subscribe to account_all_trades once per account and filter by market_id in the event handler.
         let mut subscribed_trade_accounts = std::collections::HashSet::new();
-        for (_market, account) in &self.subscriptions.account_market_trades {
+        for (_market, account) in &subscriptions.account_market_trades {
             if subscribed_trade_accounts.insert(*account) {
                 let mut payload = json!({
                     "type": "subscribe",
@@ -1500,6 +2647,24 @@ impl WsConnection {
     }
 }
 
+/// Dial a fresh socket for a supervised session, refreshing the auth token
+/// through the client's auth cache first.
+async fn dial_session(
+    url: Url,
+    auth: Option<AuthTokenSource>,
+) -> WsResult<(WebSocketStream<MaybeTlsStream<TcpStream>>, Option<String>)> {
+    let token = match auth {
+        Some(auth) => Some(
+            auth.token()
+                .await
+                .map_err(|err| WsClientError::Auth(err.to_string()))?,
+        ),
+        None => None,
+    };
+    let (stream, _) = connect_async(url.as_str()).await?;
+    Ok((stream, token))
+}
+
 pub struct WsStream {
     connection: WsConnection,
 }
@@ -1526,7 +2691,7 @@ impl Stream for WsStream {
     type Item = WsResult<WsEvent>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        let fut = self.connection.next_event();
+        let fut = self.connection.next_session_event();
         futures_util::pin_mut!(fut);
         match futures_util::ready!(fut.poll_unpin(cx)) {
             Ok(Some(event)) => Poll::Ready(Some(Ok(event))),
@@ -1542,7 +2707,152 @@ pub struct CloseFrameInfo {
     pub reason: String,
 }
 
+/// Exchange reply to a `jsonapi/sendtx` or `jsonapi/sendtxbatch` request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxResponse {
+    /// Request id (`tx_{n}` or `batch_{n}`); empty on an
+    /// [`WsEvent::UnmatchedReply`] that carried none.
+    pub id: String,
+    /// `200` on success, otherwise the exchange error code.
+    pub code: i32,
+    pub message: Option<String>,
+    /// Hashes of the accepted transactions, in submission order.
+    pub tx_hashes: Vec<String>,
+}
+
+impl TxResponse {
+    pub fn is_success(&self) -> bool {
+        self.code == CODE_OK
+    }
+
+    /// Hash of the first (for `sendtx`, the only) transaction.
+    pub fn tx_hash(&self) -> Option<&str> {
+        self.tx_hashes.first().map(String::as_str)
+    }
+}
+
+/// Reply to a transaction sent over a [`WsConnection`], resolved when the
+/// connection reads the frame carrying the same id.
+///
+/// The connection only reads frames while it is driven, either by
+/// [`WsConnection::wait_for_tx`] or by the caller's
+/// [`next_event`](WsConnection::next_event) loop. Await a `PendingTx` from
+/// another task, or use `wait_for_tx`; awaiting it in the task that owns the
+/// connection without driving it never completes.
+#[derive(Debug)]
+pub struct PendingTx {
+    id: String,
+    receiver: oneshot::Receiver<TxResponse>,
+}
+
+impl PendingTx {
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The reply, if it has already been read.
+    pub fn try_result(&mut self) -> Option<WsResult<TxResponse>> {
+        match self.receiver.try_recv() {
+            Ok(response) => Some(Ok(response)),
+            Err(oneshot::error::TryRecvError::Empty) => None,
+            Err(oneshot::error::TryRecvError::Closed) => {
+                Some(Err(WsClientError::TxAbandoned(self.id.clone())))
+            }
+        }
+    }
+}
+
+impl Future for PendingTx {
+    type Output = WsResult<TxResponse>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.receiver.poll_unpin(cx) {
+            Poll::Ready(Ok(response)) => Poll::Ready(Ok(response)),
+            Poll::Ready(Err(_)) => Poll::Ready(Err(WsClientError::TxAbandoned(this.id.clone()))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Transaction reply fields, wherever the server put them: at the top level,
+/// under `data`, or under `error`.
+#[derive(Debug, PartialEq)]
+struct TxReply {
+    id: Option<String>,
+    code: i32,
+    message: Option<String>,
+    tx_hashes: Vec<String>,
+}
+
+impl TxReply {
+    fn parse(message: &Value) -> Option<Self> {
+        let data = message.get("data").filter(|data| data.is_object());
+        let field = |key: &str| {
+            message
+                .get(key)
+                .or_else(|| data.and_then(|data| data.get(key)))
+        };
+        let kind = message
+            .get("type")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        let id = field("id").and_then(Value::as_str).map(str::to_owned);
+        let error = field("error").filter(|error| !error.is_null());
+
+        let is_reply = kind.starts_with("jsonapi/sendtx")
+            || id
+                .as_deref()
+                .is_some_and(|id| id.starts_with("tx_") || id.starts_with("batch_"));
+        let is_error = kind == "error" || error.is_some();
+        if !is_reply && !is_error {
+            return None;
+        }
+
+        let code = error
+            .and_then(|error| error.get("code"))
+            .or_else(|| field("code"))
+            .and_then(Value::as_i64)
+            .map(|code| code as i32)
+            .unwrap_or(if is_error { 0 } else { CODE_OK });
+        let message = error
+            .and_then(|error| error.get("message").unwrap_or(error).as_str())
+            .or_else(|| field("message").and_then(Value::as_str))
+            .map(str::to_owned);
+        let tx_hashes = match field("tx_hash").or_else(|| field("tx_hashes")) {
+            Some(Value::String(hash)) => vec![hash.clone()],
+            Some(Value::Array(hashes)) => hashes
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        Some(Self {
+            id,
+            code,
+            message,
+            tx_hashes,
+        })
+    }
+
+    fn into_response(self) -> TxResponse {
+        TxResponse {
+            id: self.id.unwrap_or_default(),
+            code: self.code,
+            message: self.message,
+            tx_hashes: self.tx_hashes,
+        }
+    }
+}
+
+/// Events yielded by a [`WsStream`].
+///
+/// New variants are added as the session learns to report more, so matches
+/// must keep a wildcard arm.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum WsEvent {
     Connected,
     Pong,
@@ -1555,6 +2865,23 @@ pub enum WsEvent {
     Trade(TradeEvent),
     BBO(BBOEvent),
     Closed(Option<CloseFrameInfo>),
+    /// A supervised session lost its socket and is redialling.
+    Reconnecting {
+        attempt: u32,
+    },
+    /// A supervised session reconnected, replayed its subscriptions and has
+    /// fresh snapshots for every subscribed order book.
+    Resynced {
+        generation: u64,
+    },
+    /// A subscribed channel has been silent past its [`HealthConfig`] threshold.
+    Stale {
+        channel: Channel,
+    },
+    /// A transaction reply or error frame that no pending request claimed
+    /// because its id is missing or unknown, such as a subscription error
+    /// or a reply to a request from before a reconnect.
+    UnmatchedReply(TxResponse),
     Unknown(String),
 }
 
@@ -1566,6 +2893,11 @@ pub struct OrderBookEvent {
     /// Nonce for order book updates (Premium feature)
     /// Increments with each update. Use to detect missed or out-of-order messages.
     pub nonce: Option<u64>,
+    /// Nonce of the previous update this delta builds on, when the server sends it.
+    /// A delta is contiguous when `begin_nonce` equals the last applied `nonce`.
+    pub begin_nonce: Option<u64>,
+    /// Sequencing offset of this message (snapshot or delta).
+    pub offset: Option<i64>,
 }
 
 #[derive(Debug, Clone)]
@@ -1575,6 +2907,13 @@ pub struct AccountEventEnvelope {
     pub event: AccountEvent,
 }
 
+impl AccountEventEnvelope {
+    /// Shorthand for [`AccountEvent::decode`].
+    pub fn decode(&self) -> DecodeResult<TypedAccountEvent> {
+        self.event.decode()
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AccountEvent(Value);
 
@@ -1598,6 +2937,22 @@ impl AccountEvent {
         &self.0
     }
 
+    /// Decode the raw payload into a typed account channel event.
+    ///
+    /// Missing or mistyped fields are reported as
+    /// [`WsClientError::AccountDecode`] rather than skipped.
+    pub fn decode(&self) -> DecodeResult<TypedAccountEvent> {
+        TypedAccountEvent::decode(&self.0)
+    }
+
+    /// Channel this event was received on, if it is an account channel.
+    pub fn channel(&self) -> Option<AccountChannel> {
+        self.0
+            .get("channel")
+            .and_then(Value::as_str)
+            .and_then(AccountChannel::from_channel)
+    }
+
     /// Helper to determine trade side for a specific account
     ///
     /// For account_all_trades events, determines if the account was buying or selling.
@@ -1638,7 +2993,8 @@ impl AccountEvent {
         positions_obj.get(&market_id.to_string())
     }
 
-    /// Helper to parse position side
+    /// Helper to parse position side. For a typed side with entry price and
+    /// PnL, feed decoded events to a [`PositionBook`](crate::position_book::PositionBook).
     pub fn position_side(position: &Value) -> Option<&str> {
         let sign = position.get("sign")?.as_i64()?;
         Some(match sign {
@@ -1672,6 +3028,8 @@ struct OrderBookPayload {
     /// Used to detect missed or out-of-order updates
     #[serde(default)]
     nonce: Option<u64>,
+    #[serde(default)]
+    begin_nonce: Option<u64>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -2038,6 +3396,76 @@ fn parse_account_index(channel: &str) -> WsResult<i64> {
         .ok_or_else(|| WsClientError::InvalidChannel(channel.to_string()))
 }
 
+/// Settle pending subscription acks from a server message: `subscribed/*`
+/// and `unsubscribed*` frames confirm, errors naming a channel reject it
+/// (except "already subscribed" / "not subscribed", which confirm).
+fn apply_subscription_ack(acks: &mut HashMap<Channel, SubscriptionStatus>, message: &Value) {
+    let kind = message
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let channel = message
+        .get("channel")
+        .and_then(Value::as_str)
+        .and_then(Channel::parse);
+
+    if kind.starts_with("subscribed/") || kind.starts_with("unsubscribed") {
+        let Some(status) = channel.and_then(|channel| acks.get_mut(&channel)) else {
+            return;
+        };
+        *status = match status {
+            SubscriptionStatus::Subscribing if kind.starts_with("subscribed/") => {
+                SubscriptionStatus::Subscribed
+            }
+            SubscriptionStatus::Unsubscribing if kind.starts_with("unsubscribed") => {
+                SubscriptionStatus::Unsubscribed
+            }
+            _ => return,
+        };
+        return;
+    }
+
+    if kind != "error" && message.get("error").is_none() {
+        return;
+    }
+    let error = message
+        .get("error")
+        .map(|error| match error {
+            Value::String(text) => text.clone(),
+            other => other
+                .get("message")
+                .and_then(Value::as_str)
+                .map_or_else(|| other.to_string(), str::to_owned),
+        })
+        .unwrap_or_else(|| message.to_string());
+    let lowered = error.to_lowercase();
+    for (channel, status) in acks.iter_mut() {
+        let name = channel.to_string();
+        let mentioned =
+            channel_mentioned(&error, &name) || channel_mentioned(&error, &name.replace('/', ":"));
+        if !status.is_pending() || !mentioned {
+            continue;
+        }
+        *status = match status {
+            SubscriptionStatus::Subscribing if lowered.contains("already subscribed") => {
+                SubscriptionStatus::Subscribed
+            }
+            SubscriptionStatus::Unsubscribing if lowered.contains("not subscribed") => {
+                SubscriptionStatus::Unsubscribed
+            }
+            _ => SubscriptionStatus::Rejected(error.clone()),
+        };
+    }
+}
+
+/// `name` occurs in `text` as a whole channel name (not as a prefix of a
+/// longer id, e.g. `order_book/1` in `order_book/12`).
+fn channel_mentioned(text: &str, name: &str) -> bool {
+    let id_follows = |rest: &str| rest.starts_with(|c: char| c.is_ascii_digit());
+    text.match_indices(name)
+        .any(|(at, _)| !id_follows(&text[at + name.len()..]))
+}
+
 fn classify_account_message(message_type: &str) -> Option<bool> {
     const ACCOUNT_CHANNELS: &[&str] = &[
         "account",
@@ -2068,3 +3496,316 @@ fn classify_account_message(message_type: &str) -> Option<bool> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subscription_set_tracks_shared_wire_channels() {
+        let market = MarketId::new(4);
+        let mut subscriptions = SubscriptionSet::default();
+        assert!(subscriptions.insert(Channel::OrderBook(market)));
+        assert!(subscriptions.insert(Channel::Bbo(market)));
+        assert!(!subscriptions.insert(Channel::Bbo(market)));
+
+        assert!(subscriptions.carries(Channel::OrderBook(market)));
+        assert!(subscriptions.remove(Channel::OrderBook(market)));
+        assert!(!subscriptions.carries(Channel::OrderBook(market)));
+        assert!(subscriptions.remove(Channel::Bbo(market)));
+        assert!(subscriptions.is_empty());
+    }
+
+    #[tokio::test]
+    async fn bbo_subscriptions_stay_off_the_wire() {
+        let market = MarketId::new(4);
+        let mut connection = WsConnection::replay(SubscriptionSet::default());
+        let err = connection.subscribe(Channel::Bbo(market)).await.unwrap_err();
+        assert!(matches!(err, WsClientError::MissingSource { .. }));
+        assert!(!connection.subscriptions().contains(Channel::Bbo(market)));
+
+        connection
+            .subscribe(Channel::OrderBook(market))
+            .await
+            .unwrap();
+        connection.subscribe(Channel::Bbo(market)).await.unwrap();
+        assert!(connection.subscriptions().contains(Channel::Bbo(market)));
+        assert_eq!(
+            connection.subscription_status(Channel::Bbo(market)),
+            Some(&SubscriptionStatus::Subscribing)
+        );
+        connection.unsubscribe(Channel::Bbo(market)).await.unwrap();
+        assert_eq!(
+            connection.subscription_status(Channel::OrderBook(market)),
+            Some(&SubscriptionStatus::Subscribing)
+        );
+    }
+
+    #[test]
+    fn subscription_acks_settle_pending_channels() {
+        let book = Channel::OrderBook(MarketId::new(1));
+        let other_book = Channel::OrderBook(MarketId::new(12));
+        let trades = Channel::Trade(MarketId::new(1));
+        let mut acks = HashMap::from([
+            (book, SubscriptionStatus::Subscribing),
+            (other_book, SubscriptionStatus::Subscribing),
+            (trades, SubscriptionStatus::Unsubscribing),
+        ]);
+
+        apply_subscription_ack(
+            &mut acks,
+            &json!({ "type": "subscribed/order_book", "channel": "order_book:12" }),
+        );
+        assert_eq!(acks[&other_book], SubscriptionStatus::Subscribed);
+        assert_eq!(acks[&book], SubscriptionStatus::Subscribing);
+
+        apply_subscription_ack(
+            &mut acks,
+            &json!({ "type": "error", "error": "invalid channel order_book/1" }),
+        );
+        assert!(matches!(acks[&book], SubscriptionStatus::Rejected(_)));
+
+        apply_subscription_ack(
+            &mut acks,
+            &json!({ "type": "unsubscribed", "channel": "trade:1" }),
+        );
+        assert_eq!(acks[&trades], SubscriptionStatus::Unsubscribed);
+    }
+
+    #[test]
+    fn channel_names_round_trip_in_both_forms() {
+        let market = MarketId::new(3);
+        let account = AccountId::new(42);
+        let mut subscriptions = SubscriptionSet::default();
+        subscriptions.add_order_book(market);
+        subscriptions.subscribe_height = true;
+        subscriptions.add_account_tx(account);
+        subscriptions.add_account_market_orders(market, account);
+
+        for channel in subscriptions.channels() {
+            assert_eq!(Channel::parse(&channel.to_string()), Some(channel));
+        }
+        assert_eq!(
+            Channel::parse("account_all_orders:42"),
+            Some(Channel::AccountAllOrders(account))
+        );
+        assert_eq!(Channel::parse("order_book:x"), None);
+        assert_eq!(
+            Channel::AccountMarketTrades(market, account).source(),
+            Channel::AccountAllTrades(account)
+        );
+    }
+
+    #[test]
+    fn tx_replies_are_parsed_wherever_fields_live() {
+        let ok = json!({
+            "type": "jsonapi/sendtx",
+            "data": { "id": "tx_3", "code": 200, "tx_hash": "0xabc" },
+        });
+        let reply = TxReply::parse(&ok).unwrap();
+        assert_eq!(reply.id.as_deref(), Some("tx_3"));
+        assert_eq!(
+            (reply.code, reply.tx_hashes),
+            (200, vec!["0xabc".to_string()])
+        );
+
+        let rejected = json!({
+            "id": "batch_4",
+            "error": { "code": 21104, "message": "invalid nonce" },
+        });
+        let reply = TxReply::parse(&rejected).unwrap();
+        assert_eq!(reply.code, 21104);
+        assert_eq!(reply.message.as_deref(), Some("invalid nonce"));
+
+        let market_data =
+            json!({ "type": "update/order_book", "channel": "order_book:1", "code": 0 });
+        assert_eq!(TxReply::parse(&market_data), None);
+        let subscription =
+            json!({ "type": "error", "error": "already subscribed to order_book/1" });
+        let reply = TxReply::parse(&subscription).unwrap();
+        assert_eq!(reply.id, None);
+        assert_eq!(
+            reply.message.as_deref(),
+            Some("already subscribed to order_book/1")
+        );
+    }
+
+    #[tokio::test]
+    async fn replies_resolve_only_by_request_id() {
+        let mut connection = WsConnection::replay(SubscriptionSet::default());
+        let mut first = connection.track_tx("tx_1".into());
+        let mut second = connection.track_tx("tx_2".into());
+        let frames = [
+            json!({ "type": "jsonapi/sendtx", "data": { "code": 21104, "message": "invalid nonce" } }),
+            json!({ "type": "jsonapi/sendtx", "data": { "id": "tx_2", "code": 200, "tx_hash": "0xb" } }),
+            json!({ "type": "jsonapi/sendtx", "data": { "id": "tx_9", "code": 200 } }),
+        ];
+        for frame in frames {
+            connection.push_replay_frame(Message::Text(frame.to_string()));
+        }
+
+        let unmatched = connection.next_event().await.unwrap();
+        assert!(matches!(
+            unmatched,
+            Some(WsEvent::UnmatchedReply(reply)) if reply.id.is_empty() && reply.code == 21104
+        ));
+        assert!(first.try_result().is_none(), "id-less reply must not be claimed");
+        let stale = connection.next_event().await.unwrap();
+        assert!(matches!(stale, Some(WsEvent::UnmatchedReply(reply)) if reply.id == "tx_9"));
+        assert_eq!(
+            second.try_result().unwrap().unwrap().tx_hash(),
+            Some("0xb")
+        );
+
+        let closed = connection
+            .wait_for_tx(&mut first, Duration::from_secs(1))
+            .await;
+        assert!(matches!(closed, Err(WsClientError::TxAbandoned(id)) if id == "tx_1"));
+    }
+
+    #[test]
+    fn pending_tx_reports_abandonment() {
+        let (sender, receiver) = oneshot::channel();
+        let mut pending = PendingTx {
+            id: "tx_1".into(),
+            receiver,
+        };
+        assert!(pending.try_result().is_none());
+        drop(sender);
+        assert!(matches!(
+            pending.try_result(),
+            Some(Err(WsClientError::TxAbandoned(id))) if id == "tx_1"
+        ));
+    }
+
+    /// Serve one websocket session: greet, wait for the subscribe frame, send a
+    /// book snapshot and close. Returns the subscribe frame that was received.
+    async fn serve_book_session(listener: &tokio::net::TcpListener) -> String {
+        let (socket, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let connected = json!({ "type": "connected" });
+        ws.send(Message::Text(connected.to_string())).await.unwrap();
+        let subscribe = loop {
+            match ws.next().await {
+                Some(Ok(Message::Text(text))) if text.contains("subscribe") => {
+                    break text.to_string()
+                }
+                Some(Ok(_)) => continue,
+                other => panic!("client went away before subscribing: {other:?}"),
+            }
+        };
+        let snapshot = json!({
+            "type": "subscribed/order_book",
+            "channel": "order_book:1",
+            "offset": 1,
+            "order_book": {
+                "asks": [{ "price": "101.0", "size": "1" }],
+                "bids": [{ "price": "99.0", "size": "1" }],
+            },
+        });
+        ws.send(Message::Text(snapshot.to_string())).await.unwrap();
+        let _ = ws.close(None).await;
+        subscribe
+    }
+
+    async fn supervised_book_stream(
+        client: &LighterClient,
+        max_attempts: Option<u32>,
+    ) -> WsStream {
+        client
+            .ws()
+            .subscribe_order_book(MarketId::new(1))
+            .backoff(ExponentialBackoff {
+                initial: Duration::from_millis(10),
+                max: Duration::from_millis(20),
+                multiplier: 2.0,
+            })
+            .supervise(SupervisorConfig { max_attempts })
+            .connect()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn supervised_stream_reconnects_and_resubscribes() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let first = serve_book_session(&listener).await;
+            let second = serve_book_session(&listener).await;
+            (first, second)
+        });
+
+        let client = LighterClient::new(url).await.unwrap();
+        let mut stream = supervised_book_stream(&client, None).await;
+        let mut events = Vec::new();
+        while !matches!(events.last(), Some(WsEvent::Resynced { .. })) {
+            let event = tokio::time::timeout(
+                Duration::from_secs(5),
+                stream.connection_mut().next_session_event(),
+            )
+            .await
+            .expect("session stalled")
+            .unwrap()
+            .expect("session ended");
+            events.push(event);
+        }
+
+        let (first, second) = server.await.unwrap();
+        assert_eq!(first, second, "the same subscriptions are replayed");
+        let reconnecting = events
+            .iter()
+            .position(|event| matches!(event, WsEvent::Reconnecting { attempt: 1 }))
+            .expect("reconnect reported");
+        let books: Vec<_> = events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| matches!(event, WsEvent::OrderBook(_)))
+            .map(|(index, _)| index)
+            .collect();
+        assert_eq!(books.len(), 2);
+        assert!(books[0] < reconnecting && reconnecting < books[1]);
+        assert!(matches!(
+            events.last(),
+            Some(WsEvent::Resynced { generation }) if *generation > 0
+        ));
+    }
+
+    #[tokio::test]
+    async fn supervised_stream_gives_up_after_max_attempts() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            serve_book_session(&listener).await;
+            // Dropping the listener makes every redial fail.
+        });
+
+        let client = LighterClient::new(url).await.unwrap();
+        let mut stream = supervised_book_stream(&client, Some(2)).await;
+        let mut attempts = Vec::new();
+        let error = loop {
+            let next = tokio::time::timeout(
+                Duration::from_secs(5),
+                stream.connection_mut().next_session_event(),
+            )
+            .await
+            .expect("session stalled");
+            match next {
+                Ok(Some(WsEvent::Reconnecting { attempt })) => attempts.push(attempt),
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("session ended without an error"),
+                Err(err) => break err,
+            }
+        };
+        server.await.unwrap();
+
+        assert_eq!(attempts, vec![1, 2]);
+        assert!(matches!(error, WsClientError::InvalidMessage(message) if message.contains("(2)")));
+        assert!(stream
+            .connection_mut()
+            .next_session_event()
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,520 @@
+//! Heartbeats, round-trip times and per-channel staleness for a WebSocket
+//! connection.
+//!
+//! Every [`WsConnection`](crate::ws_client::WsConnection) records when each
+//! subscribed channel last delivered a message and times the pongs to its
+//! own pings. With a [`HealthConfig`] installed it also pings on an interval
+//! and reports channels that stay silent past their threshold as
+//! [`WsEvent::Stale`](crate::ws_client::WsEvent::Stale), optionally
+//! resubscribing stale order books. [`HealthSnapshot`] is the queryable view.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    time::{Duration, Instant},
+};
+
+use crate::ws_client::{Channel, SubscriptionSet};
+
+/// How often staleness is evaluated while a config is active.
+const CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// Unanswered pings kept for matching late pongs.
+const MAX_OUTSTANDING_PINGS: usize = 32;
+/// Upper bounds of the RTT histogram buckets, in milliseconds. A final
+/// bucket catches everything slower.
+const RTT_BUCKETS_MS: [u64; 12] = [1, 2, 5, 10, 20, 50, 100, 200, 500, 1_000, 2_000, 5_000];
+
+/// Heartbeat and staleness settings.
+///
+/// The default pings every 10s and flags market data channels after 15s of
+/// silence. Account channels have no default threshold: they are quiet
+/// whenever the account is idle.
+#[derive(Debug, Clone)]
+pub struct HealthConfig {
+    /// Interval between protocol pings (`None` disables heartbeats).
+    pub heartbeat_interval: Option<Duration>,
+    /// Silence after which an order book, trade, market stats or height
+    /// channel is stale.
+    pub market_data_stale_after: Option<Duration>,
+    /// Silence after which an account or pool channel is stale.
+    pub account_stale_after: Option<Duration>,
+    /// Per-channel thresholds, taking precedence over the two above.
+    pub overrides: HashMap<Channel, Duration>,
+    /// Resubscribe an order book when it goes stale.
+    pub resubscribe_stale_books: bool,
+}
+
+impl Default for HealthConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat_interval: Some(Duration::from_secs(10)),
+            market_data_stale_after: Some(Duration::from_secs(15)),
+            account_stale_after: None,
+            overrides: HashMap::new(),
+            resubscribe_stale_books: true,
+        }
+    }
+}
+
+impl HealthConfig {
+    /// Track activity and RTT of manual pings only: no heartbeats, no
+    /// staleness events. This is what a connection starts with.
+    pub fn passive() -> Self {
+        Self {
+            heartbeat_interval: None,
+            market_data_stale_after: None,
+            account_stale_after: None,
+            overrides: HashMap::new(),
+            resubscribe_stale_books: false,
+        }
+    }
+
+    pub fn with_threshold(mut self, channel: Channel, stale_after: Duration) -> Self {
+        self.overrides.insert(channel, stale_after);
+        self
+    }
+
+    fn is_active(&self) -> bool {
+        self.heartbeat_interval.is_some()
+            || self.market_data_stale_after.is_some()
+            || self.account_stale_after.is_some()
+            || !self.overrides.is_empty()
+    }
+
+    /// Threshold for a source channel (see [`Channel::source`]).
+    fn threshold(&self, channel: Channel) -> Option<Duration> {
+        let overridden = self
+            .overrides
+            .iter()
+            .filter(|(key, _)| key.source() == channel)
+            .map(|(_, after)| *after)
+            .min();
+        overridden.or(if channel.is_market_data() {
+            self.market_data_stale_after
+        } else {
+            self.account_stale_after
+        })
+    }
+}
+
+/// Round-trip times bucketed on a fixed, roughly logarithmic scale.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyHistogram {
+    counts: [u64; RTT_BUCKETS_MS.len() + 1],
+    samples: u64,
+    total: Duration,
+    min: Option<Duration>,
+    max: Option<Duration>,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, sample: Duration) {
+        let millis = sample.as_millis();
+        let bucket = RTT_BUCKETS_MS
+            .iter()
+            .position(|bound| millis <= u128::from(*bound))
+            .unwrap_or(RTT_BUCKETS_MS.len());
+        self.counts[bucket] += 1;
+        self.samples += 1;
+        self.total += sample;
+        self.min = Some(self.min.map_or(sample, |min| min.min(sample)));
+        self.max = Some(self.max.map_or(sample, |max| max.max(sample)));
+    }
+
+    pub fn count(&self) -> u64 {
+        self.samples
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.max
+    }
+
+    pub fn mean(&self) -> Option<Duration> {
+        (self.samples > 0).then(|| self.total / self.samples as u32)
+    }
+
+    /// Upper bound of the bucket holding the `q` quantile (0.0..=1.0),
+    /// capped at the largest sample.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let max = self.max?;
+        let rank = ((q.clamp(0.0, 1.0) * self.samples as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(
+                    RTT_BUCKETS_MS
+                        .get(bucket)
+                        .map_or(max, |bound| Duration::from_millis(*bound).min(max)),
+                );
+            }
+        }
+        Some(max)
+    }
+
+    /// `(upper bound, count)` per bucket; the last bucket has no bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        self.counts.iter().enumerate().map(|(bucket, count)| {
+            let bound = RTT_BUCKETS_MS
+                .get(bucket)
+                .copied()
+                .map(Duration::from_millis);
+            (bound, *count)
+        })
+    }
+}
+
+/// Activity of one channel as seen on the wire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChannelHealth {
+    pub channel: Channel,
+    pub messages: u64,
+    pub last_message_age: Option<Duration>,
+    /// Time since the last message, or since subscribing if none arrived.
+    pub silent_for: Duration,
+    pub threshold: Option<Duration>,
+    pub stale: bool,
+}
+
+/// Point-in-time health of a connection.
+#[derive(Debug, Clone)]
+pub struct HealthSnapshot {
+    /// Time since any frame arrived on the socket.
+    pub last_message_age: Option<Duration>,
+    pub pings_sent: u64,
+    pub pongs_received: u64,
+    /// Pings still waiting for their pong.
+    pub outstanding_pings: usize,
+    pub last_rtt: Option<Duration>,
+    pub rtt: LatencyHistogram,
+    /// One entry per channel carrying data, keyed by [`Channel::source`].
+    pub channels: Vec<ChannelHealth>,
+}
+
+impl HealthSnapshot {
+    pub fn stale_channels(&self) -> impl Iterator<Item = Channel> + '_ {
+        self.channels
+            .iter()
+            .filter(|health| health.stale)
+            .map(|health| health.channel)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ChannelStats {
+    messages: u64,
+    last_message: Option<Instant>,
+    /// Start of the current silence window when no message arrived since.
+    since: Instant,
+    stale: bool,
+}
+
+impl ChannelStats {
+    fn new(now: Instant) -> Self {
+        Self {
+            messages: 0,
+            last_message: None,
+            since: now,
+            stale: false,
+        }
+    }
+
+    fn silent_for(&self, now: Instant) -> Duration {
+        let reference = self
+            .last_message
+            .map_or(self.since, |last| last.max(self.since));
+        now.saturating_duration_since(reference)
+    }
+}
+
+/// What [`HealthMonitor::poll`] wants the connection to do.
+#[derive(Debug, Default)]
+pub(crate) struct HealthActions {
+    /// Send a ping with this payload.
+    pub ping: Option<Vec<u8>>,
+    /// Channels that just went stale.
+    pub stale: Vec<Channel>,
+}
+
+#[derive(Debug)]
+pub(crate) struct HealthMonitor {
+    config: HealthConfig,
+    last_message: Option<Instant>,
+    channels: HashMap<Channel, ChannelStats>,
+    ping_seq: u64,
+    outstanding: VecDeque<(u64, Instant)>,
+    pings_sent: u64,
+    pongs_received: u64,
+    last_rtt: Option<Duration>,
+    rtt: LatencyHistogram,
+    next_ping: Option<Instant>,
+    next_check: Instant,
+}
+
+impl HealthMonitor {
+    pub(crate) fn new(config: HealthConfig, now: Instant) -> Self {
+        let mut monitor = Self {
+            config: HealthConfig::passive(),
+            last_message: None,
+            channels: HashMap::new(),
+            ping_seq: 0,
+            outstanding: VecDeque::new(),
+            pings_sent: 0,
+            pongs_received: 0,
+            last_rtt: None,
+            rtt: LatencyHistogram::default(),
+            next_ping: None,
+            next_check: now,
+        };
+        monitor.set_config(config, now);
+        monitor
+    }
+
+    pub(crate) fn config(&self) -> &HealthConfig {
+        &self.config
+    }
+
+    pub(crate) fn set_config(&mut self, config: HealthConfig, now: Instant) {
+        self.next_ping = config.heartbeat_interval.map(|interval| now + interval);
+        self.next_check = now;
+        self.config = config;
+    }
+
+    /// Restart every silence window and forget unanswered pings, e.g. after
+    /// a reconnect.
+    pub(crate) fn reset(&mut self, now: Instant) {
+        for stats in self.channels.values_mut() {
+            stats.since = now;
+            stats.stale = false;
+        }
+        self.outstanding.clear();
+        self.next_ping = self
+            .config
+            .heartbeat_interval
+            .map(|interval| now + interval);
+    }
+
+    pub(crate) fn record_message(&mut self, now: Instant) {
+        self.last_message = Some(now);
+    }
+
+    pub(crate) fn record_channel(&mut self, channel: Channel, now: Instant) {
+        let stats = self
+            .channels
+            .entry(channel.source())
+            .or_insert_with(|| ChannelStats::new(now));
+        stats.messages += 1;
+        stats.last_message = Some(now);
+        stats.stale = false;
+    }
+
+    /// Give a stale channel a fresh silence window, e.g. after resubscribing.
+    pub(crate) fn restart_channel(&mut self, channel: Channel, now: Instant) {
+        if let Some(stats) = self.channels.get_mut(&channel.source()) {
+            stats.since = now;
+            stats.stale = false;
+        }
+    }
+
+    /// Register a ping and return its payload.
+    pub(crate) fn begin_ping(&mut self, now: Instant) -> Vec<u8> {
+        self.ping_seq = self.ping_seq.wrapping_add(1);
+        self.pings_sent += 1;
+        self.outstanding.push_back((self.ping_seq, now));
+        if self.outstanding.len() > MAX_OUTSTANDING_PINGS {
+            self.outstanding.pop_front();
+        }
+        self.ping_seq.to_be_bytes().to_vec()
+    }
+
+    /// Match a pong to its ping and record the round trip.
+    pub(crate) fn record_pong(&mut self, payload: &[u8], now: Instant) -> Option<Duration> {
+        let seq = u64::from_be_bytes(payload.try_into().ok()?);
+        let position = self.outstanding.iter().position(|(id, _)| *id == seq)?;
+        let (_, sent) = self.outstanding[position];
+        // Pongs come back in order; anything older was lost.
+        self.outstanding.drain(..=position);
+        let rtt = now.saturating_duration_since(sent);
+        self.pongs_received += 1;
+        self.last_rtt = Some(rtt);
+        self.rtt.record(rtt);
+        Some(rtt)
+    }
+
+    /// When [`poll`](Self::poll) next has work, if the config is active.
+    pub(crate) fn next_deadline(&self) -> Option<Instant> {
+        if !self.config.is_active() {
+            return None;
+        }
+        Some(
+            self.next_ping
+                .map_or(self.next_check, |ping| ping.min(self.next_check)),
+        )
+    }
+
+    pub(crate) fn poll(&mut self, subscriptions: &SubscriptionSet, now: Instant) -> HealthActions {
+        let mut actions = HealthActions::default();
+        if !self.config.is_active() || now < self.next_check {
+            return actions;
+        }
+        self.next_check = now + CHECK_INTERVAL;
+
+        if let (Some(due), Some(interval)) = (self.next_ping, self.config.heartbeat_interval) {
+            if now >= due {
+                actions.ping = Some(self.begin_ping(now));
+                self.next_ping = Some(now + interval);
+            }
+        }
+
+        self.sync_channels(subscriptions, now);
+        let mut stale: Vec<Channel> = Vec::new();
+        for (channel, stats) in &mut self.channels {
+            let Some(threshold) = self.config.threshold(*channel) else {
+                continue;
+            };
+            if !stats.stale && stats.silent_for(now) > threshold {
+                stats.stale = true;
+                stale.push(*channel);
+            }
+        }
+        stale.sort_by_key(|channel| channel.to_string());
+        actions.stale = stale;
+        actions
+    }
+
+    pub(crate) fn snapshot(&self, subscriptions: &SubscriptionSet, now: Instant) -> HealthSnapshot {
+        let channels = source_channels(subscriptions)
+            .into_iter()
+            .map(|channel| {
+                let stats = self.channels.get(&channel);
+                ChannelHealth {
+                    channel,
+                    messages: stats.map_or(0, |stats| stats.messages),
+                    last_message_age: stats
+                        .and_then(|stats| stats.last_message)
+                        .map(|last| now.saturating_duration_since(last)),
+                    silent_for: stats.map_or(Duration::ZERO, |stats| stats.silent_for(now)),
+                    threshold: self.config.threshold(channel),
+                    stale: stats.is_some_and(|stats| stats.stale),
+                }
+            })
+            .collect();
+
+        HealthSnapshot {
+            last_message_age: self
+                .last_message
+                .map(|last| now.saturating_duration_since(last)),
+            pings_sent: self.pings_sent,
+            pongs_received: self.pongs_received,
+            outstanding_pings: self.outstanding.len(),
+            last_rtt: self.last_rtt,
+            rtt: self.rtt.clone(),
+            channels,
+        }
+    }
+
+    /// Track exactly the channels `subscriptions` puts on the wire.
+    fn sync_channels(&mut self, subscriptions: &SubscriptionSet, now: Instant) {
+        let wanted = source_channels(subscriptions);
+        self.channels.retain(|channel, _| wanted.contains(channel));
+        for channel in wanted {
+            self.channels
+                .entry(channel)
+                .or_insert_with(|| ChannelStats::new(now));
+        }
+    }
+}
+
+fn source_channels(subscriptions: &SubscriptionSet) -> Vec<Channel> {
+    let mut channels = Vec::new();
+    for channel in subscriptions.channels() {
+        let source = channel.source();
+        if !channels.contains(&source) {
+            channels.push(source);
+        }
+    }
+    channels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MarketId;
+
+    #[test]
+    fn silent_channels_go_stale_once_until_they_speak() {
+        let start = Instant::now();
+        let market = MarketId::new(1);
+        let mut subscriptions = SubscriptionSet::default();
+        subscriptions.add_order_book(market);
+        subscriptions.add_bbo(market);
+        subscriptions.add_trade(market);
+        let config = HealthConfig {
+            heartbeat_interval: None,
+            ..HealthConfig::default()
+        }
+        .with_threshold(Channel::Trade(market), Duration::from_secs(60));
+        let mut monitor = HealthMonitor::new(config, start);
+        monitor.poll(&subscriptions, start);
+
+        let later = start + Duration::from_secs(16);
+        monitor.record_channel(Channel::Trade(market), later);
+        let actions = monitor.poll(&subscriptions, later);
+        assert_eq!(actions.stale, vec![Channel::OrderBook(market)]);
+        assert!(monitor
+            .poll(&subscriptions, later + Duration::from_secs(1))
+            .stale
+            .is_empty());
+
+        let snapshot = monitor.snapshot(&subscriptions, later);
+        assert_eq!(snapshot.channels.len(), 2);
+        assert_eq!(
+            snapshot.stale_channels().collect::<Vec<_>>(),
+            vec![Channel::OrderBook(market)]
+        );
+
+        monitor.record_channel(Channel::OrderBook(market), later);
+        assert_eq!(
+            monitor
+                .snapshot(&subscriptions, later)
+                .stale_channels()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn pongs_are_matched_to_their_pings() {
+        let start = Instant::now();
+        let mut monitor = HealthMonitor::new(HealthConfig::default(), start);
+        let lost = monitor.begin_ping(start);
+        let answered = monitor.begin_ping(start + Duration::from_millis(5));
+
+        let rtt = monitor.record_pong(&answered, start + Duration::from_millis(45));
+        assert_eq!(rtt, Some(Duration::from_millis(40)));
+        assert_eq!(
+            monitor.record_pong(&lost, start + Duration::from_millis(50)),
+            None
+        );
+
+        let snapshot = monitor.snapshot(&SubscriptionSet::default(), start);
+        assert_eq!((snapshot.pings_sent, snapshot.pongs_received), (2, 1));
+        assert_eq!(snapshot.outstanding_pings, 0);
+        assert_eq!(snapshot.rtt.quantile(0.5), Some(Duration::from_millis(40)));
+    }
+
+    #[test]
+    fn histogram_quantiles_use_bucket_bounds() {
+        let mut histogram = LatencyHistogram::default();
+        for millis in [3, 4, 8, 9, 150] {
+            histogram.record(Duration::from_millis(millis));
+        }
+        assert_eq!(histogram.quantile(0.4), Some(Duration::from_millis(5)));
+        assert_eq!(histogram.quantile(0.8), Some(Duration::from_millis(10)));
+        assert_eq!(histogram.quantile(1.0), Some(Duration::from_millis(150)));
+        assert_eq!(histogram.mean(), Some(Duration::from_micros(34_800)));
+    }
+}
@@ -0,0 +1,684 @@
+//! Pool of WebSocket connections behind one event stream.
+//!
+//! Strategies usually split their sockets by concern: market data, private
+//! account feeds and a quiet socket for transactions whose acks must not
+//! queue behind book updates. [`WsPool`] opens those connections from one
+//! [`SubscriptionSet`] and a [`WsPoolConfig`], runs each as a supervised
+//! session on its own task and merges their events into a single
+//! [`Stream`]. Transactions go through a cloneable [`TxSender`].
+//!
+//! Every connection reconnects on its own. When one gives up for good its
+//! subscriptions move to a live connection of the same role (or any live
+//! connection able to carry them), and [`TxSender`] routes around
+//! transaction connections that are down.
+
+use std::{
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_util::Stream;
+use serde_json::Value;
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+
+use crate::{
+    errors::{WsClientError, WsResult},
+    lighter_client::LighterClient,
+    types::{AccountId, MarketId},
+    ws_client::{PendingTx, SubscriptionSet, SupervisorConfig, TxResponse, WsConnection, WsEvent},
+    ws_health::HealthConfig,
+};
+
+/// What a pooled connection carries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkRole {
+    /// Order books, BBO, trades, market stats and height.
+    Public,
+    /// Authenticated account channels.
+    Private,
+    /// Transaction submission, plus `account_tx` for its acks.
+    Transactions,
+}
+
+/// How the subscriptions of a [`WsPool`] are spread over connections.
+#[derive(Debug, Clone)]
+pub struct WsPoolConfig {
+    pub subscriptions: SubscriptionSet,
+    /// Connections for public channels. Markets are sharded across them,
+    /// keeping every channel of one market on the same connection.
+    pub public_links: usize,
+    /// Give private channels their own connection instead of sharing the
+    /// first public one.
+    pub private_link: bool,
+    /// Connections reserved for transactions. `account_tx` subscriptions
+    /// move to the first of them.
+    pub tx_links: usize,
+    pub supervisor: SupervisorConfig,
+    /// Heartbeats and staleness detection for every link.
+    pub health: Option<HealthConfig>,
+    /// Events buffered for the merged stream before connections stop reading.
+    pub event_buffer: usize,
+}
+
+impl Default for WsPoolConfig {
+    fn default() -> Self {
+        Self {
+            subscriptions: SubscriptionSet::default(),
+            public_links: 1,
+            private_link: true,
+            tx_links: 1,
+            supervisor: SupervisorConfig::default(),
+            health: None,
+            event_buffer: 1024,
+        }
+    }
+}
+
+impl WsPoolConfig {
+    pub fn new(subscriptions: SubscriptionSet) -> Self {
+        Self {
+            subscriptions,
+            ..Self::default()
+        }
+    }
+}
+
+/// State of one pooled connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    Up,
+    Reconnecting {
+        attempt: u32,
+    },
+    /// Gave up reconnecting; its subscriptions were handed to another link.
+    Down,
+}
+
+/// Health of one pooled connection, as returned by [`WsPool::health`].
+#[derive(Debug, Clone)]
+pub struct LinkHealth {
+    pub index: usize,
+    pub role: LinkRole,
+    pub state: LinkState,
+    /// Reconnects performed so far.
+    pub generation: u64,
+    /// Events read since the pool connected.
+    pub events: u64,
+    pub last_event: Option<Instant>,
+}
+
+/// A planned connection: its role, channels and whether its events reach
+/// the merged stream.
+#[derive(Debug, Clone)]
+struct LinkPlan {
+    role: LinkRole,
+    subscriptions: SubscriptionSet,
+    forward: bool,
+}
+
+enum LinkCommand {
+    Send {
+        tx_type: u8,
+        tx_info: Value,
+        reply: oneshot::Sender<WsResult<PendingTx>>,
+    },
+    SendBatch {
+        tx_types: Vec<u8>,
+        tx_infos: Vec<Value>,
+        reply: oneshot::Sender<WsResult<PendingTx>>,
+    },
+    Adopt(Box<SubscriptionSet>),
+    Shutdown,
+}
+
+struct LinkSlot {
+    role: LinkRole,
+    authenticated: bool,
+    commands: mpsc::UnboundedSender<LinkCommand>,
+    health: Mutex<LinkHealth>,
+}
+
+impl LinkSlot {
+    fn state(&self) -> LinkState {
+        self.health.lock().unwrap().state
+    }
+}
+
+struct PoolShared {
+    links: Vec<LinkSlot>,
+    next_tx: AtomicUsize,
+    closing: AtomicBool,
+}
+
+impl PoolShared {
+    /// Live link to take over `subscriptions` from `from`: same role first,
+    /// then any link that can carry them.
+    fn failover_target(&self, from: usize, subscriptions: &SubscriptionSet) -> Option<usize> {
+        let role = self.links[from].role;
+        let needs_auth = subscriptions.requires_auth();
+        let candidates = || {
+            self.links.iter().enumerate().filter(move |(index, link)| {
+                *index != from
+                    && link.state() != LinkState::Down
+                    && (link.authenticated || !needs_auth)
+            })
+        };
+        candidates()
+            .find(|(_, link)| link.role == role)
+            .or_else(|| candidates().next())
+            .map(|(index, _)| index)
+    }
+
+    /// Links to try for a transaction: live transaction links in round-robin
+    /// order, then the other authenticated links.
+    fn tx_candidates(&self) -> Vec<usize> {
+        let tx_links: Vec<usize> = (0..self.links.len())
+            .filter(|&index| self.links[index].role == LinkRole::Transactions)
+            .collect();
+        let mut order = Vec::with_capacity(self.links.len());
+        if !tx_links.is_empty() {
+            let start = self.next_tx.fetch_add(1, Ordering::Relaxed) % tx_links.len();
+            order.extend(tx_links[start..].iter().chain(&tx_links[..start]));
+        }
+        order.extend(
+            (0..self.links.len()).filter(|&index| self.links[index].role != LinkRole::Transactions),
+        );
+        order.retain(|&index| {
+            let link = &self.links[index];
+            link.authenticated && link.state() == LinkState::Up
+        });
+        order
+    }
+}
+
+/// Connections opened from one [`WsPoolConfig`], read as a single stream of
+/// [`WsEvent`]s.
+///
+/// The stream ends once every connection is down. Dropping the pool stops
+/// its connection tasks; [`WsPool::shutdown`] also closes the sockets.
+///
+/// # Example
+/// ```ignore
+/// let mut subscriptions = SubscriptionSet::default();
+/// subscriptions.add_order_book(market);
+/// subscriptions.add_account_all_orders(account);
+/// let mut pool = WsPool::connect(&client, WsPoolConfig::new(subscriptions)).await?;
+/// let tx = pool.tx_sender();
+/// while let Some(event) = pool.next().await {
+///     // ...
+///     let response = tx.submit(tx_type, tx_info, Duration::from_secs(1)).await?;
+/// }
+/// ```
+pub struct WsPool {
+    shared: Arc<PoolShared>,
+    events: mpsc::Receiver<WsEvent>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl WsPool {
+    /// Open every connection planned from `config`, each supervised.
+    pub async fn connect(client: &LighterClient, config: WsPoolConfig) -> WsResult<Self> {
+        let account = client
+            .account_id()
+            .or_else(|| config.subscriptions.account_tx.first().copied());
+        if config.tx_links > 0 && account.is_none() {
+            return Err(WsClientError::Auth(
+                "transaction links need an account".to_string(),
+            ));
+        }
+        let plans = plan_links(&config, account);
+        if plans.is_empty() {
+            return Err(WsClientError::EmptySubscriptions);
+        }
+
+        let mut connections = Vec::with_capacity(plans.len());
+        for plan in &plans {
+            let mut builder = client
+                .ws()
+                .with_subscriptions(plan.subscriptions.clone())
+                .supervise(config.supervisor.clone());
+            if let Some(health) = &config.health {
+                builder = builder.health(health.clone());
+            }
+            let stream = builder.connect().await?;
+            connections.push(stream.into_connection());
+        }
+
+        let (event_tx, events) = mpsc::channel(config.event_buffer.max(1));
+        let mut receivers = Vec::with_capacity(plans.len());
+        let links = plans
+            .iter()
+            .zip(&connections)
+            .enumerate()
+            .map(|(index, (plan, connection))| {
+                let (commands, receiver) = mpsc::unbounded_channel();
+                receivers.push(receiver);
+                LinkSlot {
+                    role: plan.role,
+                    authenticated: connection.auth_token().is_some(),
+                    commands,
+                    health: Mutex::new(LinkHealth {
+                        index,
+                        role: plan.role,
+                        state: LinkState::Up,
+                        generation: connection.generation(),
+                        events: 0,
+                        last_event: None,
+                    }),
+                }
+            })
+            .collect();
+        let shared = Arc::new(PoolShared {
+            links,
+            next_tx: AtomicUsize::new(0),
+            closing: AtomicBool::new(false),
+        });
+
+        let tasks = connections
+            .into_iter()
+            .zip(receivers)
+            .zip(plans)
+            .enumerate()
+            .map(|(index, ((connection, commands), plan))| {
+                tokio::spawn(run_link(
+                    index,
+                    connection,
+                    plan.forward,
+                    commands,
+                    event_tx.clone(),
+                    Arc::clone(&shared),
+                ))
+            })
+            .collect();
+
+        Ok(Self {
+            shared,
+            events,
+            tasks,
+        })
+    }
+
+    /// Handle for submitting transactions from any task.
+    pub fn tx_sender(&self) -> TxSender {
+        TxSender {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+
+    /// Health of every connection, in pool order.
+    pub fn health(&self) -> Vec<LinkHealth> {
+        self.shared
+            .links
+            .iter()
+            .map(|link| link.health.lock().unwrap().clone())
+            .collect()
+    }
+
+    /// Number of connections in the pool.
+    pub fn len(&self) -> usize {
+        self.shared.links.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shared.links.is_empty()
+    }
+
+    /// Next event from any connection, `None` once all are down.
+    pub async fn next_event(&mut self) -> Option<WsEvent> {
+        self.events.recv().await
+    }
+
+    /// Close every socket and wait for the connection tasks to finish.
+    pub async fn shutdown(mut self) {
+        self.shared.closing.store(true, Ordering::Relaxed);
+        for link in &self.shared.links {
+            let _ = link.commands.send(LinkCommand::Shutdown);
+        }
+        for task in self.tasks.drain(..) {
+            let _ = task.await;
+        }
+    }
+}
+
+impl Stream for WsPool {
+    type Item = WsEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl Drop for WsPool {
+    fn drop(&mut self) {
+        self.shared.closing.store(true, Ordering::Relaxed);
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// Cloneable handle that submits transactions through a [`WsPool`].
+///
+/// Each call goes to the next live transaction connection, falling back to
+/// other authenticated connections. A transaction whose send fails on one
+/// socket is retried once on the next: its signed nonce means it executes
+/// at most once.
+#[derive(Clone)]
+pub struct TxSender {
+    shared: Arc<PoolShared>,
+}
+
+impl TxSender {
+    /// Send one transaction; the returned [`PendingTx`] resolves with the
+    /// reply and can be awaited from this task.
+    pub async fn send(&self, tx_type: u8, tx_info: Value) -> WsResult<PendingTx> {
+        self.dispatch(|reply| LinkCommand::Send {
+            tx_type,
+            tx_info: tx_info.clone(),
+            reply,
+        })
+        .await
+    }
+
+    /// Send up to 50 transactions as one `sendtxbatch` request.
+    pub async fn send_batch(&self, tx_types: Vec<u8>, tx_infos: Vec<Value>) -> WsResult<PendingTx> {
+        self.dispatch(|reply| LinkCommand::SendBatch {
+            tx_types: tx_types.clone(),
+            tx_infos: tx_infos.clone(),
+            reply,
+        })
+        .await
+    }
+
+    /// Send one transaction and wait up to `timeout` for its reply.
+    pub async fn submit(
+        &self,
+        tx_type: u8,
+        tx_info: Value,
+        timeout: Duration,
+    ) -> WsResult<TxResponse> {
+        let pending = self.send(tx_type, tx_info).await?;
+        let id = pending.id().to_string();
+        tokio::time::timeout(timeout, pending)
+            .await
+            .map_err(|_| WsClientError::TxTimeout(id))?
+    }
+
+    async fn dispatch<F>(&self, command: F) -> WsResult<PendingTx>
+    where
+        F: Fn(oneshot::Sender<WsResult<PendingTx>>) -> LinkCommand,
+    {
+        let mut attempts = 0;
+        for index in self.shared.tx_candidates() {
+            let (reply, response) = oneshot::channel();
+            if self.shared.links[index]
+                .commands
+                .send(command(reply))
+                .is_err()
+            {
+                continue;
+            }
+            match response.await {
+                Ok(Ok(pending)) => return Ok(pending),
+                // The socket broke under the send; try the next link once.
+                Ok(Err(WsClientError::WebSocket(err))) if attempts == 0 => {
+                    tracing::warn!(link = index, "transaction send failed: {}", err);
+                    attempts += 1;
+                }
+                Ok(Err(err)) => return Err(err),
+                Err(_) => continue,
+            }
+        }
+        Err(WsClientError::NoTxConnection)
+    }
+}
+
+async fn run_link(
+    index: usize,
+    mut connection: WsConnection,
+    forward: bool,
+    mut commands: mpsc::UnboundedReceiver<LinkCommand>,
+    events: mpsc::Sender<WsEvent>,
+    shared: Arc<PoolShared>,
+) {
+    loop {
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(LinkCommand::Send { tx_type, tx_info, reply }) => {
+                    let _ = reply.send(connection.send_transaction(tx_type, tx_info).await);
+                }
+                Some(LinkCommand::SendBatch { tx_types, tx_infos, reply }) => {
+                    let result = connection.send_batch_transaction(tx_types, tx_infos).await;
+                    let _ = reply.send(result);
+                }
+                Some(LinkCommand::Adopt(subscriptions)) => {
+                    tracing::info!(link = index, "adopting subscriptions from a failed link");
+                    if let Err(err) = connection.add_subscriptions(*subscriptions).await {
+                        tracing::warn!(link = index, "failed to adopt subscriptions: {}", err);
+                    }
+                }
+                Some(LinkCommand::Shutdown) | None => {
+                    let _ = connection.close().await;
+                    return;
+                }
+            },
+            event = connection.next_session_event() => match event {
+                Ok(Some(event)) => {
+                    record_event(&shared.links[index], &event, connection.generation());
+                    if forward && events.send(event).await.is_err() {
+                        return;
+                    }
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    tracing::warn!(link = index, "pooled websocket failed: {}", err);
+                    break;
+                }
+            },
+        }
+    }
+
+    shared.links[index].health.lock().unwrap().state = LinkState::Down;
+    // A transaction link whose events were never forwarded has nothing
+    // worth moving.
+    if shared.closing.load(Ordering::Relaxed) || !forward {
+        return;
+    }
+    let subscriptions = connection.subscriptions().clone();
+    match shared.failover_target(index, &subscriptions) {
+        Some(target) => {
+            tracing::warn!(
+                link = index,
+                target,
+                "websocket link down, moving its subscriptions"
+            );
+            let _ = shared.links[target]
+                .commands
+                .send(LinkCommand::Adopt(Box::new(subscriptions)));
+        }
+        None => tracing::error!(
+            link = index,
+            "websocket link down and no link can take over"
+        ),
+    }
+}
+
+fn record_event(link: &LinkSlot, event: &WsEvent, generation: u64) {
+    let mut health = link.health.lock().unwrap();
+    health.events += 1;
+    health.last_event = Some(Instant::now());
+    health.generation = generation;
+    match event {
+        WsEvent::Reconnecting { attempt } => {
+            health.state = LinkState::Reconnecting { attempt: *attempt }
+        }
+        // The supervisor follows up with `Reconnecting`.
+        WsEvent::Closed(_) => {}
+        _ => health.state = LinkState::Up,
+    }
+}
+
+/// Split `config.subscriptions` into connections by role.
+/// Transaction links without `account_tx` of their own subscribe to
+/// `account`'s; they are skipped when there is none.
+fn plan_links(config: &WsPoolConfig, account: Option<AccountId>) -> Vec<LinkPlan> {
+    let all = &config.subscriptions;
+    let mut plans = Vec::new();
+
+    let mut markets: Vec<MarketId> = Vec::new();
+    for market in all
+        .order_books
+        .iter()
+        .chain(&all.bbo)
+        .chain(&all.trades)
+        .chain(&all.market_stats)
+    {
+        if !markets.contains(market) {
+            markets.push(*market);
+        }
+    }
+    let shards = config.public_links.max(1).min(markets.len().max(1));
+    if !markets.is_empty() || all.subscribe_height {
+        for shard in 0..shards {
+            let in_shard = |market: &MarketId| {
+                markets.iter().position(|m| m == market).unwrap_or(0) % shards == shard
+            };
+            let pick = |list: &Vec<MarketId>| list.iter().copied().filter(in_shard).collect();
+            let subscriptions = SubscriptionSet {
+                order_books: pick(&all.order_books),
+                bbo: pick(&all.bbo),
+                trades: pick(&all.trades),
+                market_stats: pick(&all.market_stats),
+                subscribe_height: all.subscribe_height && shard == 0,
+                ..SubscriptionSet::default()
+            };
+            plans.push(LinkPlan {
+                role: LinkRole::Public,
+                subscriptions,
+                forward: true,
+            });
+        }
+    }
+
+    let mut private = SubscriptionSet {
+        accounts: all.accounts.clone(),
+        account_all_positions: all.account_all_positions.clone(),
+        account_all_trades: all.account_all_trades.clone(),
+        account_all_orders: all.account_all_orders.clone(),
+        user_stats: all.user_stats.clone(),
+        pool_data: all.pool_data.clone(),
+        pool_info: all.pool_info.clone(),
+        notifications: all.notifications.clone(),
+        account_market: all.account_market.clone(),
+        account_market_orders: all.account_market_orders.clone(),
+        account_market_positions: all.account_market_positions.clone(),
+        account_market_trades: all.account_market_trades.clone(),
+        ..SubscriptionSet::default()
+    };
+    if config.tx_links == 0 {
+        private.account_tx = all.account_tx.clone();
+    }
+    if !private.is_empty() {
+        match plans.first_mut() {
+            Some(public) if !config.private_link => public.subscriptions.extend(private),
+            _ => plans.push(LinkPlan {
+                role: LinkRole::Private,
+                subscriptions: private,
+                forward: true,
+            }),
+        }
+    }
+
+    for link in 0..config.tx_links {
+        // A transaction link needs an authenticated channel to stay open;
+        // only the caller's own `account_tx` subscriptions are forwarded.
+        let (account_tx, forward) = if link == 0 && !all.account_tx.is_empty() {
+            (all.account_tx.clone(), true)
+        } else if let Some(account) = account {
+            (vec![account], false)
+        } else {
+            continue;
+        };
+        plans.push(LinkPlan {
+            role: LinkRole::Transactions,
+            subscriptions: SubscriptionSet {
+                account_tx,
+                ..SubscriptionSet::default()
+            },
+            forward,
+        });
+    }
+
+    plans
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(id: i32) -> MarketId {
+        MarketId::new(id)
+    }
+
+    #[test]
+    fn public_shards_keep_each_market_together() {
+        let mut subscriptions = SubscriptionSet::default();
+        for id in 0..3 {
+            subscriptions.add_order_book(market(id));
+            subscriptions.add_bbo(market(id));
+        }
+        subscriptions.add_trade(market(1));
+        let config = WsPoolConfig {
+            public_links: 2,
+            tx_links: 0,
+            ..WsPoolConfig::new(subscriptions)
+        };
+
+        let plans = plan_links(&config, None);
+        assert_eq!(plans.len(), 2);
+        assert_eq!(
+            plans[0].subscriptions.order_books,
+            vec![market(0), market(2)]
+        );
+        assert_eq!(plans[0].subscriptions.bbo, vec![market(0), market(2)]);
+        assert_eq!(plans[1].subscriptions.order_books, vec![market(1)]);
+        assert_eq!(plans[1].subscriptions.trades, vec![market(1)]);
+    }
+
+    #[test]
+    fn account_tx_moves_to_the_transaction_link() {
+        let account = AccountId::new(7);
+        let mut subscriptions = SubscriptionSet::default();
+        subscriptions.add_order_book(market(0));
+        subscriptions.add_account_all_orders(account);
+        subscriptions.add_account_tx(account);
+        let config = WsPoolConfig {
+            tx_links: 2,
+            ..WsPoolConfig::new(subscriptions)
+        };
+
+        let plans = plan_links(&config, Some(account));
+        let roles: Vec<LinkRole> = plans.iter().map(|plan| plan.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                LinkRole::Public,
+                LinkRole::Private,
+                LinkRole::Transactions,
+                LinkRole::Transactions
+            ]
+        );
+        assert!(plans[1].subscriptions.account_tx.is_empty());
+        assert_eq!(plans[2].subscriptions.account_tx, vec![account]);
+        assert!(plans[2].forward);
+        assert!(!plans[3].forward);
+    }
+}
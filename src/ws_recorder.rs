@@ -0,0 +1,897 @@
+//! Recording raw WebSocket frames to disk and replaying them as events.
+//!
+//! A [`MarketRecorder`] runs a writer thread fed through cloneable
+//! [`RecorderTap`]s; attach one to any connection with
+//! [`WsConnection::attach_recorder`] (or through
+//! [`WsStream::connection_mut`](crate::ws_client::WsStream::connection_mut)).
+//! Every text, binary and close frame is stored with its receive timestamp,
+//! together with the connection's subscriptions whenever they change.
+//!
+//! A recording is a directory of gzip-compressed segment files plus an
+//! `index.jsonl` listing the time span of each closed segment. Inside a
+//! segment every record is length-prefixed:
+//!
+//! ```text
+//! u32 len (LE) | u64 received_at, unix micros (LE) | u8 kind | payload
+//! ```
+//!
+//! where `len` counts everything after itself. Each segment opens with the
+//! subscriptions in force, so replay can start at any segment.
+//!
+//! [`ReplayStream`] feeds the frames back through the same decoding path as
+//! a live [`WsConnection`], yielding identical [`WsEvent`]s at the recorded
+//! pace, accelerated, or as fast as possible.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::mpsc,
+    task::{Context, Poll},
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use futures_util::{FutureExt, Stream};
+use serde::{Deserialize, Serialize};
+use tokio_tungstenite::tungstenite::protocol::{frame::coding::CloseCode, CloseFrame, Message};
+
+use crate::{
+    errors::RecorderError,
+    ws_client::{Channel, SubscriptionSet, WsConnection, WsEvent},
+};
+
+const SEGMENT_PREFIX: &str = "segment-";
+const SEGMENT_SUFFIX: &str = ".lrec.gz";
+const INDEX_FILE: &str = "index.jsonl";
+const SEGMENT_MAGIC: &[u8; 8] = b"LREC0001";
+/// Bytes of a record after its length prefix, excluding the payload.
+const RECORD_HEADER_LEN: usize = 9;
+
+/// Where and how a [`MarketRecorder`] writes its segments.
+#[derive(Debug, Clone)]
+pub struct RecorderConfig {
+    /// Directory holding the segments and their index. Created if missing.
+    pub dir: PathBuf,
+    /// Uncompressed bytes after which the current segment is closed.
+    pub segment_max_bytes: u64,
+    /// Age after which the current segment is closed.
+    pub segment_max_age: Duration,
+    /// gzip level, 0 (none) to 9 (best).
+    pub compression: u32,
+}
+
+impl RecorderConfig {
+    /// 64 MiB or one hour per segment, fast compression.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            segment_max_bytes: 64 * 1024 * 1024,
+            segment_max_age: Duration::from_secs(3600),
+            compression: 1,
+        }
+    }
+}
+
+/// One closed segment as listed in the index.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SegmentInfo {
+    /// File name relative to the recording directory.
+    pub file: String,
+    /// Receive time of the first record, in unix microseconds.
+    pub first_micros: u64,
+    /// Receive time of the last record, in unix microseconds.
+    pub last_micros: u64,
+    /// Number of records, subscription records included.
+    pub records: u64,
+}
+
+impl SegmentInfo {
+    pub fn start(&self) -> SystemTime {
+        from_micros(self.first_micros)
+    }
+
+    pub fn end(&self) -> SystemTime {
+        from_micros(self.last_micros)
+    }
+}
+
+/// Time index of a recording, used to pick the segment to start from.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingIndex {
+    segments: Vec<SegmentInfo>,
+}
+
+impl RecordingIndex {
+    /// Read `index.jsonl` from `dir`. Segments missing from it (the recorder
+    /// did not shut down cleanly) are scanned, and a truncated tail is
+    /// accepted up to its last complete record.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, RecorderError> {
+        let dir = dir.as_ref();
+        let mut segments = Vec::new();
+        match File::open(dir.join(INDEX_FILE)) {
+            Ok(file) => {
+                for line in BufReader::new(file).lines() {
+                    let line = line?;
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    let info: SegmentInfo =
+                        serde_json::from_str(&line).map_err(|err| RecorderError::Corrupt {
+                            file: INDEX_FILE.to_string(),
+                            reason: err.to_string(),
+                        })?;
+                    segments.push(info);
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        for file in segment_files(dir)? {
+            if segments.iter().any(|info| info.file == file) {
+                continue;
+            }
+            let mut reader = SegmentReader::open(&dir.join(&file))?;
+            let mut info: Option<SegmentInfo> = None;
+            while let Some(record) = reader.next_record()? {
+                let info = info.get_or_insert_with(|| SegmentInfo {
+                    file: file.clone(),
+                    first_micros: record.micros,
+                    last_micros: record.micros,
+                    records: 0,
+                });
+                info.last_micros = record.micros;
+                info.records += 1;
+            }
+            segments.extend(info);
+        }
+
+        segments.sort_by(|a, b| a.file.cmp(&b.file));
+        if segments.is_empty() {
+            return Err(RecorderError::Empty(dir.display().to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    pub fn segments(&self) -> &[SegmentInfo] {
+        &self.segments
+    }
+
+    /// Receive time of the first recorded frame.
+    pub fn start(&self) -> Option<SystemTime> {
+        self.segments.first().map(SegmentInfo::start)
+    }
+
+    /// Receive time of the last recorded frame.
+    pub fn end(&self) -> Option<SystemTime> {
+        self.segments.last().map(SegmentInfo::end)
+    }
+
+    /// Position of the segment containing `at`: the last one starting at or
+    /// before it, or the first when `at` precedes the recording.
+    pub fn segment_for(&self, at: SystemTime) -> usize {
+        let at = to_micros(at);
+        self.segments
+            .iter()
+            .rposition(|info| info.first_micros <= at)
+            .unwrap_or(0)
+    }
+}
+
+/// Totals reported by [`MarketRecorder::finish`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordingSummary {
+    pub segments: usize,
+    pub records: u64,
+    /// Uncompressed record bytes written.
+    pub bytes: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecordKind {
+    Text = 0,
+    Binary = 1,
+    Close = 2,
+    Subscriptions = 3,
+}
+
+impl RecordKind {
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Self::Text),
+            1 => Some(Self::Binary),
+            2 => Some(Self::Close),
+            3 => Some(Self::Subscriptions),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Record {
+    micros: u64,
+    kind: RecordKind,
+    payload: Vec<u8>,
+}
+
+impl Record {
+    /// The frame to hand to a replay connection; `None` for subscriptions.
+    fn into_message(self) -> Result<Option<Message>, RecorderError> {
+        let message = match self.kind {
+            RecordKind::Text => Message::Text(String::from_utf8(self.payload).map_err(|_| {
+                RecorderError::Corrupt {
+                    file: String::new(),
+                    reason: "text frame is not utf-8".to_string(),
+                }
+            })?),
+            RecordKind::Binary => Message::Binary(self.payload),
+            RecordKind::Close => {
+                let frame = (self.payload.len() >= 2).then(|| CloseFrame {
+                    code: CloseCode::from(u16::from_le_bytes([self.payload[0], self.payload[1]])),
+                    reason: String::from_utf8_lossy(&self.payload[2..])
+                        .into_owned()
+                        .into(),
+                });
+                Message::Close(frame)
+            }
+            RecordKind::Subscriptions => return Ok(None),
+        };
+        Ok(Some(message))
+    }
+
+    fn subscriptions(&self) -> SubscriptionSet {
+        let mut set = SubscriptionSet::default();
+        for name in String::from_utf8_lossy(&self.payload).lines() {
+            match Channel::parse(name) {
+                Some(channel) => {
+                    set.insert(channel);
+                }
+                None => tracing::warn!(name, "unknown channel in recording"),
+            }
+        }
+        set
+    }
+}
+
+enum RecorderCommand {
+    Record(Record),
+    Finish,
+}
+
+/// Cheap handle feeding frames to a [`MarketRecorder`]. Once the recorder
+/// has finished (or failed) frames are dropped silently.
+#[derive(Debug, Clone)]
+pub struct RecorderTap {
+    sender: mpsc::Sender<RecorderCommand>,
+}
+
+impl RecorderTap {
+    /// Record a received frame. Pings, pongs and raw frames are skipped.
+    pub(crate) fn frame(&self, message: &Message) {
+        let (kind, payload) = match message {
+            Message::Text(text) => (RecordKind::Text, text.as_bytes().to_vec()),
+            Message::Binary(bytes) => (RecordKind::Binary, bytes.clone()),
+            Message::Close(frame) => {
+                let mut payload = Vec::new();
+                if let Some(frame) = frame {
+                    payload.extend_from_slice(&u16::from(frame.code).to_le_bytes());
+                    payload.extend_from_slice(frame.reason.as_bytes());
+                }
+                (RecordKind::Close, payload)
+            }
+            Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => return,
+        };
+        self.record(to_micros(SystemTime::now()), kind, payload);
+    }
+
+    /// Record the subscriptions in force from now on.
+    pub(crate) fn subscriptions(&self, subscriptions: &SubscriptionSet) {
+        let names = subscriptions
+            .channels()
+            .iter()
+            .map(Channel::to_string)
+            .collect::<Vec<_>>()
+            .join("\n");
+        self.record(
+            to_micros(SystemTime::now()),
+            RecordKind::Subscriptions,
+            names.into_bytes(),
+        );
+    }
+
+    fn record(&self, micros: u64, kind: RecordKind, payload: Vec<u8>) {
+        let _ = self.sender.send(RecorderCommand::Record(Record {
+            micros,
+            kind,
+            payload,
+        }));
+    }
+}
+
+/// Writes frames from its [`RecorderTap`]s to rotating, compressed segment
+/// files on a dedicated thread, so recording never blocks a read loop.
+///
+/// Call [`finish`](Self::finish) to flush the open segment and index it;
+/// dropping the recorder does the same but discards any error.
+#[derive(Debug)]
+pub struct MarketRecorder {
+    sender: mpsc::Sender<RecorderCommand>,
+    writer: Option<JoinHandle<Result<RecordingSummary, RecorderError>>>,
+    dir: PathBuf,
+}
+
+impl MarketRecorder {
+    /// Create the directory if needed and start the writer thread. New
+    /// segments are numbered after any already in the directory, so a
+    /// recording can be resumed.
+    pub fn start(config: RecorderConfig) -> Result<Self, RecorderError> {
+        fs::create_dir_all(&config.dir)?;
+        let next_segment = segment_files(&config.dir)?
+            .iter()
+            .filter_map(|file| segment_number(file))
+            .max()
+            .map_or(0, |last| last + 1);
+
+        let dir = config.dir.clone();
+        let (sender, receiver) = mpsc::channel();
+        let writer = std::thread::Builder::new()
+            .name("lighter-recorder".to_string())
+            .spawn(move || run_writer(config, next_segment, receiver))?;
+        Ok(Self {
+            sender,
+            writer: Some(writer),
+            dir,
+        })
+    }
+
+    /// A handle for [`WsConnection::attach_recorder`].
+    pub fn tap(&self) -> RecorderTap {
+        RecorderTap {
+            sender: self.sender.clone(),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Stop recording, flush and index the open segment.
+    pub fn finish(mut self) -> Result<RecordingSummary, RecorderError> {
+        self.stop()
+    }
+
+    fn stop(&mut self) -> Result<RecordingSummary, RecorderError> {
+        let _ = self.sender.send(RecorderCommand::Finish);
+        match self.writer.take() {
+            Some(writer) => writer
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("recorder thread panicked").into())),
+            None => Ok(RecordingSummary::default()),
+        }
+    }
+}
+
+impl Drop for MarketRecorder {
+    fn drop(&mut self) {
+        if let Err(err) = self.stop() {
+            tracing::warn!(%err, "market recorder failed");
+        }
+    }
+}
+
+fn run_writer(
+    config: RecorderConfig,
+    mut next_segment: u64,
+    receiver: mpsc::Receiver<RecorderCommand>,
+) -> Result<RecordingSummary, RecorderError> {
+    let mut summary = RecordingSummary::default();
+    let mut segment: Option<SegmentWriter> = None;
+    let mut subscriptions: Option<Vec<u8>> = None;
+
+    while let Ok(RecorderCommand::Record(record)) = receiver.recv() {
+        if record.kind == RecordKind::Subscriptions {
+            subscriptions = Some(record.payload.clone());
+        }
+
+        if segment.as_ref().is_some_and(|open| {
+            open.bytes >= config.segment_max_bytes
+                || open.opened.elapsed() >= config.segment_max_age
+        }) {
+            if let Some(full) = segment.take() {
+                full.close(&config.dir)?;
+            }
+        }
+
+        let writer = match &mut segment {
+            Some(writer) => writer,
+            None => {
+                let mut writer = SegmentWriter::create(&config, next_segment)?;
+                next_segment += 1;
+                summary.segments += 1;
+                if record.kind != RecordKind::Subscriptions {
+                    if let Some(names) = &subscriptions {
+                        summary.bytes +=
+                            writer.write(record.micros, RecordKind::Subscriptions, names)?;
+                        summary.records += 1;
+                    }
+                }
+                segment.insert(writer)
+            }
+        };
+        summary.bytes += writer.write(record.micros, record.kind, &record.payload)?;
+        summary.records += 1;
+    }
+
+    if let Some(open) = segment {
+        open.close(&config.dir)?;
+    }
+    Ok(summary)
+}
+
+struct SegmentWriter {
+    encoder: GzEncoder<BufWriter<File>>,
+    info: SegmentInfo,
+    bytes: u64,
+    opened: Instant,
+}
+
+impl SegmentWriter {
+    fn create(config: &RecorderConfig, number: u64) -> Result<Self, RecorderError> {
+        let file = segment_name(number);
+        let handle = File::create(config.dir.join(&file))?;
+        let mut encoder = GzEncoder::new(
+            BufWriter::new(handle),
+            Compression::new(config.compression.min(9)),
+        );
+        encoder.write_all(SEGMENT_MAGIC)?;
+        Ok(Self {
+            encoder,
+            info: SegmentInfo {
+                file,
+                first_micros: 0,
+                last_micros: 0,
+                records: 0,
+            },
+            bytes: 0,
+            opened: Instant::now(),
+        })
+    }
+
+    /// Append one record, returning its encoded length.
+    fn write(&mut self, micros: u64, kind: RecordKind, payload: &[u8]) -> io::Result<u64> {
+        let len = u32::try_from(RECORD_HEADER_LEN + payload.len())
+            .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "frame too large to record"))?;
+        self.encoder.write_all(&len.to_le_bytes())?;
+        self.encoder.write_all(&micros.to_le_bytes())?;
+        self.encoder.write_all(&[kind as u8])?;
+        self.encoder.write_all(payload)?;
+
+        if self.info.records == 0 {
+            self.info.first_micros = micros;
+        }
+        self.info.last_micros = self.info.last_micros.max(micros);
+        self.info.records += 1;
+        let written = 4 + u64::from(len);
+        self.bytes += written;
+        Ok(written)
+    }
+
+    /// Finish the gzip stream and append the segment to the index.
+    fn close(self, dir: &Path) -> Result<(), RecorderError> {
+        let mut inner = self.encoder.finish()?;
+        inner.flush()?;
+        inner.get_ref().sync_data()?;
+
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(dir.join(INDEX_FILE))?;
+        let mut line = serde_json::to_string(&self.info).map_err(io::Error::from)?;
+        line.push('\n');
+        index.write_all(line.as_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct SegmentReader {
+    decoder: GzDecoder<BufReader<File>>,
+    file: String,
+}
+
+impl SegmentReader {
+    fn open(path: &Path) -> Result<Self, RecorderError> {
+        let file = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let mut decoder = GzDecoder::new(BufReader::new(File::open(path)?));
+        let mut magic = [0u8; 8];
+        decoder.read_exact(&mut magic)?;
+        if &magic != SEGMENT_MAGIC {
+            return Err(RecorderError::Corrupt {
+                file,
+                reason: "not a recording segment".to_string(),
+            });
+        }
+        Ok(Self { decoder, file })
+    }
+
+    /// The next complete record, or `None` at the end of the segment. A
+    /// record cut short by a crash also ends the segment.
+    fn next_record(&mut self) -> Result<Option<Record>, RecorderError> {
+        let mut len = [0u8; 4];
+        if !self.read_or_eof(&mut len)? {
+            return Ok(None);
+        }
+        let len = u32::from_le_bytes(len) as usize;
+        if len < RECORD_HEADER_LEN {
+            return Err(self.corrupt(format!("record length {len} is too short")));
+        }
+        let mut body = vec![0u8; len];
+        if !self.read_or_eof(&mut body)? {
+            tracing::warn!(file = %self.file, "recording segment ends mid-record");
+            return Ok(None);
+        }
+
+        let micros = u64::from_le_bytes(body[..8].try_into().expect("eight bytes"));
+        let kind = RecordKind::from_byte(body[8])
+            .ok_or_else(|| self.corrupt(format!("unknown record kind {}", body[8])))?;
+        body.drain(..RECORD_HEADER_LEN);
+        Ok(Some(Record {
+            micros,
+            kind,
+            payload: body,
+        }))
+    }
+
+    /// Fill `buf`, returning `false` if the stream ends first.
+    fn read_or_eof(&mut self, buf: &mut [u8]) -> Result<bool, RecorderError> {
+        match self.decoder.read_exact(buf) {
+            Ok(()) => Ok(true),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn corrupt(&self, reason: String) -> RecorderError {
+        RecorderError::Corrupt {
+            file: self.file.clone(),
+            reason,
+        }
+    }
+}
+
+/// Pace of a [`ReplayStream`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keep the recorded gaps between frames.
+    Original,
+    /// Divide the recorded gaps by this factor (`2.0` replays twice as
+    /// fast). Non-positive factors replay unthrottled.
+    Factor(f64),
+    /// Deliver frames as fast as they decode.
+    Unthrottled,
+}
+
+/// Replays a recording as [`WsEvent`]s.
+///
+/// Frames are decoded by a socketless [`WsConnection`], so order books,
+/// synthetic BBOs and account state evolve exactly as they did live; anything
+/// the connection would send is discarded. Pacing uses tokio's clock, which
+/// makes replays deterministic under a paused test runtime.
+#[derive(Debug)]
+pub struct ReplayStream {
+    dir: PathBuf,
+    index: RecordingIndex,
+    speed: ReplaySpeed,
+    connection: WsConnection,
+    segment: usize,
+    reader: Option<SegmentReader>,
+    /// Record read but not yet delivered, kept across a cancelled pacing wait.
+    pending: Option<Record>,
+    /// Frames received before this time are decoded but their events dropped.
+    skip_until: Option<u64>,
+    /// Wall-clock instant matching the first paced record's timestamp.
+    clock: Option<(tokio::time::Instant, u64)>,
+    position: Option<u64>,
+}
+
+impl ReplayStream {
+    /// Open the recording in `dir`, positioned at its first frame.
+    pub fn open(dir: impl AsRef<Path>, speed: ReplaySpeed) -> Result<Self, RecorderError> {
+        let dir = dir.as_ref().to_path_buf();
+        let index = RecordingIndex::load(&dir)?;
+        Ok(Self {
+            dir,
+            index,
+            speed,
+            connection: WsConnection::replay(SubscriptionSet::default()),
+            segment: 0,
+            reader: None,
+            pending: None,
+            skip_until: None,
+            clock: None,
+            position: None,
+        })
+    }
+
+    pub fn index(&self) -> &RecordingIndex {
+        &self.index
+    }
+
+    pub fn speed(&self) -> ReplaySpeed {
+        self.speed
+    }
+
+    /// Change the pace from the next frame on.
+    pub fn set_speed(&mut self, speed: ReplaySpeed) {
+        self.speed = speed;
+        self.clock = None;
+    }
+
+    /// The decoding connection, for its order book and account state.
+    pub fn connection(&self) -> &WsConnection {
+        &self.connection
+    }
+
+    /// Receive time of the last frame fed to the connection.
+    pub fn position(&self) -> Option<SystemTime> {
+        self.position.map(from_micros)
+    }
+
+    /// Continue from the first frame received at or after `at`.
+    ///
+    /// Decoding restarts at the segment containing `at`, with frames before
+    /// `at` applied without pacing and without surfacing events. Order book
+    /// state is therefore rebuilt from that segment's start: a book whose
+    /// snapshot was recorded in an earlier segment only recovers once the
+    /// recording contains a new snapshot.
+    pub fn seek(&mut self, at: SystemTime) {
+        self.connection = WsConnection::replay(SubscriptionSet::default());
+        self.segment = self.index.segment_for(at);
+        self.reader = None;
+        self.pending = None;
+        self.skip_until = Some(to_micros(at));
+        self.clock = None;
+        self.position = None;
+    }
+
+    /// Next event, or `None` once the recording is exhausted.
+    pub async fn next_event(&mut self) -> Result<Option<WsEvent>, RecorderError> {
+        loop {
+            // A replay connection yields `None` as soon as its queue is empty.
+            if let Some(event) = self.connection.next_event().await? {
+                if self.skip_until.is_none() {
+                    return Ok(Some(event));
+                }
+                continue;
+            }
+
+            if self.pending.is_none() {
+                self.pending = self.read_record()?;
+            }
+            let Some(micros) = self.pending.as_ref().map(|record| record.micros) else {
+                return Ok(None);
+            };
+            if self.skip_until.is_some_and(|until| micros >= until) {
+                self.skip_until = None;
+            }
+            if self.skip_until.is_none() {
+                self.pace(micros).await;
+            }
+
+            let Some(record) = self.pending.take() else {
+                continue;
+            };
+            self.position = Some(record.micros);
+            if record.kind == RecordKind::Subscriptions {
+                self.connection
+                    .replace_subscriptions(record.subscriptions());
+            } else if let Some(message) = record.into_message()? {
+                self.connection.push_replay_frame(message);
+            }
+        }
+    }
+
+    fn read_record(&mut self) -> Result<Option<Record>, RecorderError> {
+        loop {
+            if self.reader.is_none() {
+                let Some(info) = self.index.segments.get(self.segment) else {
+                    return Ok(None);
+                };
+                self.reader = Some(SegmentReader::open(&self.dir.join(&info.file))?);
+            }
+            if let Some(reader) = self.reader.as_mut() {
+                if let Some(record) = reader.next_record()? {
+                    return Ok(Some(record));
+                }
+            }
+            self.reader = None;
+            self.segment += 1;
+        }
+    }
+
+    async fn pace(&mut self, micros: u64) {
+        let factor = match self.speed {
+            ReplaySpeed::Original => 1.0,
+            ReplaySpeed::Factor(factor) if factor.is_finite() && factor > 0.0 => factor,
+            ReplaySpeed::Factor(_) | ReplaySpeed::Unthrottled => return,
+        };
+        let (started, origin) = *self
+            .clock
+            .get_or_insert_with(|| (tokio::time::Instant::now(), micros));
+        let offset = micros.saturating_sub(origin) as f64 / factor / 1_000_000.0;
+        tokio::time::sleep_until(started + Duration::from_secs_f64(offset)).await;
+    }
+}
+
+impl Stream for ReplayStream {
+    type Item = Result<WsEvent, RecorderError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let fut = self.next_event();
+        futures_util::pin_mut!(fut);
+        match futures_util::ready!(fut.poll_unpin(cx)) {
+            Ok(Some(event)) => Poll::Ready(Some(Ok(event))),
+            Ok(None) => Poll::Ready(None),
+            Err(err) => Poll::Ready(Some(Err(err))),
+        }
+    }
+}
+
+fn segment_name(number: u64) -> String {
+    format!("{SEGMENT_PREFIX}{number:06}{SEGMENT_SUFFIX}")
+}
+
+fn segment_number(file: &str) -> Option<u64> {
+    file.strip_prefix(SEGMENT_PREFIX)?
+        .strip_suffix(SEGMENT_SUFFIX)?
+        .parse()
+        .ok()
+}
+
+/// Segment file names in `dir`, in recording order.
+fn segment_files(dir: &Path) -> io::Result<Vec<String>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().into_owned();
+        if segment_number(&name).is_some() {
+            files.push(name);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn to_micros(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_micros() as u64)
+}
+
+fn from_micros(micros: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_micros(micros)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::MarketId;
+    use serde_json::json;
+
+    fn temp_recording(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "lighter-recording-{}-{}-{}",
+            name,
+            std::process::id(),
+            to_micros(SystemTime::now())
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    /// A snapshot followed by `updates` deltas on market 1, one per
+    /// millisecond, rotating segments after `segment_bytes`.
+    fn record_book(dir: &Path, updates: u64, segment_bytes: u64) -> RecordingSummary {
+        let mut config = RecorderConfig::new(dir);
+        config.segment_max_bytes = segment_bytes;
+        let recorder = MarketRecorder::start(config).unwrap();
+        let tap = recorder.tap();
+
+        let mut subscriptions = SubscriptionSet::default();
+        subscriptions.add_order_book(MarketId(1));
+        let names = Channel::OrderBook(MarketId(1)).to_string().into_bytes();
+        tap.record(1_000, RecordKind::Subscriptions, names);
+        let connected = json!({ "type": "connected" }).to_string();
+        tap.record(1_000, RecordKind::Text, connected.into_bytes());
+        let snapshot = json!({
+            "type": "subscribed/order_book",
+            "channel": "order_book:1",
+            "offset": 1,
+            "order_book": {
+                "asks": [{ "price": "101.0", "size": "1" }],
+                "bids": [{ "price": "99.0", "size": "1" }],
+            },
+        });
+        tap.record(2_000, RecordKind::Text, snapshot.to_string().into_bytes());
+        for n in 0..updates {
+            let update = json!({
+                "type": "update/order_book",
+                "channel": "order_book:1",
+                "offset": n + 2,
+                "order_book": {
+                    "asks": [],
+                    "bids": [{ "price": format!("{}.0", 70 + n), "size": "2" }],
+                },
+            });
+            tap.record(
+                3_000 + n * 1_000,
+                RecordKind::Text,
+                update.to_string().into_bytes(),
+            );
+        }
+        recorder.finish().unwrap()
+    }
+
+    async fn collect(replay: &mut ReplayStream) -> Vec<WsEvent> {
+        let mut events = Vec::new();
+        while let Some(event) = replay.next_event().await.unwrap() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[tokio::test]
+    async fn replay_reproduces_recorded_order_book() {
+        let dir = temp_recording("round-trip");
+        let summary = record_book(&dir, 20, 512);
+        let index = RecordingIndex::load(&dir).unwrap();
+        assert!(summary.segments > 1);
+        assert_eq!(index.segments().len(), summary.segments);
+        assert_eq!(index.start(), Some(from_micros(1_000)));
+        assert_eq!(index.end(), Some(from_micros(22_000)));
+        assert_eq!(index.segment_for(from_micros(500)), 0);
+        assert_eq!(index.segment_for(from_micros(22_000)), summary.segments - 1);
+
+        let mut replay = ReplayStream::open(&dir, ReplaySpeed::Unthrottled).unwrap();
+        let events = collect(&mut replay).await;
+        assert!(matches!(events[0], WsEvent::Connected));
+        let books = events
+            .iter()
+            .filter(|event| matches!(event, WsEvent::OrderBook(_)))
+            .count();
+        assert_eq!(books, 21);
+
+        let book = replay.connection().order_book_state(MarketId(1)).unwrap();
+        assert_eq!(book.bids.len(), 21);
+        assert_eq!(book.asks[0].price, "101.0");
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn seek_fast_forwards_within_the_segment_and_paces_the_rest() {
+        let dir = temp_recording("seek");
+        record_book(&dir, 20, 64 * 1024);
+
+        let mut replay = ReplayStream::open(&dir, ReplaySpeed::Factor(2.0)).unwrap();
+        replay.seek(from_micros(15_000));
+        let started = tokio::time::Instant::now();
+        let events = collect(&mut replay).await;
+
+        // Updates at 15ms..=22ms, the last one 7ms after the first at 2x.
+        assert_eq!(events.len(), 8);
+        let WsEvent::OrderBook(first) = &events[0] else {
+            panic!("expected an order book event, got {:?}", events[0]);
+        };
+        assert_eq!(first.offset, Some(14));
+        // The snapshot and the twelve skipped deltas are still applied.
+        assert_eq!(first.state.bids.len(), 14);
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_micros(3_500));
+        assert_eq!(replay.position(), Some(from_micros(22_000)));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}